@@ -0,0 +1,155 @@
+//! Benchmarks of the stages that take the most time when generating cards. The assets are
+//! generated with representative sizes so that this runs without our own background images.
+//!
+//! Run with `cargo bench`. The glyph compositing needs a TTF or OTF font which is taken from the
+//! `BENCH_FONT_FILEPATH` environment variable or some common system font locations.
+
+use chotto::{
+    card_image::{self, CardAssets, CardParams},
+    generator, ninety_ball,
+};
+use cottontail::math::Random;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use image::{
+    codecs::png::{CompressionType, FilterType, PngEncoder},
+    ColorType, RgbaImage,
+};
+
+/// A4 at 300 dpi which is what most of our backgrounds are
+const SHEET_WIDTH: u32 = 2480;
+const SHEET_HEIGHT: u32 = 3508;
+const GRID_LEFT_TOP_RIGHT_BOTTOM: (i32, i32, i32, i32) = (240, 1000, 2240, 3000);
+const FONT_SIZE: f32 = 180.0;
+
+const FONT_FILEPATH_CANDIDATES: &[&str] = &[
+    "C:/Windows/Fonts/arial.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/Library/Fonts/Arial.ttf",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+];
+
+fn bench_grid_generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("grid_generation");
+    for &count in &[1usize, 100, 1000] {
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::new("75_ball", count), &count, |b, &count| {
+            let mut random = Random::new_from_seed(1234);
+            b.iter(|| {
+                generator::create_random_number_grids(count, &[], &[(2, 2)], &[], &[], &mut random)
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("90_ball", count), &count, |b, &count| {
+            let mut random = Random::new_from_seed(1234);
+            b.iter(|| ninety_ball::create_random_books(count, &[], &[], &mut random))
+        });
+    }
+    group.finish();
+}
+
+fn bench_glyph_compositing(c: &mut Criterion) {
+    let font_bytes = match load_font_bytes() {
+        Some(font_bytes) => font_bytes,
+        None => {
+            log::warn!(
+                "Skipping the glyph compositing benchmark - please set \
+                 `BENCH_FONT_FILEPATH` to a TTF or OTF font"
+            );
+            return;
+        }
+    };
+    let background_bytes = encode_png(&create_background(), CompressionType::Fast, FilterType::Sub);
+    let assets = CardAssets::from_bytes(&background_bytes, &font_bytes).unwrap();
+
+    let mut group = c.benchmark_group("glyph_compositing");
+    group.sample_size(20);
+    let params_75_ball = CardParams::new_75_ball(GRID_LEFT_TOP_RIGHT_BOTTOM, FONT_SIZE);
+    group.bench_function("75_ball_card", |b| {
+        let mut seed = 0;
+        b.iter(|| {
+            seed += 1;
+            card_image::generate_card(&params_75_ball, &assets, seed)
+        })
+    });
+    // NOTE: 90-ball books have much smaller cells so we use a smaller font as well
+    let params_90_ball = CardParams::new_90_ball(GRID_LEFT_TOP_RIGHT_BOTTOM, FONT_SIZE / 4.0);
+    group.bench_function("90_ball_book", |b| {
+        let mut seed = 0;
+        b.iter(|| {
+            seed += 1;
+            card_image::generate_card(&params_90_ball, &assets, seed)
+        })
+    });
+    group.finish();
+}
+
+fn bench_png_encoding(c: &mut Criterion) {
+    let sheet = create_background();
+    let mut group = c.benchmark_group("png_encoding");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(sheet.as_raw().len() as u64));
+    // NOTE: These are the same settings that our `png_compression` levels use
+    for &(name, compression, filter) in &[
+        ("fast", CompressionType::Fast, FilterType::Sub),
+        ("default", CompressionType::Default, FilterType::Adaptive),
+        ("best", CompressionType::Best, FilterType::Adaptive),
+    ] {
+        group.bench_function(name, |b| b.iter(|| encode_png(&sheet, compression, filter)));
+    }
+    group.finish();
+}
+
+/// Returns a sheet with a gradient and a grid of lines so that it compresses about as well as our
+/// real backgrounds
+fn create_background() -> RgbaImage {
+    let (left, top, right, bottom) = GRID_LEFT_TOP_RIGHT_BOTTOM;
+    let cell_width = (right - left) / 5;
+    let cell_height = (bottom - top) / 5;
+    RgbaImage::from_fn(SHEET_WIDTH, SHEET_HEIGHT, |x, y| {
+        let (x, y) = (x as i32, y as i32);
+        let is_inside_grid = left <= x && x <= right && top <= y && y <= bottom;
+        let is_grid_line =
+            is_inside_grid && ((x - left) % cell_width < 8 || (y - top) % cell_height < 8);
+        if is_grid_line {
+            image::Rgba([40, 40, 40, 255])
+        } else {
+            let r = (255 * x / SHEET_WIDTH as i32) as u8;
+            let g = (255 * y / SHEET_HEIGHT as i32) as u8;
+            image::Rgba([r, g, 200, 255])
+        }
+    })
+}
+
+fn encode_png(image: &RgbaImage, compression: CompressionType, filter: FilterType) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    PngEncoder::new_with_quality(&mut bytes, compression, filter)
+        .encode(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            ColorType::Rgba8,
+        )
+        .unwrap();
+    bytes
+}
+
+fn load_font_bytes() -> Option<Vec<u8>> {
+    // NOTE: This must not start with `CHOTTO_` which would make it a draw parameter override
+    std::env::var("BENCH_FONT_FILEPATH")
+        .ok()
+        .into_iter()
+        .chain(
+            FONT_FILEPATH_CANDIDATES
+                .iter()
+                .map(|filepath| filepath.to_string()),
+        )
+        .find_map(|filepath| std::fs::read(filepath).ok())
+}
+
+criterion_group!(
+    benches,
+    bench_grid_generation,
+    bench_glyph_compositing,
+    bench_png_encoding
+);
+criterion_main!(benches);
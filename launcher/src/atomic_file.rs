@@ -0,0 +1,72 @@
+//! Writes files so that a crash or power loss never leaves a half-written file behind. Each file is
+//! first written and flushed to disk under a temporary name and only then renamed to its final
+//! name. A truncated sheet that slips into a print run can jam the RIP of the print shop.
+
+use std::io::Write;
+
+/// Unfinished files end with this extension so that nobody mistakes them for finished sheets
+const PARTIAL_FILE_EXTENSION: &str = "partial";
+
+/// Writes the given content to the given filepath atomically and creates its parent directories if
+/// necessary
+pub fn write_file_atomically(filepath: &str, content: &[u8]) {
+    try_write_file_atomically(filepath, content).unwrap_or_else(|error| panic!("{}", error));
+}
+
+/// Same as `write_file_atomically` but returns an error message instead of panicking for files we
+/// can do without
+pub fn try_write_file_atomically(filepath: &str, content: &[u8]) -> Result<(), String> {
+    if let Some(parent_directory) = std::path::Path::new(filepath).parent() {
+        std::fs::create_dir_all(parent_directory).map_err(|error| {
+            format!(
+                "Could not create directory '{}': {}",
+                parent_directory.display(),
+                error
+            )
+        })?;
+    }
+
+    let temp_filepath = format!("{}.{}", filepath, PARTIAL_FILE_EXTENSION);
+    std::fs::File::create(&temp_filepath)
+        .and_then(|mut file| {
+            file.write_all(content)?;
+            file.sync_all()
+        })
+        .map_err(|error| format!("Could not write file '{}': {}", temp_filepath, error))?;
+    std::fs::rename(&temp_filepath, filepath).map_err(|error| {
+        format!(
+            "Could not rename file '{}' to '{}': {}",
+            temp_filepath, filepath, error
+        )
+    })
+}
+
+/// Removes the unfinished files that an interrupted run left in the given directory and its
+/// subdirectories. Returns how many files were removed.
+pub fn remove_partial_files(directory: &str) -> usize {
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    let mut removed_count = 0;
+    for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+        if path.is_dir() {
+            removed_count += remove_partial_files(&path.to_string_lossy());
+        } else if path.extension().and_then(|extension| extension.to_str())
+            == Some(PARTIAL_FILE_EXTENSION)
+        {
+            match std::fs::remove_file(&path) {
+                Ok(()) => {
+                    log::warn!("Removed unfinished file '{}'", path.display());
+                    removed_count += 1;
+                }
+                Err(error) => log::warn!(
+                    "Could not remove unfinished file '{}': {}",
+                    path.display(),
+                    error
+                ),
+            }
+        }
+    }
+    removed_count
+}
@@ -0,0 +1,474 @@
+//! An append-only log of all our runs. Each entry contains the hash of its previous entry so that
+//! an auditor can check that no entry was changed or removed after the fact. Each entry also lists
+//! the hashes of all cards of its run and of all sheet files that it wrote so that the sheets can be
+//! compared against the log. The run manifest of each batch records how many entries our log had
+//! after its run so that removing the newest entries is noticed as well.
+//!
+//! Usage: `chotto verify-audit [--directory <dir>] [--seed <seed> --seed-nonce <nonce>]`
+
+use std::io::{Read, Write};
+
+use fs2::FileExt;
+
+use cottontail::core::serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    command_line_option_value,
+    manifest::{self, Card},
+    run_manifest,
+};
+
+pub const AUDIT_LOG_FILENAME: &str = "audit_log.jsonl";
+const SEED_NONCE_BYTE_COUNT: usize = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: String,
+    /// The command that created the cards, i.e. `create`, `extend` or `render-from`
+    pub command: String,
+    pub output_directory: String,
+    pub draw_parameters_hash: String,
+    /// The hash of our random seed and a random nonce. This lets us publish the log without giving
+    /// away the seed while still being able to prove later which seed was used. Empty for runs
+    /// with `rng = "secure"` which have no seed.
+    pub seed_commitment: String,
+    pub card_hashes: Vec<String>,
+    /// The written sheet files of the run. Empty for runs with `output_mode = "deltas"`.
+    // NOTE: Entries that were written before we logged our sheet files must keep their hash
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sheet_files: Vec<SheetFile>,
+    pub previous_entry_hash: String,
+    /// The hash of all fields above
+    pub entry_hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SheetFile {
+    pub filepath: String,
+    pub sha256: String,
+}
+
+impl SheetFile {
+    /// Hashes the given sheet file that was just written
+    pub fn new(filepath: &str) -> SheetFile {
+        let content = std::fs::read(filepath)
+            .unwrap_or_else(|error| panic!("Could not read file '{}': {}", filepath, error));
+        SheetFile {
+            filepath: filepath.to_owned(),
+            sha256: sha256_hex(&content),
+        }
+    }
+}
+
+/// What our run manifest needs to know about the entry that we appended for its run
+#[derive(Debug, Clone, Default)]
+pub struct AppendedEntry {
+    /// The nonce of the seed commitment which must be kept together with the seed. Empty if the
+    /// run has no seed.
+    pub seed_nonce: String,
+    /// The hash of the seed and its nonce that can be published before the seed. Empty if the run
+    /// has no seed.
+    pub seed_commitment: String,
+    /// How many entries our audit log has including the appended one
+    pub entry_count: usize,
+    pub entry_hash: String,
+}
+
+impl AuditEntry {
+    fn compute_hash(&self) -> String {
+        let entry_without_hash = AuditEntry {
+            entry_hash: String::new(),
+            ..self.clone()
+        };
+        sha256_hex(
+            serde_json::to_string(&entry_without_hash)
+                .unwrap()
+                .as_bytes(),
+        )
+    }
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+pub fn card_hash(card: &Card) -> String {
+    sha256_hex(serde_json::to_string(card).unwrap().as_bytes())
+}
+
+/// Returns the hash of the given seed followed by the given hex encoded nonce
+pub fn seed_commitment(seed: u64, seed_nonce: &str) -> String {
+    // NOTE: Our seeds are often based on the time and therefore easy to guess. Without the nonce
+    //       anyone could find our seed by hashing all likely seeds and comparing them to the
+    //       published commitment. This is why the nonce is only ever revealed with the seed.
+    let nonce_bytes = (0..seed_nonce.len())
+        .step_by(2)
+        .map(|index| {
+            seed_nonce
+                .get(index..index + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .unwrap_or_else(|| panic!("The seed nonce '{}' is not a hex number", seed_nonce))
+        })
+        .collect::<Vec<u8>>();
+    let mut bytes = seed.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&nonce_bytes);
+    sha256_hex(&bytes)
+}
+
+/// Returns a new hex encoded nonce from the secure random numbers of our operating system
+fn create_seed_nonce() -> String {
+    let mut nonce_bytes = [0u8; SEED_NONCE_BYTE_COUNT];
+    getrandom::getrandom(&mut nonce_bytes).unwrap_or_else(|error| {
+        panic!(
+            "Could not get secure random numbers from the operating system: {}",
+            error
+        )
+    });
+    nonce_bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn read_entries() -> Vec<AuditEntry> {
+    match std::fs::read_to_string(AUDIT_LOG_FILENAME) {
+        Ok(log_string) => parse_entries(&log_string),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn parse_entries(log_string: &str) -> Vec<AuditEntry> {
+    log_string
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(line_index, line)| {
+            serde_json::from_str(line).unwrap_or_else(|error| {
+                panic!(
+                    "Entry {} of '{}' is broken: {}",
+                    line_index + 1,
+                    AUDIT_LOG_FILENAME,
+                    error
+                )
+            })
+        })
+        .collect()
+}
+
+/// Appends an entry for the given run to the audit log in our working directory
+pub fn append_entry(
+    command: &str,
+    output_directory: &str,
+    draw_parameters: &str,
+    seed: Option<u64>,
+    cards: &[Card],
+    sheet_files: &[SheetFile],
+) -> AppendedEntry {
+    // NOTE: We hold an exclusive lock from reading the last entry until our entry is written.
+    //       Otherwise runs that finish at the same time (i.e. batches of `chotto serve`) would both
+    //       follow the same entry and fork our hash chain.
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(AUDIT_LOG_FILENAME)
+        .unwrap_or_else(|error| panic!("Could not open '{}': {}", AUDIT_LOG_FILENAME, error));
+    file.lock_exclusive()
+        .unwrap_or_else(|error| panic!("Could not lock '{}': {}", AUDIT_LOG_FILENAME, error));
+    let mut log_string = String::new();
+    file.read_to_string(&mut log_string)
+        .unwrap_or_else(|error| panic!("Could not read '{}': {}", AUDIT_LOG_FILENAME, error));
+    let previous_entries = parse_entries(&log_string);
+    let previous_entry_hash = previous_entries
+        .last()
+        .map(|entry| entry.entry_hash.clone())
+        .unwrap_or_default();
+    let seed_nonce = if seed.is_some() {
+        create_seed_nonce()
+    } else {
+        String::new()
+    };
+    let mut entry = AuditEntry {
+        timestamp: chrono::Local::now().to_rfc3339(),
+        command: command.to_owned(),
+        output_directory: output_directory.to_owned(),
+        draw_parameters_hash: sha256_hex(draw_parameters.as_bytes()),
+        seed_commitment: seed
+            .map(|seed| seed_commitment(seed, &seed_nonce))
+            .unwrap_or_default(),
+        card_hashes: cards.iter().map(card_hash).collect(),
+        sheet_files: sheet_files.to_vec(),
+        previous_entry_hash,
+        entry_hash: String::new(),
+    };
+    entry.entry_hash = entry.compute_hash();
+
+    writeln!(file, "{}", serde_json::to_string(&entry).unwrap())
+        .unwrap_or_else(|error| panic!("Could not write to '{}': {}", AUDIT_LOG_FILENAME, error));
+    file.unlock()
+        .unwrap_or_else(|error| panic!("Could not unlock '{}': {}", AUDIT_LOG_FILENAME, error));
+    AppendedEntry {
+        seed_nonce,
+        seed_commitment: entry.seed_commitment,
+        entry_count: previous_entries.len() + 1,
+        entry_hash: entry.entry_hash,
+    }
+}
+
+/// Checks the hash chain of our audit log and optionally whether the cards and sheet files in the
+/// given output directory match the log and which entries were created with the given seed
+pub fn verify_audit_log(args: &[String]) {
+    let entries = read_entries();
+    assert!(
+        !entries.is_empty(),
+        "There is no '{}' in the current directory",
+        AUDIT_LOG_FILENAME
+    );
+
+    let mut previous_entry_hash = String::new();
+    for (entry_index, entry) in entries.iter().enumerate() {
+        assert!(
+            entry.previous_entry_hash == previous_entry_hash,
+            "Entry {} of '{}' does not follow its previous entry - entries were removed or reordered",
+            entry_index + 1,
+            AUDIT_LOG_FILENAME
+        );
+        assert!(
+            entry.entry_hash == entry.compute_hash(),
+            "Entry {} of '{}' was changed after it was written",
+            entry_index + 1,
+            AUDIT_LOG_FILENAME
+        );
+        previous_entry_hash = entry.entry_hash.clone();
+    }
+    log::info!(
+        "The hash chain of all {} entries in '{}' is intact",
+        entries.len(),
+        AUDIT_LOG_FILENAME
+    );
+
+    if let Some(seed) = command_line_option_value(args, "--seed") {
+        let seed_nonce = command_line_option_value(args, "--seed-nonce").unwrap_or_else(|| {
+            panic!(
+                "Please specify the nonce of the seed via `--seed-nonce <nonce>` - it is listed \
+                 next to the seed in '{}'",
+                run_manifest::RUN_MANIFEST_FILENAME
+            )
+        });
+        let commitment = seed_commitment(
+            seed.parse::<u64>()
+                .unwrap_or_else(|_| panic!("Invalid seed '{}'", seed)),
+            &seed_nonce,
+        );
+        let matching_entry_numbers: Vec<usize> = entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.seed_commitment == commitment)
+            .map(|(entry_index, _)| entry_index + 1)
+            .collect();
+        assert!(
+            !matching_entry_numbers.is_empty(),
+            "No entry of '{}' was created with seed {} and nonce '{}'",
+            AUDIT_LOG_FILENAME,
+            seed,
+            seed_nonce
+        );
+        log::info!(
+            "The entries {:?} of '{}' were created with seed {}",
+            matching_entry_numbers,
+            AUDIT_LOG_FILENAME,
+            seed
+        );
+    }
+
+    if let Some(directory) = command_line_option_value(args, "--directory") {
+        // NOTE: Only `extend` adds to an existing batch. Every other command starts a new one.
+        let batch_start_index = entries
+            .iter()
+            .rposition(|entry| entry.output_directory == directory && entry.command != "extend")
+            .unwrap_or_else(|| {
+                panic!(
+                    "There is no entry for '{}' in '{}'",
+                    directory, AUDIT_LOG_FILENAME
+                )
+            });
+        let logged_card_hashes: Vec<&String> = entries[batch_start_index..]
+            .iter()
+            .filter(|entry| entry.output_directory == directory)
+            .flat_map(|entry| entry.card_hashes.iter())
+            .collect();
+        let cards = manifest::read_manifest(&directory).cards;
+        assert!(
+            cards.len() == logged_card_hashes.len(),
+            "'{}' contains {} sheets but '{}' lists {}",
+            directory,
+            cards.len(),
+            AUDIT_LOG_FILENAME,
+            logged_card_hashes.len()
+        );
+        for (card, logged_card_hash) in cards.iter().zip(logged_card_hashes) {
+            assert!(
+                card_hash(card) == *logged_card_hash,
+                "Sheet {} in '{}' does not match '{}' - it was changed after it was created",
+                card.sheet_number,
+                directory,
+                AUDIT_LOG_FILENAME
+            );
+        }
+
+        let mut missing_sheet_file_count = 0;
+        for sheet_file in entries[batch_start_index..]
+            .iter()
+            .filter(|entry| entry.output_directory == directory)
+            .flat_map(|entry| entry.sheet_files.iter())
+        {
+            // NOTE: Sheets that were uploaded to a bucket are usually not on our disk anymore
+            match std::fs::read(&sheet_file.filepath) {
+                Ok(content) => assert!(
+                    sha256_hex(&content) == sheet_file.sha256,
+                    "The sheet file '{}' does not match '{}' - it was changed after it was written",
+                    sheet_file.filepath,
+                    AUDIT_LOG_FILENAME
+                ),
+                Err(_) => missing_sheet_file_count += 1,
+            }
+        }
+        if missing_sheet_file_count > 0 {
+            log::warn!(
+                "Could not check {} sheet files of '{}' because they are not on our disk",
+                missing_sheet_file_count,
+                directory
+            );
+        }
+
+        verify_run_manifest_entries(&entries, &directory);
+        log::info!(
+            "All {} sheets in '{}' match '{}'",
+            cards.len(),
+            directory,
+            AUDIT_LOG_FILENAME
+        );
+    }
+
+    #[cfg(not(debug_assertions))]
+    crate::show_messagebox("Chotto", "The audit log is intact.", false);
+}
+
+/// Checks that our audit log still contains the entry that each run of the given batch recorded in
+/// its run manifest. A removed newest entry leaves an intact hash chain and is only noticed here.
+fn verify_run_manifest_entries(entries: &[AuditEntry], directory: &str) {
+    let filepath = format!("{}/{}", directory, run_manifest::RUN_MANIFEST_FILENAME);
+    let run_manifest: run_manifest::RunManifest = match std::fs::read_to_string(&filepath) {
+        Ok(manifest_string) => toml::from_str(&manifest_string)
+            .unwrap_or_else(|error| panic!("Could not read file '{}': {}", filepath, error)),
+        Err(_) => {
+            log::warn!(
+                "There is no '{}' so we cannot check whether entries were removed from the end \
+                 of '{}'",
+                filepath,
+                AUDIT_LOG_FILENAME
+            );
+            return;
+        }
+    };
+    for run in run_manifest
+        .runs
+        .iter()
+        .filter(|run| run.audit_entry_count > 0)
+    {
+        assert!(
+            entries.len() >= run.audit_entry_count,
+            "'{}' has only {} entries but had {} after the `{}` run of {} - entries were removed",
+            AUDIT_LOG_FILENAME,
+            entries.len(),
+            run.audit_entry_count,
+            run.command,
+            run.timestamp
+        );
+        assert!(
+            entries[run.audit_entry_count - 1].entry_hash == run.audit_entry_hash,
+            "Entry {} of '{}' is not the one that the `{}` run of {} wrote - entries were removed \
+             or replaced",
+            run.audit_entry_count,
+            AUDIT_LOG_FILENAME,
+            run.command,
+            run.timestamp
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seed_commitments_depend_on_their_nonce() {
+        let seed_nonce = create_seed_nonce();
+        assert_eq!(seed_nonce.len(), 2 * SEED_NONCE_BYTE_COUNT);
+        assert_ne!(create_seed_nonce(), seed_nonce);
+
+        let commitment = seed_commitment(1234, &seed_nonce);
+        assert_eq!(seed_commitment(1234, &seed_nonce), commitment);
+        assert_ne!(seed_commitment(1235, &seed_nonce), commitment);
+        assert_ne!(seed_commitment(1234, &create_seed_nonce()), commitment);
+        assert_ne!(sha256_hex(&1234u64.to_le_bytes()), commitment);
+    }
+
+    fn entry(previous_entry_hash: &str) -> AuditEntry {
+        let mut entry = AuditEntry {
+            timestamp: String::new(),
+            command: "create".to_owned(),
+            output_directory: String::new(),
+            draw_parameters_hash: String::new(),
+            seed_commitment: String::new(),
+            card_hashes: Vec::new(),
+            sheet_files: Vec::new(),
+            previous_entry_hash: previous_entry_hash.to_owned(),
+            entry_hash: String::new(),
+        };
+        entry.entry_hash = entry.compute_hash();
+        entry
+    }
+
+    #[test]
+    #[should_panic(expected = "entries were removed")]
+    fn notices_removed_newest_entries() {
+        let directory = std::env::temp_dir()
+            .join("chotto_test_audit_log_removed_entries")
+            .to_string_lossy()
+            .to_string();
+        std::fs::create_dir_all(&directory).unwrap();
+        let first_entry = entry("");
+        let second_entry = entry(&first_entry.entry_hash);
+        crate::run_manifest::write_run_manifest(
+            &directory,
+            "create",
+            &crate::DrawParams::default(),
+            None,
+            &AppendedEntry {
+                seed_nonce: String::new(),
+                seed_commitment: String::new(),
+                entry_count: 2,
+                entry_hash: second_entry.entry_hash,
+            },
+            (1, 10),
+            &[],
+        );
+
+        let result = std::panic::catch_unwind(|| {
+            verify_run_manifest_entries(&[first_entry.clone()], &directory)
+        });
+        std::fs::remove_dir_all(&directory).ok();
+        std::panic::resume_unwind(result.unwrap_err());
+    }
+
+    #[test]
+    fn keeps_the_hash_of_entries_without_sheet_files() {
+        let entry_string = serde_json::to_string(&entry("")).unwrap();
+        assert!(!entry_string.contains("sheet_files"));
+    }
+}
@@ -0,0 +1,94 @@
+//! Code128 barcodes that can be read by the handheld scanners at Bingo halls
+
+use cottontail::image::{Bitmap, PixelRGBA};
+
+use crate::fill_rect;
+
+/// The widths of the alternating bars and spaces of each Code128 symbol value
+const CODE128_PATTERNS: [&str; 107] = [
+    "212222", "222122", "222221", "121223", "121322", "131222", "122213", "122312", "132212",
+    "221213", "221312", "231212", "112232", "122132", "122231", "113222", "123122", "123221",
+    "223211", "221132", "221231", "213212", "223112", "312131", "311222", "321122", "321221",
+    "312212", "322112", "322211", "212123", "212321", "232121", "111323", "131123", "131321",
+    "112313", "132113", "132311", "211313", "231113", "231311", "112133", "112331", "132131",
+    "113123", "113321", "133121", "313121", "211331", "231131", "213113", "213311", "213131",
+    "311123", "311321", "331121", "312113", "312311", "332111", "314111", "221411", "431111",
+    "111224", "111422", "121124", "121421", "141122", "141221", "112214", "112412", "122114",
+    "122411", "142112", "142211", "241211", "221114", "413111", "241112", "134111", "111242",
+    "121142", "121241", "114212", "124112", "124211", "411212", "421112", "421211", "212141",
+    "214121", "412121", "111143", "111341", "131141", "114113", "114311", "411113", "411311",
+    "113141", "114131", "311141", "411131", "211412", "211214", "211232", "2331112",
+];
+const CODE128_START_B: usize = 104;
+const CODE128_STOP: usize = 106;
+/// The minimum blank space left and right of the barcode in modules
+const QUIET_ZONE_MODULES: usize = 10;
+
+/// Returns for each module (the thinnest bar width) of the barcode whether it is black. The text
+/// is encoded with the Code128 B character set which contains all printable ASCII characters.
+pub fn code128_modules(text: &str) -> Vec<bool> {
+    let values: Vec<usize> = text
+        .chars()
+        .map(|character| {
+            assert!(
+                (' '..='~').contains(&character),
+                "Cannot encode character '{}' of '{}' as Code128 barcode",
+                character,
+                text
+            );
+            character as usize - ' ' as usize
+        })
+        .collect();
+    let checksum = values
+        .iter()
+        .enumerate()
+        .fold(CODE128_START_B, |sum, (index, value)| {
+            sum + (index + 1) * value
+        })
+        % 103;
+
+    let mut symbols = vec![CODE128_START_B];
+    symbols.extend(values);
+    symbols.push(checksum);
+    symbols.push(CODE128_STOP);
+
+    let mut modules = vec![false; QUIET_ZONE_MODULES];
+    for symbol in symbols {
+        for (index, width) in CODE128_PATTERNS[symbol].chars().enumerate() {
+            let is_bar = index % 2 == 0;
+            let width = width.to_digit(10).unwrap() as usize;
+            modules.extend(std::iter::repeat(is_bar).take(width));
+        }
+    }
+    modules.extend(std::iter::repeat(false).take(QUIET_ZONE_MODULES));
+    modules
+}
+
+/// Returns an opaque black-on-white barcode bitmap including its quiet zone
+pub fn create_code128_bitmap(text: &str, module_width: u32, height: u32) -> Bitmap {
+    let modules = code128_modules(text);
+    let module_width = module_width as i32;
+    let height = height as i32;
+    let mut bitmap = Bitmap::new(modules.len() as u32 * module_width as u32, height as u32);
+    fill_rect(
+        &mut bitmap,
+        0,
+        0,
+        bitmap.width as i32,
+        height,
+        PixelRGBA::white(),
+    );
+    for (index, is_bar) in modules.iter().enumerate() {
+        if *is_bar {
+            fill_rect(
+                &mut bitmap,
+                index as i32 * module_width,
+                0,
+                module_width,
+                height,
+                PixelRGBA::black(),
+            );
+        }
+    }
+    bitmap
+}
@@ -0,0 +1,139 @@
+//! Measures how long the stages of a run take with our own background, font and draw parameters.
+//! This is not part of our documentation as it is meant for comparing performance changes of
+//! Chotto itself. The criterion benchmarks in `benches/` cover the same stages with generated
+//! assets.
+//!
+//! Usage: `chotto bench [--sheets <count>]`
+
+use cottontail::math::Random;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::{
+    command_line_option_value, create_cards,
+    png_writer::{self, PngCompression},
+    sheet_canvas::{ComposedSheet, SheetRegion},
+    Input, SheetRenderer,
+};
+
+const DEFAULT_BENCH_SHEET_COUNT: usize = 100;
+/// Encoding is slow so we only encode a few sheets per compression level
+const ENCODED_SHEET_COUNT: usize = 4;
+/// A fixed seed keeps the generated cards the same between runs
+const BENCH_SEED: u64 = 1234;
+
+pub fn run_bench(args: &[String]) {
+    let sheet_count = command_line_option_value(args, "--sheets")
+        .map(|value| {
+            value
+                .parse::<usize>()
+                .ok()
+                .filter(|&count| count > 0)
+                .unwrap_or_else(|| panic!("Invalid sheet count '{}'", value))
+        })
+        .unwrap_or(DEFAULT_BENCH_SHEET_COUNT);
+
+    let Input {
+        background_bitmap,
+        font,
+        font_hash,
+        header_font,
+        params,
+        ..
+    } = Input::new();
+
+    let start_time = std::time::Instant::now();
+    let sheet_renderer = SheetRenderer::new(
+        &background_bitmap,
+        &font,
+        font_hash,
+        header_font.as_ref(),
+        &params,
+        None,
+    );
+    let setup_seconds = start_time.elapsed().as_secs_f64();
+
+    let start_time = std::time::Instant::now();
+    let mut random = Random::new_from_seed(BENCH_SEED);
+    let cards = create_cards(&sheet_renderer, &[], sheet_count, &mut random);
+    let card_seconds = start_time.elapsed().as_secs_f64();
+
+    let start_time = std::time::Instant::now();
+    let sheet_regions: Vec<SheetRegion> = cards
+        .iter()
+        .take(ENCODED_SHEET_COUNT)
+        .map(|card| sheet_renderer.draw_sheet_region(card))
+        .collect();
+    let single_thread_draw_seconds =
+        start_time.elapsed().as_secs_f64() / sheet_regions.len() as f64;
+
+    let start_time = std::time::Instant::now();
+    cards.par_iter().for_each(|card| {
+        sheet_renderer.draw_sheet_region(card);
+    });
+    let parallel_draw_seconds = start_time.elapsed().as_secs_f64();
+
+    let sheet_background = sheet_renderer
+        .unpremultiplied_sheet(&sheet_renderer.finished_background)
+        .unwrap_or_else(|| sheet_renderer.finished_background.clone());
+    let sheet_regions: Vec<SheetRegion> = sheet_regions
+        .into_iter()
+        .map(|sheet_region| sheet_renderer.unpremultiplied_region(sheet_region))
+        .collect();
+
+    let temp_filepath = std::env::temp_dir()
+        .join("chotto_bench.png")
+        .to_string_lossy()
+        .to_string();
+    let encode_seconds: Vec<(PngCompression, f64)> = [
+        PngCompression::Fast,
+        PngCompression::Default,
+        PngCompression::Best,
+    ]
+    .iter()
+    .map(|&compression| {
+        let start_time = std::time::Instant::now();
+        for sheet_region in &sheet_regions {
+            png_writer::write_composed_png_file(
+                &ComposedSheet::new(&sheet_background, sheet_region),
+                &temp_filepath,
+                compression,
+            );
+        }
+        (
+            compression,
+            start_time.elapsed().as_secs_f64() / sheet_regions.len() as f64,
+        )
+    })
+    .collect();
+    std::fs::remove_file(&temp_filepath).ok();
+
+    log::info!(
+        "Benchmark with {} sheets of {}x{}px on {} threads:",
+        sheet_count,
+        sheet_renderer.background.width,
+        sheet_renderer.background.height,
+        rayon::current_num_threads()
+    );
+    log::info!("  Preparing the renderer:  {:8.3}s", setup_seconds);
+    log::info!(
+        "  Creating the cards:      {:8.3}s ({:.3}ms per card)",
+        card_seconds,
+        1000.0 * card_seconds / sheet_count as f64
+    );
+    log::info!(
+        "  Drawing one sheet:       {:8.3}ms on a single thread",
+        1000.0 * single_thread_draw_seconds
+    );
+    log::info!(
+        "  Drawing all sheets:      {:8.3}s ({:.1} sheets per second)",
+        parallel_draw_seconds,
+        sheet_count as f64 / parallel_draw_seconds
+    );
+    for (compression, seconds) in encode_seconds {
+        log::info!(
+            "  Encoding one sheet:      {:8.3}ms with {:?} compression",
+            1000.0 * seconds,
+            compression
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Keeps rasterized number bitmaps on disk so that repeated runs with the same font, size and
+//! colors don't need to rasterize them again
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use cottontail::image::{Bitmap, PixelRGBA};
+use image::codecs::png::PngEncoder;
+
+use crate::atomic_file;
+
+const CACHE_DIRECTORY_NAME: &str = "chotto_cache";
+
+/// Returns a hash of the given data to be used as part of a cache key
+///
+/// NOTE: The hash may change with new Rust versions which only means that we rasterize our
+///       numbers again once
+pub fn hash_of<T: Hash + ?Sized>(data: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn cache_directory(cache_key: u64) -> std::path::PathBuf {
+    std::env::temp_dir()
+        .join(CACHE_DIRECTORY_NAME)
+        .join(format!("numbers_{:016x}", cache_key))
+}
+
+/// Returns the cached premultiplied bitmaps of the given numbers if all of them are cached and
+/// readable
+pub fn load_number_bitmaps(cache_key: u64, numbers: &[i32]) -> Option<HashMap<i32, Bitmap>> {
+    let directory = cache_directory(cache_key);
+    let mut number_bitmaps = HashMap::new();
+    for &number in numbers {
+        let filepath = directory.join(format!("{}.png", number));
+        if !filepath.exists() {
+            return None;
+        }
+        // NOTE: The cache is only an optimization so we just rasterize our numbers again if a
+        //       cached file is damaged
+        let bitmap = match read_png_file(&filepath) {
+            Ok(bitmap) => bitmap,
+            Err(error) => {
+                log::debug!(
+                    "Could not read cached number bitmap '{}': {}",
+                    filepath.display(),
+                    error
+                );
+                return None;
+            }
+        };
+        number_bitmaps.insert(number, bitmap);
+    }
+    log::debug!(
+        "Loaded cached number bitmaps from '{}'",
+        directory.display()
+    );
+    Some(number_bitmaps)
+}
+
+pub fn store_number_bitmaps(cache_key: u64, number_bitmaps: &HashMap<i32, Bitmap>) {
+    let directory = cache_directory(cache_key);
+    for (number, bitmap) in number_bitmaps {
+        let filepath = directory.join(format!("{}.png", number));
+        // NOTE: We write atomically because another run may read our cache at the same time
+        let result = encode_png(bitmap).and_then(|file_bytes| {
+            atomic_file::try_write_file_atomically(&filepath.to_string_lossy(), &file_bytes)
+        });
+        if let Err(error) = result {
+            // NOTE: The cache is only an optimization so we don't stop here
+            log::debug!(
+                "Could not cache number bitmap '{}': {}",
+                filepath.display(),
+                error
+            );
+            return;
+        }
+    }
+    log::debug!("Cached number bitmaps in '{}'", directory.display());
+}
+
+/// NOTE: We store the premultiplied pixels as they are so that we don't lose precision
+fn encode_png(bitmap: &Bitmap) -> Result<Vec<u8>, String> {
+    let mut pixel_bytes = Vec::with_capacity(4 * bitmap.data.len());
+    for pixel in &bitmap.data {
+        pixel_bytes.extend_from_slice(&[pixel.r, pixel.g, pixel.b, pixel.a]);
+    }
+    let mut file_bytes = Vec::new();
+    PngEncoder::new(&mut file_bytes)
+        .encode(
+            &pixel_bytes,
+            bitmap.width,
+            bitmap.height,
+            image::ColorType::Rgba8,
+        )
+        .map_err(|error| format!("Could not encode PNG: {}", error))?;
+    Ok(file_bytes)
+}
+
+fn read_png_file(filepath: &std::path::Path) -> Result<Bitmap, String> {
+    let file_bytes = std::fs::read(filepath).map_err(|error| error.to_string())?;
+    let image = image::load_from_memory_with_format(&file_bytes, image::ImageFormat::Png)
+        .map_err(|error| error.to_string())?
+        .to_rgba8();
+    let mut bitmap = Bitmap::new(image.width(), image.height());
+    for (pixel, image_pixel) in bitmap.data.iter_mut().zip(image.pixels()) {
+        let [r, g, b, a] = image_pixel.0;
+        *pixel = PixelRGBA::new(r, g, b, a);
+    }
+    Ok(bitmap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regenerates_damaged_cache_entries() {
+        let cache_key = hash_of("regenerates_damaged_cache_entries");
+        let mut bitmap = Bitmap::new(3, 2);
+        bitmap.data[4] = PixelRGBA::new(10, 20, 30, 40);
+        let mut number_bitmaps = HashMap::new();
+        number_bitmaps.insert(7, bitmap.clone());
+
+        store_number_bitmaps(cache_key, &number_bitmaps);
+        let loaded = load_number_bitmaps(cache_key, &[7]).unwrap();
+        assert_eq!(loaded[&7].data, bitmap.data);
+
+        let filepath = cache_directory(cache_key).join("7.png");
+        let file_bytes = std::fs::read(&filepath).unwrap();
+        std::fs::write(&filepath, &file_bytes[..file_bytes.len() / 2]).unwrap();
+        assert!(load_number_bitmaps(cache_key, &[7]).is_none());
+
+        store_number_bitmaps(cache_key, &number_bitmaps);
+        assert!(load_number_bitmaps(cache_key, &[7]).is_some());
+        let _ = std::fs::remove_dir_all(cache_directory(cache_key));
+    }
+}
@@ -0,0 +1,419 @@
+//! A chat bot for streamed games. It hands out the cards of our manifest to the viewers of a Twitch
+//! channel, lets the host call numbers from the chat and announces the winners right away.
+//!
+//! Usage: `chotto bot --channel <channel> [--nick <bot account>] [--caller <user>]
+//!         [--directory <dir>]`
+//!
+//! Chat commands:
+//! - `!card` gives the viewer the next free card or repeats the card they already have
+//! - `!check` tells the viewer how many numbers of their card are still missing
+//! - `!calls` lists the numbers that were called so far
+//! - `!call 17` calls a number (only the channel owner and the given caller)
+//! - `!newgame` forgets all calls and winners but keeps the cards of the viewers (same as above)
+//!
+//! Which viewer has which card and the numbers called so far are kept in the `bot_session.json`
+//! file next to our sheets so that a restarted bot continues the same game.
+//!
+//! NOTE: Only Twitch chat is supported. It speaks IRC which we talk to via a TLS connection so
+//!       that our OAuth token is never sent in plain text. Discord needs a websocket connection to
+//!       its gateway which we do not have yet.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::{BufRead, BufReader, ErrorKind, Write},
+    net::TcpStream,
+    sync::Arc,
+    time::Duration,
+};
+
+use cottontail::core::serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    atomic_file, cancellation, claims, command_line_option_value, load_draw_params, manifest,
+    server::CardRegistry, OUTPUT_SHEETS_DIRECTORY,
+};
+
+pub const TWITCH_TOKEN_ENVIRONMENT_VARIABLE: &str = "CHOTTO_TWITCH_TOKEN";
+const TWITCH_CHAT_HOST: &str = "irc.chat.twitch.tv";
+/// The TLS port of the Twitch chat
+const TWITCH_CHAT_PORT: u16 = 6697;
+const SESSION_FILENAME: &str = "bot_session.json";
+/// Twitch drops longer chat messages
+const MAX_MESSAGE_LENGTH: usize = 500;
+/// How long we wait between reconnects after the chat connection broke
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// Lets us notice a Ctrl+C while waiting for chat messages
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct BotSession {
+    /// The serial number of the card of each viewer
+    cards_by_user: BTreeMap<String, String>,
+    called_numbers: Vec<i32>,
+    /// The viewers that were already announced as winners of the current game
+    winners: Vec<String>,
+}
+
+impl BotSession {
+    fn load(filepath: &str) -> BotSession {
+        match std::fs::read_to_string(filepath) {
+            Ok(content) => serde_json::from_str(&content)
+                .unwrap_or_else(|error| panic!("Could not read file '{}': {}", filepath, error)),
+            Err(_) => BotSession::default(),
+        }
+    }
+
+    fn save(&self, filepath: &str) {
+        let content = serde_json::to_string_pretty(self).unwrap();
+        atomic_file::write_file_atomically(filepath, content.as_bytes());
+    }
+}
+
+struct Bot {
+    registry: CardRegistry,
+    session: BotSession,
+    session_filepath: String,
+    /// The users that may call numbers and start a new game
+    callers: Vec<String>,
+    /// The highest number that can be called
+    max_number: i32,
+}
+
+impl Bot {
+    /// Returns our replies to the given chat message
+    fn handle_message(&mut self, user: &str, text: &str) -> Vec<String> {
+        let mut words = text.split_whitespace();
+        let command = words.next().unwrap_or_default().to_lowercase();
+        let is_caller = self.callers.iter().any(|caller| caller == user);
+        let replies = match (command.as_str(), is_caller) {
+            ("!card", _) => self.hand_out_card(user),
+            ("!check", _) => self.check_card(user),
+            ("!calls", _) => vec![self.called_numbers_text()],
+            ("!call", true) => match words.next().and_then(|word| word.parse::<i32>().ok()) {
+                Some(number) if (1..=self.max_number).contains(&number) => self.call_number(number),
+                Some(_) => vec![format!(
+                    "@{} please call a number between 1 and {}",
+                    user, self.max_number
+                )],
+                None => vec![format!("@{} usage: !call <number>", user)],
+            },
+            ("!newgame", true) => {
+                self.session.called_numbers.clear();
+                self.session.winners.clear();
+                vec!["A new game starts - all calls are cleared, keep your cards!".to_owned()]
+            }
+            _ => return Vec::new(),
+        };
+        self.session.save(&self.session_filepath);
+        replies
+    }
+
+    fn hand_out_card(&mut self, user: &str) -> Vec<String> {
+        if !self.session.cards_by_user.contains_key(user) {
+            let handed_out_serials: HashSet<&String> =
+                self.session.cards_by_user.values().collect();
+            let free_card = self
+                .registry
+                .cards
+                .iter()
+                .find(|card| !handed_out_serials.contains(&card.serial));
+            match free_card {
+                Some(card) => {
+                    log::info!("Handed out card '{}' to '{}'", card.serial, user);
+                    let serial = card.serial.clone();
+                    self.session.cards_by_user.insert(user.to_owned(), serial);
+                }
+                None => return vec![format!("@{} sorry, all cards are taken", user)],
+            }
+        }
+
+        let serial = &self.session.cards_by_user[user];
+        let card = self.registry.find(serial).unwrap();
+        let rows: Vec<String> = card
+            .numbers
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|number| match number {
+                        0 => "-".to_owned(),
+                        number => number.to_string(),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            })
+            .collect();
+        split_message(&format!(
+            "@{} your card {}: {}",
+            user,
+            serial,
+            rows.join(" | ")
+        ))
+    }
+
+    fn check_card(&self, user: &str) -> Vec<String> {
+        let card = match self.session.cards_by_user.get(user) {
+            Some(serial) => self.registry.find(serial).unwrap(),
+            None => return vec![format!("@{} you have no card yet - type !card", user)],
+        };
+        let called_numbers: HashSet<i32> = self.session.called_numbers.iter().cloned().collect();
+        let result = claims::check_claim(card, &called_numbers, self.registry.variant);
+        if result.win {
+            vec![format!(
+                "@{} your card {} wins ({})",
+                user,
+                card.serial,
+                result.completed_lines.join(", ")
+            )]
+        } else {
+            vec![format!(
+                "@{} your card {} still misses {} numbers",
+                user,
+                card.serial,
+                result.uncalled_numbers.len()
+            )]
+        }
+    }
+
+    fn call_number(&mut self, number: i32) -> Vec<String> {
+        if self.session.called_numbers.contains(&number) {
+            return vec![format!("{} was already called", number)];
+        }
+        self.session.called_numbers.push(number);
+        log::info!("Called number {}", number);
+
+        let mut replies = vec![format!(
+            "Number {} ({} calls so far)",
+            number,
+            self.session.called_numbers.len()
+        )];
+        let called_numbers: HashSet<i32> = self.session.called_numbers.iter().cloned().collect();
+        for (user, serial) in &self.session.cards_by_user {
+            if self.session.winners.contains(user) {
+                continue;
+            }
+            let card = self.registry.find(serial).unwrap();
+            let result = claims::check_claim(card, &called_numbers, self.registry.variant);
+            if result.win {
+                log::info!("Card '{}' of '{}' wins", serial, user);
+                replies.push(format!(
+                    "BINGO! @{} wins with card {} ({})",
+                    user,
+                    serial,
+                    result.completed_lines.join(", ")
+                ));
+                self.session.winners.push(user.clone());
+            }
+        }
+        replies
+    }
+
+    fn called_numbers_text(&self) -> String {
+        if self.session.called_numbers.is_empty() {
+            return "No numbers were called yet".to_owned();
+        }
+        let numbers: Vec<String> = self
+            .session
+            .called_numbers
+            .iter()
+            .map(|number| number.to_string())
+            .collect();
+        format!("Called so far: {}", numbers.join(" "))
+    }
+}
+
+/// Splits the given text at spaces into messages that Twitch accepts
+fn split_message(text: &str) -> Vec<String> {
+    let mut messages = vec![String::new()];
+    for word in text.split(' ') {
+        let message = messages.last_mut().unwrap();
+        if !message.is_empty() && message.len() + 1 + word.len() > MAX_MESSAGE_LENGTH {
+            messages.push(word.to_owned());
+        } else {
+            if !message.is_empty() {
+                message.push(' ');
+            }
+            message.push_str(word);
+        }
+    }
+    messages
+}
+
+/// Returns the sender and text of a chat message line like
+/// `:user!user@user.tmi.twitch.tv PRIVMSG #channel :hello`
+fn parse_chat_message(line: &str) -> Option<(&str, &str)> {
+    let line = line.strip_prefix(':')?;
+    let (prefix, rest) = line.split_once(' ')?;
+    let rest = rest.strip_prefix("PRIVMSG ")?;
+    let (_channel, text) = rest.split_once(" :")?;
+    let user = prefix.split('!').next()?;
+    Some((user, text))
+}
+
+type ChatStream = rustls::StreamOwned<rustls::ClientSession, TcpStream>;
+
+fn connect_to_chat() -> std::io::Result<ChatStream> {
+    let socket = TcpStream::connect((TWITCH_CHAT_HOST, TWITCH_CHAT_PORT))?;
+    let mut config = rustls::ClientConfig::new();
+    config
+        .root_store
+        .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+    let host = webpki::DNSNameRef::try_from_ascii_str(TWITCH_CHAT_HOST)
+        .expect("Our chat host is a valid DNS name");
+    let session = rustls::ClientSession::new(&Arc::new(config), host);
+    Ok(rustls::StreamOwned::new(session, socket))
+}
+
+fn send_line(stream: &mut ChatStream, line: &str) -> std::io::Result<()> {
+    stream.write_all(format!("{}\r\n", line).as_bytes())?;
+    stream.flush()
+}
+
+/// Joins the chat of the channel and handles its messages until the connection breaks. Returns
+/// whether the user asked us to stop.
+fn run_chat_session(
+    bot: &mut Bot,
+    channel: &str,
+    nick: &str,
+    token: &str,
+) -> std::io::Result<bool> {
+    let mut stream = connect_to_chat()?;
+    // NOTE: Our first write finishes the TLS handshake. We only set our read timeout afterwards so
+    //       that a slow handshake is not mistaken for a broken connection.
+    send_line(&mut stream, &format!("PASS oauth:{}", token))?;
+    send_line(&mut stream, &format!("NICK {}", nick))?;
+    send_line(&mut stream, &format!("JOIN #{}", channel))?;
+    stream.sock.set_read_timeout(Some(READ_TIMEOUT))?;
+    log::info!("Joined the chat of '{}' as '{}'", channel, nick);
+
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    loop {
+        if cancellation::is_cancelled() {
+            return Ok(true);
+        }
+        // NOTE: A read timeout keeps the part of the line that was read so far in `line`
+        match reader.read_line(&mut line) {
+            Ok(0) => return Ok(false),
+            Ok(_) => {}
+            Err(error)
+                if error.kind() == ErrorKind::WouldBlock || error.kind() == ErrorKind::TimedOut =>
+            {
+                continue
+            }
+            Err(error) => return Err(error),
+        }
+
+        let message = line.trim_end();
+        if let Some(server) = message.strip_prefix("PING ") {
+            send_line(reader.get_mut(), &format!("PONG {}", server))?;
+        } else if message.contains(" NOTICE * :Login authentication failed") {
+            panic!(
+                "Twitch did not accept our login - please check the token in the `{}` environment \
+                 variable",
+                TWITCH_TOKEN_ENVIRONMENT_VARIABLE
+            );
+        } else if let Some((user, text)) = parse_chat_message(message) {
+            for reply in bot.handle_message(&user.to_lowercase(), text) {
+                send_line(
+                    reader.get_mut(),
+                    &format!("PRIVMSG #{} :{}", channel, reply),
+                )?;
+            }
+        }
+        line.clear();
+    }
+}
+
+pub fn run_bot(args: &[String]) {
+    let directory = command_line_option_value(args, "--directory")
+        .unwrap_or_else(|| OUTPUT_SHEETS_DIRECTORY.to_owned());
+    let channel = command_line_option_value(args, "--channel")
+        .expect("Please give the Twitch channel via `--channel <channel>` - only Twitch chat is supported")
+        .to_lowercase();
+    let nick = command_line_option_value(args, "--nick")
+        .map(|nick| nick.to_lowercase())
+        .unwrap_or_else(|| channel.clone());
+    let token = std::env::var(TWITCH_TOKEN_ENVIRONMENT_VARIABLE).unwrap_or_else(|_| {
+        panic!(
+            "Please set the OAuth token of the bot account via the `{}` environment variable",
+            TWITCH_TOKEN_ENVIRONMENT_VARIABLE
+        )
+    });
+    let token = token.trim_start_matches("oauth:");
+
+    let mut callers = vec![channel.clone()];
+    if let Some(caller) = command_line_option_value(args, "--caller") {
+        callers.push(caller.to_lowercase());
+    }
+
+    let params = load_draw_params();
+    let cards = manifest::read_manifest(&directory).cards;
+    let registry = CardRegistry::new(cards, params.bingo_variant);
+    let session_filepath = format!("{}/{}", directory, SESSION_FILENAME);
+    let mut session = BotSession::load(&session_filepath);
+    // NOTE: The sheets might have been created again since the last session
+    session.cards_by_user.retain(|user, serial| {
+        let exists = registry.find(serial).is_some();
+        if !exists {
+            log::warn!("The card '{}' of '{}' does not exist anymore", serial, user);
+        }
+        exists
+    });
+    log::info!(
+        "Handing out {} cards of '{}' where {} are already taken",
+        registry.cards.len(),
+        directory,
+        session.cards_by_user.len()
+    );
+    let mut bot = Bot {
+        registry,
+        session,
+        session_filepath,
+        callers,
+        max_number: params.max_number(),
+    };
+
+    loop {
+        match run_chat_session(&mut bot, &channel, &nick, token) {
+            Ok(true) => break,
+            Ok(false) => log::warn!("The chat connection was closed - reconnecting"),
+            Err(error) => log::warn!("The chat connection broke: {} - reconnecting", error),
+        }
+        std::thread::sleep(RECONNECT_DELAY);
+        if cancellation::is_cancelled() {
+            break;
+        }
+    }
+    log::info!("Stopped the bot");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_calls_outside_of_our_numbers() {
+        let mut bot = Bot {
+            registry: CardRegistry::new(Vec::new(), crate::BingoVariant::Ball75),
+            session: BotSession::default(),
+            session_filepath: std::env::temp_dir()
+                .join("chotto_test_bot_session.json")
+                .to_string_lossy()
+                .to_string(),
+            callers: vec!["host".to_owned()],
+            max_number: 75,
+        };
+        for text in &["!call 0", "!call 76", "!call -3"] {
+            let replies = bot.handle_message("host", text);
+            assert_eq!(
+                replies,
+                vec!["@host please call a number between 1 and 75".to_owned()]
+            );
+        }
+        assert!(bot.session.called_numbers.is_empty());
+
+        bot.handle_message("host", "!call 75");
+        assert_eq!(bot.session.called_numbers, vec![75]);
+        std::fs::remove_file(&bot.session_filepath).ok();
+    }
+}
@@ -0,0 +1,79 @@
+//! Braille captions for our numbers so that cards can be embossed for blind players afterwards.
+//! Numbers are written as the number sign followed by the letters a-j for the digits 1-9 and 0.
+
+use cottontail::image::{Bitmap, PixelRGBA};
+
+/// The dots of a Braille cell where bit 0 is dot 1 and bit 5 is dot 6. The dots are numbered from
+/// top to bottom, first the left column (1, 2, 3) and then the right column (4, 5, 6).
+type BrailleCell = u8;
+
+const NUMBER_SIGN: BrailleCell = 0b11_1100;
+/// The letters a-j which stand for the digits 1-9 and 0
+const DIGITS: [BrailleCell; 10] = [
+    0b01_1010, // 0 = j
+    0b00_0001, // 1 = a
+    0b00_0011, // 2 = b
+    0b00_1001, // 3 = c
+    0b01_1001, // 4 = d
+    0b01_0001, // 5 = e
+    0b00_1011, // 6 = f
+    0b01_1011, // 7 = g
+    0b01_0011, // 8 = h
+    0b00_1010, // 9 = i
+];
+
+fn braille_cells(number: i32) -> Vec<BrailleCell> {
+    let mut cells = vec![NUMBER_SIGN];
+    cells.extend(
+        number
+            .to_string()
+            .bytes()
+            .map(|digit| DIGITS[(digit - b'0') as usize]),
+    );
+    cells
+}
+
+/// Returns the given number as Unicode Braille patterns which can be drawn with a Braille font
+pub fn braille_text(number: i32) -> String {
+    braille_cells(number)
+        .into_iter()
+        .map(|cell| std::char::from_u32(0x2800 + cell as u32).unwrap())
+        .collect()
+}
+
+/// Draws the given number as Braille dots with the given radius. The spacing between the dots
+/// follows the proportions of standard Braille.
+pub fn create_braille_bitmap_premultiplied(
+    number: i32,
+    dot_radius: i32,
+    color: PixelRGBA,
+) -> Bitmap {
+    let cells = braille_cells(number);
+    let dot_spacing = (5 * dot_radius) / 2;
+    let cell_spacing = 6 * dot_radius;
+    let width = (cells.len() as i32 - 1) * cell_spacing + dot_spacing + 2 * dot_radius;
+    let height = 2 * dot_spacing + 2 * dot_radius;
+
+    let mut bitmap = Bitmap::new(width as u32, height as u32);
+    for (cell_index, cell) in cells.iter().enumerate() {
+        for dot in 0..6 {
+            if cell & (1 << dot) == 0 {
+                continue;
+            }
+            let center_x = cell_index as i32 * cell_spacing + (dot / 3) * dot_spacing + dot_radius;
+            let center_y = (dot % 3) * dot_spacing + dot_radius;
+            for y in (center_y - dot_radius)..(center_y + dot_radius) {
+                for x in (center_x - dot_radius)..(center_x + dot_radius) {
+                    let distance_x = x as f32 + 0.5 - center_x as f32;
+                    let distance_y = y as f32 + 0.5 - center_y as f32;
+                    if distance_x * distance_x + distance_y * distance_y
+                        <= (dot_radius * dot_radius) as f32
+                    {
+                        bitmap.set(x, y, color);
+                    }
+                }
+            }
+        }
+    }
+    bitmap
+}
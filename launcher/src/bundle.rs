@@ -0,0 +1,230 @@
+//! Reproducibility bundles contain everything that is needed to create the exact same sheets of a
+//! run again, even months later and on another machine. They are written by
+//! `chotto --export-bundle` and used by `chotto reproduce <bundle.zip>`.
+
+use std::io::Write;
+
+use cottontail::core::{
+    collect_files,
+    serde_derive::{Deserialize, Serialize},
+};
+
+use crate::{
+    command_line_option_value, show_messagebox, show_messagebox_yes_no, unsafe_params, DrawParams,
+    DRAW_PARAMETERS_FILENAME, DRAW_PARAMETERS_FILENAMES, RECIPIENTS_FILENAME,
+};
+
+pub const BUNDLE_FILENAME: &str = "reproduction_bundle.zip";
+const BUNDLE_INFO_FILENAME: &str = "bundle.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleInfo {
+    /// The sheets are only guaranteed to be identical if they are created by the same version
+    pub chotto_version: String,
+    pub seed: u64,
+}
+
+/// Returns all files that the given run depends on
+pub fn input_filepaths(
+    params: &DrawParams,
+    background_image_filepath: &str,
+    font_filepath: &str,
+) -> Vec<String> {
+    let mut filepaths = vec![crate::find_draw_params_file()
+        .map(|(filepath, _format)| filepath)
+        .unwrap_or(DRAW_PARAMETERS_FILENAME)
+        .to_owned()];
+    filepaths.extend(design_filepaths(
+        params,
+        background_image_filepath,
+        font_filepath,
+    ));
+    filepaths.extend(params.puzzles_filepath.clone());
+    filepaths.extend(params.seating_cards_filepath.clone());
+    if std::path::Path::new(RECIPIENTS_FILENAME).exists() {
+        filepaths.push(RECIPIENTS_FILENAME.to_owned());
+    }
+    normalized_filepaths(filepaths)
+}
+
+/// Returns the images, fonts and other files that make up the look of our sheets. This leaves out
+/// the draw parameters file and the recipients.
+pub fn design_filepaths(
+    params: &DrawParams,
+    background_image_filepath: &str,
+    font_filepath: &str,
+) -> Vec<String> {
+    let mut filepaths = vec![
+        background_image_filepath.to_owned(),
+        font_filepath.to_owned(),
+    ];
+    filepaths.extend(params.referenced_filepaths());
+    filepaths.extend(params.cmyk_tiff_icc_profile_filepath.clone());
+    if let Some(directory) = &params.free_space_images_directory {
+        filepaths.extend(collect_files(directory));
+    }
+    if let Some(directory) = &params.deck_images_directory {
+        filepaths.extend(collect_files(directory));
+    }
+    normalized_filepaths(filepaths)
+}
+
+fn normalized_filepaths(filepaths: Vec<String>) -> Vec<String> {
+    let mut filepaths: Vec<String> = filepaths
+        .into_iter()
+        .map(|filepath| normalized_filepath(&filepath))
+        .collect();
+    filepaths.sort();
+    filepaths.dedup();
+    filepaths
+}
+
+/// Returns the given relative filepath with forward slashes and without a leading `./`
+pub fn normalized_filepath(filepath: &str) -> String {
+    filepath.trim_start_matches("./").replace('\\', "/")
+}
+
+/// Writes a bundle of all input files of our run and its seed into the given output directory
+pub fn export_bundle(
+    output_directory: &str,
+    params: &DrawParams,
+    background_image_filepath: &str,
+    font_filepath: &str,
+    seed: u64,
+) {
+    let bundle_filepath = format!("{}/{}", output_directory, BUNDLE_FILENAME);
+    let info = BundleInfo {
+        chotto_version: env!("CARGO_PKG_VERSION").to_owned(),
+        seed,
+    };
+    let mut entries = vec![(
+        BUNDLE_INFO_FILENAME.to_owned(),
+        serde_json::to_string_pretty(&info).unwrap().into_bytes(),
+    )];
+    for filepath in input_filepaths(params, background_image_filepath, font_filepath) {
+        assert!(
+            std::path::Path::new(&filepath).is_relative() && !filepath.contains(".."),
+            "Cannot bundle '{}' because it is outside of the directory of `chotto.exe` - please move it next to '{}'",
+            filepath,
+            DRAW_PARAMETERS_FILENAME
+        );
+        let content = std::fs::read(&filepath)
+            .unwrap_or_else(|error| panic!("Could not read file '{}': {}", filepath, error));
+        entries.push((filepath, content));
+    }
+
+    write_zip_file(&bundle_filepath, entries);
+    log::info!("Wrote reproduction bundle '{}'", bundle_filepath);
+}
+
+/// Writes the given named contents into a new zip file
+pub fn write_zip_file(zip_filepath: &str, entries: Vec<(String, Vec<u8>)>) {
+    let file = std::fs::File::create(zip_filepath)
+        .unwrap_or_else(|error| panic!("Could not create file '{}': {}", zip_filepath, error));
+    let mut zip = zip::ZipWriter::new(file);
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (name, content) in entries {
+        zip.start_file(name.as_str(), options)
+            .and_then(|_| zip.write_all(&content).map_err(zip::result::ZipError::from))
+            .unwrap_or_else(|error| {
+                panic!(
+                    "Could not write '{}' into '{}': {}",
+                    name, zip_filepath, error
+                )
+            });
+    }
+    zip.finish()
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", zip_filepath, error));
+}
+
+/// Usage: `chotto reproduce <bundle.zip> [--directory <dir>]`
+///
+/// Extracts the given bundle into its own directory and creates the sheets of its run again.
+/// Bundles can come from other people so we remove the parameters of its draw parameters file
+/// that could run commands or use accounts on our computer.
+pub fn reproduce(args: &[String]) {
+    crate::params_overrides::assert_no_overrides("reproduce a bundle");
+    let bundle_filepath = args
+        .first()
+        .filter(|arg| !arg.starts_with("--"))
+        .expect("Please specify the bundle to reproduce via `chotto reproduce <bundle.zip>`");
+    let directory = command_line_option_value(args, "--directory").unwrap_or_else(|| {
+        std::path::Path::new(bundle_filepath)
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+            + "_reproduced"
+    });
+
+    let file = std::fs::File::open(bundle_filepath)
+        .unwrap_or_else(|error| panic!("Could not open file '{}': {}", bundle_filepath, error));
+    zip::ZipArchive::new(file)
+        .and_then(|mut archive| archive.extract(&directory))
+        .unwrap_or_else(|error| {
+            panic!(
+                "Could not extract '{}' into '{}' - is the file ok?: {}",
+                bundle_filepath, directory, error
+            )
+        });
+
+    let info_filepath = format!("{}/{}", directory, BUNDLE_INFO_FILENAME);
+    let info: BundleInfo = std::fs::read_to_string(&info_filepath)
+        .ok()
+        .and_then(|info_string| serde_json::from_str(&info_string).ok())
+        .unwrap_or_else(|| {
+            panic!(
+                "'{}' is not a reproduction bundle because it has no valid '{}'",
+                bundle_filepath, BUNDLE_INFO_FILENAME
+            )
+        });
+    if info.chotto_version != env!("CARGO_PKG_VERSION") {
+        let message = format!(
+            "'{}' was created with Chotto {} but this is Chotto {}. The sheets might not be identical. Do you want to continue anyway?",
+            bundle_filepath,
+            info.chotto_version,
+            env!("CARGO_PKG_VERSION")
+        );
+        log::warn!("{}", message);
+        if !show_messagebox_yes_no("Chotto", &message) {
+            return;
+        }
+    }
+
+    let mut removed_keys = Vec::new();
+    for (filename, format) in DRAW_PARAMETERS_FILENAMES.iter() {
+        let filepath = format!("{}/{}", directory, filename);
+        let params_string = match std::fs::read_to_string(&filepath) {
+            Ok(params_string) => params_string,
+            Err(_) => continue,
+        };
+        let (params_string, removed_params_keys) =
+            unsafe_params::without_unsafe_parameters(&params_string, &filepath, *format);
+        if !removed_params_keys.is_empty() {
+            std::fs::write(&filepath, params_string)
+                .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+            removed_keys.extend(removed_params_keys);
+        }
+    }
+    if !removed_keys.is_empty() {
+        let message = format!(
+            "The parameters {} were removed from '{}' as they could run commands or use accounts \
+             on our computer. The sheets are created without them.",
+            removed_keys.join(", "),
+            bundle_filepath
+        );
+        log::warn!("{}", message);
+        show_messagebox("Chotto", &message, false);
+    }
+
+    log::info!(
+        "Reproducing '{}' with seed {} in '{}'",
+        bundle_filepath,
+        info.seed,
+        directory
+    );
+    std::env::set_current_dir(&directory)
+        .unwrap_or_else(|error| panic!("Could not enter directory '{}': {}", directory, error));
+    crate::create_sheets(false, Some(info.seed), false);
+}
@@ -0,0 +1,550 @@
+//! A window for the caller of a live game that can be shown on a big screen in the hall. It shows
+//! the last called number in huge type next to a board of all numbers where the called numbers are
+//! lit. Everything is drawn on top of our background image with our number font so that the screen
+//! matches the printed sheets.
+//!
+//! Usage: `chotto caller [--seed <seed>] [--overlay <filepath>] [--session <filepath>]
+//!         [--new-game]`
+//!
+//! The called numbers and the time of each call are written to the session file (by default
+//! `caller_session.json`) after each call. Starting the caller again continues that session unless
+//! we ask for `--new-game`.
+//!
+//! With `--overlay` we also write the screen without background to the given PNG file after each
+//! call. Streamers can add that file as image source in OBS which reloads it whenever it changes.
+//!
+//! Keys:
+//! - Space or Enter draws the next number
+//! - Backspace takes back the last number (i.e. if it was called by accident)
+//! - Escape closes the window
+//!
+//! Each called number can also be announced via text-to-speech (see `speech.rs`).
+//!
+//! NOTE: The window is only available on Windows. Other platforms read the keys from the console
+//!       and write the screen to a PNG file instead.
+
+use std::collections::HashMap;
+
+use cottontail::{
+    core::serde_derive::{Deserialize, Serialize},
+    image::{Bitmap, ColorBlendMode, PixelRGBA},
+    math::{Random, Vec2i},
+};
+
+use chotto::generator::shuffled;
+
+use crate::{
+    atomic_file, blit_centered_premultiplied, color_from_rgba, command_line_flag,
+    command_line_option_value, create_time_based_seed, create_wrapped_text_bitmaps_premultiplied,
+    nicknames,
+    png_writer::{self, PngCompression},
+    shaping,
+    speech::Speaker,
+    BingoVariant, DrawParams, FillStyle, Input, TextRasterizer,
+};
+
+const DEFAULT_SESSION_FILENAME: &str = "caller_session.json";
+#[cfg(not(windows))]
+const CALLER_SCREEN_FILENAME: &str = "caller_screen.png";
+/// The share of the width of our screen that shows the last called number
+const LAST_NUMBER_WIDTH_RATIO: f32 = 0.4;
+/// The alpha of the numbers on the board that were not called yet
+const UNCALLED_NUMBER_ALPHA: u8 = 60;
+/// The alpha of the highlight behind the numbers on the board that were called already
+const CALLED_CELL_HIGHLIGHT_ALPHA: u8 = 80;
+
+/// The numbers that were called so far and the ones that are still in the drum. This is our
+/// session file which is written after each call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallerState {
+    seed: u64,
+    /// The next number is taken from the end
+    remaining_numbers: Vec<i32>,
+    called_numbers: Vec<i32>,
+    /// The local time of each call in the same order as `called_numbers`
+    call_times: Vec<String>,
+}
+
+impl CallerState {
+    pub fn new(max_number: i32, seed: u64) -> CallerState {
+        let mut random = Random::new_from_seed(seed);
+        CallerState {
+            seed,
+            remaining_numbers: shuffled((1..=max_number).collect(), &mut random),
+            called_numbers: Vec::new(),
+            call_times: Vec::new(),
+        }
+    }
+
+    /// Returns the state of the given session file
+    fn load(filepath: &str, max_number: i32) -> CallerState {
+        let content = std::fs::read_to_string(filepath)
+            .unwrap_or_else(|error| panic!("Could not read file '{}': {}", filepath, error));
+        let state: CallerState = serde_json::from_str(&content)
+            .unwrap_or_else(|error| panic!("Could not read file '{}': {}", filepath, error));
+
+        let mut numbers: Vec<i32> = state
+            .remaining_numbers
+            .iter()
+            .chain(state.called_numbers.iter())
+            .cloned()
+            .collect();
+        numbers.sort();
+        assert!(
+            numbers == (1..=max_number).collect::<Vec<i32>>()
+                && state.call_times.len() == state.called_numbers.len(),
+            "The caller session '{}' does not belong to a game with the numbers 1-{} - please \
+             start a new game via `--new-game`",
+            filepath,
+            max_number
+        );
+        state
+    }
+
+    fn save(&self, filepath: &str) {
+        let content = serde_json::to_string_pretty(self).unwrap();
+        atomic_file::write_file_atomically(filepath, content.as_bytes());
+    }
+
+    /// Returns the next number or None if all numbers were called
+    pub fn call_next(&mut self) -> Option<i32> {
+        let number = self.remaining_numbers.pop()?;
+        self.called_numbers.push(number);
+        self.call_times
+            .push(chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string());
+        Some(number)
+    }
+
+    pub fn take_back_last(&mut self) -> Option<i32> {
+        let number = self.called_numbers.pop()?;
+        self.call_times.pop();
+        self.remaining_numbers.push(number);
+        Some(number)
+    }
+}
+
+/// The layout of the board of all numbers. 75-ball Bingo has one row per column letter like the
+/// flashboards in halls, all other variants have rows of ten numbers.
+struct Board {
+    column_count: i32,
+    row_count: i32,
+    /// The label of each row (75-ball Bingo only)
+    row_labels: Vec<String>,
+    left: i32,
+    top: i32,
+    cell_width: i32,
+    cell_height: i32,
+}
+
+impl Board {
+    fn new(params: &DrawParams, screen_width: i32, screen_height: i32) -> Board {
+        let max_number = params.max_number();
+        let (column_count, row_labels) = if params.bingo_variant == BingoVariant::Ball75 {
+            (max_number / 5, params.column_labels())
+        } else {
+            (10, Vec::new())
+        };
+        let row_count = (max_number + column_count - 1) / column_count;
+        let label_column_count = if row_labels.is_empty() { 0 } else { 1 };
+
+        let margin = screen_height / 20;
+        let left = (LAST_NUMBER_WIDTH_RATIO * screen_width as f32) as i32 + margin;
+        let top = margin;
+        let cell_width = (screen_width - left - margin) / (column_count + label_column_count);
+        let cell_height = (screen_height - 2 * margin) / row_count;
+        Board {
+            column_count,
+            row_count,
+            row_labels,
+            left: left + label_column_count * cell_width,
+            top,
+            cell_width,
+            cell_height,
+        }
+    }
+
+    /// Returns the center of the cell of the given number on our screen
+    fn cell_center(&self, number: i32) -> Vec2i {
+        let column = (number - 1) % self.column_count;
+        let row = (number - 1) / self.column_count;
+        Vec2i::new(
+            self.left + column * self.cell_width + self.cell_width / 2,
+            self.top + row * self.cell_height + self.cell_height / 2,
+        )
+    }
+
+    fn row_label_center(&self, row: i32) -> Vec2i {
+        Vec2i::new(
+            self.left - self.cell_width / 2,
+            self.top + row * self.cell_height + self.cell_height / 2,
+        )
+    }
+}
+
+/// Draws the frames of our caller screen
+pub struct CallerScreen<'a> {
+    background_premultiplied: Bitmap,
+    board: Board,
+    /// The last called number of 75-ball Bingo is shown below its column label
+    column_labels: Vec<String>,
+    last_number_text_rasterizer: TextRasterizer<'a>,
+    called_number_bitmaps_premultiplied: HashMap<i32, Bitmap>,
+    uncalled_number_bitmaps_premultiplied: HashMap<i32, Bitmap>,
+    row_label_bitmaps_premultiplied: Vec<Bitmap>,
+    called_cell_highlight_premultiplied: Bitmap,
+    /// Shown below the last called number if it has one
+    nickname_bitmaps_premultiplied: HashMap<i32, Bitmap>,
+}
+
+impl<'a> CallerScreen<'a> {
+    /// The given nicknames are shown below the last called number
+    pub fn new(input: &'a Input, nicknames: &HashMap<i32, String>) -> CallerScreen<'a> {
+        let params = &input.params;
+        let screen_width = input.background_bitmap.width as i32;
+        let screen_height = input.background_bitmap.height as i32;
+        let board = Board::new(params, screen_width, screen_height);
+
+        let board_font_size = 0.6 * i32::min(board.cell_width, board.cell_height) as f32;
+        let board_text_rasterizer = |fill_style: FillStyle| {
+            TextRasterizer::new(
+                &input.font,
+                board_font_size,
+                fill_style,
+                shaping::TextDirection::LeftToRight,
+                params.bold_text,
+                "0123456789",
+            )
+        };
+        let called_text_rasterizer = board_text_rasterizer(params.number_fill_style());
+        let (r, g, b) = params.text_color_rgb;
+        let uncalled_text_rasterizer = board_text_rasterizer(FillStyle::Solid(color_from_rgba((
+            r,
+            g,
+            b,
+            UNCALLED_NUMBER_ALPHA,
+        ))));
+        let number_bitmaps_premultiplied =
+            |text_rasterizer: &TextRasterizer| -> HashMap<i32, Bitmap> {
+                (1..=params.max_number())
+                    .map(|number| {
+                        (
+                            number,
+                            text_rasterizer.create_text_bitmap_premultiplied(&number.to_string()),
+                        )
+                    })
+                    .collect()
+            };
+        let row_label_bitmaps_premultiplied = board
+            .row_labels
+            .iter()
+            .map(|label| called_text_rasterizer.create_text_bitmap_premultiplied(label))
+            .collect();
+
+        let mut called_cell_highlight_premultiplied = Bitmap::new(
+            (board.cell_width - board.cell_width / 10) as u32,
+            (board.cell_height - board.cell_height / 10) as u32,
+        );
+        let premultiply =
+            |channel: u8| (channel as u32 * CALLED_CELL_HIGHLIGHT_ALPHA as u32 / 255) as u8;
+        called_cell_highlight_premultiplied
+            .data
+            .fill(PixelRGBA::new(
+                premultiply(r),
+                premultiply(g),
+                premultiply(b),
+                CALLED_CELL_HIGHLIGHT_ALPHA,
+            ));
+
+        let column_labels = if params.bingo_variant == BingoVariant::Ball75 {
+            params.column_labels()
+        } else {
+            Vec::new()
+        };
+        let last_number_text_rasterizer = TextRasterizer::new(
+            &input.font,
+            0.4 * screen_height as f32,
+            params.number_fill_style(),
+            shaping::TextDirection::LeftToRight,
+            params.bold_text,
+            &format!("0123456789{}", column_labels.concat()),
+        );
+
+        let nickname_text_rasterizer = TextRasterizer::new(
+            &input.font,
+            screen_height as f32 / 16.0,
+            params.number_fill_style(),
+            params.text_direction,
+            params.bold_text,
+            "",
+        );
+        let nickname_numbers: Vec<i32> = nicknames.keys().cloned().collect();
+        let mut nickname_bitmaps = create_wrapped_text_bitmaps_premultiplied(
+            &nickname_text_rasterizer,
+            &nickname_numbers
+                .iter()
+                .map(|number| nicknames[number].as_str())
+                .collect::<Vec<&str>>(),
+            (LAST_NUMBER_WIDTH_RATIO * screen_width as f32) as i32,
+        );
+        let nickname_bitmaps_premultiplied = nickname_numbers
+            .iter()
+            .enumerate()
+            .map(|(index, &number)| {
+                (
+                    number,
+                    nickname_bitmaps.remove(&(index as i32 + 1)).unwrap(),
+                )
+            })
+            .collect();
+
+        CallerScreen {
+            background_premultiplied: input.background_bitmap.to_premultiplied_alpha(),
+            called_number_bitmaps_premultiplied: number_bitmaps_premultiplied(
+                &called_text_rasterizer,
+            ),
+            uncalled_number_bitmaps_premultiplied: number_bitmaps_premultiplied(
+                &uncalled_text_rasterizer,
+            ),
+            board,
+            column_labels,
+            last_number_text_rasterizer,
+            row_label_bitmaps_premultiplied,
+            called_cell_highlight_premultiplied,
+            nickname_bitmaps_premultiplied,
+        }
+    }
+
+    /// Returns the screen for the given state with unpremultiplied alpha
+    pub fn draw(&self, state: &CallerState) -> Bitmap {
+        self.draw_onto(state, self.background_premultiplied.clone())
+    }
+
+    /// Returns the screen for the given state without our background and with unpremultiplied
+    /// alpha
+    pub fn draw_overlay(&self, state: &CallerState) -> Bitmap {
+        let background = &self.background_premultiplied;
+        self.draw_onto(
+            state,
+            Bitmap::new(background.width as u32, background.height as u32),
+        )
+    }
+
+    fn draw_onto(&self, state: &CallerState, mut screen: Bitmap) -> Bitmap {
+        let screen_height = screen.height as i32;
+
+        if let Some(&last_number) = state.called_numbers.last() {
+            let last_number_center = Vec2i::new(
+                (LAST_NUMBER_WIDTH_RATIO * screen.width as f32) as i32 / 2,
+                screen_height / 2,
+            );
+            let column = (last_number - 1) / self.board.column_count;
+            let lines = match self.column_labels.get(column as usize) {
+                Some(label) => vec![label.clone(), last_number.to_string()],
+                None => vec![last_number.to_string()],
+            };
+            let line_height = screen_height / 2;
+            for (line_index, line) in lines.iter().enumerate() {
+                let offset_y = (2 * line_index as i32 - (lines.len() as i32 - 1)) * line_height / 2;
+                blit_centered_premultiplied(
+                    &self
+                        .last_number_text_rasterizer
+                        .create_text_bitmap_premultiplied(line),
+                    &mut screen,
+                    last_number_center + Vec2i::new(0, offset_y),
+                    true,
+                );
+            }
+            if let Some(nickname_bitmap) = self.nickname_bitmaps_premultiplied.get(&last_number) {
+                blit_centered_premultiplied(
+                    nickname_bitmap,
+                    &mut screen,
+                    Vec2i::new(last_number_center.x, 11 * screen_height / 12),
+                    true,
+                );
+            }
+        }
+
+        for (row, label_bitmap) in self.row_label_bitmaps_premultiplied.iter().enumerate() {
+            blit_centered_premultiplied(
+                label_bitmap,
+                &mut screen,
+                self.board.row_label_center(row as i32),
+                true,
+            );
+        }
+        for number in 1..=self.board.column_count * self.board.row_count {
+            let center = self.board.cell_center(number);
+            let number_bitmap = if state.called_numbers.contains(&number) {
+                self.called_cell_highlight_premultiplied
+                    .blit_to_alpha_blended_premultiplied(
+                        &mut screen,
+                        center - self.called_cell_highlight_premultiplied.rect().dim / 2,
+                        true,
+                        ColorBlendMode::Normal,
+                    );
+                &self.called_number_bitmaps_premultiplied[&number]
+            } else {
+                match self.uncalled_number_bitmaps_premultiplied.get(&number) {
+                    Some(number_bitmap) => number_bitmap,
+                    None => continue,
+                }
+            };
+            blit_centered_premultiplied(number_bitmap, &mut screen, center, true);
+        }
+
+        screen.to_unpremultiplied_alpha()
+    }
+}
+
+pub fn run_caller(args: &[String]) {
+    let seed = command_line_option_value(args, "--seed")
+        .map(|value| {
+            value
+                .parse::<u64>()
+                .unwrap_or_else(|_| panic!("Invalid seed '{}'", value))
+        })
+        .unwrap_or_else(create_time_based_seed);
+    let overlay_filepath = command_line_option_value(args, "--overlay");
+    let session_filepath = command_line_option_value(args, "--session")
+        .unwrap_or_else(|| DEFAULT_SESSION_FILENAME.to_owned());
+    let new_game = command_line_flag(args, "--new-game");
+
+    let input = Input::new();
+    let nicknames = nicknames::load_nicknames(
+        input.params.nicknames_filepath.as_deref(),
+        input.params.max_number(),
+    );
+    let max_number = input.params.max_number();
+    // NOTE: We continue an earlier session by default so that a crash in the middle of a game
+    //       does not lose the numbers that were called so far
+    let state = if !new_game && std::path::Path::new(&session_filepath).exists() {
+        let state = CallerState::load(&session_filepath, max_number);
+        log::info!(
+            "Continuing the session '{}' with {} called numbers - use `--new-game` to start over",
+            session_filepath,
+            state.called_numbers.len()
+        );
+        state
+    } else {
+        log::info!("Calling the numbers 1-{} with seed {}", max_number, seed);
+        CallerState::new(max_number, seed)
+    };
+    state.save(&session_filepath);
+
+    let caller = Caller {
+        screen: CallerScreen::new(
+            &input,
+            &if input.params.number_nicknames {
+                nicknames.clone()
+            } else {
+                HashMap::new()
+            },
+        ),
+        state,
+        session_filepath,
+        overlay_filepath,
+        speaker: input.params.caller_speech.as_ref().map(|speech_params| {
+            let column_labels = if input.params.bingo_variant == BingoVariant::Ball75 {
+                input.params.column_labels()
+            } else {
+                Vec::new()
+            };
+            Speaker::new(
+                speech_params,
+                &nicknames,
+                column_labels,
+                input.params.max_number(),
+            )
+        }),
+    };
+    if let Some(overlay_filepath) = &caller.overlay_filepath {
+        log::info!("Writing the stream overlay to '{}'", overlay_filepath);
+    }
+    caller.write_overlay();
+    show_caller_screen(caller);
+}
+
+/// What the caller asked for via the keyboard
+pub enum CallerCommand {
+    Next,
+    TakeBack,
+}
+
+struct Caller<'a> {
+    screen: CallerScreen<'a>,
+    state: CallerState,
+    session_filepath: String,
+    /// A transparent version of our screen that is written after each call so that streamers can
+    /// add it as a layer on top of their video
+    overlay_filepath: Option<String>,
+    speaker: Option<Speaker>,
+}
+
+impl<'a> Caller<'a> {
+    /// Returns the new screen
+    fn handle_command(&mut self, command: CallerCommand) -> Bitmap {
+        match command {
+            CallerCommand::Next => match self.state.call_next() {
+                Some(number) => {
+                    log::info!("Called number {}", number);
+                    if let Some(speaker) = &self.speaker {
+                        speaker.announce(number);
+                    }
+                }
+                None => log::info!("All numbers were called"),
+            },
+            CallerCommand::TakeBack => {
+                if let Some(number) = self.state.take_back_last() {
+                    log::info!("Took back number {}", number);
+                }
+            }
+        }
+        self.state.save(&self.session_filepath);
+        self.write_overlay();
+        self.screen.draw(&self.state)
+    }
+
+    fn write_overlay(&self) {
+        if let Some(overlay_filepath) = &self.overlay_filepath {
+            // NOTE: The file is replaced atomically so that OBS never reads a half-written image
+            png_writer::write_png_file(
+                &self.screen.draw_overlay(&self.state),
+                overlay_filepath,
+                PngCompression::Fast,
+            );
+        }
+    }
+}
+
+#[cfg(windows)]
+fn show_caller_screen(mut caller: Caller) {
+    let first_screen = caller.screen.draw(&caller.state);
+    crate::caller_window::run_window("Chotto Caller", &first_screen, |command| {
+        caller.handle_command(command)
+    });
+}
+
+/// Without a window we read the commands from the console and write each new screen to a PNG file
+/// which can be shown by any image viewer that reloads changed files
+#[cfg(not(windows))]
+fn show_caller_screen(mut caller: Caller) {
+    use std::io::BufRead;
+
+    log::info!(
+        "Press Enter to draw the next number, type `b` to take back the last number or `q` to quit \
+         - the screen is written to '{}'",
+        CALLER_SCREEN_FILENAME
+    );
+    let first_screen = caller.screen.draw(&caller.state);
+    png_writer::write_png_file(&first_screen, CALLER_SCREEN_FILENAME, PngCompression::Fast);
+    for line in std::io::stdin().lock().lines() {
+        let command = match line.unwrap_or_default().trim() {
+            "" => CallerCommand::Next,
+            "b" => CallerCommand::TakeBack,
+            "q" => break,
+            _ => continue,
+        };
+        let screen = caller.handle_command(command);
+        png_writer::write_png_file(&screen, CALLER_SCREEN_FILENAME, PngCompression::Fast);
+    }
+}
@@ -0,0 +1,217 @@
+//! A plain Win32 window that shows the screens of `chotto caller` scaled to fit while keeping their
+//! aspect ratio
+
+use std::{cell::RefCell, ffi::OsStr, iter::once, os::windows::ffi::OsStrExt, ptr::null_mut};
+
+use cottontail::image::Bitmap;
+use winapi::{
+    shared::{
+        minwindef::{LPARAM, LRESULT, UINT, WPARAM},
+        windef::{HBRUSH, HWND, RECT},
+    },
+    um::{
+        libloaderapi::GetModuleHandleW,
+        wingdi::{
+            GetStockObject, SetStretchBltMode, StretchDIBits, BITMAPINFO, BITMAPINFOHEADER, BI_RGB,
+            BLACK_BRUSH, DIB_RGB_COLORS, HALFTONE, SRCCOPY,
+        },
+        winuser::{
+            BeginPaint, CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, EndPaint,
+            FillRect, GetClientRect, GetMessageW, InvalidateRect, LoadCursorW, PostQuitMessage,
+            RegisterClassW, TranslateMessage, CS_HREDRAW, CS_VREDRAW, CW_USEDEFAULT, IDC_ARROW,
+            MSG, PAINTSTRUCT, VK_BACK, VK_ESCAPE, VK_RETURN, VK_SPACE, WM_DESTROY, WM_ERASEBKGND,
+            WM_KEYDOWN, WM_PAINT, WNDCLASSW, WS_OVERLAPPEDWINDOW, WS_VISIBLE,
+        },
+    },
+};
+
+use crate::caller::CallerCommand;
+
+const INITIAL_WINDOW_WIDTH: i32 = 1280;
+const INITIAL_WINDOW_HEIGHT: i32 = 720;
+
+/// The screen that our window shows
+struct Frame {
+    /// GDI expects the pixels in BGRA order
+    pixels_bgra: Vec<u8>,
+    width: i32,
+    height: i32,
+}
+
+thread_local! {
+    // NOTE: Our window procedure is called by Windows and cannot take any state of its own
+    static FRAME: RefCell<Frame> = RefCell::new(Frame {
+        pixels_bgra: Vec::new(),
+        width: 0,
+        height: 0,
+    });
+}
+
+fn to_wide_string(text: &str) -> Vec<u16> {
+    OsStr::new(text).encode_wide().chain(once(0)).collect()
+}
+
+fn set_frame(screen: &Bitmap) {
+    FRAME.with(|frame| {
+        *frame.borrow_mut() = Frame {
+            pixels_bgra: screen
+                .data
+                .iter()
+                .flat_map(|pixel| [pixel.b, pixel.g, pixel.r, pixel.a])
+                .collect(),
+            width: screen.width as i32,
+            height: screen.height as i32,
+        }
+    });
+}
+
+unsafe fn paint(window: HWND) {
+    let mut paint_struct: PAINTSTRUCT = std::mem::zeroed();
+    let device_context = BeginPaint(window, &mut paint_struct);
+    let mut client_rect: RECT = std::mem::zeroed();
+    GetClientRect(window, &mut client_rect);
+    FillRect(
+        device_context,
+        &client_rect,
+        GetStockObject(BLACK_BRUSH as i32) as HBRUSH,
+    );
+
+    FRAME.with(|frame| {
+        let frame = frame.borrow();
+        if frame.width == 0 || frame.height == 0 {
+            return;
+        }
+        let window_width = client_rect.right - client_rect.left;
+        let window_height = client_rect.bottom - client_rect.top;
+        let scale = f32::min(
+            window_width as f32 / frame.width as f32,
+            window_height as f32 / frame.height as f32,
+        );
+        let draw_width = (frame.width as f32 * scale) as i32;
+        let draw_height = (frame.height as f32 * scale) as i32;
+
+        let mut bitmap_info: BITMAPINFO = std::mem::zeroed();
+        bitmap_info.bmiHeader = BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: frame.width,
+            // NOTE: A negative height means our rows are ordered top to bottom
+            biHeight: -frame.height,
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB,
+            biSizeImage: 0,
+            biXPelsPerMeter: 0,
+            biYPelsPerMeter: 0,
+            biClrUsed: 0,
+            biClrImportant: 0,
+        };
+        SetStretchBltMode(device_context, HALFTONE as i32);
+        StretchDIBits(
+            device_context,
+            (window_width - draw_width) / 2,
+            (window_height - draw_height) / 2,
+            draw_width,
+            draw_height,
+            0,
+            0,
+            frame.width,
+            frame.height,
+            frame.pixels_bgra.as_ptr() as *const _,
+            &bitmap_info,
+            DIB_RGB_COLORS,
+            SRCCOPY,
+        );
+    });
+    EndPaint(window, &paint_struct);
+}
+
+unsafe extern "system" fn window_procedure(
+    window: HWND,
+    message: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match message {
+        WM_PAINT => {
+            paint(window);
+            0
+        }
+        // NOTE: We paint the whole window ourselves which avoids flickering
+        WM_ERASEBKGND => 1,
+        WM_DESTROY => {
+            PostQuitMessage(0);
+            0
+        }
+        _ => DefWindowProcW(window, message, wparam, lparam),
+    }
+}
+
+/// Shows the given first screen and then asks for a new screen after each command of the caller
+/// until the window is closed
+pub fn run_window(
+    title: &str,
+    first_screen: &Bitmap,
+    mut on_command: impl FnMut(CallerCommand) -> Bitmap,
+) {
+    set_frame(first_screen);
+    let class_name_wide = to_wide_string("ChottoCaller");
+    let title_wide = to_wide_string(title);
+
+    unsafe {
+        let instance = GetModuleHandleW(null_mut());
+        let window_class = WNDCLASSW {
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(window_procedure),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance,
+            hIcon: null_mut(),
+            hCursor: LoadCursorW(null_mut(), IDC_ARROW),
+            hbrBackground: null_mut(),
+            lpszMenuName: null_mut(),
+            lpszClassName: class_name_wide.as_ptr(),
+        };
+        assert!(
+            RegisterClassW(&window_class) != 0,
+            "Could not register the caller window"
+        );
+        let window = CreateWindowExW(
+            0,
+            class_name_wide.as_ptr(),
+            title_wide.as_ptr(),
+            WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            INITIAL_WINDOW_WIDTH,
+            INITIAL_WINDOW_HEIGHT,
+            null_mut(),
+            null_mut(),
+            instance,
+            null_mut(),
+        );
+        assert!(!window.is_null(), "Could not open the caller window");
+
+        let mut message: MSG = std::mem::zeroed();
+        while GetMessageW(&mut message, null_mut(), 0, 0) > 0 {
+            // NOTE: Key presses are posted to our message queue so we can handle them right here
+            //       where we have access to the state of the caller
+            if message.message == WM_KEYDOWN {
+                let command = match message.wParam as i32 {
+                    VK_SPACE | VK_RETURN => Some(CallerCommand::Next),
+                    VK_BACK => Some(CallerCommand::TakeBack),
+                    VK_ESCAPE => {
+                        DestroyWindow(window);
+                        None
+                    }
+                    _ => None,
+                };
+                if let Some(command) = command {
+                    set_frame(&on_command(command));
+                    InvalidateRect(window, null_mut(), 0);
+                }
+            }
+            TranslateMessage(&message);
+            DispatchMessageW(&message);
+        }
+    }
+}
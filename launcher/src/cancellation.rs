@@ -0,0 +1,28 @@
+//! Lets us stop a long run with Ctrl+C without leaving an inconsistent output directory behind. The
+//! first Ctrl+C asks our workers to stop after the sheets they are currently drawing so that we can
+//! still write the manifest of all finished sheets. A second Ctrl+C stops immediately.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+pub fn install_ctrl_c_handler() {
+    let result = ctrlc::set_handler(|| {
+        if CANCELLED.swap(true, Ordering::SeqCst) {
+            log::error!("Stopped immediately - the output directory may be incomplete");
+            log::logger().flush();
+            std::process::exit(130);
+        }
+        log::warn!(
+            "Stopping after the sheets that are currently drawn - press Ctrl+C again to stop immediately"
+        );
+    });
+    if let Err(error) = result {
+        log::warn!("Could not install the Ctrl+C handler: {}", error);
+    }
+}
+
+/// Returns true if the user asked us to stop
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
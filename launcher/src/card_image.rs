@@ -0,0 +1,229 @@
+//! Creates a single card as an in-memory image without touching the filesystem, i.e. for a chat bot
+//! that posts a new card on demand. The same seed always gives the same card.
+//!
+//! This draws the numbers with a single font and color on top of a background. The full set of
+//! features of the draw parameters file (headers, chips, gradients, ...) is only available when
+//! running Chotto itself.
+//!
+//! ```ignore
+//! let assets = CardAssets::from_bytes(&background_png_bytes, &font_bytes)?;
+//! let params = CardParams::new_75_ball((40, 200, 760, 920), 64.0);
+//! let (card, image) = generate_card(&params, &assets, 1234);
+//! ```
+
+use cottontail::{
+    image::{Bitmap, PixelRGBA},
+    math::{Random, Vec2i},
+};
+
+use crate::{
+    cells::{self, Cell, CellRenderer},
+    generator, ninety_ball,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardVariant {
+    /// 5x5 grid with the numbers 1-75 and a free space in the center
+    Ball75,
+    /// A book of six 9x3 tickets stacked on top of each other
+    Ball90,
+}
+
+#[derive(Debug, Clone)]
+pub struct CardParams {
+    pub variant: CardVariant,
+    /// The pixel rect of the whole grid on the background as (left, top, right, bottom)
+    pub grid_left_top_right_bottom: (i32, i32, i32, i32),
+    pub font_size: f32,
+    pub text_color_rgba: (u8, u8, u8, u8),
+    /// Drawn into the free space of 75-ball cards
+    pub free_space_text: Option<String>,
+}
+
+impl CardParams {
+    pub fn new_75_ball(grid_left_top_right_bottom: (i32, i32, i32, i32), font_size: f32) -> Self {
+        CardParams {
+            variant: CardVariant::Ball75,
+            grid_left_top_right_bottom,
+            font_size,
+            text_color_rgba: (0, 0, 0, 255),
+            free_space_text: None,
+        }
+    }
+
+    pub fn new_90_ball(grid_left_top_right_bottom: (i32, i32, i32, i32), font_size: f32) -> Self {
+        CardParams {
+            variant: CardVariant::Ball90,
+            ..CardParams::new_75_ball(grid_left_top_right_bottom, font_size)
+        }
+    }
+
+    fn grid_size(&self) -> (i32, i32) {
+        match self.variant {
+            CardVariant::Ball75 => (5, 5),
+            CardVariant::Ball90 => (
+                ninety_ball::COLUMN_COUNT as i32,
+                (ninety_ball::TICKETS_PER_BOOK * ninety_ball::TICKET_ROW_COUNT) as i32,
+            ),
+        }
+    }
+}
+
+/// The background and font of our cards. These can be loaded once and reused for many cards.
+pub struct CardAssets {
+    /// The background with unpremultiplied alpha
+    pub background: Bitmap,
+    pub font: fontdue::Font,
+}
+
+impl CardAssets {
+    /// Decodes the given contents of a background image file (PNG, JPEG, BMP or TGA) and a font
+    /// file (TTF or OTF)
+    pub fn from_bytes(background_image_bytes: &[u8], font_bytes: &[u8]) -> Result<Self, String> {
+        let image = image::load_from_memory(background_image_bytes)
+            .map_err(|error| format!("Cannot decode background image: {}", error))?
+            .to_rgba8();
+        let mut background = Bitmap::new(image.width(), image.height());
+        for (pixel, image_pixel) in background.data.iter_mut().zip(image.pixels()) {
+            let [r, g, b, a] = image_pixel.0;
+            *pixel = PixelRGBA::new(r, g, b, a);
+        }
+        let font = fontdue::Font::from_bytes(font_bytes, fontdue::FontSettings::default())
+            .map_err(|error| format!("Cannot decode font: {}", error))?;
+        Ok(CardAssets { background, font })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardData {
+    pub seed: u64,
+    /// The numbers of the card row by row where empty cells and the free space are 0
+    pub numbers: Vec<Vec<i32>>,
+}
+
+/// Returns the numbers and the finished image of the card with the given seed
+pub fn generate_card(
+    params: &CardParams,
+    assets: &CardAssets,
+    seed: u64,
+) -> (CardData, image::RgbaImage) {
+    let mut random = Random::new_from_seed(seed);
+    let (column_count, row_count) = params.grid_size();
+    let grid = match params.variant {
+        CardVariant::Ball75 => {
+            generator::create_random_number_grids(1, &[], &[(2, 2)], &[], &[], &mut random)
+        }
+        CardVariant::Ball90 => ninety_ball::create_random_books(1, &[], &[], &mut random),
+    }
+    .pop()
+    .unwrap();
+    let numbers: Vec<Vec<i32>> = (0..row_count)
+        .map(|y| (0..column_count).map(|x| grid.get(x, y)).collect())
+        .collect();
+
+    let (left, top, right, bottom) = params.grid_left_top_right_bottom;
+    let mut sheet = assets.background.to_premultiplied_alpha();
+    cells::draw_cells(
+        &numbers,
+        Vec2i::new(left, top),
+        (right - left) / column_count,
+        (bottom - top) / row_count,
+        &[&TextCellRenderer { params, assets }],
+        &mut sheet,
+    );
+
+    let sheet = sheet.to_unpremultiplied_alpha();
+    let pixels: Vec<u8> = sheet
+        .data
+        .iter()
+        .flat_map(|pixel| [pixel.r, pixel.g, pixel.b, pixel.a])
+        .collect();
+    let image =
+        image::RgbaImage::from_raw(sheet.width as u32, sheet.height as u32, pixels).unwrap();
+    (CardData { seed, numbers }, image)
+}
+
+struct TextCellRenderer<'a> {
+    params: &'a CardParams,
+    assets: &'a CardAssets,
+}
+
+impl<'a> CellRenderer for TextCellRenderer<'a> {
+    fn draw_cell(&self, cell: &Cell, sheet: &mut Bitmap) {
+        let text = if cell.value != 0 {
+            cell.value.to_string()
+        } else if self.params.variant == CardVariant::Ball75 && (cell.column, cell.row) == (2, 2) {
+            match &self.params.free_space_text {
+                Some(text) => text.clone(),
+                None => return,
+            }
+        } else {
+            return;
+        };
+
+        let text_bitmap = create_text_bitmap_premultiplied(
+            &self.assets.font,
+            self.params.font_size,
+            self.params.text_color_rgba,
+            &text,
+        );
+        text_bitmap.blit_to_alpha_blended_premultiplied(
+            sheet,
+            cell.center() - text_bitmap.rect().dim / 2,
+            true,
+            cottontail::image::ColorBlendMode::Normal,
+        );
+    }
+}
+
+/// Returns the given text in the given color with premultiplied alpha
+fn create_text_bitmap_premultiplied(
+    font: &fontdue::Font,
+    font_size: f32,
+    color_rgba: (u8, u8, u8, u8),
+    text: &str,
+) -> Bitmap {
+    let mut layout = fontdue::layout::Layout::new(fontdue::layout::CoordinateSystem::PositiveYDown);
+    layout.append(
+        &[font],
+        &fontdue::layout::TextStyle::new(text, font_size, 0),
+    );
+    let glyphs = layout.glyphs();
+    let width = glyphs
+        .iter()
+        .map(|glyph| glyph.x.round() as i32 + glyph.width as i32)
+        .max()
+        .unwrap_or(0);
+    let height = glyphs
+        .iter()
+        .map(|glyph| glyph.y.round() as i32 + glyph.height as i32)
+        .max()
+        .unwrap_or(0);
+
+    let (r, g, b, a) = color_rgba;
+    let mut text_bitmap = Bitmap::new(u32::max(1, width as u32), u32::max(1, height as u32));
+    for glyph in glyphs {
+        let (metrics, coverage) = font.rasterize(glyph.key.c, font_size);
+        for y in 0..metrics.height as i32 {
+            for x in 0..metrics.width as i32 {
+                let alpha =
+                    coverage[(y * metrics.width as i32 + x) as usize] as u32 * a as u32 / 255;
+                let pos = Vec2i::new(glyph.x.round() as i32 + x, glyph.y.round() as i32 + y);
+                if alpha == 0 || pos.x < 0 || pos.y < 0 {
+                    continue;
+                }
+                text_bitmap.set(
+                    pos.x,
+                    pos.y,
+                    PixelRGBA::new(
+                        (r as u32 * alpha / 255) as u8,
+                        (g as u32 * alpha / 255) as u8,
+                        (b as u32 * alpha / 255) as u8,
+                        alpha as u8,
+                    ),
+                );
+            }
+        }
+    }
+    text_bitmap
+}
@@ -0,0 +1,110 @@
+//! Reading of cards whose numbers were decided by an external system, i.e. a generator that was
+//! audited by a regulator. Such cards are drawn via `chotto render-from --cards cards.json`.
+//!
+//! The card file looks like this where each card lists the numbers of its grid row by row and
+//! empty cells and the free space are 0:
+//!
+//! {
+//!   "cards": [
+//!     { "numbers": [[1, 16, 31, 46, 61], [2, 17, 32, 47, 62], ...], "serial": "A-0001" },
+//!     ...
+//!   ]
+//! }
+
+use std::collections::HashSet;
+
+use cottontail::{
+    core::serde_derive::{Deserialize, Serialize},
+    image::Grid,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedCard {
+    pub numbers: Vec<Vec<i32>>,
+    /// Replaces the serial number that Chotto would give the card otherwise
+    #[serde(default)]
+    pub serial: Option<String>,
+}
+
+impl ImportedCard {
+    pub fn number_grid(&self) -> Grid<i32> {
+        let mut grid = Grid::new(self.numbers[0].len() as u32, self.numbers.len() as u32);
+        for (y, row) in self.numbers.iter().enumerate() {
+            for (x, number) in row.iter().enumerate() {
+                grid.set(x as i32, y as i32, *number);
+            }
+        }
+        grid
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CardFile {
+    pub cards: Vec<ImportedCard>,
+}
+
+/// Reads the given card file and checks that all of its cards fit our grid
+pub fn read_card_file(
+    filepath: &str,
+    column_count: i32,
+    row_count: i32,
+    max_number: i32,
+    free_space_cells: &[(i32, i32)],
+) -> Vec<ImportedCard> {
+    let card_file_string = std::fs::read_to_string(filepath)
+        .unwrap_or_else(|error| panic!("Could not read file '{}': {}", filepath, error));
+    let card_file: CardFile = serde_json::from_str(&card_file_string)
+        .unwrap_or_else(|error| panic!("Could not read cards from '{}': {}", filepath, error));
+    assert!(
+        !card_file.cards.is_empty(),
+        "There are no cards in '{}'",
+        filepath
+    );
+
+    for (card_index, card) in card_file.cards.iter().enumerate() {
+        assert!(
+            card.numbers.len() == row_count as usize
+                && card
+                    .numbers
+                    .iter()
+                    .all(|row| row.len() == column_count as usize),
+            "Card {} in '{}' must have {} rows of {} numbers each",
+            card_index + 1,
+            filepath,
+            row_count,
+            column_count
+        );
+
+        let mut seen_numbers = HashSet::new();
+        for (y, row) in card.numbers.iter().enumerate() {
+            for (x, &number) in row.iter().enumerate() {
+                assert!(
+                    0 <= number && number <= max_number,
+                    "Card {} in '{}' contains the number {} which is not between 1 and {}",
+                    card_index + 1,
+                    filepath,
+                    number,
+                    max_number
+                );
+                assert!(
+                    number == 0 || seen_numbers.insert(number),
+                    "Card {} in '{}' contains the number {} more than once",
+                    card_index + 1,
+                    filepath,
+                    number
+                );
+                assert!(
+                    number == 0 || !free_space_cells.contains(&(x as i32, y as i32)),
+                    "Card {} in '{}' has the number {} in its free space at [{}, {}] - please use 0 there",
+                    card_index + 1,
+                    filepath,
+                    number,
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    card_file.cards
+}
@@ -0,0 +1,69 @@
+//! Generation of cards from a fixed cell layout. Each cell of the layout is either empty, has a
+//! fixed content that is the same on every card (i.e. `FREE` or the name of a sponsor) or is
+//! filled with a random entry of a pool. Entries of pools are represented by their 1-based
+//! position in the list of all entries of all pools.
+
+use crate::generator::{
+    accepted_by_all, check_rejected_grid_count, is_duplicate, shuffled, CardConstraint,
+    RandomSource,
+};
+use cottontail::image::Grid;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutCell {
+    Empty,
+    /// Stays 0 in the generated grids and is drawn the same way on every card
+    Fixed,
+    /// Gets a random entry of the pool with the given index
+    Pool(usize),
+}
+
+/// Returns one grid per card where each row of the given layout becomes a row of the grid. A
+/// card never uses the same entry twice and the new cards never duplicate each other or the given
+/// cards of an earlier batch.
+/// NOTE: Each pool needs at least as many entries as there are cells that are filled from it. This
+///       is checked when loading our draw parameters.
+pub fn create_random_grids(
+    card_count: usize,
+    layout: &[Vec<LayoutCell>],
+    pools: &[Vec<i32>],
+    constraints: &[&dyn CardConstraint],
+    previous_grids: &[Grid<i32>],
+    random: &mut dyn RandomSource,
+) -> Vec<Grid<i32>> {
+    log::debug!(
+        "Generating {} grids from a cell layout after {} previous grids",
+        card_count,
+        previous_grids.len()
+    );
+
+    let row_count = layout.len();
+    let column_count = layout.first().map(|row| row.len()).unwrap_or(0);
+    let mut grids: Vec<Grid<i32>> = previous_grids.to_vec();
+    let mut rejected_grid_count = 0;
+    while grids.len() < previous_grids.len() + card_count {
+        let mut pool_entries: Vec<Vec<i32>> = pools
+            .iter()
+            .map(|entries| shuffled(entries.clone(), random))
+            .collect();
+
+        let mut grid = Grid::new(column_count as u32, row_count as u32);
+        for (y, row) in layout.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                if let LayoutCell::Pool(pool_index) = cell {
+                    let entry = pool_entries[*pool_index].pop().unwrap();
+                    grid.set(x as i32, y as i32, entry);
+                }
+            }
+        }
+
+        if is_duplicate(&grid, &grids) || !accepted_by_all(constraints, &grid, &grids) {
+            rejected_grid_count += 1;
+            check_rejected_grid_count(rejected_grid_count);
+            continue;
+        }
+        rejected_grid_count = 0;
+        grids.push(grid);
+    }
+    grids.split_off(previous_grids.len())
+}
@@ -0,0 +1,143 @@
+//! The cell layout and pools of cards that are described cell by cell. A cell of the layout is
+//! either empty, the name of a pool in curly braces (i.e. `{sponsors}`) which is filled with a
+//! random entry of that pool or any other text which is drawn as is on every card (i.e. `FREE`).
+//!
+//! The entries of all pools are numbered from 1 in the alphabetical order of the pool names. The
+//! numbers in our cards and manifest refer to these entries.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chotto::cell_layout::LayoutCell;
+
+use crate::DRAW_PARAMETERS_FILENAME;
+
+/// Reads a layout from a CSV file without header line where each line is a row of our grid
+pub fn load_cell_layout_file(filepath: &str) -> Vec<Vec<String>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(filepath)
+        .unwrap_or_else(|error| panic!("Could not read file '{}': {}", filepath, error));
+    reader
+        .records()
+        .map(|record| {
+            record
+                .unwrap_or_else(|error| panic!("Could not read file '{}': {}", filepath, error))
+                .iter()
+                .map(|cell| cell.trim().to_owned())
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the name of the pool if the given cell text refers to one
+fn pool_name(cell: &str) -> Option<&str> {
+    cell.strip_prefix('{')
+        .and_then(|cell| cell.strip_suffix('}'))
+        .map(|name| name.trim())
+}
+
+/// Returns the entries of all pools in the order of their numbers
+pub fn pool_entries(pools: &BTreeMap<String, Vec<String>>) -> Vec<&str> {
+    pools
+        .values()
+        .flat_map(|entries| entries.iter().map(|entry| entry.as_str()))
+        .collect()
+}
+
+/// Returns the numbers of the entries of each pool in alphabetical order of the pool names
+pub fn pool_numbers(pools: &BTreeMap<String, Vec<String>>) -> Vec<Vec<i32>> {
+    let mut next_number = 1;
+    pools
+        .values()
+        .map(|entries| {
+            let numbers: Vec<i32> = (next_number..next_number + entries.len() as i32).collect();
+            next_number += entries.len() as i32;
+            numbers
+        })
+        .collect()
+}
+
+/// Returns our layout for the card generation
+pub fn layout_cells(
+    layout: &[Vec<String>],
+    pools: &BTreeMap<String, Vec<String>>,
+) -> Vec<Vec<LayoutCell>> {
+    let pool_names: Vec<&String> = pools.keys().collect();
+    layout
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| match pool_name(cell) {
+                    Some(name) => LayoutCell::Pool(
+                        pool_names
+                            .iter()
+                            .position(|pool_name| pool_name.as_str() == name)
+                            .unwrap(),
+                    ),
+                    None if cell.is_empty() => LayoutCell::Empty,
+                    None => LayoutCell::Fixed,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the (column, row) location and text of each cell with a fixed content
+pub fn fixed_cell_texts(layout: &[Vec<String>]) -> Vec<((i32, i32), &str)> {
+    let mut fixed_cells = Vec::new();
+    for (row, row_cells) in layout.iter().enumerate() {
+        for (column, cell) in row_cells.iter().enumerate() {
+            if !cell.is_empty() && pool_name(cell).is_none() {
+                fixed_cells.push(((column as i32, row as i32), cell.as_str()));
+            }
+        }
+    }
+    fixed_cells
+}
+
+/// Panics if our layout is not rectangular or if it uses pools that do not exist or that have too
+/// few entries
+pub fn validate(layout: &[Vec<String>], pools: &BTreeMap<String, Vec<String>>) {
+    let column_count = layout.first().map(|row| row.len()).unwrap_or(0);
+    assert!(
+        column_count > 0,
+        "The `cell_layout` is empty - please fix it in '{}'!",
+        DRAW_PARAMETERS_FILENAME
+    );
+    for (row, row_cells) in layout.iter().enumerate() {
+        assert!(
+            row_cells.len() == column_count,
+            "Row {} of the `cell_layout` has {} cells but the first row has {} - please fix it in \
+             '{}'!",
+            row + 1,
+            row_cells.len(),
+            column_count,
+            DRAW_PARAMETERS_FILENAME
+        );
+    }
+
+    let mut pool_usage_counts: HashMap<&str, usize> = HashMap::new();
+    for name in layout.iter().flatten().filter_map(|cell| pool_name(cell)) {
+        *pool_usage_counts.entry(name).or_insert(0) += 1;
+    }
+    for (name, usage_count) in pool_usage_counts {
+        let entries = pools.get(name).unwrap_or_else(|| {
+            panic!(
+                "The `cell_layout` uses the pool '{}' which is missing in `cell_pools` - please \
+                 fix it in '{}'!",
+                name, DRAW_PARAMETERS_FILENAME
+            )
+        });
+        let entry_count = entries.len();
+        assert!(
+            entry_count >= usage_count,
+            "The pool '{}' has {} entries but the `cell_layout` uses it in {} cells - please fix \
+             it in '{}'!",
+            name,
+            entry_count,
+            usage_count,
+            DRAW_PARAMETERS_FILENAME
+        );
+    }
+}
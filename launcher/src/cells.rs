@@ -0,0 +1,74 @@
+//! Drawing of the grid cells of a sheet. Custom cell content like icons, barcodes or colored chips
+//! can be drawn by implementing `CellRenderer`.
+
+use cottontail::{image::Bitmap, math::Vec2i};
+
+/// A cell of the grid on a sheet
+#[derive(Debug, Clone, Copy)]
+pub struct Cell {
+    pub column: i32,
+    pub row: i32,
+    /// The pixel rect of the cell on the sheet
+    pub left: i32,
+    pub top: i32,
+    pub width: i32,
+    pub height: i32,
+    /// The number of the cell which is 0 for empty cells and the free space
+    pub value: i32,
+}
+
+impl Cell {
+    pub fn center(&self) -> Vec2i {
+        Vec2i::new(self.left + self.width / 2, self.top + self.height / 2)
+    }
+}
+
+pub trait CellRenderer {
+    /// Draws the content of the given cell onto the sheet. Implementations should not draw outside
+    /// of the pixel rect of the cell.
+    fn draw_cell(&self, cell: &Cell, sheet: &mut Bitmap);
+}
+
+/// Returns the cells of the given grid of numbers which is given row by row
+pub fn grid_cells(
+    numbers: &[Vec<i32>],
+    top_left: Vec2i,
+    cell_width: i32,
+    cell_height: i32,
+) -> Vec<Cell> {
+    numbers
+        .iter()
+        .enumerate()
+        .flat_map(|(row, row_numbers)| {
+            row_numbers
+                .iter()
+                .enumerate()
+                .map(move |(column, &value)| Cell {
+                    column: column as i32,
+                    row: row as i32,
+                    left: top_left.x + column as i32 * cell_width,
+                    top: top_left.y + row as i32 * cell_height,
+                    width: cell_width,
+                    height: cell_height,
+                    value,
+                })
+        })
+        .collect()
+}
+
+/// Calls all renderers in order for each cell of the given grid of numbers which is given row by
+/// row
+pub fn draw_cells(
+    numbers: &[Vec<i32>],
+    top_left: Vec2i,
+    cell_width: i32,
+    cell_height: i32,
+    renderers: &[&dyn CellRenderer],
+    sheet: &mut Bitmap,
+) {
+    for cell in grid_cells(numbers, top_left, cell_width, cell_height) {
+        for renderer in renderers {
+            renderer.draw_cell(&cell, sheet);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! Checks whether a card wins against the numbers that were called so far
+
+use std::collections::HashSet;
+
+use chotto::ninety_ball;
+use cottontail::core::serde_derive::{Deserialize, Serialize};
+
+use crate::{manifest::Card, BingoVariant};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimResult {
+    pub win: bool,
+    /// Descriptions of all completed lines, i.e. `row 2`, `diagonal 1` or `ticket 3 full house`
+    pub completed_lines: Vec<String>,
+    /// The numbers of the card that were not called yet
+    pub uncalled_numbers: Vec<i32>,
+}
+
+/// Returns true if all given numbers were called. Empty cells and the free space are 0 and count
+/// as called.
+fn is_complete<'a>(
+    numbers: impl IntoIterator<Item = &'a i32>,
+    called_numbers: &HashSet<i32>,
+) -> bool {
+    numbers
+        .into_iter()
+        .all(|number| *number == 0 || called_numbers.contains(number))
+}
+
+/// Cards with a single grid (all variants but 90-ball) win with any complete row, column or
+/// diagonal. Only square grids have diagonals. Lines without any numbers (i.e. a row of fixed cells
+/// of a cell layout) never win.
+fn completed_lines_single_grid(numbers: &[Vec<i32>], called_numbers: &HashSet<i32>) -> Vec<String> {
+    line_numbers_single_grid(numbers)
+        .into_iter()
+        .filter(|(_name, line)| {
+            line.iter().any(|number| *number != 0) && is_complete(line, called_numbers)
+        })
+        .map(|(name, _line)| name)
+        .collect()
+}
+
+/// Returns the name and numbers of each row, column and diagonal of the given grid
+fn line_numbers_single_grid(numbers: &[Vec<i32>]) -> Vec<(String, Vec<i32>)> {
+    let row_count = numbers.len();
+    let column_count = numbers.first().map(|row| row.len()).unwrap_or(0);
+    let mut lines: Vec<(String, Vec<i32>)> = numbers
+        .iter()
+        .enumerate()
+        .map(|(row_index, row)| (format!("row {}", row_index + 1), row.clone()))
+        .collect();
+    lines.extend((0..column_count).map(|column_index| {
+        (
+            format!("column {}", column_index + 1),
+            numbers.iter().map(|row| row[column_index]).collect(),
+        )
+    }));
+    if row_count == column_count {
+        let size = row_count;
+        lines.push((
+            "diagonal 1".to_owned(),
+            (0..size).map(|index| numbers[index][index]).collect(),
+        ));
+        lines.push((
+            "diagonal 2".to_owned(),
+            (0..size)
+                .map(|index| numbers[index][size - 1 - index])
+                .collect(),
+        ));
+    }
+    lines
+}
+
+/// 90-ball tickets win with one line, two lines or a full house. The tickets of a book are checked
+/// separately.
+fn completed_lines_90_ball(numbers: &[Vec<i32>], called_numbers: &HashSet<i32>) -> Vec<String> {
+    let mut completed_lines = Vec::new();
+    for (ticket_index, ticket) in numbers.chunks(ninety_ball::TICKET_ROW_COUNT).enumerate() {
+        let completed_rows: Vec<usize> = ticket
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| is_complete(row.iter(), called_numbers))
+            .map(|(row_index, _)| row_index + 1)
+            .collect();
+        if completed_rows.len() == ticket.len() {
+            completed_lines.push(format!("ticket {} full house", ticket_index + 1));
+        } else {
+            completed_lines.extend(
+                completed_rows
+                    .iter()
+                    .map(|row| format!("ticket {} row {}", ticket_index + 1, row)),
+            );
+        }
+    }
+    completed_lines
+}
+
+/// Returns the numbers of each line that wins on its own, leaving out empty cells and the free
+/// space. These are the rows, columns and diagonals of cards with a single grid and the ticket rows
+/// of 90-ball books. Lines without any numbers are left out.
+pub fn line_numbers(card: &Card, variant: BingoVariant) -> Vec<Vec<i32>> {
+    let mut lines: Vec<Vec<i32>> = if variant == BingoVariant::Ball90 {
+        card.numbers.clone()
+    } else {
+        line_numbers_single_grid(&card.numbers)
+            .into_iter()
+            .map(|(_name, line)| line)
+            .collect()
+    };
+    for line in lines.iter_mut() {
+        line.retain(|number| *number != 0);
+    }
+    lines.retain(|line| !line.is_empty());
+    lines
+}
+
+/// Returns the numbers that each make up a full house. This is the whole card for cards with a
+/// single grid and each ticket for 90-ball books.
+pub fn full_house_numbers(card: &Card, variant: BingoVariant) -> Vec<Vec<i32>> {
+    let ticket_row_count = match variant {
+        BingoVariant::Ball75
+        | BingoVariant::Ball80
+        | BingoVariant::Ball30
+        | BingoVariant::Loteria
+        | BingoVariant::ScavengerHunt
+        | BingoVariant::CellLayout => card.numbers.len(),
+        BingoVariant::Ball90 => ninety_ball::TICKET_ROW_COUNT,
+    };
+    card.numbers
+        .chunks(usize::max(1, ticket_row_count))
+        .map(|ticket| {
+            ticket
+                .iter()
+                .flatten()
+                .cloned()
+                .filter(|number| *number != 0)
+                .collect()
+        })
+        .collect()
+}
+
+pub fn check_claim(
+    card: &Card,
+    called_numbers: &HashSet<i32>,
+    variant: BingoVariant,
+) -> ClaimResult {
+    let completed_lines = match variant {
+        BingoVariant::Ball75
+        | BingoVariant::Ball80
+        | BingoVariant::Ball30
+        | BingoVariant::Loteria
+        | BingoVariant::ScavengerHunt
+        | BingoVariant::CellLayout => completed_lines_single_grid(&card.numbers, called_numbers),
+        BingoVariant::Ball90 => completed_lines_90_ball(&card.numbers, called_numbers),
+    };
+    let mut uncalled_numbers: Vec<i32> = card
+        .numbers
+        .iter()
+        .flatten()
+        .cloned()
+        .filter(|number| *number != 0 && !called_numbers.contains(number))
+        .collect();
+    uncalled_numbers.sort();
+    ClaimResult {
+        win: !completed_lines.is_empty(),
+        completed_lines,
+        uncalled_numbers,
+    }
+}
@@ -0,0 +1,127 @@
+//! Parsing of the colors in our draw parameters. Every color can be given as tuple like
+//! `[255, 128, 64]`, as hex string like `"#FF8040"` or `"#FF804080"` (with alpha) or by its name
+//! like `"orange"`. Color parameters use one of the `deserialize_with` functions below.
+
+use cottontail::core::serde_derive::Deserialize;
+use serde::{de::Error, Deserialize as _, Deserializer};
+
+/// The CSS names of the most common colors
+const NAMED_COLORS: [(&str, (u8, u8, u8)); 24] = [
+    ("black", (0, 0, 0)),
+    ("white", (255, 255, 255)),
+    ("gray", (128, 128, 128)),
+    ("grey", (128, 128, 128)),
+    ("silver", (192, 192, 192)),
+    ("red", (255, 0, 0)),
+    ("maroon", (128, 0, 0)),
+    ("orange", (255, 165, 0)),
+    ("gold", (255, 215, 0)),
+    ("yellow", (255, 255, 0)),
+    ("olive", (128, 128, 0)),
+    ("lime", (0, 255, 0)),
+    ("green", (0, 128, 0)),
+    ("teal", (0, 128, 128)),
+    ("cyan", (0, 255, 255)),
+    ("aqua", (0, 255, 255)),
+    ("blue", (0, 0, 255)),
+    ("navy", (0, 0, 128)),
+    ("purple", (128, 0, 128)),
+    ("magenta", (255, 0, 255)),
+    ("fuchsia", (255, 0, 255)),
+    ("pink", (255, 192, 203)),
+    ("brown", (165, 42, 42)),
+    ("beige", (245, 245, 220)),
+];
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorValue {
+    Rgb(u8, u8, u8),
+    Rgba(u8, u8, u8, u8),
+    Text(String),
+}
+
+/// Parses colors like `"#F84"`, `"#FF8040"`, `"#FF804080"`, `"orange"` or `"transparent"`
+fn parse_color_text(text: &str) -> Result<(u8, u8, u8, u8), String> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix('#') {
+        let hex_digits: Option<Vec<u8>> = hex
+            .chars()
+            .map(|digit| digit.to_digit(16).map(|value| value as u8))
+            .collect();
+        let digits = hex_digits
+            .ok_or_else(|| format!("'{}' is not a valid hex color like \"#FF8040\"", text))?;
+        return match digits.len() {
+            3 => Ok((17 * digits[0], 17 * digits[1], 17 * digits[2], 255)),
+            6 | 8 => {
+                let mut channels = digits.chunks(2).map(|pair| 16 * pair[0] + pair[1]);
+                Ok((
+                    channels.next().unwrap(),
+                    channels.next().unwrap(),
+                    channels.next().unwrap(),
+                    channels.next().unwrap_or(255),
+                ))
+            }
+            _ => Err(format!(
+                "'{}' is not a valid hex color like \"#FF8040\" - it needs 3, 6 or 8 digits",
+                text
+            )),
+        };
+    }
+
+    let name = text.to_lowercase();
+    if name == "transparent" {
+        return Ok((0, 0, 0, 0));
+    }
+    NAMED_COLORS
+        .iter()
+        .find(|(color_name, _)| *color_name == name)
+        .map(|(_, (r, g, b))| (*r, *g, *b, 255))
+        .ok_or_else(|| {
+            format!(
+                "'{}' is not a known color name - please use a hex color like \"#FF8040\" instead",
+                text
+            )
+        })
+}
+
+fn color_value_to_rgba(value: ColorValue) -> Result<(u8, u8, u8, u8), String> {
+    match value {
+        ColorValue::Rgb(r, g, b) => Ok((r, g, b, 255)),
+        ColorValue::Rgba(r, g, b, a) => Ok((r, g, b, a)),
+        ColorValue::Text(text) => parse_color_text(&text),
+    }
+}
+
+fn color_value_to_rgb(value: ColorValue) -> Result<(u8, u8, u8), String> {
+    match color_value_to_rgba(value)? {
+        (r, g, b, 255) => Ok((r, g, b)),
+        _ => Err("This color cannot be transparent - please leave out its alpha value".to_owned()),
+    }
+}
+
+pub fn rgb<'de, D: Deserializer<'de>>(deserializer: D) -> Result<(u8, u8, u8), D::Error> {
+    color_value_to_rgb(ColorValue::deserialize(deserializer)?).map_err(D::Error::custom)
+}
+
+pub fn rgba<'de, D: Deserializer<'de>>(deserializer: D) -> Result<(u8, u8, u8, u8), D::Error> {
+    color_value_to_rgba(ColorValue::deserialize(deserializer)?).map_err(D::Error::custom)
+}
+
+pub fn optional_rgb<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<(u8, u8, u8)>, D::Error> {
+    Option::<ColorValue>::deserialize(deserializer)?
+        .map(color_value_to_rgb)
+        .transpose()
+        .map_err(D::Error::custom)
+}
+
+pub fn optional_rgba<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<(u8, u8, u8, u8)>, D::Error> {
+    Option::<ColorValue>::deserialize(deserializer)?
+        .map(color_value_to_rgba)
+        .transpose()
+        .map_err(D::Error::custom)
+}
@@ -0,0 +1,148 @@
+//! Countdown and advent calendar cards. With `mode = "countdown"` each sheet shows one big number
+//! (i.e. the days until our event or the door of an advent calendar) together with a caption. The
+//! number and the caption are fitted into their regions like the texts of our seating cards.
+
+use std::collections::HashMap;
+
+use cottontail::{
+    core::serde_derive::{Deserialize, Serialize},
+    math::Vec2i,
+};
+
+use crate::{
+    expand_text_variables, seating_cards, sheet_canvas::SheetCanvas, DrawParams, LoadedFont,
+    TextRasterizer,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CountdownParams {
+    /// The region of the number relative to the trim box
+    number_pixel_location_left_top_right_bottom: (u32, u32, u32, u32),
+    /// Can contain `{number}` and the same placeholders as `extra_texts`
+    caption: Option<String>,
+    caption_pixel_location_left_top_right_bottom: Option<(u32, u32, u32, u32)>,
+    /// Counts down from our sheet count to 1 instead of counting up from 1
+    #[serde(default)]
+    descending: bool,
+}
+
+impl CountdownParams {
+    /// Returns a description of what is wrong with the countdown if anything
+    pub fn problem(&self) -> Option<&'static str> {
+        let is_valid_region =
+            |(left, top, right, bottom): (u32, u32, u32, u32)| left < right && top < bottom;
+        if !is_valid_region(self.number_pixel_location_left_top_right_bottom) {
+            Some(
+                "must have its left value smaller than its right value and its top value smaller \
+                 than its bottom value in `number_pixel_location_left_top_right_bottom`",
+            )
+        } else if self.caption.is_some()
+            != self.caption_pixel_location_left_top_right_bottom.is_some()
+        {
+            Some("must have both a `caption` and a `caption_pixel_location_left_top_right_bottom`")
+        } else if !self
+            .caption_pixel_location_left_top_right_bottom
+            .map(is_valid_region)
+            .unwrap_or(true)
+        {
+            Some(
+                "must have its left value smaller than its right value and its top value smaller \
+                 than its bottom value in `caption_pixel_location_left_top_right_bottom`",
+            )
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number that is shown on the given (1-based) sheet of the given number of sheets
+    pub fn number_of_sheet(&self, sheet_number: usize, sheet_count: usize) -> u32 {
+        if self.descending && sheet_number <= sheet_count {
+            (sheet_count + 1 - sheet_number) as u32
+        } else {
+            sheet_number as u32
+        }
+    }
+}
+
+pub struct Countdown<'a> {
+    params: CountdownParams,
+    number_rasterizer: TextRasterizer<'a>,
+    caption_rasterizer: TextRasterizer<'a>,
+}
+
+impl<'a> Countdown<'a> {
+    pub fn new(
+        params: &DrawParams,
+        font: &'a LoadedFont,
+        header_font: &'a LoadedFont,
+    ) -> Option<Countdown<'a>> {
+        let countdown_params = params.countdown.clone()?;
+        let number_rasterizer = TextRasterizer::new(
+            font,
+            params.text_font_size as f32,
+            params.number_fill_style(),
+            params.text_direction,
+            params.bold_text,
+            "0123456789",
+        )
+        .with_effects(params.number_effects.as_ref());
+        let caption_rasterizer = TextRasterizer::new(
+            header_font,
+            params
+                .header_text_font_size
+                .unwrap_or(params.text_font_size) as f32,
+            params.header_fill_style(None),
+            params.text_direction,
+            params.bold_text,
+            &format!(
+                "0123456789{}",
+                countdown_params.caption.clone().unwrap_or_default()
+            ),
+        )
+        .with_effects(params.header_text_effects.as_ref());
+        Some(Countdown {
+            params: countdown_params,
+            number_rasterizer,
+            caption_rasterizer,
+        })
+    }
+
+    /// Draws the given number and our caption onto the given sheet whose trim box starts at the
+    /// given offset. The text variables must already contain the `{number}`.
+    pub fn draw(
+        &self,
+        sheet: &mut SheetCanvas,
+        trim_offset: Vec2i,
+        number: u32,
+        text_variables: &HashMap<String, String>,
+        gamma_correct_blending: bool,
+    ) {
+        let region_on_sheet = |(left, top, right, bottom): (u32, u32, u32, u32)| {
+            (
+                trim_offset.x + left as i32,
+                trim_offset.y + top as i32,
+                trim_offset.x + right as i32,
+                trim_offset.y + bottom as i32,
+            )
+        };
+        seating_cards::draw_text_fitted_into_region(
+            &self.number_rasterizer,
+            &number.to_string(),
+            region_on_sheet(self.params.number_pixel_location_left_top_right_bottom),
+            sheet,
+            gamma_correct_blending,
+        );
+        if let (Some(caption), Some(region)) = (
+            &self.params.caption,
+            self.params.caption_pixel_location_left_top_right_bottom,
+        ) {
+            seating_cards::draw_text_fitted_into_region(
+                &self.caption_rasterizer,
+                &expand_text_variables(caption, text_variables),
+                region_on_sheet(region),
+                sheet,
+                gamma_correct_blending,
+            );
+        }
+    }
+}
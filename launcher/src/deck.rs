@@ -0,0 +1,92 @@
+//! The image deck of Lotería cards. The images in our `deck_images_directory` are sorted by
+//! filename and numbered from 1 to 54 in that order. The numbers in our cards and manifest refer to
+//! these images.
+
+use std::collections::HashMap;
+
+use chotto::{generator::RandomSource, loteria};
+use cottontail::{core::collect_files, image::Bitmap};
+
+use crate::{load_cell_image_premultiplied, BACKGROUND_IMAGE_EXTENSIONS, DRAW_PARAMETERS_FILENAME};
+
+pub const CALL_ORDER_FILENAME: &str = "deck_call_order.csv";
+
+/// Returns the filepaths of the images of our deck sorted by filename
+pub fn deck_image_filepaths(directory: &str) -> Vec<String> {
+    let mut filepaths: Vec<String> = collect_files(directory)
+        .into_iter()
+        .filter(|filepath| {
+            BACKGROUND_IMAGE_EXTENSIONS
+                .iter()
+                .any(|extension| filepath.to_lowercase().ends_with(extension))
+        })
+        .collect();
+    filepaths.sort();
+    assert!(
+        filepaths.len() == loteria::DECK_SIZE,
+        "A Lotería deck needs {} images but there are {} images in '{}' - please fix \
+         `deck_images_directory` in '{}'!",
+        loteria::DECK_SIZE,
+        filepaths.len(),
+        directory,
+        DRAW_PARAMETERS_FILENAME
+    );
+    filepaths
+}
+
+fn image_name(filepath: &str) -> String {
+    std::path::Path::new(filepath)
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Returns the image of each card of our deck by its number. The images keep their aspect ratio
+/// and leave a small margin to the borders of their grid cell.
+pub fn load_deck_images_premultiplied(
+    directory: &str,
+    cell_width: i32,
+    cell_height: i32,
+) -> HashMap<i32, Bitmap> {
+    let margin = i32::min(cell_width, cell_height) / 20;
+    deck_image_filepaths(directory)
+        .iter()
+        .enumerate()
+        .map(|(index, filepath)| {
+            let image = load_cell_image_premultiplied(
+                filepath,
+                cell_width - 2 * margin,
+                cell_height - 2 * margin,
+            );
+            (index as i32 + 1, image)
+        })
+        .collect()
+}
+
+/// Writes the order in which the caller draws the images of our deck so that the game can be
+/// replayed from the seed of our run
+pub fn write_call_order(output_directory: &str, directory: &str, random: &mut dyn RandomSource) {
+    let filepaths = deck_image_filepaths(directory);
+    let call_order = loteria::create_call_order(random);
+
+    let filepath = format!("{}/{}", output_directory, CALL_ORDER_FILENAME);
+    let mut writer = csv::Writer::from_path(&filepath)
+        .unwrap_or_else(|error| panic!("Could not create file '{}': {}", filepath, error));
+    writer
+        .write_record(&["call", "card", "image"])
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    for (index, &card) in call_order.iter().enumerate() {
+        writer
+            .write_record(&[
+                (index + 1).to_string(),
+                card.to_string(),
+                image_name(&filepaths[card as usize - 1]),
+            ])
+            .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    }
+    writer
+        .flush()
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    log::info!("Wrote the deck call order to '{}'", filepath);
+}
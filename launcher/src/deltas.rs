@@ -0,0 +1,51 @@
+//! A compact alternative to writing full sheets. We write our background once and only describe
+//! where each card places its numbers. The full sheets can later be created on demand via
+//! `chotto render` which is useful if the sheets are printed somewhere else.
+
+use cottontail::{
+    core::serde_derive::{Deserialize, Serialize},
+    image::Bitmap,
+};
+
+use crate::atomic_file;
+
+pub const BACKGROUND_FILENAME: &str = "background.png";
+pub const DELTAS_DIRECTORY: &str = "deltas";
+
+/// A single number on a sheet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NumberPlacement {
+    pub number: i32,
+    pub column: i32,
+    pub row: i32,
+    /// The center of the number in pixels on the final sheet before its rotation or mirroring
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Everything that we draw on top of the background for a single sheet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CardDelta {
+    pub sheet_number: usize,
+    pub serial: String,
+    pub placements: Vec<NumberPlacement>,
+}
+
+/// Writes the given unpremultiplied background into the given directory
+pub fn write_background(directory: &str, background: &Bitmap) {
+    background.write_to_png_file(&format!("{}/{}", directory, BACKGROUND_FILENAME));
+}
+
+/// Writes the delta of a card as `deltas/sheet_<number>.json` into the given directory
+pub fn write_card_delta(directory: &str, delta: &CardDelta) {
+    let deltas_directory = format!("{}/{}", directory, DELTAS_DIRECTORY);
+    std::fs::create_dir_all(&deltas_directory).unwrap_or_else(|error| {
+        panic!(
+            "Could not create directory '{}': {}",
+            deltas_directory, error
+        )
+    });
+    let filepath = format!("{}/sheet_{}.json", deltas_directory, delta.sheet_number);
+    let delta_string = serde_json::to_string(delta).unwrap();
+    atomic_file::write_file_atomically(&filepath, delta_string.as_bytes());
+}
@@ -0,0 +1,171 @@
+//! Sends each generated sheet as email attachment to the respective row of the recipients file
+
+use std::collections::HashMap;
+
+use cottontail::core::{
+    read_file_whole,
+    serde_derive::{Deserialize, Serialize},
+};
+use lettre::{
+    message::{header::ContentType, Attachment, MultiPart, SinglePart},
+    transport::smtp::authentication::Credentials,
+    Message, SmtpTransport, Transport,
+};
+
+use crate::{
+    collect_sheet_filepaths, command_line_option_value, expand_text_variables, load_draw_params,
+    load_recipients, manifest, DRAW_PARAMETERS_FILENAME, OUTPUT_SHEETS_DIRECTORY,
+    RECIPIENTS_FILENAME,
+};
+
+pub const SMTP_PASSWORD_ENVIRONMENT_VARIABLE: &str = "CHOTTO_SMTP_PASSWORD";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailParams {
+    smtp_server: String,
+    smtp_port: Option<u16>,
+    smtp_username: String,
+    sender_address: String,
+    subject: String,
+    body: String,
+    /// Name of the column in the recipients file that contains the email addresses
+    recipient_address_column: Option<String>,
+}
+
+/// Usage: `chotto email [--directory <dir>]`
+pub fn email_sheets(args: &[String]) {
+    let directory = command_line_option_value(args, "--directory")
+        .unwrap_or_else(|| OUTPUT_SHEETS_DIRECTORY.to_owned());
+    let params = load_draw_params();
+    let email_params = params.email.clone().unwrap_or_else(|| {
+        panic!(
+            "Please first add an `[email]` section to '{}'",
+            DRAW_PARAMETERS_FILENAME
+        )
+    });
+    let password = std::env::var(SMTP_PASSWORD_ENVIRONMENT_VARIABLE).unwrap_or_else(|_| {
+        panic!(
+            "Please set the password of the email account via the `{}` environment variable",
+            SMTP_PASSWORD_ENVIRONMENT_VARIABLE
+        )
+    });
+    let address_column = email_params
+        .recipient_address_column
+        .clone()
+        .unwrap_or_else(|| "email".to_owned());
+
+    let recipients = load_recipients();
+    let sheet_filepaths = collect_sheet_filepaths(&directory, params.directory_layout());
+    assert!(
+        sheet_filepaths.len() == recipients.len(),
+        "There are {} sheets in '{}' but {} rows in '{}' - please create the sheets again first",
+        sheet_filepaths.len(),
+        directory,
+        recipients.len(),
+        RECIPIENTS_FILENAME
+    );
+    // NOTE: Our sheets show the serial of their card as `{card_id}` which is not necessarily their
+    //       sheet number, i.e. with scrambled serial numbers
+    let serials: HashMap<usize, String> = manifest::read_manifest(&directory)
+        .cards
+        .into_iter()
+        .map(|card| (card.sheet_number, card.serial))
+        .collect();
+
+    let mut mailer = SmtpTransport::relay(&email_params.smtp_server)
+        .unwrap_or_else(|error| {
+            panic!(
+                "Could not connect to '{}': {}",
+                email_params.smtp_server, error
+            )
+        })
+        .credentials(Credentials::new(
+            email_params.smtp_username.clone(),
+            password,
+        ));
+    if let Some(port) = email_params.smtp_port {
+        mailer = mailer.port(port);
+    }
+    let mailer = mailer.build();
+
+    let mut text_variables: HashMap<String, String> = HashMap::new();
+    text_variables.insert(
+        "date".to_owned(),
+        chrono::Local::now().format("%Y-%m-%d").to_string(),
+    );
+    if let Some(user_text_variables) = &params.text_variables {
+        text_variables.extend(user_text_variables.clone());
+    }
+
+    for (sheet_index, (recipient, (sheet_number, sheet_filepath))) in
+        recipients.iter().zip(sheet_filepaths.iter()).enumerate()
+    {
+        let serial = serials.get(sheet_number).unwrap_or_else(|| {
+            panic!(
+                "Sheet {} is missing from the manifest of '{}' - please create the sheets again \
+                 first",
+                sheet_number, directory
+            )
+        });
+        let mut sheet_text_variables = text_variables.clone();
+        sheet_text_variables.insert("card_id".to_owned(), serial.clone());
+        sheet_text_variables.extend(recipient.clone());
+
+        let address = recipient.get(&address_column).unwrap_or_else(|| {
+            panic!(
+                "There is no column named '{}' in '{}'",
+                address_column, RECIPIENTS_FILENAME
+            )
+        });
+        let attachment_filename = std::path::Path::new(sheet_filepath)
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let attachment_content_type = if attachment_filename.ends_with(".tif") {
+            "image/tiff"
+        } else {
+            "image/png"
+        };
+        let attachment_data = read_file_whole(sheet_filepath)
+            .unwrap_or_else(|error| panic!("Could not read '{}': {}", sheet_filepath, error));
+
+        let message = Message::builder()
+            .from(email_params.sender_address.parse().unwrap_or_else(|error| {
+                panic!(
+                    "Invalid sender address '{}': {}",
+                    email_params.sender_address, error
+                )
+            }))
+            .to(address.parse().unwrap_or_else(|error| {
+                panic!(
+                    "Invalid email address '{}' in row {} of '{}': {}",
+                    address,
+                    sheet_index + 2,
+                    RECIPIENTS_FILENAME,
+                    error
+                )
+            }))
+            .subject(expand_text_variables(
+                &email_params.subject,
+                &sheet_text_variables,
+            ))
+            .multipart(
+                MultiPart::mixed()
+                    .singlepart(SinglePart::plain(expand_text_variables(
+                        &email_params.body,
+                        &sheet_text_variables,
+                    )))
+                    .singlepart(Attachment::new(attachment_filename).body(
+                        attachment_data,
+                        ContentType::parse(attachment_content_type).unwrap(),
+                    )),
+            )
+            .unwrap_or_else(|error| panic!("Could not create email to '{}': {}", address, error));
+
+        mailer
+            .send(&message)
+            .unwrap_or_else(|error| panic!("Could not send email to '{}': {}", address, error));
+        log::info!("Sent '{}' to '{}'", sheet_filepath, address);
+    }
+}
@@ -0,0 +1,397 @@
+//! Built-in card designs whose backgrounds are generated from simple shapes and gradients. This
+//! lets first-time users create decent sheets without bringing their own background image.
+//!
+//! Usage: `chotto gallery` lists the designs
+//!    or: `chotto gallery <design> [--directory <dir>]`
+//!
+//! Writes a `background.png`, a copy of a system font and the matching draw parameters into the
+//! given directory (the current one by default).
+
+use cottontail::image::{Bitmap, PixelRGBA};
+
+use crate::{
+    command_line_option_value, default_draw_params, draw_params_to_toml_string,
+    find_draw_params_file,
+    png_writer::{self, PngCompression},
+    show_messagebox, ExtraText, DRAW_PARAMETERS_FILENAME, TOML_DOCUMENTATION_HEADER,
+};
+
+/// A4 at 300 dpi
+const SHEET_WIDTH: i32 = 2480;
+const SHEET_HEIGHT: i32 = 3508;
+const TITLE_BAND_TOP_BOTTOM: (i32, i32) = (160, 640);
+const COLUMN_HEADERS_TOP_BOTTOM: (i32, i32) = (720, 1000);
+const GRID_LEFT_TOP_RIGHT_BOTTOM: (i32, i32, i32, i32) = (240, 1040, 2240, 3040);
+const SERIAL_NUMBER_Y: i32 = 3260;
+const BACKGROUND_FILENAME: &str = "background.png";
+
+/// Bold fonts come first as they make the numbers easier to read from afar
+const SYSTEM_FONT_FILEPATHS: &[&str] = &[
+    "C:/Windows/Fonts/arialbd.ttf",
+    "C:/Windows/Fonts/arial.ttf",
+    "C:/Windows/Fonts/verdanab.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans-Bold.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans-Bold.ttf",
+    "/Library/Fonts/Arial Bold.ttf",
+    "/System/Library/Fonts/Supplemental/Arial Bold.ttf",
+];
+
+#[derive(Debug, Clone, Copy)]
+enum Decoration {
+    None,
+    /// Colorful dots around the grid
+    Confetti,
+    /// White dots of different sizes around the grid
+    Snow,
+}
+
+struct Design {
+    name: &'static str,
+    description: &'static str,
+    title: &'static str,
+    gradient_top_rgb: (u8, u8, u8),
+    gradient_bottom_rgb: (u8, u8, u8),
+    title_band_rgb: (u8, u8, u8),
+    title_text_rgb: (u8, u8, u8),
+    frame_rgb: (u8, u8, u8),
+    /// One color per column
+    cell_colors_rgb: [(u8, u8, u8); 5],
+    number_text_rgb: (u8, u8, u8),
+    header_text_rgb: (u8, u8, u8),
+    decoration: Decoration,
+}
+
+const DESIGNS: &[Design] = &[
+    Design {
+        name: "classic",
+        description: "Navy on white like the cards of a Bingo hall",
+        title: "BINGO",
+        gradient_top_rgb: (255, 255, 255),
+        gradient_bottom_rgb: (232, 236, 244),
+        title_band_rgb: (30, 50, 110),
+        title_text_rgb: (255, 255, 255),
+        frame_rgb: (30, 50, 110),
+        cell_colors_rgb: [(255, 255, 255); 5],
+        number_text_rgb: (30, 50, 110),
+        header_text_rgb: (30, 50, 110),
+        decoration: Decoration::None,
+    },
+    Design {
+        name: "kids",
+        description: "Pastel columns and confetti for children's parties",
+        title: "Fun Bingo",
+        gradient_top_rgb: (255, 236, 179),
+        gradient_bottom_rgb: (179, 229, 252),
+        title_band_rgb: (255, 112, 67),
+        title_text_rgb: (255, 255, 255),
+        frame_rgb: (120, 80, 160),
+        cell_colors_rgb: [
+            (255, 205, 210),
+            (255, 224, 178),
+            (255, 249, 196),
+            (200, 230, 201),
+            (187, 222, 251),
+        ],
+        number_text_rgb: (74, 20, 140),
+        header_text_rgb: (74, 20, 140),
+        decoration: Decoration::Confetti,
+    },
+    Design {
+        name: "seasonal",
+        description: "Red, green and snow for winter and holiday events",
+        title: "Holiday Bingo",
+        gradient_top_rgb: (30, 80, 50),
+        gradient_bottom_rgb: (12, 40, 28),
+        title_band_rgb: (178, 34, 34),
+        title_text_rgb: (255, 255, 255),
+        frame_rgb: (230, 200, 120),
+        cell_colors_rgb: [(250, 250, 245); 5],
+        number_text_rgb: (150, 20, 20),
+        header_text_rgb: (240, 210, 130),
+        decoration: Decoration::Snow,
+    },
+];
+
+pub fn create_from_gallery(args: &[String]) {
+    let design_name = match args.first().filter(|arg| !arg.starts_with("--")) {
+        Some(design_name) => design_name,
+        None => {
+            log::info!("Built-in designs (create one via `chotto gallery <design>`):");
+            for design in DESIGNS {
+                log::info!("  {:10} {}", design.name, design.description);
+            }
+            return;
+        }
+    };
+    let design = DESIGNS
+        .iter()
+        .find(|design| design.name == design_name.as_str())
+        .unwrap_or_else(|| {
+            panic!(
+                "There is no built-in design '{}' - please choose one of {:?}",
+                design_name,
+                DESIGNS.iter().map(|design| design.name).collect::<Vec<_>>()
+            )
+        });
+    let directory =
+        command_line_option_value(args, "--directory").unwrap_or_else(|| ".".to_owned());
+    std::fs::create_dir_all(&directory)
+        .unwrap_or_else(|error| panic!("Could not create directory '{}': {}", directory, error));
+    std::env::set_current_dir(&directory)
+        .unwrap_or_else(|error| panic!("Could not enter directory '{}': {}", directory, error));
+    if let Some((params_filepath, _format)) = find_draw_params_file() {
+        panic!(
+            "There is already a '{}' in '{}' - please choose an empty directory via \
+             `--directory <dir>`",
+            params_filepath, directory
+        );
+    }
+
+    let background = create_background(design);
+    png_writer::write_png_file(&background, BACKGROUND_FILENAME, PngCompression::Best);
+
+    // NOTE: We cannot ship a font ourselves so we take one that comes with the operating system
+    let font_filename = SYSTEM_FONT_FILEPATHS.iter().find_map(|filepath| {
+        let filename = std::path::Path::new(filepath)
+            .file_name()?
+            .to_string_lossy();
+        std::fs::copy(filepath, filename.as_ref()).ok()?;
+        Some(filename.to_string())
+    });
+    if font_filename.is_none() {
+        log::warn!(
+            "Could not find a system font - please copy a TTF file into '{}' and set the \
+             `font_filepath` parameter in '{}'",
+            directory,
+            DRAW_PARAMETERS_FILENAME
+        );
+    }
+
+    let (left, top, right, bottom) = GRID_LEFT_TOP_RIGHT_BOTTOM;
+    let cell_height = (bottom - top) / 5;
+    let mut params = default_draw_params(SHEET_WIDTH as u32, SHEET_HEIGHT as u32);
+    params.background_image_filepath = Some(BACKGROUND_FILENAME.to_owned());
+    params.font_filepath = font_filename;
+    params.bingo_grid_pixel_location_left_top_right_bottom =
+        (left as u32, top as u32, right as u32, bottom as u32);
+    params.text_font_size = (cell_height / 2) as u32;
+    params.text_color_rgb = design.number_text_rgb;
+    params.bold_text = true;
+    params.free_space_text = Some("FREE".to_owned());
+    params.column_headers_pixel_location_top_bottom = Some((
+        COLUMN_HEADERS_TOP_BOTTOM.0 as u32,
+        COLUMN_HEADERS_TOP_BOTTOM.1 as u32,
+    ));
+    params.header_text_font_size = Some((cell_height / 2) as u32);
+    params.header_text_color_rgb = Some(design.header_text_rgb);
+    params.serial_number_pixel_location_x_y =
+        Some(((SHEET_WIDTH / 2) as u32, SERIAL_NUMBER_Y as u32));
+    params.extra_texts = Some(vec![ExtraText {
+        text: design.title.to_owned(),
+        x: (SHEET_WIDTH / 2) as u32,
+        y: ((TITLE_BAND_TOP_BOTTOM.0 + TITLE_BAND_TOP_BOTTOM.1) / 2) as u32,
+        size: Some(((TITLE_BAND_TOP_BOTTOM.1 - TITLE_BAND_TOP_BOTTOM.0) / 2) as u32),
+        color: Some(design.title_text_rgb),
+    }]);
+    let params_string = format!(
+        "{}\n\n{}",
+        TOML_DOCUMENTATION_HEADER,
+        draw_params_to_toml_string(&params)
+    );
+    std::fs::write(DRAW_PARAMETERS_FILENAME, &params_string).unwrap_or_else(|error| {
+        panic!(
+            "Could not create file '{}': {}",
+            DRAW_PARAMETERS_FILENAME, error
+        )
+    });
+
+    log::info!("Created the '{}' design in '{}'", design.name, directory);
+    show_messagebox(
+        "Chotto",
+        &format!(
+            "Created the '{}' design in '{}'. Please run `chotto.exe` from there to create our \
+             sheets or change the design in '{}' first.",
+            design.name, directory, DRAW_PARAMETERS_FILENAME
+        ),
+        false,
+    );
+}
+
+fn create_background(design: &Design) -> Bitmap {
+    let mut bitmap = Bitmap::new(SHEET_WIDTH as u32, SHEET_HEIGHT as u32);
+    for y in 0..SHEET_HEIGHT {
+        let color = lerp_rgb(
+            design.gradient_top_rgb,
+            design.gradient_bottom_rgb,
+            y as f32 / (SHEET_HEIGHT - 1) as f32,
+        );
+        for x in 0..SHEET_WIDTH {
+            bitmap.set(x, y, color);
+        }
+    }
+
+    let (grid_left, grid_top, grid_right, grid_bottom) = GRID_LEFT_TOP_RIGHT_BOTTOM;
+    let (band_top, band_bottom) = TITLE_BAND_TOP_BOTTOM;
+    match design.decoration {
+        Decoration::None => {}
+        Decoration::Confetti => draw_dots(&mut bitmap, &design.cell_colors_rgb, 18.0, 42.0, 1.0),
+        Decoration::Snow => draw_dots(&mut bitmap, &[(255, 255, 255)], 6.0, 26.0, 0.8),
+    }
+
+    let margin = 60.0;
+    fill_rounded_rect(
+        &mut bitmap,
+        (
+            margin,
+            band_top as f32,
+            (SHEET_WIDTH as f32) - margin,
+            band_bottom as f32,
+        ),
+        80.0,
+        design.title_band_rgb,
+    );
+    fill_rounded_rect(
+        &mut bitmap,
+        (
+            grid_left as f32 - 24.0,
+            grid_top as f32 - 24.0,
+            grid_right as f32 + 24.0,
+            grid_bottom as f32 + 24.0,
+        ),
+        48.0,
+        design.frame_rgb,
+    );
+    let cell_width = (grid_right - grid_left) / 5;
+    let cell_height = (grid_bottom - grid_top) / 5;
+    for row in 0..5 {
+        for column in 0..5 {
+            let left = (grid_left + column * cell_width) as f32;
+            let top = (grid_top + row * cell_height) as f32;
+            fill_rounded_rect(
+                &mut bitmap,
+                (
+                    left + 10.0,
+                    top + 10.0,
+                    left + cell_width as f32 - 10.0,
+                    top + cell_height as f32 - 10.0,
+                ),
+                36.0,
+                design.cell_colors_rgb[column as usize],
+            );
+        }
+    }
+    bitmap
+}
+
+/// Scatters dots of the given colors evenly over the sheet while keeping the title band and the
+/// grid free
+fn draw_dots(
+    bitmap: &mut Bitmap,
+    colors_rgb: &[(u8, u8, u8)],
+    min_radius: f32,
+    max_radius: f32,
+    opacity: f32,
+) {
+    const DOT_COUNT: usize = 400;
+    // NOTE: The additive recurrence with the plastic number spreads the dots evenly without
+    //       clumps and gives the same pattern every time
+    const PLASTIC_NUMBER: f64 = 1.324_717_957_244_746;
+    let step_x = 1.0 / PLASTIC_NUMBER;
+    let step_y = 1.0 / (PLASTIC_NUMBER * PLASTIC_NUMBER);
+    let (grid_left, _grid_top, grid_right, grid_bottom) = GRID_LEFT_TOP_RIGHT_BOTTOM;
+    let (band_top, band_bottom) = TITLE_BAND_TOP_BOTTOM;
+    for index in 0..DOT_COUNT {
+        let x = ((0.5 + step_x * index as f64) % 1.0) as f32 * SHEET_WIDTH as f32;
+        let y = ((0.5 + step_y * index as f64) % 1.0) as f32 * SHEET_HEIGHT as f32;
+        let size = ((index as f64 * 0.618_034) % 1.0) as f32;
+        let radius = min_radius + size * (max_radius - min_radius);
+        let is_on_grid = x + radius > (grid_left - 40) as f32
+            && x - radius < (grid_right + 40) as f32
+            && y + radius > (COLUMN_HEADERS_TOP_BOTTOM.0 - 40) as f32
+            && y - radius < (grid_bottom + 40) as f32;
+        let is_on_band =
+            y + radius > (band_top - 30) as f32 && y - radius < (band_bottom + 30) as f32;
+        let is_on_serial_number = (y - SERIAL_NUMBER_Y as f32).abs() < 120.0
+            && (x - (SHEET_WIDTH / 2) as f32).abs() < 500.0;
+        if is_on_grid || is_on_band || is_on_serial_number {
+            continue;
+        }
+        let color = colors_rgb[index % colors_rgb.len()];
+        fill_rounded_rect_blended(
+            bitmap,
+            (x - radius, y - radius, x + radius, y + radius),
+            radius,
+            color,
+            opacity,
+        );
+    }
+}
+
+/// Fills the given rect as (left, top, right, bottom) with rounded corners of the given radius
+fn fill_rounded_rect(
+    bitmap: &mut Bitmap,
+    rect: (f32, f32, f32, f32),
+    corner_radius: f32,
+    color_rgb: (u8, u8, u8),
+) {
+    fill_rounded_rect_blended(bitmap, rect, corner_radius, color_rgb, 1.0);
+}
+
+/// Like `fill_rounded_rect` but blends the color with the given opacity. Rects whose corner radius
+/// is half their size are circles. The edges are anti-aliased.
+fn fill_rounded_rect_blended(
+    bitmap: &mut Bitmap,
+    (left, top, right, bottom): (f32, f32, f32, f32),
+    corner_radius: f32,
+    color_rgb: (u8, u8, u8),
+    opacity: f32,
+) {
+    let center_x = (left + right) / 2.0;
+    let center_y = (top + bottom) / 2.0;
+    let half_width = (right - left) / 2.0;
+    let half_height = (bottom - top) / 2.0;
+    let first_x = i32::max(0, left.floor() as i32);
+    let last_x = i32::min(bitmap.width as i32 - 1, right.ceil() as i32);
+    let first_y = i32::max(0, top.floor() as i32);
+    let last_y = i32::min(bitmap.height as i32 - 1, bottom.ceil() as i32);
+    for y in first_y..=last_y {
+        for x in first_x..=last_x {
+            // NOTE: This is the signed distance of the pixel center to the edge of the rounded rect
+            let offset_x = (x as f32 + 0.5 - center_x).abs() - (half_width - corner_radius);
+            let offset_y = (y as f32 + 0.5 - center_y).abs() - (half_height - corner_radius);
+            let outside_distance =
+                (f32::max(offset_x, 0.0).powi(2) + f32::max(offset_y, 0.0).powi(2)).sqrt();
+            let inside_distance = f32::min(f32::max(offset_x, offset_y), 0.0);
+            let distance = outside_distance + inside_distance - corner_radius;
+            let coverage = (0.5 - distance).max(0.0).min(1.0) * opacity;
+            if coverage > 0.0 {
+                let background = bitmap.get(x, y);
+                let blend = |background: u8, color: u8| {
+                    (background as f32 + coverage * (color as f32 - background as f32)).round()
+                        as u8
+                };
+                bitmap.set(
+                    x,
+                    y,
+                    PixelRGBA::new(
+                        blend(background.r, color_rgb.0),
+                        blend(background.g, color_rgb.1),
+                        blend(background.b, color_rgb.2),
+                        255,
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn lerp_rgb(start: (u8, u8, u8), end: (u8, u8, u8), percent: f32) -> PixelRGBA {
+    let lerp =
+        |start: u8, end: u8| (start as f32 + percent * (end as f32 - start as f32)).round() as u8;
+    PixelRGBA::new(
+        lerp(start.0, end.0),
+        lerp(start.1, end.1),
+        lerp(start.2, end.2),
+        255,
+    )
+}
@@ -0,0 +1,612 @@
+//! Generation of random number grids for 75-ball, 80-ball and 30-ball Bingo
+
+use std::{cell::RefCell, collections::HashSet};
+
+use cottontail::{image::Grid, math::Random};
+
+/// Consecutive rejected grids after which we give up because our constraints are too strict
+const MAX_REJECTED_GRID_COUNT: usize = 100_000;
+
+/// A source of random numbers for our generators. Besides the fast `Random` of cottontail this is
+/// implemented by a cryptographically secure generator for regulated games.
+pub trait RandomSource {
+    /// Returns a uniformly distributed number in `0..bound`
+    fn u32_bounded_exclusive(&mut self, bound: u32) -> u32;
+}
+
+impl RandomSource for Random {
+    fn u32_bounded_exclusive(&mut self, bound: u32) -> u32 {
+        Random::u32_bounded_exclusive(self, bound)
+    }
+}
+
+/// Hands out the given elements in random order. After all elements were handed out it starts
+/// again with all of them.
+pub struct RandomBag<T: Clone> {
+    pub elems: Vec<T>,
+    /// The elements before this index were already handed out in the current round
+    next_index: usize,
+}
+
+impl<T: Clone> RandomBag<T> {
+    pub fn new(elems: Vec<T>) -> RandomBag<T> {
+        RandomBag {
+            elems,
+            next_index: 0,
+        }
+    }
+
+    /// Starts a new round with all elements
+    pub fn reset(&mut self) {
+        self.next_index = 0;
+    }
+
+    pub fn get_next(&mut self, random: &mut dyn RandomSource) -> T {
+        assert!(
+            !self.elems.is_empty(),
+            "Cannot pick an element of an empty bag"
+        );
+        if self.next_index == self.elems.len() {
+            self.reset();
+        }
+        let remaining_count = (self.elems.len() - self.next_index) as u32;
+        let index = self.next_index + random.u32_bounded_exclusive(remaining_count) as usize;
+        self.elems.swap(self.next_index, index);
+        self.next_index += 1;
+        self.elems[self.next_index - 1].clone()
+    }
+}
+
+/// A rule that each newly generated grid must follow. This can be used to inject custom rules like
+/// "no card may contain both 13 and 66" into the generators.
+pub trait CardConstraint {
+    /// Returns true if the `candidate` grid may be added to the `existing` grids. Empty cells and
+    /// the free space are 0.
+    fn accept(&self, candidate: &Grid<i32>, existing: &[Grid<i32>]) -> bool;
+}
+
+/// Rejects grids that contain all of the given numbers at once
+pub struct ForbiddenNumberCombination {
+    pub numbers: Vec<i32>,
+}
+
+impl CardConstraint for ForbiddenNumberCombination {
+    fn accept(&self, candidate: &Grid<i32>, _existing: &[Grid<i32>]) -> bool {
+        !self
+            .numbers
+            .iter()
+            .all(|number| candidate.data.contains(number))
+    }
+}
+
+/// Makes it less likely that several cards complete a line at the same call. Grids are rejected if
+/// one of their lines (rows and for square grids also columns and diagonals) shares too many
+/// numbers with a line of an existing grid.
+/// NOTE: We start strict and allow more shared numbers per line whenever we rejected too many grids
+///       in a row, similar to the matching cells tolerance of our column picker
+pub struct MinimizeSharedLines {
+    state: RefCell<SharedLinesState>,
+}
+
+struct SharedLinesState {
+    /// Lines must not contain this many numbers of an existing line
+    shared_numbers_limit: usize,
+    /// All subsets of size `shared_numbers_limit` of the lines of the first `indexed_grid_count`
+    /// existing grids
+    line_subsets: HashSet<Vec<i32>>,
+    indexed_grid_count: usize,
+    rejected_grid_count: usize,
+}
+
+const INITIAL_SHARED_NUMBERS_LIMIT: usize = 3;
+const MAX_REJECTED_GRIDS_PER_SHARED_NUMBERS_LIMIT: usize = 2_000;
+
+impl MinimizeSharedLines {
+    pub fn new() -> MinimizeSharedLines {
+        MinimizeSharedLines {
+            state: RefCell::new(SharedLinesState {
+                shared_numbers_limit: INITIAL_SHARED_NUMBERS_LIMIT,
+                line_subsets: HashSet::new(),
+                indexed_grid_count: 0,
+                rejected_grid_count: 0,
+            }),
+        }
+    }
+
+    /// Returns all subsets of the given size of each line of the given grid
+    fn line_subsets(grid: &Grid<i32>, subset_size: usize) -> Vec<Vec<i32>> {
+        let width = grid.width as i32;
+        let height = grid.height as i32;
+        let mut lines: Vec<Vec<i32>> = (0..height)
+            .map(|y| (0..width).map(|x| grid.get(x, y)).collect())
+            .collect();
+        if width == height {
+            lines.extend((0..width).map(|x| (0..height).map(|y| grid.get(x, y)).collect()));
+            lines.push((0..width).map(|index| grid.get(index, index)).collect());
+            lines.push(
+                (0..width)
+                    .map(|index| grid.get(width - 1 - index, index))
+                    .collect(),
+            );
+        }
+        lines
+            .into_iter()
+            .flat_map(|mut line| {
+                line.retain(|number| *number != 0);
+                line.sort();
+                get_all_subsets_of_size_k(subset_size, &line)
+            })
+            .collect()
+    }
+}
+
+impl Default for MinimizeSharedLines {
+    fn default() -> Self {
+        MinimizeSharedLines::new()
+    }
+}
+
+impl CardConstraint for MinimizeSharedLines {
+    fn accept(&self, candidate: &Grid<i32>, existing: &[Grid<i32>]) -> bool {
+        let mut state = self.state.borrow_mut();
+        if state.rejected_grid_count >= MAX_REJECTED_GRIDS_PER_SHARED_NUMBERS_LIMIT {
+            state.shared_numbers_limit += 1;
+            state.line_subsets.clear();
+            state.indexed_grid_count = 0;
+            state.rejected_grid_count = 0;
+            log::debug!(
+                "Allowing lines to share {} numbers after {} grids",
+                state.shared_numbers_limit - 1,
+                existing.len()
+            );
+        }
+        for grid in &existing[state.indexed_grid_count..] {
+            let subsets = MinimizeSharedLines::line_subsets(grid, state.shared_numbers_limit);
+            state.line_subsets.extend(subsets);
+        }
+        state.indexed_grid_count = existing.len();
+
+        let accepted = MinimizeSharedLines::line_subsets(candidate, state.shared_numbers_limit)
+            .iter()
+            .all(|subset| !state.line_subsets.contains(subset));
+        if accepted {
+            state.rejected_grid_count = 0;
+        } else {
+            state.rejected_grid_count += 1;
+        }
+        accepted
+    }
+}
+
+/// Returns true if the candidate grid passes all given constraints
+pub fn accepted_by_all(
+    constraints: &[&dyn CardConstraint],
+    candidate: &Grid<i32>,
+    existing: &[Grid<i32>],
+) -> bool {
+    constraints
+        .iter()
+        .all(|constraint| constraint.accept(candidate, existing))
+}
+
+/// Returns true if the candidate grid has exactly the same numbers at the same places as one of
+/// the existing grids
+pub fn is_duplicate(candidate: &Grid<i32>, existing: &[Grid<i32>]) -> bool {
+    existing.iter().any(|grid| grid.data == candidate.data)
+}
+
+/// Panics if we rejected too many grids in a row
+pub fn check_rejected_grid_count(rejected_grid_count: usize) {
+    assert!(
+        rejected_grid_count < MAX_REJECTED_GRID_COUNT,
+        "Could not generate a grid that satisfies all constraints after {} tries - \
+         are the constraints too strict?",
+        MAX_REJECTED_GRID_COUNT
+    );
+}
+
+/// Picks random column arrangements that are as different as possible to the previously picked
+/// arrangements of the same column
+struct ColumnPicker {
+    bag: RandomBag<Vec<i32>>,
+    picked: Vec<Vec<i32>>,
+    matching_cells_tolerance: usize,
+    failed_pick_count: usize,
+}
+
+impl ColumnPicker {
+    fn new(numbers: &[i32], row_count: usize) -> ColumnPicker {
+        ColumnPicker {
+            bag: RandomBag::new(get_all_possible_arrangements_of_size_k(row_count, numbers)),
+            picked: Vec::new(),
+            matching_cells_tolerance: 0,
+            failed_pick_count: 0,
+        }
+    }
+
+    fn pick(&mut self, col_index: usize, random: &mut dyn RandomSource) -> Vec<i32> {
+        loop {
+            let new_column = self.bag.get_next(random);
+
+            if self
+                .picked
+                .iter()
+                .map(|previous_column| count_matching_cells(&new_column, previous_column))
+                .max()
+                .unwrap_or(0)
+                > self.matching_cells_tolerance
+            {
+                self.failed_pick_count += 1;
+                if self.failed_pick_count >= self.bag.elems.len() {
+                    // We tried out all possible arrangements with this tolerance level.
+                    // To generate more columns we need to increase our tolerance and therefore
+                    // allow new columns to be more similar to existing ones.
+                    self.matching_cells_tolerance += 1;
+                    self.failed_pick_count = 0;
+                    log::debug!(
+                        "Increased matching cells tolerance of column {} to {} after {} columns",
+                        col_index,
+                        self.matching_cells_tolerance,
+                        self.picked.len()
+                    );
+                    self.bag.reset();
+                }
+                continue;
+            }
+
+            return new_column;
+        }
+    }
+}
+
+/// NOTE: In this function we make sure that each column k of each newly generated grid is
+///       maximally different to each respective column k of the previously generated grids.
+///       We do this by first generating all possible arrangements for each column. Then for
+///       each new grid we randomly pick one such arrangement for column k until we get a column
+///       that is different enough from the respective column k of the previous grids.
+/// NOTE: Doing this column based approach is more precise and faster than comparing whole grids
+///       because we can test all possibilities for each column faster. The problem is that our
+///       solution space is smaller than with the grid-based approach. This is ok for our case
+///       though as we won't generate more than a few thousand sheets
+/// NOTE: If a grid assembled from the picked columns is rejected by one of the given constraints
+///       we throw its columns away and try again
+/// NOTE: The `free_cells` are given as (column, row) and stay empty (0)
+/// NOTE: The `previous_grids` are grids of an earlier batch which we want to extend. The new grids
+///       are made as different as possible to them as well and never duplicate them.
+///
+pub fn create_random_number_grids(
+    sheet_count: usize,
+    excluded_numbers: &[i32],
+    free_cells: &[(i32, i32)],
+    constraints: &[&dyn CardConstraint],
+    previous_grids: &[Grid<i32>],
+    random: &mut dyn RandomSource,
+) -> Vec<Grid<i32>> {
+    create_random_number_grids_from_columns(
+        &column_numbers_75_ball(excluded_numbers),
+        5,
+        sheet_count,
+        free_cells,
+        constraints,
+        previous_grids,
+        random,
+    )
+}
+
+/// Creates 3x3 grids for 30-ball speed Bingo. Works like `create_random_number_grids` but without
+/// free cells.
+pub fn create_random_number_grids_30_ball(
+    sheet_count: usize,
+    constraints: &[&dyn CardConstraint],
+    previous_grids: &[Grid<i32>],
+    random: &mut dyn RandomSource,
+) -> Vec<Grid<i32>> {
+    create_random_number_grids_from_columns(
+        &column_numbers_30_ball(),
+        3,
+        sheet_count,
+        &[],
+        constraints,
+        previous_grids,
+        random,
+    )
+}
+
+/// Creates 4x4 grids for 80-ball Bingo. Works like `create_random_number_grids` but without free
+/// cells.
+pub fn create_random_number_grids_80_ball(
+    sheet_count: usize,
+    constraints: &[&dyn CardConstraint],
+    previous_grids: &[Grid<i32>],
+    random: &mut dyn RandomSource,
+) -> Vec<Grid<i32>> {
+    create_random_number_grids_from_columns(
+        &column_numbers_80_ball(),
+        4,
+        sheet_count,
+        &[],
+        constraints,
+        previous_grids,
+        random,
+    )
+}
+
+/// Creates grids with one column for each of the given column numbers and the given number of rows
+fn create_random_number_grids_from_columns(
+    column_numbers: &[Vec<i32>],
+    row_count: usize,
+    sheet_count: usize,
+    free_cells: &[(i32, i32)],
+    constraints: &[&dyn CardConstraint],
+    previous_grids: &[Grid<i32>],
+    random: &mut dyn RandomSource,
+) -> Vec<Grid<i32>> {
+    log::debug!(
+        "Generating {} number grids after {} previous grids",
+        sheet_count,
+        previous_grids.len()
+    );
+
+    let column_count = column_numbers.len();
+    let mut column_pickers: Vec<ColumnPicker> = column_numbers
+        .iter()
+        .map(|numbers| ColumnPicker::new(numbers, row_count))
+        .collect();
+    for previous_grid in previous_grids {
+        for (x, column_picker) in column_pickers.iter_mut().enumerate() {
+            let column = (0..row_count as i32)
+                .map(|y| previous_grid.get(x as i32, y))
+                .collect();
+            column_picker.picked.push(column);
+        }
+    }
+
+    let mut grids: Vec<Grid<i32>> = previous_grids.to_vec();
+    let mut rejected_grid_count = 0;
+    while grids.len() < previous_grids.len() + sheet_count {
+        let columns: Vec<Vec<i32>> = column_pickers
+            .iter_mut()
+            .enumerate()
+            .map(|(col_index, column_picker)| column_picker.pick(col_index, random))
+            .collect();
+
+        let mut grid = Grid::new(column_count as u32, row_count as u32);
+        for y in 0..row_count as i32 {
+            for x in 0..column_count as i32 {
+                if free_cells.contains(&(x, y)) {
+                    continue;
+                }
+
+                grid.set(x, y, columns[x as usize][y as usize]);
+            }
+        }
+
+        if is_duplicate(&grid, &grids) || !accepted_by_all(constraints, &grid, &grids) {
+            rejected_grid_count += 1;
+            check_rejected_grid_count(rejected_grid_count);
+            continue;
+        }
+        rejected_grid_count = 0;
+
+        for (column_picker, column) in column_pickers.iter_mut().zip(columns.into_iter()) {
+            column_picker.picked.push(column);
+        }
+        grids.push(grid);
+    }
+
+    grids.split_off(previous_grids.len())
+}
+
+/// Returns the numbers that can appear in each column of a 75-ball grid. Column 0 contains 1-15,
+/// column 1 contains 16-30 and so on.
+pub fn column_numbers_75_ball(excluded_numbers: &[i32]) -> Vec<Vec<i32>> {
+    (0..5)
+        .map(|column| {
+            (15 * column + 1..=15 * column + 15)
+                .filter(|number| !excluded_numbers.contains(number))
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the numbers that can appear in each column of an 80-ball grid. Column 0 contains 1-20,
+/// column 1 contains 21-40 and so on.
+pub fn column_numbers_80_ball() -> Vec<Vec<i32>> {
+    (0..4)
+        .map(|column| (20 * column + 1..=20 * column + 20).collect())
+        .collect()
+}
+
+/// Returns the numbers that can appear in each column of a 30-ball grid. Column 0 contains 1-10,
+/// column 1 contains 11-20 and column 2 contains 21-30.
+pub fn column_numbers_30_ball() -> Vec<Vec<i32>> {
+    (0..3)
+        .map(|column| (10 * column + 1..=10 * column + 10).collect())
+        .collect()
+}
+
+fn get_all_possible_arrangements_of_size_k<ElemType: Clone + Copy + Eq + PartialEq>(
+    k: usize,
+    elements: &[ElemType],
+) -> Vec<Vec<ElemType>> {
+    assert!(0 < k && k <= elements.len());
+
+    if k == 1 {
+        let mut result = Vec::new();
+        for elem in elements {
+            result.push(vec![*elem]);
+        }
+        return result;
+    }
+
+    let k_minus_one_subsets = get_all_possible_arrangements_of_size_k(k - 1, elements);
+
+    let mut result = Vec::new();
+    for k_minus_one_subset in k_minus_one_subsets {
+        for &fixed in elements {
+            let mut subset = k_minus_one_subset.clone();
+            if subset.contains(&fixed) {
+                continue;
+            }
+            subset.push(fixed);
+            result.push(subset);
+        }
+    }
+
+    result
+}
+
+/// Returns all subsets of the given size where each subset keeps the order of the given elements
+fn get_all_subsets_of_size_k<ElemType: Clone>(
+    k: usize,
+    elements: &[ElemType],
+) -> Vec<Vec<ElemType>> {
+    if k == 0 {
+        return vec![Vec::new()];
+    }
+    if elements.len() < k {
+        return Vec::new();
+    }
+    let mut result: Vec<Vec<ElemType>> = get_all_subsets_of_size_k(k - 1, &elements[1..])
+        .into_iter()
+        .map(|mut subset| {
+            subset.insert(0, elements[0].clone());
+            subset
+        })
+        .collect();
+    result.extend(get_all_subsets_of_size_k(k, &elements[1..]));
+    result
+}
+
+fn count_matching_cells(column: &[i32], existing_column: &[i32]) -> usize {
+    column
+        .iter()
+        .zip(existing_column.iter())
+        .filter(|(left, right)| left == right)
+        .count()
+}
+
+/// Returns the given elements in random order
+pub fn shuffled<T: Clone>(elems: Vec<T>, random: &mut dyn RandomSource) -> Vec<T> {
+    let count = elems.len();
+    let mut bag = RandomBag::new(elems);
+    (0..count).map(|_| bag.get_next(random)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(grid: &Grid<i32>, x: i32) -> Vec<i32> {
+        (0..grid.height as i32).map(|y| grid.get(x, y)).collect()
+    }
+
+    #[test]
+    fn column_picker_picks_arrangements_within_its_tolerance() {
+        let numbers: Vec<i32> = (1..=15).collect();
+        let mut random = Random::new_from_seed(1234);
+        let mut column_picker = ColumnPicker::new(&numbers, 5);
+        for _ in 0..500 {
+            let column = column_picker.pick(0, &mut random);
+            assert_eq!(column.len(), 5);
+            assert!(column.iter().all(|number| numbers.contains(number)));
+            assert_eq!(column.iter().collect::<HashSet<_>>().len(), column.len());
+            for previous_column in &column_picker.picked {
+                assert!(
+                    count_matching_cells(&column, previous_column)
+                        <= column_picker.matching_cells_tolerance
+                );
+            }
+            column_picker.picked.push(column);
+        }
+    }
+
+    #[test]
+    fn grids_have_unique_numbers_from_their_columns() {
+        let excluded_numbers = [7, 13, 42, 66];
+        let free_cells = [(2, 2)];
+        let grids = create_random_number_grids(
+            300,
+            &excluded_numbers,
+            &free_cells,
+            &[],
+            &[],
+            &mut Random::new_from_seed(1234),
+        );
+        assert_eq!(grids.len(), 300);
+        let column_numbers = column_numbers_75_ball(&excluded_numbers);
+        for (grid_index, grid) in grids.iter().enumerate() {
+            assert!(!is_duplicate(grid, &grids[..grid_index]));
+            assert_eq!(grid.get(2, 2), 0);
+            for x in 0..5 {
+                let numbers: Vec<i32> = column(grid, x)
+                    .into_iter()
+                    .filter(|&number| number != 0)
+                    .collect();
+                assert_eq!(numbers.len(), if x == 2 { 4 } else { 5 });
+                assert_eq!(numbers.iter().collect::<HashSet<_>>().len(), numbers.len());
+                assert!(numbers
+                    .iter()
+                    .all(|number| column_numbers[x as usize].contains(number)));
+                assert!(numbers
+                    .iter()
+                    .all(|number| !excluded_numbers.contains(number)));
+            }
+        }
+    }
+
+    #[test]
+    fn forbidden_number_combinations_never_appear() {
+        let forbidden = ForbiddenNumberCombination {
+            numbers: vec![1, 11],
+        };
+        let grids = create_random_number_grids_30_ball(
+            300,
+            &[&forbidden],
+            &[],
+            &mut Random::new_from_seed(1234),
+        );
+        assert_eq!(grids.len(), 300);
+        assert!(grids.iter().all(|grid| forbidden.accept(grid, &[])));
+        assert!(grids
+            .iter()
+            .any(|grid| grid.data.contains(&1) || grid.data.contains(&11)));
+    }
+
+    #[test]
+    fn minimize_shared_lines_keeps_lines_of_different_grids_apart() {
+        let minimize_shared_lines = MinimizeSharedLines::new();
+        let previous_grids =
+            create_random_number_grids_80_ball(20, &[], &[], &mut Random::new_from_seed(4321));
+        let grids = create_random_number_grids_80_ball(
+            100,
+            &[&minimize_shared_lines],
+            &previous_grids,
+            &mut Random::new_from_seed(1234),
+        );
+        assert_eq!(grids.len(), 100);
+
+        // NOTE: Grids that were accepted with a lower limit also keep the final limit
+        let shared_numbers_limit = minimize_shared_lines.state.borrow().shared_numbers_limit;
+        let grid_line_subsets: Vec<HashSet<Vec<i32>>> = grids
+            .iter()
+            .map(|grid| {
+                MinimizeSharedLines::line_subsets(grid, shared_numbers_limit)
+                    .into_iter()
+                    .collect()
+            })
+            .collect();
+        for (grid_index, line_subsets) in grid_line_subsets.iter().enumerate() {
+            for previous_grid in &previous_grids {
+                assert!(
+                    MinimizeSharedLines::line_subsets(previous_grid, shared_numbers_limit)
+                        .iter()
+                        .all(|subset| !line_subsets.contains(subset))
+                );
+            }
+            for other_line_subsets in &grid_line_subsets[..grid_index] {
+                assert!(line_subsets.is_disjoint(other_line_subsets));
+            }
+        }
+    }
+}
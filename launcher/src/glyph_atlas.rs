@@ -0,0 +1,172 @@
+//! The rasterized glyphs and text layouts of a font in one size. Each atlas is immutable once it
+//! is built so that all our worker threads can use it without locking. Every font keeps the
+//! atlases of the sizes that it is drawn in, which lets all text rasterizers of the same font and
+//! size share their glyphs no matter what color or gradient they use.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use cottontail::{image::Bitmap, math::Vec2i};
+
+/// Where the glyphs of a text go in its (untrimmed) text bitmap
+#[derive(Debug, Clone)]
+pub struct TextLayout {
+    pub width: u32,
+    pub height: u32,
+    pub glyphs: Vec<(char, Vec2i)>,
+}
+
+pub struct GlyphAtlas {
+    font_size: f32,
+    /// The glyphs are white so that they can be colored once the whole text is put together
+    glyph_bitmaps_premultiplied: HashMap<char, Bitmap>,
+    /// The layouts of the texts that we know upfront like our numbers
+    layouts: HashMap<String, TextLayout>,
+}
+
+impl GlyphAtlas {
+    /// Returns the white glyph of the given character which is rasterized on the fly if it is not
+    /// part of our atlas
+    pub fn glyph(&self, font: &fontdue::Font, character: char) -> Cow<Bitmap> {
+        match self.glyph_bitmaps_premultiplied.get(&character) {
+            Some(bitmap) => Cow::Borrowed(bitmap),
+            None => Cow::Owned(create_glyph_bitmap_premultiplied(
+                font,
+                self.font_size,
+                character,
+            )),
+        }
+    }
+
+    /// Returns the layout of the given text which is laid out on the fly if it is not part of our
+    /// atlas
+    pub fn layout(&self, font: &fontdue::Font, text: &str) -> Cow<TextLayout> {
+        match self.layouts.get(text) {
+            Some(layout) => Cow::Borrowed(layout),
+            None => Cow::Owned(layout_text(font, self.font_size, text)),
+        }
+    }
+
+    fn contains(&self, characters: &str, texts: &[&str]) -> bool {
+        characters
+            .chars()
+            .all(|character| self.glyph_bitmaps_premultiplied.contains_key(&character))
+            && texts.iter().all(|text| self.layouts.contains_key(*text))
+    }
+}
+
+/// The atlases of a font by their font size
+#[derive(Default)]
+pub struct GlyphAtlases {
+    atlases: RwLock<HashMap<u32, Arc<GlyphAtlas>>>,
+}
+
+impl GlyphAtlases {
+    /// Returns the atlas of the given size which contains at least the given characters and the
+    /// layouts of the given texts. If our current atlas misses some of them we replace it with a
+    /// bigger one. Rasterizers that still hold the old atlas keep working with it.
+    pub fn get(
+        &self,
+        font: &fontdue::Font,
+        font_size: f32,
+        characters: &str,
+        texts: &[&str],
+    ) -> Arc<GlyphAtlas> {
+        let key = font_size.to_bits();
+        if let Some(atlas) = self.atlases.read().unwrap().get(&key) {
+            if atlas.contains(characters, texts) {
+                return atlas.clone();
+            }
+        }
+
+        let mut atlases = self.atlases.write().unwrap();
+        let mut glyph_bitmaps_premultiplied = HashMap::new();
+        let mut layouts = HashMap::new();
+        if let Some(atlas) = atlases.get(&key) {
+            if atlas.contains(characters, texts) {
+                return atlas.clone();
+            }
+            glyph_bitmaps_premultiplied = atlas.glyph_bitmaps_premultiplied.clone();
+            layouts = atlas.layouts.clone();
+        }
+        // NOTE: The glyphs of our texts are needed as well when drawing them
+        for character in characters
+            .chars()
+            .chain(texts.iter().flat_map(|text| text.chars()))
+        {
+            glyph_bitmaps_premultiplied
+                .entry(character)
+                .or_insert_with(|| create_glyph_bitmap_premultiplied(font, font_size, character));
+        }
+        for text in texts {
+            layouts
+                .entry(text.to_string())
+                .or_insert_with(|| layout_text(font, font_size, text));
+        }
+
+        let atlas = Arc::new(GlyphAtlas {
+            font_size,
+            glyph_bitmaps_premultiplied,
+            layouts,
+        });
+        atlases.insert(key, atlas.clone());
+        atlas
+    }
+}
+
+/// Returns the glyph in white
+fn create_glyph_bitmap_premultiplied(font: &fontdue::Font, font_size: f32, glyph: char) -> Bitmap {
+    let (metrics, image_bytes) = font.rasterize(glyph, font_size);
+    Bitmap::from_greyscale_bytes_premultiplied(
+        &image_bytes,
+        metrics.width as u32,
+        metrics.height as u32,
+    )
+}
+
+/// Lays out the given simple text in a single line
+fn layout_text(font: &fontdue::Font, font_size: f32, text: &str) -> TextLayout {
+    let mut layout = fontdue::layout::Layout::new(fontdue::layout::CoordinateSystem::PositiveYDown);
+    layout.append(
+        &[font],
+        &fontdue::layout::TextStyle::new(text, font_size, 0),
+    );
+    let glyphs = layout.glyphs();
+
+    let x_min = glyphs
+        .iter()
+        .fold(std::f32::MAX, |acc, glyph_pos| f32::min(acc, glyph_pos.x));
+    let y_min = glyphs
+        .iter()
+        .fold(std::f32::MAX, |acc, glyph_pos| f32::min(acc, glyph_pos.y));
+    let offset_x = if x_min < 0.0 { -x_min } else { 0.0 };
+    let offset_y = if y_min < 0.0 { -y_min } else { 0.0 };
+    let x_max = offset_x
+        + glyphs.iter().fold(std::f32::MIN, |acc, glyph_pos| {
+            f32::max(acc, glyph_pos.x + glyph_pos.width as f32)
+        });
+    let y_max = offset_y
+        + glyphs.iter().fold(std::f32::MIN, |acc, glyph_pos| {
+            f32::max(acc, glyph_pos.y + glyph_pos.height as f32)
+        });
+
+    TextLayout {
+        width: x_max.ceil() as u32,
+        height: y_max.ceil() as u32,
+        glyphs: glyphs
+            .iter()
+            .map(|glyph_pos| {
+                (
+                    glyph_pos.key.c,
+                    Vec2i::new(
+                        (offset_x + glyph_pos.x.round()) as i32,
+                        (offset_y + glyph_pos.y.round()) as i32,
+                    ),
+                )
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,700 @@
+//! Draws the grid cells of our sheets on the graphics card with `compositing_backend = "gpu"`. This
+//! is only compiled with the `gpu` feature. We upload the part of our background that lies under
+//! the grid and an atlas of all bitmaps that our cells are made of once. Each sheet then only needs
+//! a list of quads that place these bitmaps. The GPU draws the grid regions of many sheets one
+//! after another and we read them back in batches. Everything outside of the grid is still drawn
+//! on the CPU afterwards.
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    num::NonZeroU32,
+};
+
+use cottontail::{
+    image::{Bitmap, PixelRGBA},
+    math::Vec2i,
+};
+use wgpu::util::DeviceExt;
+
+use crate::{cells, manifest::Card, CardCellRenderer, CellBitmapKey, SheetRenderer};
+
+/// The number of grid regions that we draw before we read them back at once
+const BATCH_SHEET_COUNT: usize = 16;
+/// Wide enough for the number bitmaps of all our variants in a few rows
+const MIN_ATLAS_WIDTH: u32 = 2048;
+/// Each quad is given by its rect on the grid region and the top left corner of its bitmap in our
+/// atlas as six floats
+const INSTANCE_BYTE_COUNT: usize = 6 * 4;
+
+// NOTE: We fetch the texels of our atlas without filtering. Our quads sit on whole pixels so each
+//       pixel center of a quad lands in the middle of exactly one texel of its bitmap.
+const SHADER: &str = r#"
+[[block]]
+struct Uniforms {
+    region_size: vec2<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> uniforms: Uniforms;
+[[group(0), binding(1)]]
+var atlas: texture_2d<f32>;
+
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] atlas_pos: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(
+    [[builtin(vertex_index)]] vertex_index: u32,
+    [[location(0)]] target_rect: vec4<f32>,
+    [[location(1)]] atlas_pos: vec2<f32>
+) -> VertexOutput {
+    var corners: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0)
+    );
+    let offset = corners[vertex_index] * target_rect.zw;
+    let pixel = target_rect.xy + offset;
+    var output: VertexOutput;
+    output.position = vec4<f32>(
+        2.0 * pixel.x / uniforms.region_size.x - 1.0,
+        1.0 - 2.0 * pixel.y / uniforms.region_size.y,
+        0.0,
+        1.0
+    );
+    output.atlas_pos = atlas_pos + offset;
+    return output;
+}
+
+[[stage(fragment)]]
+fn fs_main(input: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return textureLoad(atlas, vec2<i32>(floor(input.atlas_pos)), 0);
+}
+"#;
+
+pub struct GpuCompositor {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    grid_region_background: wgpu::Texture,
+    grid_region_target: wgpu::Texture,
+    grid_region_target_view: wgpu::TextureView,
+    region_width: u32,
+    region_height: u32,
+    /// The rows of our grid regions are padded to `wgpu::COPY_BYTES_PER_ROW_ALIGNMENT` when we
+    /// read them back
+    padded_bytes_per_row: u32,
+    readback_buffers: Vec<wgpu::Buffer>,
+    /// The top left corner of each of our cell bitmaps in the atlas
+    atlas_positions: HashMap<CellBitmapKey, (u32, u32)>,
+}
+
+impl GpuCompositor {
+    /// Returns `None` if the GPU cannot draw the cells of the given cards exactly like our CPU does
+    /// in which case we draw them on the CPU as usual
+    pub fn new(sheet_renderer: &SheetRenderer, cards: &[Card]) -> Option<GpuCompositor> {
+        if sheet_renderer.params.gamma_correct_blending {
+            log::warn!(
+                "Our GPU cannot blend with `gamma_correct_blending = true` yet - drawing our cells \
+                 on the CPU instead"
+            );
+            return None;
+        }
+        let region_width = sheet_renderer.grid_region_background.width;
+        let region_height = sheet_renderer.grid_region_background.height;
+        if region_width == 0 || region_height == 0 {
+            return None;
+        }
+
+        // NOTE: We only put the bitmaps into our atlas that the cells of our cards actually use
+        let mut cell_bitmaps: Vec<(CellBitmapKey, &Bitmap)> = Vec::new();
+        let mut known_bitmap_keys = HashSet::new();
+        for card in cards {
+            for_each_cell_bitmap(sheet_renderer, card, |key, bitmap, _pos| {
+                if bitmap.width > 0 && bitmap.height > 0 && known_bitmap_keys.insert(key) {
+                    cell_bitmaps.push((key, bitmap));
+                }
+            });
+        }
+        let bitmap_sizes: Vec<(u32, u32)> = cell_bitmaps
+            .iter()
+            .map(|(_key, bitmap)| (bitmap.width, bitmap.height))
+            .collect();
+        let atlas_width = bitmap_sizes
+            .iter()
+            .map(|&(width, _height)| width)
+            .fold(MIN_ATLAS_WIDTH, u32::max);
+        let (atlas_positions, atlas_height) = pack_atlas(&bitmap_sizes, atlas_width);
+
+        let instance = wgpu::Instance::new(wgpu::Backends::PRIMARY);
+        let adapter =
+            match pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                force_fallback_adapter: false,
+                compatible_surface: None,
+            })) {
+                Some(adapter) => adapter,
+                None => {
+                    log::warn!(
+                        "Could not find a graphics card - drawing our cells on the CPU instead"
+                    );
+                    return None;
+                }
+            };
+        let (device, queue) = match pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("Chotto"),
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::default(),
+            },
+            None,
+        )) {
+            Ok(device_and_queue) => device_and_queue,
+            Err(error) => {
+                log::warn!(
+                    "Could not use our graphics card '{}' - drawing on the CPU instead: {}",
+                    adapter.get_info().name,
+                    error
+                );
+                return None;
+            }
+        };
+        let max_texture_size = device.limits().max_texture_dimension_2d;
+        let largest_texture_size = [region_width, region_height, atlas_width, atlas_height]
+            .iter()
+            .copied()
+            .fold(0, u32::max);
+        if largest_texture_size > max_texture_size {
+            log::warn!(
+                "Our grid or the images of our cells need a texture of {}px which is more than the \
+                 {}px that our graphics card supports - drawing our cells on the CPU instead",
+                largest_texture_size,
+                max_texture_size
+            );
+            return None;
+        }
+        log::info!(
+            "Drawing our cells on the graphics card '{}'",
+            adapter.get_info().name
+        );
+
+        let grid_region_background = create_texture(
+            &device,
+            region_width,
+            region_height,
+            wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST,
+        );
+        upload_bitmap(
+            &queue,
+            &grid_region_background,
+            &sheet_renderer.grid_region_background,
+            (0, 0),
+        );
+        let grid_region_target = create_texture(
+            &device,
+            region_width,
+            region_height,
+            wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+        );
+        let grid_region_target_view =
+            grid_region_target.create_view(&wgpu::TextureViewDescriptor::default());
+        let atlas = create_texture(
+            &device,
+            atlas_width,
+            u32::max(1, atlas_height),
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        );
+        for ((_key, bitmap), &atlas_position) in cell_bitmaps.iter().zip(atlas_positions.iter()) {
+            upload_bitmap(&queue, &atlas, bitmap, atlas_position);
+        }
+
+        let uniform_bytes: Vec<u8> = [region_width as f32, region_height as f32]
+            .iter()
+            .flat_map(|value| value.to_le_bytes().to_vec())
+            .collect();
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Uniforms"),
+            contents: &uniform_bytes,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(
+                        &atlas.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("Cells"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        // NOTE: Premultiplied blending on the GPU rounds like our
+        //       `blit_to_alpha_blended_premultiplied_rows` because the source is added unscaled.
+        //       Graphics cards may still convert the blended floats back to bytes a little
+        //       differently so a channel can be off by one (see our tests).
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Cells"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: INSTANCE_BYTE_COUNT as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x2],
+                }],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }],
+            }),
+        });
+
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = (4 * region_width + alignment - 1) / alignment * alignment;
+        let readback_buffers = (0..BATCH_SHEET_COUNT)
+            .map(|_| {
+                device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Grid region"),
+                    size: padded_bytes_per_row as u64 * region_height as u64,
+                    usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                })
+            })
+            .collect();
+
+        Some(GpuCompositor {
+            device,
+            queue,
+            pipeline,
+            bind_group,
+            grid_region_background,
+            grid_region_target,
+            grid_region_target_view,
+            region_width,
+            region_height,
+            padded_bytes_per_row,
+            readback_buffers,
+            atlas_positions: cell_bitmaps
+                .iter()
+                .map(|&(key, _bitmap)| key)
+                .zip(atlas_positions.into_iter())
+                .collect(),
+        })
+    }
+
+    /// Returns the same as `SheetRenderer::draw_grid_region` for each of the given cards by its
+    /// sheet number. The cards must be some of the ones that we were created with.
+    pub fn draw_grid_regions(
+        &self,
+        sheet_renderer: &SheetRenderer,
+        cards: &[Card],
+    ) -> HashMap<usize, Bitmap> {
+        let mut grid_regions = HashMap::new();
+        for batch in cards.chunks(BATCH_SHEET_COUNT) {
+            let mut instance_bytes: Vec<u8> = Vec::new();
+            let mut instance_ranges = Vec::new();
+            for card in batch {
+                let first_instance = (instance_bytes.len() / INSTANCE_BYTE_COUNT) as u32;
+                for_each_cell_bitmap(sheet_renderer, card, |key, bitmap, pos| {
+                    if bitmap.width == 0 || bitmap.height == 0 {
+                        return;
+                    }
+                    let (atlas_x, atlas_y) = self.atlas_positions[&key];
+                    for value in &[
+                        pos.x as f32,
+                        pos.y as f32,
+                        bitmap.width as f32,
+                        bitmap.height as f32,
+                        atlas_x as f32,
+                        atlas_y as f32,
+                    ] {
+                        instance_bytes.extend_from_slice(&value.to_le_bytes());
+                    }
+                });
+                let end_instance = (instance_bytes.len() / INSTANCE_BYTE_COUNT) as u32;
+                instance_ranges.push(first_instance..end_instance);
+            }
+            let instance_buffer = if instance_bytes.is_empty() {
+                None
+            } else {
+                Some(
+                    self.device
+                        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                            label: Some("Cells"),
+                            contents: &instance_bytes,
+                            usage: wgpu::BufferUsages::VERTEX,
+                        }),
+                )
+            };
+
+            // NOTE: All sheets of a batch reuse the same target texture. The commands of an
+            //       encoder run in order so each sheet is copied out before the next one starts.
+            let region_size = wgpu::Extent3d {
+                width: self.region_width,
+                height: self.region_height,
+                depth_or_array_layers: 1,
+            };
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            for (instance_range, readback_buffer) in
+                instance_ranges.iter().zip(self.readback_buffers.iter())
+            {
+                encoder.copy_texture_to_texture(
+                    whole_texture(&self.grid_region_background),
+                    whole_texture(&self.grid_region_target),
+                    region_size,
+                );
+                if let Some(instance_buffer) = instance_buffer
+                    .as_ref()
+                    .filter(|_| !instance_range.is_empty())
+                {
+                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: None,
+                        color_attachments: &[wgpu::RenderPassColorAttachment {
+                            view: &self.grid_region_target_view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: true,
+                            },
+                        }],
+                        depth_stencil_attachment: None,
+                    });
+                    render_pass.set_pipeline(&self.pipeline);
+                    render_pass.set_bind_group(0, &self.bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, instance_buffer.slice(..));
+                    render_pass.draw(0..6, instance_range.clone());
+                }
+                encoder.copy_texture_to_buffer(
+                    whole_texture(&self.grid_region_target),
+                    wgpu::ImageCopyBuffer {
+                        buffer: readback_buffer,
+                        layout: wgpu::ImageDataLayout {
+                            offset: 0,
+                            bytes_per_row: NonZeroU32::new(self.padded_bytes_per_row),
+                            rows_per_image: None,
+                        },
+                    },
+                    region_size,
+                );
+            }
+            self.queue.submit(Some(encoder.finish()));
+
+            let mappings: Vec<_> = self.readback_buffers[..batch.len()]
+                .iter()
+                .map(|buffer| buffer.slice(..).map_async(wgpu::MapMode::Read))
+                .collect();
+            self.device.poll(wgpu::Maintain::Wait);
+            for ((card, readback_buffer), mapping) in batch
+                .iter()
+                .zip(self.readback_buffers.iter())
+                .zip(mappings.into_iter())
+            {
+                pollster::block_on(mapping).unwrap_or_else(|error| {
+                    panic!(
+                        "Could not read our cells back from the graphics card: {:?}",
+                        error
+                    )
+                });
+                let grid_region = bitmap_from_padded_rows(
+                    &readback_buffer.slice(..).get_mapped_range(),
+                    self.region_width,
+                    self.region_height,
+                    self.padded_bytes_per_row,
+                );
+                readback_buffer.unmap();
+                grid_regions.insert(card.sheet_number, grid_region);
+            }
+        }
+        grid_regions
+    }
+}
+
+/// Calls the given function with each bitmap that makes up the cells of the given card, its key
+/// and the location of its top left corner on our grid region in the order in which they are drawn
+fn for_each_cell_bitmap<'b>(
+    sheet_renderer: &'b SheetRenderer,
+    card: &'b Card,
+    mut draw: impl FnMut(CellBitmapKey, &'b Bitmap, Vec2i),
+) {
+    let cell_renderer = CardCellRenderer {
+        sheet_renderer,
+        card,
+    };
+    for cell in cells::grid_cells(
+        &card.numbers,
+        sheet_renderer.top_left - sheet_renderer.grid_region_pos,
+        sheet_renderer.cell_width,
+        sheet_renderer.cell_height,
+    ) {
+        cell_renderer.for_each_bitmap(&cell, |key, bitmap, center| {
+            draw(key, bitmap, center - bitmap.rect().dim / 2)
+        });
+    }
+}
+
+/// Places bitmaps with the given sizes next to each other in rows from top to bottom. Returns the
+/// top left corner of each bitmap and the height of the atlas. None of the bitmaps must be wider
+/// than the atlas.
+fn pack_atlas(bitmap_sizes: &[(u32, u32)], atlas_width: u32) -> (Vec<(u32, u32)>, u32) {
+    let mut positions = Vec::with_capacity(bitmap_sizes.len());
+    let mut x = 0;
+    let mut y = 0;
+    let mut row_height = 0;
+    for &(width, height) in bitmap_sizes {
+        assert!(width <= atlas_width, "Our atlas is too narrow");
+        if x + width > atlas_width {
+            x = 0;
+            y += row_height;
+            row_height = 0;
+        }
+        positions.push((x, y));
+        x += width;
+        row_height = u32::max(row_height, height);
+    }
+    (positions, y + row_height)
+}
+
+fn create_texture(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    usage: wgpu::TextureUsages,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage,
+    })
+}
+
+fn whole_texture(texture: &wgpu::Texture) -> wgpu::ImageCopyTexture {
+    wgpu::ImageCopyTexture {
+        texture,
+        mip_level: 0,
+        origin: wgpu::Origin3d::ZERO,
+        aspect: wgpu::TextureAspect::All,
+    }
+}
+
+/// Copies the given premultiplied bitmap into the given texture with its top left corner at the
+/// given position
+fn upload_bitmap(queue: &wgpu::Queue, texture: &wgpu::Texture, bitmap: &Bitmap, pos: (u32, u32)) {
+    let mut pixel_bytes = Vec::with_capacity(4 * bitmap.data.len());
+    for pixel in &bitmap.data {
+        pixel_bytes.extend_from_slice(&[pixel.r, pixel.g, pixel.b, pixel.a]);
+    }
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d {
+                x: pos.0,
+                y: pos.1,
+                z: 0,
+            },
+            aspect: wgpu::TextureAspect::All,
+        },
+        &pixel_bytes,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: NonZeroU32::new(4 * bitmap.width),
+            rows_per_image: None,
+        },
+        wgpu::Extent3d {
+            width: bitmap.width,
+            height: bitmap.height,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+fn bitmap_from_padded_rows(
+    bytes: &[u8],
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+) -> Bitmap {
+    let mut bitmap = Bitmap::new(width, height);
+    for (y, row) in bytes
+        .chunks(padded_bytes_per_row as usize)
+        .take(height as usize)
+        .enumerate()
+    {
+        for (x, pixel) in row.chunks(4).take(width as usize).enumerate() {
+            bitmap.data[y * width as usize + x] =
+                PixelRGBA::new(pixel[0], pixel[1], pixel[2], pixel[3]);
+        }
+    }
+    bitmap
+}
+
+#[cfg(test)]
+mod tests {
+    use cottontail::math::Random;
+
+    use super::*;
+    use crate::{create_cards, default_draw_params, load_font};
+
+    const FONT_FILEPATH_CANDIDATES: &[&str] = &[
+        "C:/Windows/Fonts/arial.ttf",
+        "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+        "/usr/share/fonts/TTF/DejaVuSans.ttf",
+        "/Library/Fonts/Arial.ttf",
+        "/System/Library/Fonts/Supplemental/Arial.ttf",
+    ];
+    /// Graphics cards may round the blended floats to the nearest byte or the one next to it
+    const MAX_CHANNEL_DIFFERENCE: i32 = 1;
+
+    #[test]
+    fn gpu_draws_the_same_grid_regions_as_the_cpu() {
+        let font_filepath = match FONT_FILEPATH_CANDIDATES
+            .iter()
+            .find(|filepath| std::path::Path::new(filepath).exists())
+        {
+            Some(filepath) => filepath,
+            None => return,
+        };
+        let font = load_font(font_filepath);
+        let params = default_draw_params(1240, 1754);
+        let mut background = Bitmap::new(1240, 1754);
+        for (index, pixel) in background.data.iter_mut().enumerate() {
+            *pixel = PixelRGBA::new(index as u8, (index / 1240) as u8, 200, 255);
+        }
+        let sheet_renderer = SheetRenderer::new(&background, &font, 0, None, &params, None);
+        let mut random = Random::new_from_seed(1234);
+        let cards = create_cards(&sheet_renderer, &[], BATCH_SHEET_COUNT + 3, &mut random);
+
+        // NOTE: Machines without a graphics card can only test the CPU path
+        let gpu_compositor = match GpuCompositor::new(&sheet_renderer, &cards) {
+            Some(gpu_compositor) => gpu_compositor,
+            None => return,
+        };
+        let gpu_grid_regions = gpu_compositor.draw_grid_regions(&sheet_renderer, &cards);
+        assert_eq!(gpu_grid_regions.len(), cards.len());
+        for card in &cards {
+            let gpu_grid_region = &gpu_grid_regions[&card.sheet_number];
+            let cpu_grid_region = sheet_renderer.draw_grid_region(card);
+            assert_eq!(
+                (gpu_grid_region.width, gpu_grid_region.height),
+                (cpu_grid_region.width, cpu_grid_region.height)
+            );
+            for (index, (gpu_pixel, cpu_pixel)) in gpu_grid_region
+                .data
+                .iter()
+                .zip(cpu_grid_region.data.iter())
+                .enumerate()
+            {
+                let channel_pairs = [
+                    (gpu_pixel.r, cpu_pixel.r),
+                    (gpu_pixel.g, cpu_pixel.g),
+                    (gpu_pixel.b, cpu_pixel.b),
+                    (gpu_pixel.a, cpu_pixel.a),
+                ];
+                assert!(
+                    channel_pairs.iter().all(|&(gpu_channel, cpu_channel)| {
+                        (gpu_channel as i32 - cpu_channel as i32).abs() <= MAX_CHANNEL_DIFFERENCE
+                    }),
+                    "Sheet {} differs at pixel {}: {:?} on the GPU and {:?} on the CPU",
+                    card.sheet_number,
+                    index,
+                    gpu_pixel,
+                    cpu_pixel
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn packed_bitmaps_do_not_overlap_and_stay_inside_the_atlas() {
+        let bitmap_sizes: Vec<(u32, u32)> = (1..=40)
+            .map(|index| (10 + 7 * index % 53, 5 + 11 * index % 29))
+            .collect();
+        let atlas_width = 100;
+        let (positions, atlas_height) = pack_atlas(&bitmap_sizes, atlas_width);
+
+        let rects: Vec<(u32, u32, u32, u32)> = positions
+            .iter()
+            .zip(bitmap_sizes.iter())
+            .map(|(&(x, y), &(width, height))| (x, y, x + width, y + height))
+            .collect();
+        for (index, &(left, top, right, bottom)) in rects.iter().enumerate() {
+            assert!(right <= atlas_width && bottom <= atlas_height);
+            for &(other_left, other_top, other_right, other_bottom) in &rects[index + 1..] {
+                assert!(
+                    right <= other_left
+                        || other_right <= left
+                        || bottom <= other_top
+                        || other_bottom <= top
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! Stands in for `gpu_compositing.rs` if Chotto was built without the `gpu` feature. Most of our
+//! users draw on the CPU and should not need a graphics API to build Chotto.
+
+use std::collections::HashMap;
+
+use cottontail::image::Bitmap;
+
+use crate::{manifest::Card, SheetRenderer};
+
+/// Can never be created without GPU support
+pub enum GpuCompositor {}
+
+impl GpuCompositor {
+    /// Always returns `None` so that we draw our cells on the CPU. Our draw parameters with
+    /// `compositing_backend = "gpu"` are already rejected when we load them.
+    pub fn new(_sheet_renderer: &SheetRenderer, _cards: &[Card]) -> Option<GpuCompositor> {
+        None
+    }
+
+    pub fn draw_grid_regions(
+        &self,
+        _sheet_renderer: &SheetRenderer,
+        _cards: &[Card],
+    ) -> HashMap<usize, Bitmap> {
+        match *self {}
+    }
+}
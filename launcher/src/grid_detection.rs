@@ -0,0 +1,334 @@
+//! Finds the pre-printed grid of our background image so that we don't need to measure its pixel
+//! coordinates by hand.
+//!
+//! Usage: `chotto detect-grid [--no-write]`
+//!
+//! Writes the found grid location into our draw parameters unless `--no-write` is given.
+//!
+//! We look for long horizontal and vertical lines that stand out from the rest of the image. The
+//! horizontal lines that span the same range of columns form our grid together with the vertical
+//! lines between them. The outer lines give us the grid rectangle and the inner lines the
+//! boundaries of the individual cells.
+
+use cottontail::image::Bitmap;
+
+use crate::{atomic_file, command_line_flag, params_migration, Input, DRAW_PARAMETERS_FILENAME};
+
+/// A pixel belongs to a line if its brightness differs at least this much from the typical
+/// brightness of the image
+const LINE_BRIGHTNESS_DIFFERENCE: f32 = 0.25;
+/// Lines must be at least this long relative to the width or height of the image
+const MIN_LINE_LENGTH_RATIO: f32 = 0.2;
+/// Lines belong to the same grid if their ends differ at most this much relative to the width or
+/// height of the image
+const LINE_END_TOLERANCE_RATIO: f32 = 0.02;
+
+/// A horizontal or vertical line given by its position across and its extent along its direction
+#[derive(Debug, Clone, Copy)]
+struct Line {
+    position: i32,
+    start: i32,
+    end: i32,
+}
+
+pub struct DetectedGrid {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+    /// The x positions of all vertical lines from left to right including the outer ones
+    pub column_boundaries: Vec<i32>,
+    /// The y positions of all horizontal lines from top to bottom including the outer ones
+    pub row_boundaries: Vec<i32>,
+}
+
+pub fn detect_grid(args: &[String]) {
+    let write_params = !command_line_flag(args, "--no-write");
+    let input = Input::new();
+    let (expected_column_count, expected_row_count) = input.params.grid_size();
+
+    let grid = locate_grid(&input.background_bitmap).unwrap_or_else(|| {
+        panic!(
+            "Could not find a grid in the background image '{}' - please measure the \
+             `bingo_grid_pixel_location_left_top_right_bottom` by hand",
+            input.background_image_filepath
+        )
+    });
+
+    let column_count = grid.column_boundaries.len() as i32 - 1;
+    let row_count = grid.row_boundaries.len() as i32 - 1;
+    log::info!(
+        "Found a grid with {} columns and {} rows:\n\
+         \n\
+         bingo_grid_pixel_location_left_top_right_bottom = [{}, {}, {}, {}]\n\
+         \n\
+         Column boundaries (x): {:?}\n\
+         Row boundaries (y): {:?}",
+        column_count,
+        row_count,
+        grid.left,
+        grid.top,
+        grid.right,
+        grid.bottom,
+        grid.column_boundaries,
+        grid.row_boundaries
+    );
+    // NOTE: We still use the detected rectangle if the cell lines do not match, i.e. because some
+    //       of them are too faint or the background only has an outer frame
+    if (column_count, row_count) != (expected_column_count, expected_row_count) {
+        log::warn!(
+            "Our {:?} cards need {} columns and {} rows but the grid in the background image has \
+             {} columns and {} rows - please check the result",
+            input.params.bingo_variant,
+            expected_column_count,
+            expected_row_count,
+            column_count,
+            row_count
+        );
+    }
+    if !are_evenly_spaced(&grid.column_boundaries) || !are_evenly_spaced(&grid.row_boundaries) {
+        log::warn!(
+            "The cells of the grid in the background image have different sizes but Chotto \
+             always divides the grid into cells of the same size - please check the result"
+        );
+    }
+
+    if write_params {
+        write_grid_location(&grid);
+    }
+}
+
+/// Returns the biggest grid of straight lines in the given image if there is any
+pub fn locate_grid(bitmap: &Bitmap) -> Option<DetectedGrid> {
+    let line_mask = find_line_pixels(bitmap);
+    let width = bitmap.width as i32;
+    let height = bitmap.height as i32;
+    let horizontal_lines = find_lines(&line_mask, width, height, true);
+    let vertical_lines = find_lines(&line_mask, width, height, false);
+    find_grid(&horizontal_lines, &vertical_lines, width, height)
+}
+
+/// Returns for each pixel whether it stands out from the typical brightness of the image
+fn find_line_pixels(bitmap: &Bitmap) -> Vec<bool> {
+    // NOTE: Transparent parts of the background are printed onto white paper
+    let brightness: Vec<f32> = bitmap
+        .data
+        .iter()
+        .map(|pixel| {
+            let alpha = pixel.a as f32 / 255.0;
+            let luma =
+                (0.2126 * pixel.r as f32 + 0.7152 * pixel.g as f32 + 0.0722 * pixel.b as f32)
+                    / 255.0;
+            luma * alpha + (1.0 - alpha)
+        })
+        .collect();
+
+    let mut histogram = [0usize; 256];
+    for value in &brightness {
+        histogram[(value * 255.0).round() as usize] += 1;
+    }
+    let mut count = 0;
+    let median_index = histogram
+        .iter()
+        .position(|&bucket_count| {
+            count += bucket_count;
+            count >= brightness.len() / 2
+        })
+        .unwrap_or(255);
+    let median = median_index as f32 / 255.0;
+
+    brightness
+        .iter()
+        .map(|value| (value - median).abs() >= LINE_BRIGHTNESS_DIFFERENCE)
+        .collect()
+}
+
+/// Returns all horizontal (or vertical) lines that are long enough. Neighbouring rows (or columns)
+/// of a thick line are merged into a single line at their center.
+fn find_lines(line_mask: &[bool], width: i32, height: i32, horizontal: bool) -> Vec<Line> {
+    let (length, count) = if horizontal {
+        (width, height)
+    } else {
+        (height, width)
+    };
+    let min_length = (length as f32 * MIN_LINE_LENGTH_RATIO) as i32;
+    let tolerance = i32::max(2, (length as f32 * LINE_END_TOLERANCE_RATIO) as i32);
+    let is_line_pixel = |position: i32, along: i32| {
+        let (x, y) = if horizontal {
+            (along, position)
+        } else {
+            (position, along)
+        };
+        line_mask[(y * width + x) as usize]
+    };
+
+    // NOTE: We only keep the longest run of each row which is enough for the lines of a grid
+    let mut runs: Vec<Line> = Vec::new();
+    for position in 0..count {
+        let mut longest: Option<Line> = None;
+        let mut run_start = None;
+        for along in 0..=length {
+            let is_inside = along < length && is_line_pixel(position, along);
+            match (is_inside, run_start) {
+                (true, None) => run_start = Some(along),
+                (false, Some(start)) => {
+                    let run_length = along - start;
+                    if run_length >= min_length
+                        && longest
+                            .map(|line| run_length > line.end - line.start)
+                            .unwrap_or(true)
+                    {
+                        longest = Some(Line {
+                            position,
+                            start,
+                            end: along,
+                        });
+                    }
+                    run_start = None;
+                }
+                _ => {}
+            }
+        }
+        runs.extend(longest);
+    }
+
+    let mut lines: Vec<Line> = Vec::new();
+    let mut thickness = 1;
+    for run in runs {
+        match lines.last_mut() {
+            Some(last)
+                if run.position == last.position + thickness
+                    && (run.start - last.start).abs() <= tolerance
+                    && (run.end - last.end).abs() <= tolerance =>
+            {
+                thickness += 1;
+                last.start = i32::min(last.start, run.start);
+                last.end = i32::max(last.end, run.end);
+            }
+            _ => {
+                if let Some(last) = lines.last_mut() {
+                    last.position += thickness / 2;
+                }
+                thickness = 1;
+                lines.push(run);
+            }
+        }
+    }
+    if let Some(last) = lines.last_mut() {
+        last.position += thickness / 2;
+    }
+    lines
+}
+
+/// Returns the biggest group of horizontal lines with the same ends together with the vertical
+/// lines that cross all of them
+fn find_grid(
+    horizontal_lines: &[Line],
+    vertical_lines: &[Line],
+    width: i32,
+    height: i32,
+) -> Option<DetectedGrid> {
+    let horizontal_tolerance = i32::max(2, (width as f32 * LINE_END_TOLERANCE_RATIO) as i32);
+    let vertical_tolerance = i32::max(2, (height as f32 * LINE_END_TOLERANCE_RATIO) as i32);
+    let rows: Vec<&Line> = horizontal_lines
+        .iter()
+        .map(|candidate| {
+            horizontal_lines
+                .iter()
+                .filter(|line| {
+                    (line.start - candidate.start).abs() <= horizontal_tolerance
+                        && (line.end - candidate.end).abs() <= horizontal_tolerance
+                })
+                .collect::<Vec<&Line>>()
+        })
+        .filter(|group| group.len() >= 2)
+        .max_by_key(|group| group.len())?;
+
+    let top = rows.iter().map(|line| line.position).min()?;
+    let bottom = rows.iter().map(|line| line.position).max()?;
+    let mut row_starts: Vec<i32> = rows.iter().map(|line| line.start).collect();
+    let mut row_ends: Vec<i32> = rows.iter().map(|line| line.end).collect();
+    row_starts.sort();
+    row_ends.sort();
+    let left_end = row_starts[row_starts.len() / 2];
+    let right_end = row_ends[row_ends.len() / 2];
+
+    let columns: Vec<&Line> = vertical_lines
+        .iter()
+        .filter(|line| {
+            left_end - horizontal_tolerance <= line.position
+                && line.position <= right_end + horizontal_tolerance
+                && line.start <= top + vertical_tolerance
+                && line.end >= bottom - vertical_tolerance
+        })
+        .collect();
+    if columns.len() < 2 {
+        return None;
+    }
+
+    let column_boundaries: Vec<i32> = columns.iter().map(|line| line.position).collect();
+    let row_boundaries: Vec<i32> = rows.iter().map(|line| line.position).collect();
+    Some(DetectedGrid {
+        left: *column_boundaries.first()?,
+        top,
+        right: *column_boundaries.last()?,
+        bottom,
+        column_boundaries,
+        row_boundaries,
+    })
+}
+
+fn are_evenly_spaced(boundaries: &[i32]) -> bool {
+    let sizes: Vec<i32> = boundaries
+        .windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .collect();
+    let smallest = sizes.iter().cloned().min().unwrap_or(0);
+    let biggest = sizes.iter().cloned().max().unwrap_or(0);
+    biggest - smallest <= i32::max(2, biggest / 20)
+}
+
+/// Replaces the grid location in our draw parameters file while keeping everything else as is
+fn write_grid_location(grid: &DetectedGrid) {
+    let assignment = format!(
+        "bingo_grid_pixel_location_left_top_right_bottom = [{}, {}, {}, {}]",
+        grid.left, grid.top, grid.right, grid.bottom
+    );
+    // NOTE: YAML and JSON files are written by other programs which need to take over the values
+    //       themselves
+    let params_string = match std::fs::read_to_string(DRAW_PARAMETERS_FILENAME) {
+        Ok(params_string) => params_string,
+        Err(_) => {
+            log::info!(
+                "Our draw parameters are not given in '{}' - please copy the values above into \
+                 them by hand",
+                DRAW_PARAMETERS_FILENAME
+            );
+            return;
+        }
+    };
+    let lines: Vec<String> = params_string
+        .lines()
+        .map(|line| {
+            if params_migration::assigned_key(line)
+                == Some("bingo_grid_pixel_location_left_top_right_bottom")
+            {
+                assignment.clone()
+            } else {
+                line.to_owned()
+            }
+        })
+        .collect();
+
+    let backup_filepath = format!("{}.bak", DRAW_PARAMETERS_FILENAME);
+    atomic_file::write_file_atomically(&backup_filepath, params_string.as_bytes());
+    atomic_file::write_file_atomically(
+        DRAW_PARAMETERS_FILENAME,
+        (lines.join("\n") + "\n").as_bytes(),
+    );
+    log::info!(
+        "Wrote the grid location into '{}' - the old file was kept as '{}'",
+        DRAW_PARAMETERS_FILENAME,
+        backup_filepath
+    );
+}
@@ -0,0 +1,146 @@
+//! Loads very large scanned backgrounds (i.e. poster-size artwork with 100+ megapixels) without
+//! holding the whole file, its decoded pixels and our bitmap in memory at the same time. We map
+//! the PNG file into memory instead of reading it and decode it row by row straight into our
+//! bitmap. If our sheets are smaller than the scan we shrink each band of rows as soon as it is
+//! decoded so that the full-size image never exists in memory at all.
+
+use cottontail::image::{Bitmap, PixelRGBA};
+
+/// Backgrounds with more pixels than this are loaded via `load_png_memory_mapped`
+const LARGE_BACKGROUND_PIXEL_COUNT: u64 = 50_000_000;
+
+/// Returns the size of the given background image if it is a PNG file that is big enough to be
+/// loaded via `load_png_memory_mapped`
+pub fn large_png_size(filepath: &str) -> Option<(u32, u32)> {
+    if !filepath.to_lowercase().ends_with(".png") {
+        return None;
+    }
+    let file_bytes = map_file(filepath);
+    let (info, _reader) = png::Decoder::new(&file_bytes[..]).read_info().ok()?;
+    if info.width as u64 * info.height as u64 > LARGE_BACKGROUND_PIXEL_COUNT {
+        Some((info.width, info.height))
+    } else {
+        None
+    }
+}
+
+/// Decodes the given PNG file row by row. If a target size is given that is smaller than the image
+/// we shrink it to that size while decoding by averaging the pixels that fall into each target
+/// pixel. Otherwise the image keeps its size and needs to be resized afterwards.
+pub fn load_png_memory_mapped(filepath: &str, target_size: Option<(u32, u32)>) -> Bitmap {
+    let file_bytes = map_file(filepath);
+    let mut decoder = png::Decoder::new(&file_bytes[..]);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let (info, mut reader) = decoder.read_info().unwrap_or_else(|error| {
+        panic!(
+            "Cannot decode image file '{}' - is the file ok?: {}",
+            filepath, error
+        )
+    });
+    // NOTE: The rows of interlaced images arrive in multiple passes which we cannot shrink on the
+    //       fly. Interlacing is rare for scans so we just load them as a whole.
+    if reader.info().interlaced {
+        log::warn!(
+            "The background image '{}' is interlaced which needs a lot more memory to load - \
+             please save it without interlacing if Chotto runs out of memory",
+            filepath
+        );
+        return Bitmap::from_png_file_or_panic(filepath);
+    }
+
+    let source_width = info.width as usize;
+    let source_height = info.height as usize;
+    let (target_width, target_height) = match target_size {
+        Some((width, height)) if width <= info.width && height <= info.height => {
+            (width as usize, height as usize)
+        }
+        _ => (source_width, source_height),
+    };
+    log::info!(
+        "Decoding the large background image '{}' with {}x{}px in bands of rows into {}x{}px",
+        filepath,
+        source_width,
+        source_height,
+        target_width,
+        target_height
+    );
+
+    let target_columns: Vec<usize> = (0..source_width)
+        .map(|x| x * target_width / source_width)
+        .collect();
+    let mut bitmap = Bitmap::new(target_width as u32, target_height as u32);
+    // NOTE: We sum up the colors weighted by their alpha so that transparent pixels don't darken
+    //       their neighbours
+    let mut band_sums = vec![[0u64; 4]; target_width];
+    let mut band_counts = vec![0u64; target_width];
+    let mut band_row = 0;
+    for source_y in 0..source_height {
+        let row =
+            reader.next_row().ok().flatten().unwrap_or_else(|| {
+                panic!("Image file '{}' is incomplete - is the file ok?", filepath)
+            });
+        let target_y = source_y * target_height / source_height;
+        if target_y != band_row {
+            write_band(&mut bitmap, band_row, &mut band_sums, &mut band_counts);
+            band_row = target_y;
+        }
+
+        let samples_per_pixel = row.len() / source_width;
+        for (pixel_samples, &target_x) in row.chunks_exact(samples_per_pixel).zip(&target_columns) {
+            let [r, g, b, a] = match pixel_samples {
+                [grey] => [*grey, *grey, *grey, 255],
+                [grey, a] => [*grey, *grey, *grey, *a],
+                [r, g, b] => [*r, *g, *b, 255],
+                [r, g, b, a, ..] => [*r, *g, *b, *a],
+                [] => unreachable!(),
+            };
+            let sums = &mut band_sums[target_x];
+            sums[0] += r as u64 * a as u64;
+            sums[1] += g as u64 * a as u64;
+            sums[2] += b as u64 * a as u64;
+            sums[3] += a as u64;
+            band_counts[target_x] += 1;
+        }
+    }
+    write_band(&mut bitmap, band_row, &mut band_sums, &mut band_counts);
+    bitmap
+}
+
+/// Writes the averaged pixels of a finished band into the given row of our bitmap and resets the
+/// band for the next row
+fn write_band(
+    bitmap: &mut Bitmap,
+    row: usize,
+    band_sums: &mut [[u64; 4]],
+    band_counts: &mut [u64],
+) {
+    let width = bitmap.width as usize;
+    let target_row = &mut bitmap.data[row * width..(row + 1) * width];
+    for ((pixel, sums), count) in target_row
+        .iter_mut()
+        .zip(band_sums.iter_mut())
+        .zip(band_counts.iter_mut())
+    {
+        *pixel = if sums[3] == 0 {
+            PixelRGBA::transparent()
+        } else {
+            PixelRGBA::new(
+                (sums[0] / sums[3]) as u8,
+                (sums[1] / sums[3]) as u8,
+                (sums[2] / sums[3]) as u8,
+                (sums[3] / u64::max(1, *count)) as u8,
+            )
+        };
+        *sums = [0; 4];
+        *count = 0;
+    }
+}
+
+fn map_file(filepath: &str) -> memmap2::Mmap {
+    let file = std::fs::File::open(filepath)
+        .unwrap_or_else(|error| panic!("Cannot open image file '{}': {}", filepath, error));
+    // NOTE: We only read from the mapping. Changing the file while Chotto starts up is not
+    //       supported just like with any other of our input files.
+    unsafe { memmap2::Mmap::map(&file) }
+        .unwrap_or_else(|error| panic!("Cannot read image file '{}': {}", filepath, error))
+}
@@ -0,0 +1,9 @@
+//! The card generation of Chotto which can also be used by other programs
+
+pub mod card_image;
+pub mod cell_layout;
+pub mod cells;
+pub mod generator;
+pub mod loteria;
+pub mod ninety_ball;
+pub mod scavenger_hunt;
@@ -0,0 +1,76 @@
+//! Writes log messages to the console and to a log file next to our executable. The log file
+//! always contains all messages so that we can diagnose problems after the fact.
+
+use std::{fs::File, io::Write, sync::Mutex};
+
+const LOG_FILENAME: &str = "chotto.log";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verbosity {
+    /// Only warnings and errors are printed to the console
+    Quiet,
+    Normal,
+    /// Also prints debug messages to the console
+    Verbose,
+}
+
+struct Logger {
+    console_level: log::LevelFilter,
+    logfile: Option<Mutex<File>>,
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Debug
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let message = format!(
+            "{} {:<5} {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            record.level(),
+            record.args()
+        );
+        if record.level() <= self.console_level {
+            if record.level() <= log::Level::Warn {
+                eprintln!("{}", message);
+            } else {
+                println!("{}", message);
+            }
+        }
+        if let Some(logfile) = &self.logfile {
+            if let Ok(mut logfile) = logfile.lock() {
+                writeln!(logfile, "{}", message).ok();
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(logfile) = &self.logfile {
+            if let Ok(mut logfile) = logfile.lock() {
+                logfile.flush().ok();
+            }
+        }
+    }
+}
+
+pub fn init_logging(verbosity: Verbosity) {
+    let console_level = match verbosity {
+        Verbosity::Quiet => log::LevelFilter::Warn,
+        Verbosity::Normal => log::LevelFilter::Info,
+        Verbosity::Verbose => log::LevelFilter::Debug,
+    };
+    // NOTE: We don't want to fail just because we can't write our logfile
+    let logfile = File::create(LOG_FILENAME).ok().map(Mutex::new);
+    let logger = Logger {
+        console_level,
+        logfile,
+    };
+
+    log::set_boxed_logger(Box::new(logger)).expect("Could not initialize logging");
+    log::set_max_level(log::LevelFilter::Debug);
+}
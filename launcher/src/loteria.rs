@@ -0,0 +1,125 @@
+//! Generation of Lotería cards. Each card (tabla) is a 4x4 grid of images that are drawn without
+//! replacement from a deck of 54 images. An image is represented by its 1-based position in the
+//! deck.
+
+use crate::generator::{
+    accepted_by_all, check_rejected_grid_count, shuffled, CardConstraint, RandomSource,
+};
+use cottontail::image::Grid;
+
+pub const DECK_SIZE: usize = 54;
+pub const GRID_SIZE: usize = 4;
+const IMAGES_PER_CARD: usize = GRID_SIZE * GRID_SIZE;
+
+/// Returns one grid per card. The new cards never contain the same set of images as each other or
+/// as the given cards of an earlier batch.
+/// NOTE: We prefer the images that appear on the fewest cards so far so that each image of the
+///       deck wins about equally often. If such a card is rejected we pick the images of the next
+///       tries purely at random.
+pub fn create_random_cards(
+    card_count: usize,
+    constraints: &[&dyn CardConstraint],
+    previous_cards: &[Grid<i32>],
+    random: &mut dyn RandomSource,
+) -> Vec<Grid<i32>> {
+    log::debug!(
+        "Generating {} Lotería cards after {} previous cards",
+        card_count,
+        previous_cards.len()
+    );
+
+    let mut image_usage_counts = vec![0usize; DECK_SIZE + 1];
+    for card in previous_cards {
+        for &image in &card.data {
+            image_usage_counts[image as usize] += 1;
+        }
+    }
+
+    let mut cards: Vec<Grid<i32>> = previous_cards.to_vec();
+    let mut rejected_card_count = 0;
+    while cards.len() < previous_cards.len() + card_count {
+        let mut images = shuffled((1..=DECK_SIZE as i32).collect(), random);
+        if rejected_card_count == 0 {
+            // NOTE: The sort is stable so images with the same usage count stay shuffled
+            images.sort_by_key(|image| image_usage_counts[*image as usize]);
+        }
+        images.truncate(IMAGES_PER_CARD);
+        let images = shuffled(images, random);
+
+        let mut card = Grid::new(GRID_SIZE as u32, GRID_SIZE as u32);
+        for (index, &image) in images.iter().enumerate() {
+            card.set(
+                (index % GRID_SIZE) as i32,
+                (index / GRID_SIZE) as i32,
+                image,
+            );
+        }
+
+        if has_same_images(&card, &cards) || !accepted_by_all(constraints, &card, &cards) {
+            rejected_card_count += 1;
+            check_rejected_grid_count(rejected_card_count);
+            continue;
+        }
+        rejected_card_count = 0;
+
+        for &image in &images {
+            image_usage_counts[image as usize] += 1;
+        }
+        cards.push(card);
+    }
+    cards.split_off(previous_cards.len())
+}
+
+/// Returns the order in which the caller draws the images of a shuffled deck
+pub fn create_call_order(random: &mut dyn RandomSource) -> Vec<i32> {
+    shuffled((1..=DECK_SIZE as i32).collect(), random)
+}
+
+/// Returns true if one of the existing cards has the same images as the candidate card. The place
+/// of an image does not matter as a card wins once all of its images were called.
+fn has_same_images(candidate: &Grid<i32>, existing: &[Grid<i32>]) -> bool {
+    let mut candidate_images = candidate.data.clone();
+    candidate_images.sort();
+    existing.iter().any(|card| {
+        let mut images = card.data.clone();
+        images.sort();
+        images == candidate_images
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::ForbiddenNumberCombination;
+    use cottontail::math::Random;
+    use std::collections::HashSet;
+
+    #[test]
+    fn cards_have_unique_images_and_image_sets() {
+        let previous_cards = create_random_cards(50, &[], &[], &mut Random::new_from_seed(4321));
+        let forbidden = ForbiddenNumberCombination {
+            numbers: vec![1, 2],
+        };
+        let cards = create_random_cards(
+            300,
+            &[&forbidden],
+            &previous_cards,
+            &mut Random::new_from_seed(1234),
+        );
+        assert_eq!(cards.len(), 300);
+        for (card_index, card) in cards.iter().enumerate() {
+            assert_eq!(card.data.len(), IMAGES_PER_CARD);
+            assert_eq!(
+                card.data.iter().collect::<HashSet<_>>().len(),
+                IMAGES_PER_CARD
+            );
+            assert!(card
+                .data
+                .iter()
+                .all(|&image| 1 <= image && image <= DECK_SIZE as i32));
+            assert!(forbidden.accept(card, &[]));
+            assert!(!has_same_images(card, &previous_cards));
+            assert!(!has_same_images(card, &cards[..card_index]));
+        }
+    }
+}
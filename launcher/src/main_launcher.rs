@@ -1,7 +1,11 @@
 // #![windows_subsystem = "windows"]
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::{SystemTime, UNIX_EPOCH},
 };
 
@@ -13,445 +17,6313 @@ use cottontail::{
     image::{Color, Grid, PixelRGBA},
 };
 
+use chotto::{
+    cell_layout,
+    cells::{self, Cell, CellRenderer},
+    generator::{
+        self, CardConstraint, ForbiddenNumberCombination, MinimizeSharedLines, RandomBag,
+        RandomSource,
+    },
+    loteria, ninety_ball, scavenger_hunt,
+};
 use cottontail::{
     core::{collect_files, read_file_whole},
     image::Bitmap,
-    math::{Random, Shufflebag, Vec2i},
+    math::{Random, Vec2i},
 };
-use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+use manifest::{Card, Manifest};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use sheet_canvas::{ComposedSheet, SheetCanvas, SheetRegion};
+
+mod atomic_file;
+mod audit_log;
+mod barcode;
+mod bench;
+mod bitmap_cache;
+mod bot;
+mod braille;
+mod bundle;
+mod caller;
+#[cfg(windows)]
+mod caller_window;
+mod cancellation;
+mod card_import;
+mod cell_layout_params;
+mod claims;
+mod colors;
+mod countdown;
+mod deck;
+mod deltas;
+mod email;
+mod gallery;
+mod glyph_atlas;
+#[cfg(feature = "gpu")]
+mod gpu_compositing;
+#[cfg(not(feature = "gpu"))]
+#[path = "gpu_compositing_unavailable.rs"]
+mod gpu_compositing;
+mod grid_detection;
+mod large_background;
+mod logging;
+mod manifest;
+mod nicknames;
+mod output_color;
+mod overlays;
+mod params_migration;
+mod params_overrides;
+mod params_validation;
+mod players;
+mod png_writer;
+mod pos_export;
+mod post_process;
+#[cfg(windows)]
+mod printing;
+mod proof;
+mod puzzle;
+mod report;
+mod rpc;
+mod run_manifest;
+mod s3_upload;
+mod scan;
+mod scavenger_items;
+mod seating_cards;
+mod secure_random;
+mod security_code;
+mod serial_number;
+mod server;
+mod shaping;
+mod sheet_canvas;
+mod simulation;
+mod speech;
+mod template;
+mod text_effects;
+mod ticket_stub;
+mod unsafe_params;
+
+/// Suppresses all message boxes. Used by the batches that `chotto serve` starts for its clients.
+const HEADLESS_FLAG: &str = "--headless";
+static HEADLESS: AtomicBool = AtomicBool::new(false);
 
 fn main() {
     set_panic_hook();
 
-    let input = Input::new();
-    let font = input.font;
-    let background = input.background_bitmap;
-    let sheet_count = input.params.number_of_sheets_to_generate;
-    let top_left = Vec2i::new(
-        input
-            .params
-            .bingo_grid_pixel_location_left_top_right_bottom
-            .0 as i32,
-        input
-            .params
-            .bingo_grid_pixel_location_left_top_right_bottom
-            .1 as i32,
-    );
-    let bottom_right = Vec2i::new(
-        input
-            .params
-            .bingo_grid_pixel_location_left_top_right_bottom
-            .2 as i32,
-        input
-            .params
-            .bingo_grid_pixel_location_left_top_right_bottom
-            .3 as i32,
-    );
-    let font_size = input.params.text_font_size as f32;
-    let text_color = PixelRGBA::new(
-        input.params.text_color_rgb.0,
-        input.params.text_color_rgb.1,
-        input.params.text_color_rgb.2,
-        255,
-    )
-    .to_color();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let verbosity = if command_line_flag(&args, "--verbose") {
+        logging::Verbosity::Verbose
+    } else if command_line_flag(&args, "--quiet") {
+        logging::Verbosity::Quiet
+    } else {
+        logging::Verbosity::Normal
+    };
+    logging::init_logging(verbosity);
+    log::debug!("Started with arguments {:?}", args);
+    cancellation::install_ctrl_c_handler();
+    HEADLESS.store(command_line_flag(&args, HEADLESS_FLAG), Ordering::SeqCst);
 
-    if path_exists("output_sheets") {
-        std::fs::remove_dir_all("output_sheets").ok();
+    // NOTE: Our `--set key=value` overrides are read when loading the draw parameters
+    let args: Vec<String> = params_overrides::remove_command_line_overrides(args)
+        .into_iter()
+        .filter(|arg| arg != "--verbose" && arg != "--quiet" && arg != HEADLESS_FLAG)
+        .collect();
+    match args.first().map(|command| command.as_str()) {
+        None => create_sheets(false, None, false),
+        Some("--dry-run") => create_sheets(true, None, false),
+        Some("--export-bundle") => create_sheets(false, None, true),
+        Some("reproduce") => bundle::reproduce(&args[1..]),
+        Some("gallery") => gallery::create_from_gallery(&args[1..]),
+        Some("export-template") => template::export_template(&args[1..]),
+        Some("import-template") => template::import_template(&args[1..]),
+        Some("print") => print_sheets(&args[1..]),
+        Some("email") => email::email_sheets(&args[1..]),
+        Some("extend") => extend_sheets(&args[1..]),
+        Some("render") => render_sheets_from_manifest(&args[1..]),
+        Some("render-from") => render_sheets_from_card_file(&args[1..]),
+        Some("proof") => proof::create_proof_sheets(&args[1..]),
+        Some("detect-grid") => grid_detection::detect_grid(&args[1..]),
+        Some("verify-code") => security_code::verify_code(&args[1..]),
+        Some("scan") => scan::scan_card(&args[1..]),
+        Some("verify-audit") => audit_log::verify_audit_log(&args[1..]),
+        Some("export-pos") => pos_export::export_pos(&args[1..]),
+        Some("serve") => server::serve(&args[1..]),
+        Some("bot") => bot::run_bot(&args[1..]),
+        Some("caller") => caller::run_caller(&args[1..]),
+        Some("export-nicknames") => nicknames::export_nicknames(&args[1..]),
+        Some("simulate") => simulation::simulate_games(&args[1..]),
+        // NOTE: This is meant for comparing performance changes and therefore not documented
+        Some("bench") => bench::run_bench(&args[1..]),
+        Some(command) => panic!("Unknown command '{}'", command),
     }
+}
 
-    let cell_width = (bottom_right.x - top_left.x) / 5;
-    let cell_height = (bottom_right.y - top_left.y) / 5;
-    let number_bitmaps_premultiplied =
-        create_number_bitmaps_premultiplied(font, font_size, text_color);
+/// Usage: `chotto print --printer "HP LaserJet" [--copies 2] [--collate] [--directory <dir>]`
+fn print_sheets(args: &[String]) {
+    let printer_name = command_line_option_value(args, "--printer")
+        .expect("Please specify a printer via `--printer \"<printer name>\"`");
+    let copies = command_line_option_value(args, "--copies")
+        .map(|value| {
+            value
+                .parse::<usize>()
+                .unwrap_or_else(|_| panic!("Invalid number of copies '{}'", value))
+        })
+        .unwrap_or(1);
+    let collate = command_line_flag(args, "--collate");
+    let directory = command_line_option_value(args, "--directory")
+        .unwrap_or_else(|| OUTPUT_SHEETS_DIRECTORY.to_owned());
+    let params = load_draw_params();
 
-    create_random_number_grids(sheet_count)
-        .into_par_iter()
-        .enumerate()
-        .for_each(|(sheet_index, number_grid)| {
-            let mut background = background.clone();
-            for y in 0..5 {
-                for x in 0..5 {
-                    if x == 2 && y == 2 {
-                        continue;
-                    }
-                    let center = top_left
-                        + Vec2i::new(
-                            x * cell_width + cell_width / 2,
-                            y * cell_height + cell_height / 2,
-                        );
+    let sheet_filepaths: Vec<String> =
+        collect_sheet_filepaths(&directory, params.directory_layout())
+            .into_iter()
+            .map(|(_sheet_number, filepath)| filepath)
+            .collect();
+    assert!(
+        !sheet_filepaths.is_empty(),
+        "There are no sheets in '{}' to print",
+        directory
+    );
+    // NOTE: Our CMYK TIFF sheets are meant for print shops and cannot be drawn by Windows printing
+    assert!(
+        sheet_filepaths
+            .iter()
+            .all(|filepath| filepath.ends_with(".png")),
+        "The sheets in '{}' are CMYK TIFF files which we cannot print - please print them with the \
+         program of your print shop or create PNG sheets without `cmyk_tiff_icc_profile_filepath`",
+        directory
+    );
 
-                    let number = number_grid.get(x, y);
-                    let number_bitmap = number_bitmaps_premultiplied.get(&number).unwrap();
-                    number_bitmap.blit_to_alpha_blended_premultiplied(
-                        &mut background,
-                        center - number_bitmap.rect().dim / 2,
-                        true,
-                        cottontail::image::ColorBlendMode::Normal,
-                    );
-                }
+    // NOTE: Collated prints go 1,2,3,1,2,3 while uncollated prints go 1,1,2,2,3,3
+    let print_order: Vec<&String> = if collate {
+        (0..copies).flat_map(|_| sheet_filepaths.iter()).collect()
+    } else {
+        sheet_filepaths
+            .iter()
+            .flat_map(|filepath| std::iter::repeat(filepath).take(copies))
+            .collect()
+    };
+
+    #[cfg(windows)]
+    printing::print_png_files(&printer_name, &print_order);
+    #[cfg(not(windows))]
+    panic!(
+        "Printing {} sheets on '{}' is only supported on Windows",
+        print_order.len(),
+        printer_name
+    );
+}
+
+/// Returns the sheet numbers and filepaths of all PNG and TIFF sheets that were written to the given
+/// output directory with the given layout ordered by their sheet number. Other files named after a
+/// sheet, i.e. the `deltas/sheet_1.json` of `output_mode = "deltas"`, are skipped.
+fn collect_sheet_filepaths(
+    directory: &str,
+    layout: manifest::DirectoryLayout,
+) -> Vec<(usize, String)> {
+    let mut filepaths = collect_files(directory);
+    if let Ok(entries) = std::fs::read_dir(directory) {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            if entry.path().is_dir() {
+                filepaths.extend(collect_files(&entry.path().to_string_lossy()));
             }
+        }
+    }
 
-            background
-                .to_unpremultiplied_alpha()
-                .write_to_png_file(&format!("output_sheets/sheet_{}.png", sheet_index + 1));
-        });
+    let mut sheet_filepaths: Vec<(usize, String)> = filepaths
+        .into_iter()
+        .filter_map(|filepath| {
+            let path = std::path::Path::new(&filepath);
+            let extension = path.extension()?.to_string_lossy().to_string();
+            if !SHEET_FILE_EXTENSIONS.contains(&extension.as_str()) {
+                return None;
+            }
+            let filename = path.file_stem()?.to_string_lossy().to_string();
+            let sheet_number = filename
+                .strip_prefix("sheet_")?
+                .parse::<usize>()
+                .ok()
+                .filter(|&sheet_number| sheet_number > 0)?;
+            let sheet_filepath = format!(
+                "{}.{}",
+                sheet_filepath_without_extension(directory, sheet_number, layout),
+                extension
+            );
+            if std::path::Path::new(&sheet_filepath) != path {
+                return None;
+            }
+            Some((sheet_number, filepath))
+        })
+        .collect();
+    sheet_filepaths.sort();
+    sheet_filepaths
+}
+
+fn command_line_option_value(args: &[String], option_name: &str) -> Option<String> {
+    args.iter().position(|arg| arg == option_name).map(|index| {
+        args.get(index + 1)
+            .unwrap_or_else(|| panic!("Missing value for '{}'", option_name))
+            .clone()
+    })
+}
+
+fn command_line_flag(args: &[String], flag_name: &str) -> bool {
+    args.iter().any(|arg| arg == flag_name)
+}
+
+/// Creates all sheets with a new random seed unless a `seed` is given. With `export_bundle` we also
+/// write a reproduction bundle next to the sheets.
+fn create_sheets(dry_run: bool, seed: Option<u64>, export_bundle: bool) {
+    if export_bundle {
+        // NOTE: The bundle would not contain the overridden parameters otherwise
+        params_overrides::assert_no_overrides("export a reproduction bundle");
+    }
+    let Input {
+        background_image_filepath,
+        font_filepath,
+        background_bitmap,
+        font,
+        font_hash,
+        header_font,
+        params,
+        recipients,
+    } = Input::new();
+    assert!(
+        recipients.is_none() || params.players.is_none(),
+        "The `players` parameter cannot be used together with '{}' - please remove one of them",
+        RECIPIENTS_FILENAME
+    );
+    let sheet_count = match (&recipients, params.mode) {
+        (Some(recipients), _) => recipients.len(),
+        (None, Mode::Puzzle) => params.puzzles().len(),
+        (None, Mode::SeatingCards) => params.seating_card_texts().len(),
+        (None, _) => params.sheet_count(),
+    };
+
+    let sheet_renderer = SheetRenderer::new(
+        &background_bitmap,
+        &font,
+        font_hash,
+        header_font.as_ref(),
+        &params,
+        recipients.as_ref(),
+    );
+
+    for (number, number_bitmap) in sheet_renderer.number_bitmaps_premultiplied.iter() {
+        if number_bitmap.width as i32 > sheet_renderer.cell_width
+            || number_bitmap.height as i32 > sheet_renderer.cell_height
+        {
+            log::warn!(
+                "Number {} is {}x{}px which is bigger than its {}x{}px grid cell - consider decreasing the font size",
+                number,
+                number_bitmap.width,
+                number_bitmap.height,
+                sheet_renderer.cell_width,
+                sheet_renderer.cell_height
+            );
+            break;
+        }
+    }
+
+    if let Some(&(column, row, contrast)) = sheet_renderer
+        .low_contrast_cells
+        .iter()
+        .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap())
+    {
+        if params.auto_number_outline {
+            log::info!(
+                "Added an outline to our numbers because they are hard to read in {} grid cells",
+                sheet_renderer.low_contrast_cells.len()
+            );
+        } else {
+            log::warn!(
+                "Numbers are hard to read in {} grid cells - the worst is cell [{}, {}] with a contrast of {:.1}:1 but at least {:.1}:1 is recommended. Consider changing the text color, adding a `number_chip` or setting `auto_number_outline = true`",
+                sheet_renderer.low_contrast_cells.len(),
+                column,
+                row,
+                contrast,
+                params.minimum_number_contrast.unwrap_or(DEFAULT_MINIMUM_NUMBER_CONTRAST)
+            );
+        }
+    }
+
+    let cmyk_icc_profile_data = load_cmyk_icc_profile_data(&params);
+
+    let estimate = estimate_run(&sheet_renderer, cmyk_icc_profile_data.as_ref(), sheet_count);
+    log::info!("{}", estimate.summary());
+
+    if dry_run {
+        create_dry_run_preview(&sheet_renderer, &estimate);
+        return;
+    }
 
+    if let (OutputMode::Sheets, Ok(available_bytes)) =
+        (params.output_mode, fs2::available_space("."))
+    {
+        if estimate.total_file_bytes > available_bytes {
+            let message = format!(
+                "Creating {} sheets needs about {:.1}GB of disk space but there is only {:.1}GB available. Do you want to continue anyway?",
+                sheet_count,
+                estimate.total_file_bytes as f64 / GIGABYTE,
+                available_bytes as f64 / GIGABYTE
+            );
+            log::warn!("{}", message);
+            if !show_messagebox_yes_no("Chotto", &message) {
+                return;
+            }
+        }
+    }
+
+    let output_directory = match prepare_output_directory(params.existing_output_policy) {
+        Some(output_directory) => output_directory,
+        None => return,
+    };
+    log::info!("Writing sheets to '{}'", output_directory);
+
+    let (mut random, seed) = create_random(params.rng, seed);
+    let start_time = std::time::Instant::now();
+    let mut cards = create_cards(&sheet_renderer, &[], sheet_count, random.as_mut());
+
+    let written_sheets = write_sheets_or_deltas(
+        &sheet_renderer,
+        &cards,
+        &output_directory,
+        cmyk_icc_profile_data.as_ref(),
+    );
+    cards.truncate(written_sheets.sheet_count);
+
+    let audit_entry = audit_log::append_entry(
+        "create",
+        &output_directory,
+        &report::draw_params_to_string(&params),
+        seed,
+        &cards,
+        &written_sheets.sheet_files,
+    );
+    run_manifest::write_run_manifest(
+        &output_directory,
+        "create",
+        &params,
+        seed,
+        &audit_entry,
+        (1, cards.len()),
+        &bundle::input_filepaths(&params, &background_image_filepath, &font_filepath),
+    );
+    let finished_sheet_count = cards.len();
+    players::write_player_list(&output_directory, &params, &cards);
+    if let Some(directory) = &params.deck_images_directory {
+        if params.bingo_variant == BingoVariant::Loteria {
+            deck::write_call_order(&output_directory, directory, random.as_mut());
+        }
+    }
+    manifest::write_manifest(
+        &output_directory,
+        &Manifest { cards },
+        params.directory_layout(),
+    );
+    report::write_report(
+        &output_directory,
+        &report::RunReport {
+            params: &params,
+            seed_commitment: &audit_entry.seed_commitment,
+            entropy_source: params.rng.entropy_source(),
+            sheet_count: finished_sheet_count,
+            elapsed_seconds: start_time.elapsed().as_secs_f64(),
+            thumbnails: written_sheets.thumbnails,
+        },
+    );
+    if cancellation::is_cancelled() {
+        if let Some(s3_uploader) = &written_sheets.s3_uploader {
+            s3_uploader.finish_upload(&output_directory);
+        }
+        report_cancelled_run(finished_sheet_count, sheet_count, &output_directory);
+        return;
+    }
+    match (export_bundle, seed) {
+        (true, Some(seed)) => bundle::export_bundle(
+            &output_directory,
+            &params,
+            &background_image_filepath,
+            &font_filepath,
+            seed,
+        ),
+        (true, None) => log::warn!(
+            "Did not write a reproduction bundle because the cards of `rng = \"secure\"` must \
+             not be recreatable"
+        ),
+        (false, _) => {}
+    }
+    if let Some(s3_uploader) = &written_sheets.s3_uploader {
+        s3_uploader.finish_upload(&output_directory);
+    }
+
+    log::info!(
+        "Finished creating {} sheets in '{}'",
+        sheet_count,
+        output_directory
+    );
     #[cfg(not(debug_assertions))]
     show_messagebox("Chotto", "Finished creating sheets. Enjoy!", false);
 }
 
-const MAX_SHEET_COUNT: usize = 10_000;
+/// Usage: `chotto extend --count 500 [--batch <dir>]`
+fn extend_sheets(args: &[String]) {
+    let batch_directory = command_line_option_value(args, "--batch")
+        .unwrap_or_else(|| OUTPUT_SHEETS_DIRECTORY.to_owned());
+    let count = command_line_option_value(args, "--count")
+        .map(|value| {
+            value
+                .parse::<usize>()
+                .unwrap_or_else(|_| panic!("Invalid number of sheets '{}'", value))
+        })
+        .expect("Please specify how many sheets to add via `--count <number>`");
 
-#[derive(Debug, Serialize, Deserialize)]
-struct DrawParams {
-    number_of_sheets_to_generate: usize,
-    text_font_size: u32,
-    text_color_rgb: (u8, u8, u8),
-    bingo_grid_pixel_location_left_top_right_bottom: (u32, u32, u32, u32),
+    let Input {
+        background_image_filepath,
+        font_filepath,
+        background_bitmap,
+        font,
+        font_hash,
+        header_font,
+        params,
+        recipients,
+    } = Input::new();
+    assert!(
+        recipients.is_none(),
+        "Personalized sheets cannot be extended - please add the new rows to '{}' and create all sheets again",
+        RECIPIENTS_FILENAME
+    );
+    if let Some(cards_per_player) = params.cards_per_player {
+        assert!(
+            count % cards_per_player == 0,
+            "Please add a multiple of {} sheets (our `cards_per_player`) so that each new player gets all of their sheets",
+            cards_per_player
+        );
+    }
+
+    // NOTE: An earlier run that was interrupted may have left unfinished sheets behind
+    atomic_file::remove_partial_files(&batch_directory);
+    let previous_cards = manifest::read_manifest(&batch_directory).cards;
+    assert!(
+        previous_cards.len() + count <= MAX_SHEET_COUNT,
+        "The maximum sheet count is {} but '{}' already contains {} sheets",
+        MAX_SHEET_COUNT,
+        batch_directory,
+        previous_cards.len()
+    );
+    let (column_count, row_count) = params.grid_size();
+    assert!(
+        previous_cards.iter().all(|card| {
+            (!params.mode.has_grid() && card.numbers.is_empty())
+                || card.numbers.len() == row_count as usize
+                    && card
+                        .numbers
+                        .iter()
+                        .all(|row| row.len() == column_count as usize)
+        }),
+        "The sheets in '{}' were created for a different `bingo_variant` than given in '{}'",
+        batch_directory,
+        DRAW_PARAMETERS_FILENAME
+    );
+    log::info!(
+        "Adding {} sheets to the {} sheets in '{}'",
+        count,
+        previous_cards.len(),
+        batch_directory
+    );
+
+    let sheet_renderer = SheetRenderer::new(
+        &background_bitmap,
+        &font,
+        font_hash,
+        header_font.as_ref(),
+        &params,
+        None,
+    );
+    let cmyk_icc_profile_data = load_cmyk_icc_profile_data(&params);
+
+    let (mut random, seed) = create_random(params.rng, None);
+    let mut cards = create_cards(&sheet_renderer, &previous_cards, count, random.as_mut());
+    let written_sheets = write_sheets_or_deltas(
+        &sheet_renderer,
+        &cards,
+        &batch_directory,
+        cmyk_icc_profile_data.as_ref(),
+    );
+    cards.truncate(written_sheets.sheet_count);
+
+    let audit_entry = audit_log::append_entry(
+        "extend",
+        &batch_directory,
+        &report::draw_params_to_string(&params),
+        seed,
+        &cards,
+        &written_sheets.sheet_files,
+    );
+    run_manifest::write_run_manifest(
+        &batch_directory,
+        "extend",
+        &params,
+        seed,
+        &audit_entry,
+        (previous_cards.len() + 1, previous_cards.len() + cards.len()),
+        &bundle::input_filepaths(&params, &background_image_filepath, &font_filepath),
+    );
+    let finished_sheet_count = cards.len();
+    let mut all_cards = previous_cards;
+    all_cards.extend(cards);
+    players::write_player_list(&batch_directory, &params, &all_cards);
+    manifest::write_manifest(
+        &batch_directory,
+        &Manifest { cards: all_cards },
+        params.directory_layout(),
+    );
+    if let Some(s3_uploader) = &written_sheets.s3_uploader {
+        s3_uploader.finish_upload(&batch_directory);
+    }
+    if cancellation::is_cancelled() {
+        report_cancelled_run(finished_sheet_count, count, &batch_directory);
+        return;
+    }
+
+    log::info!("Finished adding {} sheets to '{}'", count, batch_directory);
+    #[cfg(not(debug_assertions))]
+    show_messagebox("Chotto", "Finished adding sheets. Enjoy!", false);
+}
+
+fn load_cmyk_icc_profile_data(params: &DrawParams) -> Option<Vec<u8>> {
+    params
+        .cmyk_tiff_icc_profile_filepath
+        .as_ref()
+        .map(|filepath| {
+            read_file_whole(filepath)
+                .expect(&format!("Cannot read ICC profile file '{}'", filepath))
+        })
+}
+
+/// Usage: `chotto render [--directory <dir>] [--from 1] [--to 100]`
+///
+/// Draws the full sheets of an earlier run from its manifest. This is mainly meant for runs that
+/// were created with `output_mode = "deltas"`.
+fn render_sheets_from_manifest(args: &[String]) {
+    let directory = command_line_option_value(args, "--directory")
+        .unwrap_or_else(|| OUTPUT_SHEETS_DIRECTORY.to_owned());
+    let parse_sheet_number = |option_name: &str| {
+        command_line_option_value(args, option_name).map(|value| {
+            value
+                .parse::<usize>()
+                .unwrap_or_else(|_| panic!("Invalid sheet number '{}'", value))
+        })
+    };
+    let first_sheet_number = parse_sheet_number("--from").unwrap_or(1);
+    let last_sheet_number = parse_sheet_number("--to").unwrap_or(std::usize::MAX);
+
+    let Input {
+        background_bitmap,
+        font,
+        font_hash,
+        header_font,
+        params,
+        recipients,
+        ..
+    } = Input::new();
+    let cards: Vec<Card> = manifest::read_manifest(&directory)
+        .cards
+        .into_iter()
+        .filter(|card| {
+            first_sheet_number <= card.sheet_number && card.sheet_number <= last_sheet_number
+        })
+        .collect();
+    assert!(
+        !cards.is_empty(),
+        "There are no sheets between {} and {} in '{}'",
+        first_sheet_number,
+        last_sheet_number,
+        directory
+    );
+    log::info!("Drawing {} sheets in '{}'", cards.len(), directory);
+
+    let sheet_renderer = SheetRenderer::new(
+        &background_bitmap,
+        &font,
+        font_hash,
+        header_font.as_ref(),
+        &params,
+        recipients.as_ref(),
+    );
+    let cmyk_icc_profile_data = load_cmyk_icc_profile_data(&params);
+    let written_sheets = render_sheets(
+        &sheet_renderer,
+        &cards,
+        &directory,
+        cmyk_icc_profile_data.as_ref(),
+    );
+    if let Some(s3_uploader) = &written_sheets.s3_uploader {
+        s3_uploader.finish_upload(&directory);
+    }
+    if cancellation::is_cancelled() {
+        report_cancelled_run(written_sheets.sheet_count, cards.len(), &directory);
+        return;
+    }
+
+    log::info!("Finished drawing {} sheets in '{}'", cards.len(), directory);
+    #[cfg(not(debug_assertions))]
+    show_messagebox("Chotto", "Finished drawing sheets. Enjoy!", false);
+}
+
+/// Usage: `chotto render-from --cards cards.json`
+///
+/// Draws sheets whose numbers were decided by an external system instead of by us. See the
+/// `card_import` module for the format of the card file.
+fn render_sheets_from_card_file(args: &[String]) {
+    let card_filepath = command_line_option_value(args, "--cards")
+        .expect("Please specify the card file via `--cards <filepath>`");
+
+    let Input {
+        background_image_filepath,
+        font_filepath,
+        background_bitmap,
+        font,
+        font_hash,
+        header_font,
+        params,
+        recipients,
+    } = Input::new();
+    let (column_count, row_count) = params.grid_size();
+    let imported_cards = card_import::read_card_file(
+        &card_filepath,
+        column_count,
+        row_count,
+        params.max_number(),
+        &params.free_space_cells(),
+    );
+    if let Some(recipients) = &recipients {
+        assert!(
+            recipients.len() == imported_cards.len(),
+            "There are {} cards in '{}' but {} recipients in '{}'",
+            imported_cards.len(),
+            card_filepath,
+            recipients.len(),
+            RECIPIENTS_FILENAME
+        );
+    }
+    let sheet_count = imported_cards.len();
+
+    let sheet_renderer = SheetRenderer::new(
+        &background_bitmap,
+        &font,
+        font_hash,
+        header_font.as_ref(),
+        &params,
+        recipients.as_ref(),
+    );
+    let cmyk_icc_profile_data = load_cmyk_icc_profile_data(&params);
+
+    let output_directory = match prepare_output_directory(params.existing_output_policy) {
+        Some(output_directory) => output_directory,
+        None => return,
+    };
+    log::info!(
+        "Writing {} sheets from '{}' to '{}'",
+        sheet_count,
+        card_filepath,
+        output_directory
+    );
+
+    // NOTE: The numbers are given but we still need randomness for things like bonus cells
+    let (mut random, seed) = create_random(params.rng, None);
+    let start_time = std::time::Instant::now();
+    let number_grids: Vec<Grid<i32>> = imported_cards
+        .iter()
+        .map(|card| card.number_grid())
+        .collect();
+    let mut cards = create_cards_from_number_grids(
+        sheet_renderer.params,
+        &sheet_renderer.free_space_image_names(),
+        &[],
+        &number_grids,
+        random.as_mut(),
+    );
+    for (card, imported_card) in cards.iter_mut().zip(imported_cards.iter()) {
+        if let Some(serial) = &imported_card.serial {
+            card.serial = serial.clone();
+        }
+    }
+
+    let written_sheets = write_sheets_or_deltas(
+        &sheet_renderer,
+        &cards,
+        &output_directory,
+        cmyk_icc_profile_data.as_ref(),
+    );
+    cards.truncate(written_sheets.sheet_count);
+
+    let audit_entry = audit_log::append_entry(
+        "render-from",
+        &output_directory,
+        &report::draw_params_to_string(&params),
+        seed,
+        &cards,
+        &written_sheets.sheet_files,
+    );
+    let mut input_filepaths =
+        bundle::input_filepaths(&params, &background_image_filepath, &font_filepath);
+    input_filepaths.push(card_filepath.clone());
+    run_manifest::write_run_manifest(
+        &output_directory,
+        "render-from",
+        &params,
+        seed,
+        &audit_entry,
+        (1, cards.len()),
+        &input_filepaths,
+    );
+    let finished_sheet_count = cards.len();
+    players::write_player_list(&output_directory, &params, &cards);
+    manifest::write_manifest(
+        &output_directory,
+        &Manifest { cards },
+        params.directory_layout(),
+    );
+    report::write_report(
+        &output_directory,
+        &report::RunReport {
+            params: &params,
+            seed_commitment: &audit_entry.seed_commitment,
+            entropy_source: params.rng.entropy_source(),
+            sheet_count: finished_sheet_count,
+            elapsed_seconds: start_time.elapsed().as_secs_f64(),
+            thumbnails: written_sheets.thumbnails,
+        },
+    );
+    if let Some(s3_uploader) = &written_sheets.s3_uploader {
+        s3_uploader.finish_upload(&output_directory);
+    }
+    if cancellation::is_cancelled() {
+        report_cancelled_run(finished_sheet_count, sheet_count, &output_directory);
+        return;
+    }
+
+    log::info!(
+        "Finished creating {} sheets from '{}' in '{}'",
+        sheet_count,
+        card_filepath,
+        output_directory
+    );
+    #[cfg(not(debug_assertions))]
+    show_messagebox("Chotto", "Finished creating sheets. Enjoy!", false);
+}
+
+/// The outcome of writing our sheets
+struct WrittenSheets {
+    /// How many of the given cards were written. This is less than all cards if the user cancelled
+    /// our run, in which case the written cards are always the first ones.
+    sheet_count: usize,
+    /// The thumbnails that were written for the report
+    thumbnails: Vec<report::Thumbnail>,
+    /// The written sheet files ordered by their sheet number for our audit log
+    sheet_files: Vec<audit_log::SheetFile>,
+    /// Uploads the files that describe our run (i.e. manifest, report and thumbnails) once the last
+    /// of them was written if we upload our sheets to a bucket
+    s3_uploader: Option<Arc<s3_upload::S3Uploader>>,
+}
+
+/// Tells the user how many sheets were finished before they cancelled our run
+fn report_cancelled_run(finished_sheet_count: usize, sheet_count: usize, output_directory: &str) {
+    let message = format!(
+        "Stopped after {} of {} sheets. The finished sheets and their manifest are in '{}'.",
+        finished_sheet_count, sheet_count, output_directory
+    );
+    log::warn!("{}", message);
+    #[cfg(not(debug_assertions))]
+    show_messagebox("Chotto", &message, false);
+}
+
+/// Writes the given cards as full sheets or as deltas depending on our `output_mode`
+fn write_sheets_or_deltas(
+    sheet_renderer: &SheetRenderer,
+    cards: &[Card],
+    output_directory: &str,
+    cmyk_icc_profile_data: Option<&Vec<u8>>,
+) -> WrittenSheets {
+    match sheet_renderer.params.output_mode {
+        OutputMode::Sheets => render_sheets(
+            sheet_renderer,
+            cards,
+            output_directory,
+            cmyk_icc_profile_data,
+        ),
+        OutputMode::Deltas => {
+            deltas::write_background(
+                output_directory,
+                &sheet_renderer.background.to_unpremultiplied_alpha(),
+            );
+            let mut sheet_count = 0;
+            for chunk in cards.chunks(SHEETS_PER_CHUNK) {
+                if cancellation::is_cancelled() {
+                    break;
+                }
+                chunk.par_iter().for_each(|card| {
+                    deltas::write_card_delta(output_directory, &sheet_renderer.card_delta(card))
+                });
+                sheet_count += chunk.len();
+            }
+            log::info!(
+                "Wrote the background and {} deltas - use `chotto render` to draw the full sheets",
+                sheet_count
+            );
+            WrittenSheets {
+                sheet_count,
+                thumbnails: Vec::new(),
+                sheet_files: Vec::new(),
+                s3_uploader: None,
+            }
+        }
+    }
+}
+
+/// The sheets that we draw, queue and encode at the same time must fit into this many bytes. This
+/// keeps poster-size sheets from running out of memory on machines with 8 GB.
+const SHEET_MEMORY_BUDGET_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Returns how many sheets of the given size we draw at the same time
+fn draw_thread_count(sheet_width: u32, sheet_height: u32) -> usize {
+    // NOTE: Each drawing thread holds a sheet and its unpremultiplied version. Half as many encoder
+    //       threads hold one sheet each plus twice as many queued sheets.
+    let sheet_memory_bytes = 4 * sheet_width as u64 * sheet_height as u64;
+    let sheets_per_draw_thread = 4;
+    let thread_count = SHEET_MEMORY_BUDGET_BYTES / (sheets_per_draw_thread * sheet_memory_bytes);
+    usize::max(
+        1,
+        usize::min(rayon::current_num_threads(), thread_count as usize),
+    )
+}
+
+/// Calls `map_card` for each of the given cards on the given threads and returns its results in the
+/// order of the cards. Each call only gets its own card and a clone of the given state so that the
+/// order in which our threads finish never changes the result of a card.
+fn map_cards_in_parallel<State, Output, MapCard>(
+    thread_pool: &rayon::ThreadPool,
+    cards: &[Card],
+    state: State,
+    map_card: MapCard,
+) -> Vec<Output>
+where
+    State: Clone + Send,
+    Output: Send,
+    MapCard: Fn(&mut State, &Card) -> Output + Sync + Send,
+{
+    thread_pool.install(|| cards.par_iter().map_with(state, &map_card).collect())
+}
+
+/// Draws and writes the given cards into the output directory in parallel. If the user cancels our
+/// run we stop after the chunk of sheets that is currently drawn.
+fn render_sheets(
+    sheet_renderer: &SheetRenderer,
+    cards: &[Card],
+    output_directory: &str,
+    cmyk_icc_profile_data: Option<&Vec<u8>>,
+) -> WrittenSheets {
+    // NOTE: Each sheet is drawn only from its own card and written to the file of its own sheet
+    //       number. The order in which our threads finish therefore never changes which file
+    //       shows which card (see `map_cards_in_parallel`).
+    debug_assert!(
+        cards
+            .windows(2)
+            .all(|pair| pair[1].sheet_number == pair[0].sheet_number + 1),
+        "Our cards must be sorted by their consecutive sheet numbers"
+    );
+
+    // NOTE: Encoding the sheets takes longer than drawing them. We therefore hand our drawn sheets
+    //       over to dedicated encoder threads so that drawing and encoding overlap. The bounded
+    //       queue keeps us from drawing more sheets than we can hold in memory.
+    let draw_thread_count = draw_thread_count(
+        sheet_renderer.background.width as u32,
+        sheet_renderer.background.height as u32,
+    );
+    if draw_thread_count < rayon::current_num_threads() {
+        log::info!(
+            "Drawing only {} sheets at the same time so that our big sheets fit into memory",
+            draw_thread_count
+        );
+    }
+    let draw_thread_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(draw_thread_count)
+        .build()
+        .expect("Could not create our drawing threads");
+    let encoder_thread_count = usize::max(1, draw_thread_count / 2);
+    let (sheet_sender, sheet_receiver) =
+        std::sync::mpsc::sync_channel::<(SheetRegion, usize, String)>(2 * encoder_thread_count);
+    let sheet_receiver = std::sync::Arc::new(std::sync::Mutex::new(sheet_receiver));
+    // NOTE: Each sheet file is hashed right after writing it as it might be removed after uploading
+    let sheet_files = Arc::new(std::sync::Mutex::new(Vec::new()));
+    // NOTE: Uploading happens on the encoder threads so that only a few sheets are on our local
+    //       disk at any time
+    let s3_uploader = sheet_renderer
+        .params
+        .s3_upload
+        .as_ref()
+        .map(|s3_params| std::sync::Arc::new(s3_upload::S3Uploader::new(s3_params)));
+    // NOTE: Our sheets only keep the region that their card was drawn onto. All of them share the
+    //       same background which we transform and unpremultiply only once.
+    let sheet_background_premultiplied = transform_sheet(
+        sheet_renderer.finished_background.clone(),
+        sheet_renderer.params.output_rotation,
+        sheet_renderer.params.output_mirror,
+    );
+    let sheet_background = Arc::new(
+        sheet_renderer
+            .unpremultiplied_sheet(&sheet_background_premultiplied)
+            .unwrap_or_else(|| sheet_background_premultiplied.clone()),
+    );
+    let encoder_threads: Vec<std::thread::JoinHandle<()>> = (0..encoder_thread_count)
+        .map(|_| {
+            let sheet_receiver = sheet_receiver.clone();
+            let sheet_background = sheet_background.clone();
+            let cmyk_icc_profile_data = cmyk_icc_profile_data.cloned();
+            let png_compression = sheet_renderer.params.png_compression;
+            let output_color_profile = sheet_renderer.params.output_color_profile;
+            let s3_uploader = s3_uploader.clone();
+            let sheet_files = sheet_files.clone();
+            let output_directory = output_directory.to_owned();
+            std::thread::spawn(move || loop {
+                let next_sheet = sheet_receiver.lock().unwrap().recv();
+                match next_sheet {
+                    Ok((sheet_region, sheet_number, filepath)) => {
+                        write_sheet_file(
+                            &ComposedSheet::new(&sheet_background, &sheet_region),
+                            &filepath,
+                            cmyk_icc_profile_data.as_ref(),
+                            png_compression,
+                            output_color_profile,
+                        );
+                        sheet_files
+                            .lock()
+                            .unwrap()
+                            .push((sheet_number, audit_log::SheetFile::new(&filepath)));
+                        if let Some(s3_uploader) = &s3_uploader {
+                            s3_uploader.upload_sheet_file(&filepath, &output_directory);
+                        }
+                    }
+                    Err(_) => break,
+                }
+            })
+        })
+        .collect();
+
+    let gpu_compositor = if sheet_renderer.params.compositing_backend == CompositingBackend::Gpu {
+        gpu_compositing::GpuCompositor::new(sheet_renderer, cards)
+    } else {
+        None
+    };
+
+    let last_sheet_number = cards.last().map(|card| card.sheet_number).unwrap_or(0);
+    let mut thumbnails = Vec::new();
+    let mut sheet_count = 0;
+    for (chunk_index, chunk) in cards.chunks(SHEETS_PER_CHUNK).enumerate() {
+        if cancellation::is_cancelled() {
+            break;
+        }
+        let chunk_start_time = std::time::Instant::now();
+        let first_sheet_index = chunk_index * SHEETS_PER_CHUNK;
+        // NOTE: The GPU draws the grid regions of the whole chunk at once. We take each of them out
+        //       of the map when its sheet is drawn so that it is not kept around any longer.
+        let gpu_grid_regions = std::sync::Mutex::new(
+            gpu_compositor
+                .as_ref()
+                .map(|gpu_compositor| gpu_compositor.draw_grid_regions(sheet_renderer, chunk))
+                .unwrap_or_default(),
+        );
+        let chunk_thumbnails: Vec<report::Thumbnail> = map_cards_in_parallel(
+            &draw_thread_pool,
+            chunk,
+            sheet_sender.clone(),
+            |sheet_sender, card| {
+                let gpu_grid_region = gpu_grid_regions.lock().unwrap().remove(&card.sheet_number);
+                let grid_region =
+                    gpu_grid_region.unwrap_or_else(|| sheet_renderer.draw_grid_region(card));
+                let sheet_region_premultiplied = transform_sheet_region(
+                    sheet_renderer.draw_sheet_region_around_grid_region(card, grid_region),
+                    sheet_renderer.finished_background.width as i32,
+                    sheet_renderer.finished_background.height as i32,
+                    sheet_renderer.params.output_rotation,
+                    sheet_renderer.params.output_mirror,
+                );
+                let sheet_filepath = sheet_filepath(
+                    sheet_renderer.params,
+                    output_directory,
+                    card.sheet_number,
+                    cmyk_icc_profile_data,
+                );
+
+                let thumbnail = if card.sheet_number <= report::THUMBNAIL_COUNT {
+                    let sheet_premultiplied = ComposedSheet::new(
+                        &sheet_background_premultiplied,
+                        &sheet_region_premultiplied,
+                    )
+                    .to_bitmap();
+                    let thumbnail_filepath = report::write_thumbnail(
+                        &sheet_premultiplied,
+                        output_directory,
+                        card.sheet_number,
+                    );
+                    // NOTE: Our report links to the uploaded sheet if the local one is removed
+                    let linked_sheet_filepath = match &s3_uploader {
+                        Some(s3_uploader) if s3_uploader.removes_local_files() => {
+                            s3_uploader.object_url(&sheet_filepath, output_directory)
+                        }
+                        _ => sheet_filepath
+                            .strip_prefix(&format!("{}/", output_directory))
+                            .unwrap_or(&sheet_filepath)
+                            .to_owned(),
+                    };
+                    Some(report::Thumbnail {
+                        sheet_number: card.sheet_number,
+                        serial: card.serial.clone(),
+                        sheet_filepath: linked_sheet_filepath,
+                        thumbnail_filepath,
+                    })
+                } else {
+                    None
+                };
+
+                let sheet_region =
+                    sheet_renderer.unpremultiplied_region(sheet_region_premultiplied);
+                sheet_sender
+                    .send((sheet_region, card.sheet_number, sheet_filepath))
+                    .expect("All sheet encoder threads stopped unexpectedly");
+                thumbnail
+            },
+        )
+        .into_iter()
+        .flatten()
+        .collect();
+        thumbnails.extend(chunk_thumbnails);
+        log::info!(
+            "Drew sheets {}-{} of {} in {:.2}s",
+            cards[first_sheet_index].sheet_number,
+            cards[first_sheet_index].sheet_number + chunk.len() - 1,
+            last_sheet_number,
+            chunk_start_time.elapsed().as_secs_f32()
+        );
+        sheet_count += chunk.len();
+    }
+
+    // NOTE: Dropping our sender lets the encoder threads finish once the queue is empty
+    drop(sheet_sender);
+    let encoding_start_time = std::time::Instant::now();
+    for encoder_thread in encoder_threads {
+        encoder_thread.join().ok();
+    }
+    log::info!(
+        "Finished writing the remaining sheets in {:.2}s",
+        encoding_start_time.elapsed().as_secs_f32()
+    );
+    if let Some(post_process_command) = &sheet_renderer.params.post_process_command {
+        let sheets: Vec<(String, &Card)> = cards[..sheet_count]
+            .iter()
+            .map(|card| {
+                let sheet_filepath = sheet_filepath(
+                    sheet_renderer.params,
+                    output_directory,
+                    card.sheet_number,
+                    cmyk_icc_profile_data,
+                );
+                (sheet_filepath, card)
+            })
+            .collect();
+        post_process::post_process_sheets(
+            post_process_command,
+            sheet_renderer.params.post_process_parallelism,
+            &sheets,
+        );
+    }
+
+    thumbnails.sort_by_key(|thumbnail| thumbnail.sheet_number);
+    let mut sheet_files = std::mem::take(&mut *sheet_files.lock().unwrap());
+    sheet_files.sort_by_key(|(sheet_number, _)| *sheet_number);
+    let sheet_files = sheet_files
+        .into_iter()
+        .map(|(_, sheet_file)| sheet_file)
+        .collect();
+    WrittenSheets {
+        sheet_count,
+        thumbnails,
+        sheet_files,
+        s3_uploader,
+    }
+}
+
+/// Returns the random numbers for the cards of a new run and the seed that recreates them. The
+/// given seed is used if there is one (i.e. to reproduce a bundle). Runs with `rng = "secure"`
+/// have no seed because nobody must be able to recreate their cards.
+fn create_random(rng: RngSource, seed: Option<u64>) -> (Box<dyn RandomSource>, Option<u64>) {
+    if let Some(seed) = seed {
+        log::info!("Using the given random seed {}", seed);
+        return (Box::new(Random::new_from_seed(seed)), Some(seed));
+    }
+    match rng {
+        RngSource::Time => {
+            let seed = create_time_based_seed();
+            log::info!("Using random seed {} from {}", seed, rng.entropy_source());
+            (Box::new(Random::new_from_seed(seed)), Some(seed))
+        }
+        RngSource::Secure => {
+            log::info!("Using random numbers from {}", rng.entropy_source());
+            (Box::new(secure_random::SecureRandom::new()), None)
+        }
+    }
+}
+
+fn create_time_based_seed() -> u64 {
+    let start = SystemTime::now();
+    let since_the_epoch = start.duration_since(UNIX_EPOCH).unwrap();
+    (since_the_epoch.as_nanos() & (std::u64::MAX as u128)) as u64
+}
+
+/// Decides the contents of all our sheets. If we extend an earlier batch its cards are given as
+/// `previous_cards` and the new cards continue their sheet numbers and never duplicate them.
+fn create_cards(
+    sheet_renderer: &SheetRenderer,
+    previous_cards: &[Card],
+    sheet_count: usize,
+    random: &mut dyn RandomSource,
+) -> Vec<Card> {
+    // NOTE: Raffle tickets only differ by their serial numbers, seating cards by their text and
+    //       countdowns by their number
+    if !sheet_renderer.params.mode.has_grid() {
+        let empty_grids: Vec<Grid<i32>> = (0..sheet_count).map(|_| Grid::new(0, 0)).collect();
+        let mut cards = create_cards_from_number_grids(
+            sheet_renderer.params,
+            &sheet_renderer.free_space_image_names(),
+            previous_cards,
+            &empty_grids,
+            random,
+        );
+        if sheet_renderer.params.mode == Mode::SeatingCards {
+            let texts = sheet_renderer.params.seating_card_texts();
+            assert!(
+                previous_cards.len() + sheet_count <= texts.len(),
+                "There are only {} lines in our `seating_cards_filepath` but {} sheets are needed \
+                 - please add more names or table numbers to it",
+                texts.len(),
+                previous_cards.len() + sheet_count
+            );
+            for (card, text) in cards
+                .iter_mut()
+                .zip(texts.into_iter().skip(previous_cards.len()))
+            {
+                card.seating_card_text = Some(text);
+            }
+        }
+        if let Some(countdown) = &sheet_renderer.params.countdown {
+            let countdown_sheet_count = sheet_renderer.params.sheet_count();
+            for card in cards.iter_mut() {
+                card.countdown_number =
+                    Some(countdown.number_of_sheet(card.sheet_number, countdown_sheet_count));
+            }
+        }
+        return cards;
+    }
+    // NOTE: Puzzles are drawn in the order of their file. Extending a batch continues with the
+    //       puzzles that follow the ones of the batch.
+    if sheet_renderer.params.mode == Mode::Puzzle {
+        let puzzles = sheet_renderer.params.puzzles();
+        assert!(
+            previous_cards.len() + sheet_count <= puzzles.len(),
+            "There are only {} puzzles in our `puzzles_filepath` but {} sheets are needed - please \
+             add more puzzles to it",
+            puzzles.len(),
+            previous_cards.len() + sheet_count
+        );
+        let puzzles = &puzzles[previous_cards.len()..previous_cards.len() + sheet_count];
+        let number_grids: Vec<Grid<i32>> =
+            puzzles.iter().map(|puzzle| puzzle.number_grid()).collect();
+        let mut cards = create_cards_from_number_grids(
+            sheet_renderer.params,
+            &sheet_renderer.free_space_image_names(),
+            previous_cards,
+            &number_grids,
+            random,
+        );
+        for (card, puzzle) in cards.iter_mut().zip(puzzles.iter()) {
+            if let Some(serial) = &puzzle.serial {
+                card.serial = serial.clone();
+            }
+        }
+        return cards;
+    }
+
+    let previous_grids: Vec<Grid<i32>> = previous_cards
+        .iter()
+        .map(|card| card.number_grid())
+        .collect();
+
+    let forbidden_number_combinations: Vec<ForbiddenNumberCombination> = sheet_renderer
+        .params
+        .forbidden_number_combinations
+        .iter()
+        .map(|numbers| ForbiddenNumberCombination {
+            numbers: numbers.clone(),
+        })
+        .collect();
+    let minimize_shared_lines = MinimizeSharedLines::new();
+    let mut constraints: Vec<&dyn CardConstraint> = forbidden_number_combinations
+        .iter()
+        .map(|constraint| constraint as &dyn CardConstraint)
+        .collect();
+    if sheet_renderer.params.optimize == Optimization::MinimizeTies {
+        constraints.push(&minimize_shared_lines);
+    }
+
+    let number_grids = match sheet_renderer.params.bingo_variant {
+        BingoVariant::Ball75 => generator::create_random_number_grids(
+            sheet_count,
+            &sheet_renderer.params.excluded_numbers,
+            &sheet_renderer.params.free_space_cells(),
+            &constraints,
+            &previous_grids,
+            random,
+        ),
+        BingoVariant::Ball90 => {
+            ninety_ball::create_random_books(sheet_count, &constraints, &previous_grids, random)
+        }
+        BingoVariant::Ball80 => generator::create_random_number_grids_80_ball(
+            sheet_count,
+            &constraints,
+            &previous_grids,
+            random,
+        ),
+        BingoVariant::Ball30 => generator::create_random_number_grids_30_ball(
+            sheet_count,
+            &constraints,
+            &previous_grids,
+            random,
+        ),
+        BingoVariant::Loteria => {
+            loteria::create_random_cards(sheet_count, &constraints, &previous_grids, random)
+        }
+        BingoVariant::CellLayout => {
+            let params = sheet_renderer.params;
+            cell_layout::create_random_grids(
+                sheet_count,
+                &cell_layout_params::layout_cells(
+                    params.cell_layout.as_ref().unwrap(),
+                    &params.cell_pools,
+                ),
+                &cell_layout_params::pool_numbers(&params.cell_pools),
+                &constraints,
+                &previous_grids,
+                random,
+            )
+        }
+        BingoVariant::ScavengerHunt => {
+            let scavenger_hunt = sheet_renderer.params.scavenger_hunt.as_ref().unwrap();
+            scavenger_hunt::create_random_cards(
+                sheet_count,
+                &scavenger_hunt.categories(),
+                scavenger_hunt.layout(),
+                &constraints,
+                &previous_grids,
+                random,
+            )
+        }
+    };
+    create_cards_from_number_grids(
+        sheet_renderer.params,
+        &sheet_renderer.free_space_image_names(),
+        previous_cards,
+        &number_grids,
+        random,
+    )
+}
+
+/// Creates cards with the given numbers and decides the rest of their contents like serial
+/// numbers and bonus cells. All random decisions happen here one card after another so that the
+/// contents of each sheet only depend on our seed and never on how our threads were scheduled.
+fn create_cards_from_number_grids(
+    params: &DrawParams,
+    free_space_image_names: &[String],
+    previous_cards: &[Card],
+    number_grids: &[Grid<i32>],
+    random: &mut dyn RandomSource,
+) -> Vec<Card> {
+    let first_sheet_number = previous_cards
+        .iter()
+        .map(|card| card.sheet_number)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let mut cards: Vec<Card> = number_grids
+        .iter()
+        .enumerate()
+        .map(|(index, number_grid)| Card::new(first_sheet_number + index, number_grid))
+        .collect();
+
+    if !free_space_image_names.is_empty() {
+        let mut free_space_image_bag = RandomBag::new(free_space_image_names.to_vec());
+        for card in cards.iter_mut() {
+            let sheet_index = card.sheet_number - 1;
+            let name = match params.free_space_image_selection {
+                FreeSpaceImageSelection::Random => free_space_image_bag.get_next(random),
+                FreeSpaceImageSelection::RoundRobin => {
+                    free_space_image_names[sheet_index % free_space_image_names.len()].clone()
+                }
+            };
+            card.free_space_image = Some(name);
+        }
+    }
+
+    let accent_color_names = params.accent_color_names();
+    if !accent_color_names.is_empty() {
+        // NOTE: The bag spreads the colors evenly so that no color is left out in small runs
+        let mut accent_color_bag = RandomBag::new(accent_color_names.clone());
+        for card in cards.iter_mut() {
+            card.accent_color = Some(accent_color_bag.get_next(random));
+        }
+        for name in &accent_color_names {
+            log::info!(
+                "{} of {} sheets have the accent color '{}'",
+                cards
+                    .iter()
+                    .filter(|card| card.accent_color.as_ref() == Some(name))
+                    .count(),
+                cards.len(),
+                name
+            );
+        }
+    }
+
+    let sheet_numbers: Vec<usize> = cards.iter().map(|card| card.sheet_number).collect();
+    let previous_serials: HashSet<String> = previous_cards
+        .iter()
+        .map(|card| card.serial.clone())
+        .collect();
+    let serials = serial_number::create_serial_numbers(
+        &params.serial_number,
+        &sheet_numbers,
+        &previous_serials,
+        random,
+    );
+    for (card, serial) in cards.iter_mut().zip(serials.into_iter()) {
+        card.serial = serial;
+    }
+
+    if let Some(secret_key) = &params.security_code_secret_key {
+        for card in cards.iter_mut() {
+            card.security_code = Some(security_code::create_security_code(
+                &card.numbers,
+                secret_key,
+            ));
+        }
+    }
+
+    if !params.lucky_numbers.is_empty() {
+        for card in cards.iter_mut() {
+            card.lucky_numbers = card
+                .numbers
+                .iter()
+                .flatten()
+                .cloned()
+                .filter(|number| params.lucky_numbers.contains(number))
+                .collect();
+            card.lucky_numbers.sort();
+        }
+        log::info!(
+            "{} of {} sheets contain a lucky number",
+            cards
+                .iter()
+                .filter(|card| !card.lucky_numbers.is_empty())
+                .count(),
+            cards.len()
+        );
+    }
+
+    if params.bonus_cells_x_y.is_some() || params.random_bonus_cell_count.is_some() {
+        for card in cards.iter_mut() {
+            let number_cells: Vec<(usize, usize)> = card
+                .numbers
+                .iter()
+                .enumerate()
+                .flat_map(|(y, row)| {
+                    row.iter()
+                        .enumerate()
+                        .filter(|(_x, number)| **number != 0)
+                        .map(move |(x, _number)| (x, y))
+                })
+                .collect();
+
+            if let Some(bonus_cells) = &params.bonus_cells_x_y {
+                card.bonus_cells = bonus_cells
+                    .iter()
+                    .map(|&(x, y)| (x as usize, y as usize))
+                    .filter(|cell| number_cells.contains(cell))
+                    .collect();
+            }
+            if let Some(bonus_cell_count) = params.random_bonus_cell_count {
+                let random_cells: Vec<(usize, usize)> = generator::shuffled(number_cells, random)
+                    .into_iter()
+                    .filter(|cell| !card.bonus_cells.contains(cell))
+                    .take(bonus_cell_count as usize)
+                    .collect();
+                card.bonus_cells.extend(random_cells);
+            }
+            card.bonus_cells.sort_by_key(|&(x, y)| (y, x));
+        }
+    }
+
+    if params.shuffle_output_order {
+        shuffle_sheet_numbers(&mut cards, random);
+    }
+
+    cards
+}
+
+/// Shuffles the sheet numbers (and thereby the filenames and the print order) among the given
+/// cards and sorts the cards by their new sheet numbers. The serial numbers and contents of our
+/// cards stay the same.
+fn shuffle_sheet_numbers(cards: &mut [Card], random: &mut dyn RandomSource) {
+    let sheet_numbers: Vec<usize> = cards.iter().map(|card| card.sheet_number).collect();
+    let shuffled_sheet_numbers = generator::shuffled(sheet_numbers, random);
+    for (card, sheet_number) in cards.iter_mut().zip(shuffled_sheet_numbers.into_iter()) {
+        card.sheet_number = sheet_number;
+    }
+    cards.sort_by_key(|card| card.sheet_number);
+}
+
+/// Returns the directory that our sheets should be written to or `None` if we should stop
+fn prepare_output_directory(policy: ExistingOutputPolicy) -> Option<String> {
+    if policy == ExistingOutputPolicy::Timestamped {
+        let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M").to_string();
+        let mut output_directory = format!("{}_{}", OUTPUT_SHEETS_DIRECTORY, timestamp);
+        let mut counter = 2;
+        // NOTE: We create the directory right away so that runs which start in the same minute
+        //       (i.e. batches of `chotto serve`) never pick the same directory
+        loop {
+            match std::fs::create_dir(&output_directory) {
+                Ok(()) => return Some(output_directory),
+                Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+                    output_directory =
+                        format!("{}_{}_{}", OUTPUT_SHEETS_DIRECTORY, timestamp, counter);
+                    counter += 1;
+                }
+                Err(error) => panic!(
+                    "Could not create directory '{}': {}",
+                    output_directory, error
+                ),
+            }
+        }
+    }
+
+    if !path_exists(OUTPUT_SHEETS_DIRECTORY) {
+        return Some(OUTPUT_SHEETS_DIRECTORY.to_owned());
+    }
+    match policy {
+        ExistingOutputPolicy::Delete => {}
+        ExistingOutputPolicy::Error => panic!(
+            "The directory '{}' already exists - please move or delete it first",
+            OUTPUT_SHEETS_DIRECTORY
+        ),
+        ExistingOutputPolicy::Prompt => {
+            let delete_confirmed = show_messagebox_yes_no(
+                "Chotto",
+                &format!(
+                    "The directory '{}' already contains sheets from a previous run. Do you want to delete them?",
+                    OUTPUT_SHEETS_DIRECTORY
+                ),
+            );
+            if !delete_confirmed {
+                log::info!(
+                    "Keeping existing '{}' and stopping",
+                    OUTPUT_SHEETS_DIRECTORY
+                );
+                return None;
+            }
+        }
+        ExistingOutputPolicy::Timestamped => unreachable!(),
+    }
+
+    log::info!("Deleting existing '{}'", OUTPUT_SHEETS_DIRECTORY);
+    std::fs::remove_dir_all(OUTPUT_SHEETS_DIRECTORY).unwrap_or_else(|error| {
+        panic!(
+            "Could not delete '{}' - is a sheet still opened somewhere?: {}",
+            OUTPUT_SHEETS_DIRECTORY, error
+        )
+    });
+    Some(OUTPUT_SHEETS_DIRECTORY.to_owned())
+}
+
+/// Returns the sheet mirrored first and then rotated clockwise by the given degrees
+fn transform_sheet(sheet: Bitmap, rotation: u32, mirror: OutputMirror) -> Bitmap {
+    if rotation == 0 && mirror == OutputMirror::None {
+        return sheet;
+    }
+
+    let width = sheet.width as i32;
+    let height = sheet.height as i32;
+    let (result_width, result_height) = if rotation == 90 || rotation == 270 {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    let mut result = Bitmap::new(result_width as u32, result_height as u32);
+    for y in 0..height {
+        for x in 0..width {
+            let (mirrored_x, mirrored_y) = match mirror {
+                OutputMirror::None => (x, y),
+                OutputMirror::Horizontal => (width - 1 - x, y),
+                OutputMirror::Vertical => (x, height - 1 - y),
+            };
+            let (target_x, target_y) = match rotation {
+                90 => (height - 1 - mirrored_y, mirrored_x),
+                180 => (width - 1 - mirrored_x, height - 1 - mirrored_y),
+                270 => (mirrored_y, width - 1 - mirrored_x),
+                _ => (mirrored_x, mirrored_y),
+            };
+            result.set(target_x, target_y, sheet.get(x, y));
+        }
+    }
+    result
+}
+
+/// Returns the given region of a sheet with the given size transformed like `transform_sheet`
+/// transforms the whole sheet
+fn transform_sheet_region(
+    region: SheetRegion,
+    sheet_width: i32,
+    sheet_height: i32,
+    rotation: u32,
+    mirror: OutputMirror,
+) -> SheetRegion {
+    let width = region.bitmap.width as i32;
+    let height = region.bitmap.height as i32;
+    let (mirrored_x, mirrored_y) = match mirror {
+        OutputMirror::None => (region.pos.x, region.pos.y),
+        OutputMirror::Horizontal => (sheet_width - region.pos.x - width, region.pos.y),
+        OutputMirror::Vertical => (region.pos.x, sheet_height - region.pos.y - height),
+    };
+    let (x, y) = match rotation {
+        90 => (sheet_height - mirrored_y - height, mirrored_x),
+        180 => (
+            sheet_width - mirrored_x - width,
+            sheet_height - mirrored_y - height,
+        ),
+        270 => (mirrored_y, sheet_width - mirrored_x - width),
+        _ => (mirrored_x, mirrored_y),
+    };
+    SheetRegion {
+        pos: Vec2i::new(x, y),
+        bitmap: transform_sheet(region.bitmap, rotation, mirror),
+    }
+}
+
+fn sheet_filepath_without_extension(
+    output_directory: &str,
+    sheet_number: usize,
+    layout: manifest::DirectoryLayout,
+) -> String {
+    match layout.subdirectory_name(sheet_number) {
+        Some(subdirectory_name) => format!(
+            "{}/{}/sheet_{}",
+            output_directory, subdirectory_name, sheet_number
+        ),
+        None => format!("{}/sheet_{}", output_directory, sheet_number),
+    }
+}
+
+/// Returns the file that the sheet with the given sheet number is written to
+fn sheet_filepath(
+    params: &DrawParams,
+    output_directory: &str,
+    sheet_number: usize,
+    cmyk_icc_profile_data: Option<&Vec<u8>>,
+) -> String {
+    format!(
+        "{}.{}",
+        sheet_filepath_without_extension(output_directory, sheet_number, params.directory_layout()),
+        sheet_file_extension(cmyk_icc_profile_data)
+    )
+}
+
+/// All extensions that `sheet_file_extension` can return
+const SHEET_FILE_EXTENSIONS: [&str; 2] = ["png", "tif"];
+
+/// Our sheets are written as CMYK TIFF files if an ICC profile is given and as PNG otherwise
+fn sheet_file_extension(cmyk_icc_profile_data: Option<&Vec<u8>>) -> &'static str {
+    if cmyk_icc_profile_data.is_some() {
+        "tif"
+    } else {
+        "png"
+    }
+}
+
+/// Writes the given unpremultiplied sheet as PNG or as CMYK TIFF if an ICC profile is given. The
+/// filepath must have the extension returned by `sheet_file_extension`.
+fn write_sheet_file(
+    sheet: &ComposedSheet,
+    filepath: &str,
+    cmyk_icc_profile_data: Option<&Vec<u8>>,
+    png_compression: png_writer::PngCompression,
+    output_color_profile: output_color::OutputColorProfile,
+) {
+    if let Some(icc_profile_data) = cmyk_icc_profile_data {
+        write_cmyk_tiff_file(sheet, filepath, icc_profile_data);
+    } else {
+        match output_color_profile {
+            output_color::OutputColorProfile::Color => {
+                png_writer::write_composed_png_file(sheet, filepath, png_compression)
+            }
+            output_color::OutputColorProfile::Grayscale => {
+                png_writer::write_composed_greyscale_png_file(sheet, filepath, png_compression)
+            }
+            output_color::OutputColorProfile::Monochrome => {
+                png_writer::write_composed_monochrome_png_file(sheet, filepath, png_compression)
+            }
+        }
+    }
+}
+
+const MEGABYTE: f64 = 1024.0 * 1024.0;
+const GIGABYTE: f64 = 1024.0 * 1024.0 * 1024.0;
+
+struct RunEstimate {
+    sheet_count: usize,
+    sheet_width: i32,
+    sheet_height: i32,
+    sheet_file_bytes: u64,
+    total_file_bytes: u64,
+    memory_bytes: u64,
+    duration_seconds: f64,
+}
+
+impl RunEstimate {
+    fn summary(&self) -> String {
+        format!(
+            "Sheets to create: {}\n\
+             Sheet size: {}x{}px\n\
+             Estimated disk space: {:.1}MB per sheet, {:.2}GB in total\n\
+             Estimated memory usage: {:.0}MB\n\
+             Estimated duration: {:.0}s",
+            self.sheet_count,
+            self.sheet_width,
+            self.sheet_height,
+            self.sheet_file_bytes as f64 / MEGABYTE,
+            self.total_file_bytes as f64 / GIGABYTE,
+            self.memory_bytes as f64 / MEGABYTE,
+            self.duration_seconds,
+        )
+    }
+}
+
+/// Draws and saves a single sheet to a temporary file to estimate how long creating and saving all
+/// sheets will take and how much disk space and memory it will need
+fn estimate_run(
+    sheet_renderer: &SheetRenderer,
+    cmyk_icc_profile_data: Option<&Vec<u8>>,
+    sheet_count: usize,
+) -> RunEstimate {
+    let mut random = Random::new_from_seed(create_time_based_seed());
+    let cards = create_cards(sheet_renderer, &[], 1, &mut random);
+    let temp_filepath_without_extension = std::env::temp_dir()
+        .join("chotto_estimate")
+        .to_string_lossy()
+        .to_string();
+
+    let sheet_background = sheet_renderer
+        .unpremultiplied_sheet(&sheet_renderer.finished_background)
+        .unwrap_or_else(|| sheet_renderer.finished_background.clone());
+
+    let start_time = std::time::Instant::now();
+    let sheet_region =
+        sheet_renderer.unpremultiplied_region(sheet_renderer.draw_sheet_region(&cards[0]));
+    let temp_filepath = format!(
+        "{}.{}",
+        temp_filepath_without_extension,
+        sheet_file_extension(cmyk_icc_profile_data)
+    );
+    write_sheet_file(
+        &ComposedSheet::new(&sheet_background, &sheet_region),
+        &temp_filepath,
+        cmyk_icc_profile_data,
+        sheet_renderer.params.png_compression,
+        sheet_renderer.params.output_color_profile,
+    );
+    let sheet_duration_seconds = start_time.elapsed().as_secs_f64();
+    let sheet_file_bytes = std::fs::metadata(&temp_filepath)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    std::fs::remove_file(&temp_filepath).ok();
+
+    let thread_count = usize::max(
+        1,
+        usize::min(
+            draw_thread_count(sheet_background.width, sheet_background.height),
+            sheet_count,
+        ),
+    );
+    let sheet_memory_bytes = 4 * sheet_background.width as u64 * sheet_background.height as u64;
+    let region_memory_bytes =
+        4 * sheet_region.bitmap.width as u64 * sheet_region.bitmap.height as u64;
+    RunEstimate {
+        sheet_count,
+        sheet_width: sheet_background.width as i32,
+        sheet_height: sheet_background.height as i32,
+        sheet_file_bytes,
+        total_file_bytes: sheet_file_bytes * sheet_count as u64,
+        // NOTE: All threads share our background and its unpremultiplied version. Each thread only
+        //       needs the region of its sheet that differs from it plus its unpremultiplied version.
+        memory_bytes: 2 * sheet_memory_bytes + 2 * region_memory_bytes * thread_count as u64,
+        duration_seconds: sheet_duration_seconds * sheet_count as f64 / thread_count as f64,
+    }
+}
+
+/// Draws a single sheet and annotates it with the grid cells and text locations so that we can
+/// check our draw parameters without creating all sheets
+fn create_dry_run_preview(sheet_renderer: &SheetRenderer, estimate: &RunEstimate) {
+    let mut random = Random::new_from_seed(create_time_based_seed());
+    let cards = create_cards(sheet_renderer, &[], 1, &mut random);
+    let mut preview = sheet_renderer.draw_sheet(&cards[0]);
+
+    let annotation_color = PixelRGBA::new(255, 0, 255, 255);
+    let (column_count, row_count) = sheet_renderer.params.grid_size();
+    for y in 0..row_count {
+        for x in 0..column_count {
+            let cell_left = sheet_renderer.top_left.x + x * sheet_renderer.cell_width;
+            let cell_top = sheet_renderer.top_left.y + y * sheet_renderer.cell_height;
+            draw_rect_outline(
+                &mut preview,
+                cell_left,
+                cell_top,
+                sheet_renderer.cell_width,
+                sheet_renderer.cell_height,
+                annotation_color,
+            );
+        }
+    }
+    let mut text_locations = Vec::new();
+    if let Some((x, y)) = sheet_renderer.params.serial_number_pixel_location_x_y {
+        text_locations.push(Vec2i::new(x as i32, y as i32));
+    }
+    for extra_text in &sheet_renderer.extra_texts {
+        text_locations.push(Vec2i::new(extra_text.x as i32, extra_text.y as i32));
+    }
+    for overlay in &sheet_renderer.params.overlays {
+        text_locations.push(overlay.location());
+    }
+    for location in text_locations {
+        let center = sheet_renderer.trim_offset + location;
+        fill_rect(
+            &mut preview,
+            center.x - 8,
+            center.y,
+            17,
+            1,
+            annotation_color,
+        );
+        fill_rect(
+            &mut preview,
+            center.x,
+            center.y - 8,
+            1,
+            17,
+            annotation_color,
+        );
+    }
+    preview
+        .to_unpremultiplied_alpha()
+        .write_to_png_file(DRY_RUN_PREVIEW_FILENAME);
+
+    let summary = format!(
+        "Dry run finished - no sheets were created.\n\n\
+         {}\n\
+         Grid cell size: {}x{}px\n\n\
+         An annotated preview was written to '{}'.",
+        estimate.summary(),
+        sheet_renderer.cell_width,
+        sheet_renderer.cell_height,
+        DRY_RUN_PREVIEW_FILENAME
+    );
+    show_messagebox("Chotto", &summary, false);
+}
+
+/// Contains everything that is needed to draw our sheets which can be prepared once upfront
+struct SheetRenderer<'a> {
+    params: &'a DrawParams,
+    recipients: Option<&'a Vec<HashMap<String, String>>>,
+
+    background: Bitmap,
+    /// Our background with the sheet mask and the output color profile already applied. Each final
+    /// sheet only differs from it in the region that its card was drawn onto.
+    finished_background: Bitmap,
+    /// If the background has no transparent pixels our final sheets will not have any either. In
+    /// that case the premultiplied and unpremultiplied versions of our sheets are the same.
+    background_is_opaque: bool,
+    /// The part of the background that is covered by the grid cells. Each sheet starts drawing
+    /// onto a copy of this small region and only grows it where it draws its texts.
+    grid_region_background: Bitmap,
+    grid_region_pos: Vec2i,
+    trim_offset: Vec2i,
+    top_left: Vec2i,
+    cell_width: i32,
+    cell_height: i32,
+
+    number_bitmaps_premultiplied: HashMap<i32, Bitmap>,
+    header_text_rasterizer: TextRasterizer<'a>,
+    free_space_cells: Vec<(i32, i32)>,
+    /// The content of the cells that are the same on every card of a cell layout by their
+    /// (column, row) location
+    fixed_cell_bitmaps_premultiplied: HashMap<(i32, i32), Bitmap>,
+    free_space_bitmap_premultiplied: Option<Bitmap>,
+    /// Contains the filename and the resized bitmap of each free space image
+    free_space_images_premultiplied: Vec<(String, Bitmap)>,
+    column_header_bitmaps_premultiplied: Vec<Bitmap>,
+    bonus_cell_tint_premultiplied: Bitmap,
+    bonus_cell_image_premultiplied: Option<Bitmap>,
+    number_chip_premultiplied: Option<Bitmap>,
+    /// Replaces the regular number bitmaps of our lucky numbers if they have their own color
+    lucky_number_bitmaps_premultiplied: HashMap<i32, Bitmap>,
+    lucky_number_star_premultiplied: Option<Bitmap>,
+    braille_bitmaps_premultiplied: Option<HashMap<i32, Bitmap>>,
+    /// Only contains the numbers that have a nickname
+    nickname_bitmaps_premultiplied: HashMap<i32, Bitmap>,
+    /// The [column, row] location and contrast ratio of all grid cells where our numbers are hard
+    /// to read on the background
+    low_contrast_cells: Vec<(i32, i32, f32)>,
+
+    text_variables: HashMap<String, String>,
+    extra_texts: Vec<ExtraText>,
+    extra_text_rasterizers: Vec<TextRasterizer<'a>>,
+    /// The texts of the cards that got one of the colors of our `accent_palette` by its name
+    accents: HashMap<String, Accent<'a>>,
+
+    overlay_bitmap_premultiplied: Option<Bitmap>,
+    overlay_center: Vec2i,
+    overlay_layer: overlays::OverlayLayer<'a>,
+    ticket_stub: Option<ticket_stub::TicketStub<'a>>,
+    seating_card_text_rasterizer: Option<TextRasterizer<'a>>,
+    countdown: Option<countdown::Countdown<'a>>,
+    /// The coverage of each pixel of our sheets for die-cut sheets with rounded corners or a mask
+    sheet_mask: Option<Vec<u8>>,
+}
+
+impl<'a> SheetRenderer<'a> {
+    fn new(
+        background: &Bitmap,
+        font: &'a LoadedFont,
+        font_hash: u64,
+        header_font: Option<&'a LoadedFont>,
+        params: &'a DrawParams,
+        recipients: Option<&'a Vec<HashMap<String, String>>>,
+    ) -> SheetRenderer<'a> {
+        // NOTE: Numbers that are blended onto transparent pixels look washed out once the sheet is
+        //       printed, so we put our background onto paper first
+        let paper_background = params
+            .paper_color_rgb
+            .map(|paper_color| composite_onto_paper(background, paper_color));
+        if paper_background.is_none() && background.data.iter().any(|pixel| pixel.a < 255) {
+            log::info!(
+                "Our background image has transparent pixels - if our numbers look washed out we \
+                 can add `paper_color_rgb` to '{}'",
+                DRAW_PARAMETERS_FILENAME
+            );
+        }
+        let background = paper_background.as_ref().unwrap_or(background);
+
+        let ink_saver_background = if params.ink_saver {
+            Some(lighten_for_ink_saver(
+                background,
+                params.ink_saver_strength(),
+            ))
+        } else {
+            None
+        };
+        let background = ink_saver_background.as_ref().unwrap_or(background);
+
+        // NOTE: All pixel locations given in the draw parameters are relative to the trim box
+        //       which is moved by the bleed and the crop marks margin
+        let (mut background, trim_offset) = if params.bleed_px.is_some() || params.crop_marks {
+            extend_with_bleed_and_crop_marks(
+                background,
+                params.bleed_px.unwrap_or(0) as i32,
+                params
+                    .bleed_color_rgb
+                    .map(|(r, g, b)| PixelRGBA::new(r, g, b, 255)),
+                params.crop_marks,
+            )
+        } else {
+            (background.clone(), Vec2i::zero())
+        };
+        let top_left = trim_offset
+            + Vec2i::new(
+                params.bingo_grid_pixel_location_left_top_right_bottom.0 as i32,
+                params.bingo_grid_pixel_location_left_top_right_bottom.1 as i32,
+            );
+        let bottom_right = trim_offset
+            + Vec2i::new(
+                params.bingo_grid_pixel_location_left_top_right_bottom.2 as i32,
+                params.bingo_grid_pixel_location_left_top_right_bottom.3 as i32,
+            );
+        let (column_count, row_count) = params.grid_size();
+        let cell_width = (bottom_right.x - top_left.x) / column_count;
+        let cell_height = (bottom_right.y - top_left.y) / row_count;
+
+        // NOTE: The column colors are the same on every sheet so we paint them into our background
+        //       which also lets our contrast check see them
+        for (column, &(r, g, b)) in params.bingo_variant.column_colors_rgb().iter().enumerate() {
+            let mut color = PixelRGBA::new(r, g, b, 255);
+            if params.ink_saver {
+                color = lighten_pixel_for_ink_saver(color, params.ink_saver_strength());
+            }
+            fill_rect(
+                &mut background,
+                top_left.x + column as i32 * cell_width,
+                top_left.y,
+                cell_width,
+                row_count * cell_height,
+                color,
+            );
+        }
+
+        let grid_region_pos = Vec2i::new(i32::max(0, top_left.x), i32::max(0, top_left.y));
+        let grid_region_right = i32::min(
+            background.width as i32,
+            top_left.x + column_count * cell_width,
+        );
+        let grid_region_bottom = i32::min(
+            background.height as i32,
+            top_left.y + row_count * cell_height,
+        );
+        let grid_region_background = copy_bitmap_region(
+            &background,
+            grid_region_pos,
+            i32::max(0, grid_region_right - grid_region_pos.x),
+            i32::max(0, grid_region_bottom - grid_region_pos.y),
+        );
+        let sheet_mask = create_sheet_mask(params, background.width, background.height);
+        let mut finished_background = background.clone();
+        if let Some(sheet_mask) = &sheet_mask {
+            apply_mask_premultiplied(
+                &mut finished_background,
+                Vec2i::zero(),
+                sheet_mask,
+                background.width,
+            );
+        }
+        output_color::convert_sheet_premultiplied(
+            &mut finished_background,
+            params.output_color_profile,
+        );
+        // NOTE: Greyscale and monochrome sheets are drawn onto white paper in the end
+        let background_is_opaque = params.output_color_profile
+            != output_color::OutputColorProfile::Color
+            || (sheet_mask.is_none() && background.data.iter().all(|pixel| pixel.a == 255));
+
+        let numbers: Vec<i32> = (1..=params.max_number()).collect();
+        let column_labels = params.column_labels();
+        let prefix_column_labels = if params.prefix_numbers_with_column_letter {
+            Some(column_labels.as_slice())
+        } else {
+            None
+        };
+        let number_bitmaps_cache_key = bitmap_cache::hash_of(&format!(
+            "{} {} {:?} {} {:?} {:?} {} {:?}",
+            font_hash,
+            params.text_font_size,
+            params.number_fill_style(),
+            params.prefix_numbers_with_column_letter,
+            column_labels,
+            params.bingo_variant,
+            params.bold_text,
+            params.number_effects
+        ));
+        let deck_images_premultiplied = params
+            .deck_images_directory
+            .as_ref()
+            .filter(|_| params.bingo_variant == BingoVariant::Loteria)
+            .map(|directory| {
+                deck::load_deck_images_premultiplied(directory, cell_width, cell_height)
+            });
+        let item_texts: Option<Vec<&str>> = match params.bingo_variant {
+            BingoVariant::ScavengerHunt => params
+                .scavenger_hunt
+                .as_ref()
+                .map(|scavenger_hunt| scavenger_hunt.items()),
+            BingoVariant::CellLayout => Some(cell_layout_params::pool_entries(&params.cell_pools)),
+            _ => None,
+        };
+        let draws_number_texts = deck_images_premultiplied.is_none() && item_texts.is_none();
+        let item_bitmaps_premultiplied = item_texts.map(|item_texts| {
+            let item_text_rasterizer = TextRasterizer::new(
+                font,
+                params.text_font_size as f32,
+                params.number_fill_style(),
+                params.text_direction,
+                params.bold_text,
+                "",
+            )
+            .with_effects(params.number_effects.as_ref());
+            create_wrapped_text_bitmaps_premultiplied(
+                &item_text_rasterizer,
+                &item_texts,
+                cell_width,
+            )
+        });
+        // NOTE: The cards of a Lotería deck, the items of a scavenger hunt and the pool entries of a
+        //       cell layout are drawn in place of the numbers
+        let mut number_bitmaps_premultiplied = match deck_images_premultiplied
+            .or(item_bitmaps_premultiplied)
+        {
+            Some(item_bitmaps_premultiplied) => item_bitmaps_premultiplied,
+            None => match bitmap_cache::load_number_bitmaps(number_bitmaps_cache_key, &numbers) {
+                Some(number_bitmaps_premultiplied) => number_bitmaps_premultiplied,
+                None => {
+                    let mut number_text_rasterizer = TextRasterizer::new(
+                        font,
+                        params.text_font_size as f32,
+                        params.number_fill_style(),
+                        shaping::TextDirection::LeftToRight,
+                        params.bold_text,
+                        &format!("0123456789{}", column_labels.concat()),
+                    )
+                    .with_effects(params.number_effects.as_ref());
+                    let number_bitmaps_premultiplied = create_number_bitmaps_premultiplied(
+                        &mut number_text_rasterizer,
+                        params.bingo_variant,
+                        params.max_number(),
+                        prefix_column_labels,
+                    );
+                    bitmap_cache::store_number_bitmaps(
+                        number_bitmaps_cache_key,
+                        &number_bitmaps_premultiplied,
+                    );
+                    number_bitmaps_premultiplied
+                }
+            },
+        };
+
+        let header_font = header_font.unwrap_or(font);
+        let header_text_rasterizer = TextRasterizer::new(
+            header_font,
+            params
+                .header_text_font_size
+                .unwrap_or(params.text_font_size) as f32,
+            params.header_fill_style(None),
+            params.text_direction,
+            params.bold_text,
+            "#0123456789",
+        )
+        .with_effects(params.header_text_effects.as_ref());
+        let free_space_bitmap_premultiplied = params
+            .free_space_text
+            .as_ref()
+            .map(|text| header_text_rasterizer.create_text_bitmap_premultiplied(text));
+        let fixed_cell_texts = match &params.cell_layout {
+            Some(layout) if params.bingo_variant == BingoVariant::CellLayout => {
+                cell_layout_params::fixed_cell_texts(layout)
+            }
+            _ => Vec::new(),
+        };
+        let mut fixed_cell_text_bitmaps = create_wrapped_text_bitmaps_premultiplied(
+            &header_text_rasterizer,
+            &fixed_cell_texts
+                .iter()
+                .map(|(_cell, text)| *text)
+                .collect::<Vec<&str>>(),
+            cell_width,
+        );
+        let fixed_cell_bitmaps_premultiplied: HashMap<(i32, i32), Bitmap> = fixed_cell_texts
+            .iter()
+            .enumerate()
+            .map(|(index, (cell, _text))| {
+                (
+                    *cell,
+                    fixed_cell_text_bitmaps.remove(&(index as i32 + 1)).unwrap(),
+                )
+            })
+            .collect();
+        let free_space_images_premultiplied = params
+            .free_space_images_directory
+            .as_ref()
+            .map(|directory| {
+                load_free_space_images_premultiplied(directory, cell_width, cell_height)
+            })
+            .unwrap_or_default();
+        let column_header_texts: Vec<String> = match &params.column_headers {
+            Some(column_headers) => column_headers.clone(),
+            None if params.mode == Mode::Bingo && params.bingo_variant == BingoVariant::Ball75 => {
+                column_labels.clone()
+            }
+            None => Vec::new(),
+        };
+        let column_header_bitmaps_premultiplied: Vec<Bitmap> = column_header_texts
+            .iter()
+            .map(|text| {
+                if params.vertical_column_headers {
+                    header_text_rasterizer.create_vertical_text_bitmap_premultiplied(text)
+                } else {
+                    header_text_rasterizer.create_text_bitmap_premultiplied(text)
+                }
+            })
+            .collect();
+
+        let (tint_r, tint_g, tint_b, tint_a) =
+            params.bonus_cell_color_rgba.unwrap_or((255, 215, 0, 96));
+        let mut bonus_cell_tint_premultiplied = Bitmap::new(cell_width as u32, cell_height as u32);
+        fill_rect(
+            &mut bonus_cell_tint_premultiplied,
+            0,
+            0,
+            cell_width,
+            cell_height,
+            PixelRGBA::new(
+                (tint_r as u32 * tint_a as u32 / 255) as u8,
+                (tint_g as u32 * tint_a as u32 / 255) as u8,
+                (tint_b as u32 * tint_a as u32 / 255) as u8,
+                tint_a,
+            ),
+        );
+        let bonus_cell_image_premultiplied = params
+            .bonus_cell_image_filepath
+            .as_ref()
+            .map(|filepath| load_cell_image_premultiplied(filepath, cell_width, cell_height));
+        let number_chip_premultiplied = params.number_chip.as_ref().map(|number_chip| {
+            create_number_chip_premultiplied(number_chip, cell_width, cell_height)
+        });
+        let lucky_number_bitmaps_premultiplied: HashMap<i32, Bitmap> = match params
+            .lucky_number_color_rgb
+        {
+            Some(color) => {
+                let lucky_number_text_rasterizer = TextRasterizer::new(
+                    font,
+                    params.text_font_size as f32,
+                    FillStyle::Solid(color_from_rgb(color)),
+                    shaping::TextDirection::LeftToRight,
+                    params.bold_text,
+                    &format!("0123456789{}", column_labels.concat()),
+                )
+                .with_effects(params.number_effects.as_ref());
+                params
+                    .lucky_numbers
+                    .iter()
+                    .map(|&number| {
+                        let text = number_text(number, params.bingo_variant, prefix_column_labels);
+                        (
+                            number,
+                            lucky_number_text_rasterizer.create_text_bitmap_premultiplied(&text),
+                        )
+                    })
+                    .collect()
+            }
+            None => HashMap::new(),
+        };
+        let lucky_number_star_premultiplied = if params.lucky_number_star {
+            Some(create_star_badge_premultiplied(
+                i32::min(cell_width, cell_height) as f32 / 6.0,
+                params
+                    .lucky_number_color_rgb
+                    .unwrap_or(DEFAULT_LUCKY_NUMBER_STAR_COLOR_RGB),
+            ))
+        } else {
+            None
+        };
+
+        let braille_bitmaps_premultiplied = if params.braille_captions {
+            Some(create_braille_bitmaps_premultiplied(params, cell_height))
+        } else {
+            None
+        };
+
+        let nickname_bitmaps_premultiplied = if params.number_nicknames {
+            create_nickname_bitmaps_premultiplied(font, params, cell_width, cell_height)
+        } else {
+            HashMap::new()
+        };
+
+        let low_contrast_cells = find_low_contrast_cells(
+            &grid_region_background,
+            top_left - grid_region_pos,
+            cell_width,
+            cell_height,
+            params,
+        );
+        let needs_number_outlines = params.auto_number_outline
+            && !low_contrast_cells.is_empty()
+            && params.bingo_variant != BingoVariant::Loteria;
+        if needs_number_outlines {
+            add_number_outlines(
+                &mut number_bitmaps_premultiplied,
+                params.number_fill_style().color_at(0.5, 0.5),
+                params,
+            );
+        }
+
+        let mut text_variables: HashMap<String, String> = HashMap::new();
+        text_variables.insert(
+            "date".to_owned(),
+            chrono::Local::now().format("%Y-%m-%d").to_string(),
+        );
+        if let Some(user_text_variables) = &params.text_variables {
+            text_variables.extend(user_text_variables.clone());
+        }
+        let extra_texts = params.extra_texts.clone().unwrap_or_default();
+        let extra_text_rasterizers: Vec<TextRasterizer> = extra_texts
+            .iter()
+            .map(|extra_text| {
+                TextRasterizer::new(
+                    header_font,
+                    extra_text
+                        .size
+                        .or(params.header_text_font_size)
+                        .unwrap_or(params.text_font_size) as f32,
+                    params.header_fill_style(extra_text.color),
+                    params.text_direction,
+                    params.bold_text,
+                    &format!("0123456789{}", extra_text.text),
+                )
+                .with_effects(params.header_text_effects.as_ref())
+            })
+            .collect();
+
+        // NOTE: The images of a Lotería deck, scavenger hunt items and cell layout entries keep
+        //       their look
+        let accent_numbers = params.accent_palette_numbers && draws_number_texts;
+        let accents: HashMap<String, Accent> = params
+            .accent_palette
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(palette_index, accent_color)| {
+                let color = color_from_rgb(accent_color.color_rgb);
+                let accent_header_text_rasterizer = TextRasterizer::new(
+                    header_font,
+                    params
+                        .header_text_font_size
+                        .unwrap_or(params.text_font_size) as f32,
+                    FillStyle::Solid(color),
+                    params.text_direction,
+                    params.bold_text,
+                    "#0123456789",
+                )
+                .with_effects(params.header_text_effects.as_ref());
+                let number_bitmaps_premultiplied = if accent_numbers {
+                    let mut accent_number_text_rasterizer = TextRasterizer::new(
+                        font,
+                        params.text_font_size as f32,
+                        FillStyle::Solid(color),
+                        shaping::TextDirection::LeftToRight,
+                        params.bold_text,
+                        &format!("0123456789{}", column_labels.concat()),
+                    )
+                    .with_effects(params.number_effects.as_ref());
+                    let mut number_bitmaps_premultiplied = create_number_bitmaps_premultiplied(
+                        &mut accent_number_text_rasterizer,
+                        params.bingo_variant,
+                        params.max_number(),
+                        prefix_column_labels,
+                    );
+                    if needs_number_outlines {
+                        add_number_outlines(&mut number_bitmaps_premultiplied, color, params);
+                    }
+                    Some(number_bitmaps_premultiplied)
+                } else {
+                    None
+                };
+                let accent = Accent {
+                    palette_index,
+                    column_header_bitmaps_premultiplied: column_header_texts
+                        .iter()
+                        .map(|text| {
+                            if params.vertical_column_headers {
+                                accent_header_text_rasterizer
+                                    .create_vertical_text_bitmap_premultiplied(text)
+                            } else {
+                                accent_header_text_rasterizer.create_text_bitmap_premultiplied(text)
+                            }
+                        })
+                        .collect(),
+                    free_space_bitmap_premultiplied: params.free_space_text.as_ref().map(|text| {
+                        accent_header_text_rasterizer.create_text_bitmap_premultiplied(text)
+                    }),
+                    extra_text_rasterizers: extra_texts
+                        .iter()
+                        .map(|extra_text| {
+                            TextRasterizer::new(
+                                header_font,
+                                extra_text
+                                    .size
+                                    .or(params.header_text_font_size)
+                                    .unwrap_or(params.text_font_size)
+                                    as f32,
+                                FillStyle::Solid(
+                                    extra_text.color.map(color_from_rgb).unwrap_or(color),
+                                ),
+                                params.text_direction,
+                                params.bold_text,
+                                &format!("0123456789{}", extra_text.text),
+                            )
+                            .with_effects(params.header_text_effects.as_ref())
+                        })
+                        .collect(),
+                    header_text_rasterizer: accent_header_text_rasterizer,
+                    number_bitmaps_premultiplied,
+                };
+                (accent_color.name.clone(), accent)
+            })
+            .collect();
+
+        let overlay_bitmap_premultiplied = params.overlay_image_filepath.as_ref().map(|filepath| {
+            create_overlay_bitmap_premultiplied(
+                filepath,
+                params.overlay_scale.unwrap_or(1.0),
+                params.overlay_opacity.unwrap_or(1.0),
+            )
+        });
+        let overlay_center = trim_offset
+            + params
+                .overlay_pixel_location_x_y
+                .map(|(x, y)| Vec2i::new(x as i32, y as i32))
+                .unwrap_or_else(|| {
+                    let trim_dim = Vec2i::new(background.width as i32, background.height as i32)
+                        - trim_offset
+                        - trim_offset;
+                    trim_dim / 2
+                });
+        let overlay_layer = overlays::OverlayLayer::new(params, header_font);
+        let ticket_stub = ticket_stub::TicketStub::new(params, header_font);
+        let countdown = if params.mode == Mode::Countdown {
+            countdown::Countdown::new(params, font, header_font)
+        } else {
+            None
+        };
+        let seating_card_text_rasterizer = if params.mode == Mode::SeatingCards {
+            Some(
+                TextRasterizer::new(
+                    font,
+                    params.text_font_size as f32,
+                    params.number_fill_style(),
+                    params.text_direction,
+                    params.bold_text,
+                    "0123456789",
+                )
+                .with_effects(params.number_effects.as_ref()),
+            )
+        } else {
+            None
+        };
+
+        SheetRenderer {
+            params,
+            recipients,
+            background,
+            finished_background,
+            background_is_opaque,
+            grid_region_background,
+            grid_region_pos,
+            trim_offset,
+            top_left,
+            cell_width,
+            cell_height,
+            number_bitmaps_premultiplied,
+            header_text_rasterizer,
+            free_space_cells: params.free_space_cells(),
+            fixed_cell_bitmaps_premultiplied,
+            free_space_bitmap_premultiplied,
+            free_space_images_premultiplied,
+            column_header_bitmaps_premultiplied,
+            bonus_cell_tint_premultiplied,
+            bonus_cell_image_premultiplied,
+            number_chip_premultiplied,
+            lucky_number_bitmaps_premultiplied,
+            lucky_number_star_premultiplied,
+            braille_bitmaps_premultiplied,
+            nickname_bitmaps_premultiplied,
+            low_contrast_cells,
+            text_variables,
+            extra_texts,
+            extra_text_rasterizers,
+            accents,
+            overlay_bitmap_premultiplied,
+            overlay_center,
+            overlay_layer,
+            ticket_stub,
+            seating_card_text_rasterizer,
+            countdown,
+            sheet_mask,
+        }
+    }
+
+    /// Returns the unpremultiplied version of the given final sheet or `None` if it is the same as
+    /// the premultiplied one which saves us a pass over the whole sheet
+    fn unpremultiplied_sheet(&self, sheet_premultiplied: &Bitmap) -> Option<Bitmap> {
+        if self.background_is_opaque {
+            None
+        } else {
+            Some(sheet_premultiplied.to_unpremultiplied_alpha())
+        }
+    }
+
+    /// Same as `unpremultiplied_sheet` for the region of a final sheet
+    fn unpremultiplied_region(&self, region_premultiplied: SheetRegion) -> SheetRegion {
+        match self.unpremultiplied_sheet(&region_premultiplied.bitmap) {
+            Some(bitmap) => SheetRegion {
+                pos: region_premultiplied.pos,
+                bitmap,
+            },
+            None => region_premultiplied,
+        }
+    }
+
+    fn free_space_image_names(&self) -> Vec<String> {
+        self.free_space_images_premultiplied
+            .iter()
+            .map(|(name, _bitmap)| name.clone())
+            .collect()
+    }
+
+    fn accent(&self, card: &Card) -> Option<&Accent<'a>> {
+        card.accent_color
+            .as_ref()
+            .and_then(|name| self.accents.get(name))
+    }
+
+    /// Returns where the given card places its numbers on top of our background
+    fn card_delta(&self, card: &Card) -> deltas::CardDelta {
+        let mut placements = Vec::new();
+        for (row, row_numbers) in card.numbers.iter().enumerate() {
+            for (column, &number) in row_numbers.iter().enumerate() {
+                if number == 0 {
+                    continue;
+                }
+                placements.push(deltas::NumberPlacement {
+                    number,
+                    column: column as i32,
+                    row: row as i32,
+                    x: self.top_left.x + column as i32 * self.cell_width + self.cell_width / 2,
+                    y: self.top_left.y + row as i32 * self.cell_height + self.cell_height / 2,
+                });
+            }
+        }
+        deltas::CardDelta {
+            sheet_number: card.sheet_number,
+            serial: card.serial.clone(),
+            placements,
+        }
+    }
+
+    /// Returns the final sheet with premultiplied alpha
+    fn draw_sheet(&self, card: &Card) -> Bitmap {
+        let region = self.draw_sheet_region(card);
+        ComposedSheet::new(&self.finished_background, &region).to_bitmap()
+    }
+
+    /// Returns the part of the final sheet with premultiplied alpha that differs from our
+    /// `finished_background`
+    fn draw_sheet_region(&self, card: &Card) -> SheetRegion {
+        self.draw_sheet_region_around_grid_region(card, self.draw_grid_region(card))
+    }
+
+    /// Returns our `grid_region_background` with the cells of the given card
+    fn draw_grid_region(&self, card: &Card) -> Bitmap {
+        let mut grid_region = self.grid_region_background.clone();
+        cells::draw_cells(
+            &card.numbers,
+            self.top_left - self.grid_region_pos,
+            self.cell_width,
+            self.cell_height,
+            &[&CardCellRenderer {
+                sheet_renderer: self,
+                card,
+            }],
+            &mut grid_region,
+        );
+        grid_region
+    }
+
+    /// Same as `draw_sheet_region` for a card whose grid region was already drawn elsewhere, i.e.
+    /// on the GPU
+    fn draw_sheet_region_around_grid_region(
+        &self,
+        card: &Card,
+        grid_region: Bitmap,
+    ) -> SheetRegion {
+        let params = self.params;
+        let sheet_index = card.sheet_number - 1;
+        let mut sheet = SheetCanvas::new(
+            &self.background,
+            SheetRegion {
+                pos: self.grid_region_pos,
+                bitmap: grid_region,
+            },
+        );
+
+        if let (Some(rasterizer), Some(text), Some((left, top, right, bottom))) = (
+            &self.seating_card_text_rasterizer,
+            &card.seating_card_text,
+            params.seating_card_text_pixel_location_left_top_right_bottom,
+        ) {
+            seating_cards::draw_text_fitted_into_region(
+                rasterizer,
+                text,
+                (
+                    self.trim_offset.x + left as i32,
+                    self.trim_offset.y + top as i32,
+                    self.trim_offset.x + right as i32,
+                    self.trim_offset.y + bottom as i32,
+                ),
+                &mut sheet,
+                params.gamma_correct_blending,
+            );
+        }
+
+        let accent = self.accent(card);
+        let header_text_rasterizer = accent
+            .map(|accent| &accent.header_text_rasterizer)
+            .unwrap_or(&self.header_text_rasterizer);
+        let column_header_bitmaps = accent
+            .map(|accent| &accent.column_header_bitmaps_premultiplied)
+            .unwrap_or(&self.column_header_bitmaps_premultiplied);
+        let extra_text_rasterizers = accent
+            .map(|accent| &accent.extra_text_rasterizers)
+            .unwrap_or(&self.extra_text_rasterizers);
+
+        if let Some((top, bottom)) = params.column_headers_pixel_location_top_bottom {
+            for (x, header_bitmap) in column_header_bitmaps.iter().enumerate() {
+                let center = Vec2i::new(
+                    self.top_left.x + x as i32 * self.cell_width + self.cell_width / 2,
+                    self.trim_offset.y + (top + bottom) as i32 / 2,
+                );
+                sheet.blit_centered_premultiplied(
+                    header_bitmap,
+                    center,
+                    params.gamma_correct_blending,
+                );
+            }
+        }
+
+        if let Some((x, y)) = params.serial_number_pixel_location_x_y {
+            let serial_number_bitmap = header_text_rasterizer
+                .create_text_bitmap_premultiplied(&format!("#{}", card.serial));
+            sheet.blit_centered_premultiplied(
+                &serial_number_bitmap,
+                self.trim_offset + Vec2i::new(x as i32, y as i32),
+                params.gamma_correct_blending,
+            );
+        }
+
+        if let (Some((x, y)), Some(security_code)) =
+            (params.security_code_pixel_location_x_y, &card.security_code)
+        {
+            let security_code_bitmap =
+                header_text_rasterizer.create_text_bitmap_premultiplied(security_code);
+            sheet.blit_centered_premultiplied(
+                &security_code_bitmap,
+                self.trim_offset + Vec2i::new(x as i32, y as i32),
+                params.gamma_correct_blending,
+            );
+        }
+
+        if let Some((x, y)) = params.barcode_pixel_location_x_y {
+            let barcode_bitmap = barcode::create_code128_bitmap(
+                &card.serial,
+                params.barcode_module_width_px.unwrap_or(2),
+                params
+                    .barcode_height_px
+                    .unwrap_or(self.cell_height as u32 / 2),
+            );
+            sheet.blit_centered_premultiplied(
+                &barcode_bitmap,
+                self.trim_offset + Vec2i::new(x as i32, y as i32),
+                params.gamma_correct_blending,
+            );
+        }
+
+        let mut sheet_text_variables = self.text_variables.clone();
+        sheet_text_variables.insert("card_id".to_owned(), card.serial.clone());
+        if let Some(recipients) = self.recipients {
+            sheet_text_variables.extend(recipients[sheet_index].clone());
+        }
+        if let (Some(countdown), Some(number)) = (&self.countdown, card.countdown_number) {
+            sheet_text_variables.insert("number".to_owned(), number.to_string());
+            countdown.draw(
+                &mut sheet,
+                self.trim_offset,
+                number,
+                &sheet_text_variables,
+                params.gamma_correct_blending,
+            );
+        }
+        for (extra_text, text_rasterizer) in
+            self.extra_texts.iter().zip(extra_text_rasterizers.iter())
+        {
+            let text = expand_text_variables(&extra_text.text, &sheet_text_variables);
+            let text_bitmap = text_rasterizer.create_text_bitmap_premultiplied(&text);
+            sheet.blit_centered_premultiplied(
+                &text_bitmap,
+                self.trim_offset + Vec2i::new(extra_text.x as i32, extra_text.y as i32),
+                params.gamma_correct_blending,
+            );
+        }
+
+        if let Some(ticket_stub) = &self.ticket_stub {
+            ticket_stub.draw(
+                &mut sheet,
+                self.trim_offset,
+                &card.serial,
+                &sheet_text_variables,
+                params.gamma_correct_blending,
+            );
+        }
+
+        if let Some(overlay_bitmap) = &self.overlay_bitmap_premultiplied {
+            sheet.blit_centered_premultiplied(
+                overlay_bitmap,
+                self.overlay_center,
+                params.gamma_correct_blending,
+            );
+        }
+
+        self.overlay_layer.draw(
+            &mut sheet,
+            self.trim_offset,
+            &sheet_text_variables,
+            params.gamma_correct_blending,
+        );
+
+        if params.output_color_profile == output_color::OutputColorProfile::Monochrome {
+            // NOTE: Dithering spreads the error of each pixel onto its neighbours so the result
+            //       depends on the whole sheet and not only on the pixels that we drew onto
+            sheet.region_covering(0, 0, sheet.width(), sheet.height());
+        }
+        let mut region = sheet.into_region();
+        if let Some(sheet_mask) = &self.sheet_mask {
+            apply_mask_premultiplied(
+                &mut region.bitmap,
+                region.pos,
+                sheet_mask,
+                self.background.width,
+            );
+        }
+        output_color::convert_sheet_premultiplied(&mut region.bitmap, params.output_color_profile);
+
+        region
+    }
+}
+
+/// The texts of the cards with one of the colors of our `accent_palette`. They replace the regular
+/// texts of our `SheetRenderer` which are drawn with the header text color.
+struct Accent<'a> {
+    /// The position of our color in the `accent_palette`
+    palette_index: usize,
+    header_text_rasterizer: TextRasterizer<'a>,
+    column_header_bitmaps_premultiplied: Vec<Bitmap>,
+    free_space_bitmap_premultiplied: Option<Bitmap>,
+    extra_text_rasterizers: Vec<TextRasterizer<'a>>,
+    /// Only exists with `accent_palette_numbers = true`
+    number_bitmaps_premultiplied: Option<HashMap<i32, Bitmap>>,
+}
+
+/// Names one of the bitmaps that our cells are made of by the numbers that select it. Lets the GPU
+/// find a bitmap in its atlas again without relying on where the bitmap lives in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum CellBitmapKey {
+    FixedCell {
+        column: i32,
+        row: i32,
+    },
+    FreeSpaceImage {
+        image_index: usize,
+    },
+    FreeSpaceText {
+        accent_index: Option<usize>,
+    },
+    BonusCellTint,
+    BonusCellImage,
+    NumberChip,
+    LuckyNumberStar,
+    LuckyNumber {
+        number: i32,
+    },
+    Number {
+        number: i32,
+        accent_index: Option<usize>,
+    },
+    Caption {
+        number: i32,
+    },
+}
+
+/// Draws the free space, bonus cells and numbers of a card
+struct CardCellRenderer<'a, 'b> {
+    sheet_renderer: &'b SheetRenderer<'a>,
+    card: &'b Card,
+}
+
+impl<'a, 'b> CellRenderer for CardCellRenderer<'a, 'b> {
+    fn draw_cell(&self, cell: &Cell, sheet: &mut Bitmap) {
+        let gamma_correct_blending = self.sheet_renderer.params.gamma_correct_blending;
+        self.for_each_bitmap(cell, |_key, bitmap, center| {
+            blit_centered_premultiplied(bitmap, sheet, center, gamma_correct_blending)
+        });
+    }
+}
+
+impl<'a, 'b> CardCellRenderer<'a, 'b> {
+    /// Calls the given function with each bitmap that makes up the given cell, its key and the
+    /// location of its center in the order in which they are drawn on top of each other
+    fn for_each_bitmap(&self, cell: &Cell, mut draw: impl FnMut(CellBitmapKey, &'b Bitmap, Vec2i)) {
+        let renderer = self.sheet_renderer;
+        let center = cell.center();
+        let accent = renderer.accent(self.card);
+
+        if let Some(fixed_cell_bitmap) = renderer
+            .fixed_cell_bitmaps_premultiplied
+            .get(&(cell.column, cell.row))
+        {
+            let key = CellBitmapKey::FixedCell {
+                column: cell.column,
+                row: cell.row,
+            };
+            draw(key, fixed_cell_bitmap, center);
+            return;
+        }
+
+        if renderer.free_space_cells.contains(&(cell.column, cell.row)) {
+            if let Some(name) = &self.card.free_space_image {
+                let image_index = renderer
+                    .free_space_images_premultiplied
+                    .iter()
+                    .position(|(image_name, _bitmap)| image_name == name)
+                    .unwrap();
+                let (_name, free_space_image) =
+                    &renderer.free_space_images_premultiplied[image_index];
+                draw(
+                    CellBitmapKey::FreeSpaceImage { image_index },
+                    free_space_image,
+                    center,
+                );
+            }
+            let free_space_bitmap = accent
+                .map(|accent| &accent.free_space_bitmap_premultiplied)
+                .unwrap_or(&renderer.free_space_bitmap_premultiplied);
+            if let Some(free_space_bitmap) = free_space_bitmap {
+                let key = CellBitmapKey::FreeSpaceText {
+                    accent_index: accent.map(|accent| accent.palette_index),
+                };
+                draw(key, free_space_bitmap, center);
+            }
+            return;
+        }
+
+        if cell.value == 0 {
+            // Empty cell
+            return;
+        }
+
+        if self
+            .card
+            .bonus_cells
+            .contains(&(cell.column as usize, cell.row as usize))
+        {
+            draw(
+                CellBitmapKey::BonusCellTint,
+                &renderer.bonus_cell_tint_premultiplied,
+                center,
+            );
+            if let Some(bonus_cell_image) = &renderer.bonus_cell_image_premultiplied {
+                draw(CellBitmapKey::BonusCellImage, bonus_cell_image, center);
+            }
+        }
+
+        if let Some(number_chip) = &renderer.number_chip_premultiplied {
+            draw(CellBitmapKey::NumberChip, number_chip, center);
+        }
+
+        if let Some(star) = &renderer.lucky_number_star_premultiplied {
+            if self.card.lucky_numbers.contains(&cell.value) {
+                // NOTE: The star sits in the top right corner so that it does not cover the number
+                draw(
+                    CellBitmapKey::LuckyNumberStar,
+                    star,
+                    Vec2i::new(
+                        cell.left + cell.width - star.width as i32 / 2 - cell.width / 16,
+                        cell.top + star.height as i32 / 2 + cell.height / 16,
+                    ),
+                );
+            }
+        }
+
+        let (number_key, number_bitmap) = match renderer
+            .lucky_number_bitmaps_premultiplied
+            .get(&cell.value)
+        {
+            Some(lucky_number_bitmap) => (
+                CellBitmapKey::LuckyNumber { number: cell.value },
+                lucky_number_bitmap,
+            ),
+            None => {
+                let accent_number_bitmaps = accent.and_then(|accent| {
+                    accent
+                        .number_bitmaps_premultiplied
+                        .as_ref()
+                        .map(|number_bitmaps| (accent.palette_index, number_bitmaps))
+                });
+                let (accent_index, number_bitmaps) = match accent_number_bitmaps {
+                    Some((palette_index, number_bitmaps)) => (Some(palette_index), number_bitmaps),
+                    None => (None, &renderer.number_bitmaps_premultiplied),
+                };
+                let key = CellBitmapKey::Number {
+                    number: cell.value,
+                    accent_index,
+                };
+                (key, number_bitmaps.get(&cell.value).unwrap())
+            }
+        };
+        let caption_bitmap = match &renderer.braille_bitmaps_premultiplied {
+            Some(braille_bitmaps) => braille_bitmaps.get(&cell.value),
+            None => renderer.nickname_bitmaps_premultiplied.get(&cell.value),
+        };
+        if let Some(caption_bitmap) = caption_bitmap {
+            // NOTE: We move the number up to make room for its caption below
+            draw(
+                number_key,
+                number_bitmap,
+                center - Vec2i::new(0, cell.height / 8),
+            );
+            draw(
+                CellBitmapKey::Caption { number: cell.value },
+                caption_bitmap,
+                Vec2i::new(center.x, cell.top + 7 * cell.height / 8),
+            );
+            return;
+        }
+        draw(number_key, number_bitmap, center);
+    }
+}
+
+const MAX_SHEET_COUNT: usize = 10_000;
+/// The number of sheets that are created in parallel before we log our progress
+const SHEETS_PER_CHUNK: usize = 100;
+/// The WCAG contrast ratio that is recommended for large text
+const DEFAULT_MINIMUM_NUMBER_CONTRAST: f32 = 3.0;
+/// The WCAG contrast ratio that is recommended for normal text at the highest conformance level
+const LARGE_PRINT_MINIMUM_NUMBER_CONTRAST: f32 = 7.0;
+const DEFAULT_LUCKY_NUMBER_STAR_COLOR_RGB: (u8, u8, u8) = (255, 196, 0);
+/// Keeps a light grey version of our background which is still recognizable on paper
+const DEFAULT_INK_SAVER_STRENGTH: f32 = 0.25;
+const DRAW_PARAMETERS_FILENAME: &str = "draw_parameters.txt";
+/// Our draw parameters can also be given as YAML or JSON file instead, i.e. when they are created
+/// by another program
+const DRAW_PARAMETERS_FILENAMES: [(&str, DrawParamsFormat); 4] = [
+    (DRAW_PARAMETERS_FILENAME, DrawParamsFormat::Toml),
+    ("draw_parameters.yaml", DrawParamsFormat::Yaml),
+    ("draw_parameters.yml", DrawParamsFormat::Yaml),
+    ("draw_parameters.json", DrawParamsFormat::Json),
+];
+const RECIPIENTS_FILENAME: &str = "recipients.csv";
+const OUTPUT_SHEETS_DIRECTORY: &str = "output_sheets";
+const BACKGROUND_IMAGE_EXTENSIONS: &[&str] = &[".png", ".jpg", ".jpeg", ".bmp", ".tga"];
+const DRY_RUN_PREVIEW_FILENAME: &str = "dry_run_preview.png";
+const COLUMN_LETTERS: [char; 5] = ['B', 'I', 'N', 'G', 'O'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum FreeSpaceImageSelection {
+    Random,
+    RoundRobin,
+}
+
+impl Default for FreeSpaceImageSelection {
+    fn default() -> Self {
+        FreeSpaceImageSelection::Random
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum BingoVariant {
+    /// 5x5 grid with the numbers 1-75 and a free space in the center
+    #[serde(rename = "75_ball")]
+    Ball75,
+    /// A book of six 9x3 tickets stacked on top of each other which together contain each number
+    /// from 1-90 exactly once
+    #[serde(rename = "90_ball")]
+    Ball90,
+    /// 4x4 grid with the numbers 1-80 and no free space. Each column has its own background color.
+    #[serde(rename = "80_ball", alias = "80ball")]
+    Ball80,
+    /// 3x3 grid with the numbers 1-30 and no free space for short speed Bingo games
+    #[serde(rename = "30_ball")]
+    Ball30,
+    /// 4x4 grid of images from a deck of 54 images
+    #[serde(rename = "loteria")]
+    Loteria,
+    /// 5x5 grid of items from categorized lists
+    #[serde(rename = "scavenger_hunt")]
+    ScavengerHunt,
+    /// Grid of any size where each cell is described by our `cell_layout`
+    #[serde(rename = "cell_layout")]
+    CellLayout,
+}
+
+impl Default for BingoVariant {
+    fn default() -> Self {
+        BingoVariant::Ball75
+    }
+}
+
+impl BingoVariant {
+    /// Returns the background color of each column if this variant has a color scheme. The columns
+    /// of 80-ball cards are traditionally red, yellow, blue and silver.
+    fn column_colors_rgb(self) -> Vec<(u8, u8, u8)> {
+        match self {
+            BingoVariant::Ball80 => vec![
+                (220, 50, 50),
+                (250, 210, 60),
+                (60, 120, 220),
+                (192, 192, 192),
+            ],
+            _ => Vec::new(),
+        }
+    }
+
+    fn has_free_space(self) -> bool {
+        self == BingoVariant::Ball75
+    }
+
+    /// Returns the (column, row) location of the free space cells if this variant has one by default
+    fn default_free_space_cells(self) -> Vec<(i32, i32)> {
+        if self.has_free_space() {
+            vec![(2, 2)]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// What to do if the output directory already contains sheets from a previous run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ExistingOutputPolicy {
+    /// Asks whether the existing sheets should be deleted
+    Prompt,
+    Delete,
+    Error,
+    /// Writes into a new directory with the current date and time in its name
+    Timestamped,
+}
+
+impl Default for ExistingOutputPolicy {
+    fn default() -> Self {
+        ExistingOutputPolicy::Prompt
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum OutputMirror {
+    None,
+    /// Flips left and right
+    Horizontal,
+    /// Flips top and bottom
+    Vertical,
+}
+
+impl Default for OutputMirror {
+    fn default() -> Self {
+        OutputMirror::None
+    }
+}
+
+/// Where the grid cells of our sheets are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CompositingBackend {
+    Cpu,
+    /// Only available if Chotto was built with the `gpu` feature
+    Gpu,
+}
+
+impl Default for CompositingBackend {
+    fn default() -> Self {
+        CompositingBackend::Cpu
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RngSource {
+    /// Seeds our random number generator with the current time
+    Time,
+    /// Draws all random numbers from a cryptographically secure random number generator that is
+    /// seeded by the operating system. The seed is never written anywhere so that nobody can
+    /// recreate the cards.
+    Secure,
+}
+
+impl RngSource {
+    /// Describes where our random seed came from for the logs and reports
+    fn entropy_source(self) -> &'static str {
+        match self {
+            RngSource::Time => "the current time",
+            RngSource::Secure => {
+                "a ChaCha20 generator seeded by the secure random number generator of the \
+                 operating system"
+            }
+        }
+    }
+}
+
+impl Default for RngSource {
+    fn default() -> Self {
+        RngSource::Time
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Preset {
+    /// Big bold numbers with high contrast for visually impaired players
+    LargePrint,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Mode {
+    Bingo,
+    /// Sequentially numbered two-part tickets without a grid. The ticket shows the serial number
+    /// and the matching stub is our `ticket_stub`.
+    Raffle,
+    /// 9x9 puzzles like Sudoku whose givens are read from our `puzzles_filepath`
+    Puzzle,
+    /// One name or table number of our `seating_cards_filepath` per sheet without a grid
+    SeatingCards,
+    /// One big number with a caption per sheet for countdowns and advent calendars
+    Countdown,
+}
+
+impl Default for Mode {
+    fn default() -> Self {
+        Mode::Bingo
+    }
+}
+
+impl Mode {
+    fn has_grid(self) -> bool {
+        match self {
+            Mode::Bingo | Mode::Puzzle => true,
+            Mode::Raffle | Mode::SeatingCards | Mode::Countdown => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum OutputMode {
+    /// Writes each sheet as image file
+    Sheets,
+    /// Writes the background once and a small JSON file per sheet which describes where its
+    /// numbers go
+    Deltas,
+}
+
+impl Default for OutputMode {
+    fn default() -> Self {
+        OutputMode::Sheets
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Optimization {
+    None,
+    /// Avoids that the lines of our sheets share many numbers so that fewer games end with
+    /// several players completing a line at the same call
+    MinimizeTies,
+}
+
+impl Default for Optimization {
+    fn default() -> Self {
+        Optimization::None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum NumberChipShape {
+    Circle,
+    RoundedRect,
+}
+
+/// A filled shape that is drawn behind each number to make it readable on busy backgrounds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct NumberChip {
+    shape: NumberChipShape,
+    #[serde(deserialize_with = "colors::rgba")]
+    color_rgba: (u8, u8, u8, u8),
+    /// The radius of the circle or the corner radius of the rounded rect
+    radius_px: Option<u32>,
+    /// The space between the rounded rect and the border of its grid cell
+    padding_px: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ExtraText {
+    text: String,
+    x: u32,
+    y: u32,
+    size: Option<u32>,
+    #[serde(default, deserialize_with = "colors::optional_rgb")]
+    color: Option<(u8, u8, u8)>,
+}
+
+/// One of the colors that our cards randomly pick from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AccentColor {
+    /// How we call the cards with this color during the game, i.e. `blue`
+    name: String,
+    #[serde(deserialize_with = "colors::rgb")]
+    color_rgb: (u8, u8, u8),
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DrawParams {
+    #[serde(default)]
+    config_version: u32,
+    number_of_sheets_to_generate: usize,
+    preset: Option<Preset>,
+    text_font_size: u32,
+    #[serde(deserialize_with = "colors::rgb")]
+    text_color_rgb: (u8, u8, u8),
+    /// Not needed for raffle tickets and seating cards
+    #[serde(default)]
+    bingo_grid_pixel_location_left_top_right_bottom: (u32, u32, u32, u32),
+    #[serde(default)]
+    mode: Mode,
+    puzzles_filepath: Option<String>,
+    seating_cards_filepath: Option<String>,
+    seating_card_text_pixel_location_left_top_right_bottom: Option<(u32, u32, u32, u32)>,
+    #[serde(default, deserialize_with = "colors::optional_rgba")]
+    text_color_rgba: Option<(u8, u8, u8, u8)>,
+    text_gradient: Option<TextGradient>,
+    #[serde(default)]
+    bingo_variant: BingoVariant,
+    deck_images_directory: Option<String>,
+    scavenger_hunt: Option<scavenger_items::ScavengerHuntParams>,
+    cell_layout: Option<Vec<Vec<String>>>,
+    cell_layout_filepath: Option<String>,
+    #[serde(default)]
+    excluded_numbers: Vec<i32>,
+    #[serde(default)]
+    forbidden_number_combinations: Vec<Vec<i32>>,
+    #[serde(default)]
+    optimize: Optimization,
+    background_image_filepath: Option<String>,
+    #[serde(default, deserialize_with = "colors::optional_rgb")]
+    paper_color_rgb: Option<(u8, u8, u8)>,
+    #[serde(default)]
+    ink_saver: bool,
+    ink_saver_strength: Option<f32>,
+    font_filepath: Option<String>,
+    output_width_px: Option<u32>,
+    output_height_px: Option<u32>,
+    output_dpi: Option<u32>,
+    output_size_mm: Option<(f32, f32)>,
+    #[serde(default)]
+    rng: RngSource,
+    #[serde(default)]
+    existing_output_policy: ExistingOutputPolicy,
+    sheets_per_directory: Option<usize>,
+    players: Option<usize>,
+    cards_per_player: Option<usize>,
+    #[serde(default)]
+    shuffle_output_order: bool,
+    /// Clockwise rotation in degrees
+    #[serde(default)]
+    output_rotation: u32,
+    #[serde(default)]
+    output_mirror: OutputMirror,
+    #[serde(default)]
+    output_color_profile: output_color::OutputColorProfile,
+    #[serde(default)]
+    png_compression: png_writer::PngCompression,
+    #[serde(default)]
+    compositing_backend: CompositingBackend,
+    #[serde(default)]
+    output_mode: OutputMode,
+    post_process_command: Option<String>,
+    post_process_parallelism: Option<usize>,
+    #[serde(default)]
+    prefix_numbers_with_column_letter: bool,
+    column_labels: Option<Vec<String>>,
+    #[serde(default)]
+    gamma_correct_blending: bool,
+    header_font_filepath: Option<String>,
+    header_text_font_size: Option<u32>,
+    #[serde(default, deserialize_with = "colors::optional_rgb")]
+    header_text_color_rgb: Option<(u8, u8, u8)>,
+    #[serde(default)]
+    text_direction: shaping::TextDirection,
+    free_space_text: Option<String>,
+    free_space_cells_x_y: Option<Vec<(u32, u32)>>,
+    free_space_images_directory: Option<String>,
+    #[serde(default)]
+    free_space_image_selection: FreeSpaceImageSelection,
+    column_headers_pixel_location_top_bottom: Option<(u32, u32)>,
+    column_headers: Option<Vec<String>>,
+    #[serde(default)]
+    vertical_column_headers: bool,
+    bonus_cells_x_y: Option<Vec<(u32, u32)>>,
+    random_bonus_cell_count: Option<u32>,
+    #[serde(default, deserialize_with = "colors::optional_rgba")]
+    bonus_cell_color_rgba: Option<(u8, u8, u8, u8)>,
+    bonus_cell_image_filepath: Option<String>,
+    #[serde(default)]
+    lucky_numbers: Vec<i32>,
+    #[serde(default, deserialize_with = "colors::optional_rgb")]
+    lucky_number_color_rgb: Option<(u8, u8, u8)>,
+    #[serde(default)]
+    lucky_number_star: bool,
+    #[serde(default)]
+    accent_palette_numbers: bool,
+    number_chip: Option<NumberChip>,
+    minimum_number_contrast: Option<f32>,
+    #[serde(default)]
+    auto_number_outline: bool,
+    #[serde(default)]
+    bold_text: bool,
+    #[serde(default)]
+    braille_captions: bool,
+    braille_font_filepath: Option<String>,
+    braille_dot_radius_px: Option<u32>,
+    #[serde(default)]
+    number_nicknames: bool,
+    nicknames_filepath: Option<String>,
+    nickname_font_size: Option<u32>,
+    serial_number_pixel_location_x_y: Option<(u32, u32)>,
+    #[serde(flatten)]
+    serial_number: serial_number::SerialNumberParams,
+    security_code_secret_key: Option<String>,
+    security_code_pixel_location_x_y: Option<(u32, u32)>,
+    barcode_pixel_location_x_y: Option<(u32, u32)>,
+    barcode_module_width_px: Option<u32>,
+    barcode_height_px: Option<u32>,
+    cmyk_tiff_icc_profile_filepath: Option<String>,
+    bleed_px: Option<u32>,
+    #[serde(default, deserialize_with = "colors::optional_rgb")]
+    bleed_color_rgb: Option<(u8, u8, u8)>,
+    #[serde(default)]
+    crop_marks: bool,
+    corner_radius_px: Option<u32>,
+    mask_image_filepath: Option<String>,
+    overlay_image_filepath: Option<String>,
+    overlay_pixel_location_x_y: Option<(u32, u32)>,
+    overlay_scale: Option<f32>,
+    overlay_opacity: Option<f32>,
+    extra_texts: Option<Vec<ExtraText>>,
+    text_variables: Option<HashMap<String, String>>,
+    header_text_gradient: Option<TextGradient>,
+    number_effects: Option<text_effects::TextEffects>,
+    header_text_effects: Option<text_effects::TextEffects>,
+    accent_palette: Option<Vec<AccentColor>>,
+    ticket_stub: Option<ticket_stub::TicketStubParams>,
+    countdown: Option<countdown::CountdownParams>,
+    email: Option<email::EmailParams>,
+    pos_export: Option<pos_export::PosExportParams>,
+    s3_upload: Option<s3_upload::S3UploadParams>,
+    caller_speech: Option<speech::CallerSpeechParams>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    cell_pools: BTreeMap<String, Vec<String>>,
+    /// NOTE: This must stay the last field as arrays of tables must come last in TOML files
+    #[serde(default)]
+    overlays: Vec<overlays::OverlayParams>,
+}
+
+impl DrawParams {
+    /// Returns how much of the darkness of our background is kept in ink saver mode
+    fn ink_saver_strength(&self) -> f32 {
+        self.ink_saver_strength
+            .unwrap_or(DEFAULT_INK_SAVER_STRENGTH)
+    }
+
+    /// Returns the labels of the five columns of 75-ball Bingo which are B-I-N-G-O by default
+    fn column_labels(&self) -> Vec<String> {
+        self.column_labels.clone().unwrap_or_else(|| {
+            COLUMN_LETTERS
+                .iter()
+                .map(|letter| letter.to_string())
+                .collect()
+        })
+    }
+
+    /// Returns the number of columns and rows of the whole grid on a sheet
+    fn grid_size(&self) -> (i32, i32) {
+        if self.mode == Mode::Puzzle {
+            return (
+                puzzle::PUZZLE_GRID_SIZE as i32,
+                puzzle::PUZZLE_GRID_SIZE as i32,
+            );
+        }
+        match self.bingo_variant {
+            BingoVariant::Ball75 => (5, 5),
+            BingoVariant::Ball90 => (
+                ninety_ball::COLUMN_COUNT as i32,
+                (ninety_ball::TICKETS_PER_BOOK * ninety_ball::TICKET_ROW_COUNT) as i32,
+            ),
+            BingoVariant::Ball80 => (4, 4),
+            BingoVariant::Ball30 => (3, 3),
+            BingoVariant::Loteria => (loteria::GRID_SIZE as i32, loteria::GRID_SIZE as i32),
+            BingoVariant::ScavengerHunt => (
+                scavenger_hunt::GRID_SIZE as i32,
+                scavenger_hunt::GRID_SIZE as i32,
+            ),
+            BingoVariant::CellLayout => {
+                let layout = self.cell_layout.as_ref().unwrap_or_else(|| {
+                    panic!(
+                        "Cards with a cell layout need a `cell_layout` or `cell_layout_filepath` - \
+                         please add one of them to '{}'!",
+                        DRAW_PARAMETERS_FILENAME
+                    )
+                });
+                (
+                    layout.first().map(|row| row.len()).unwrap_or(0) as i32,
+                    layout.len() as i32,
+                )
+            }
+        }
+    }
+
+    /// Returns the highest number that can appear on a sheet. For Lotería, scavenger hunt and cell
+    /// layout cards this is the number of images, items or pool entries. Raffle tickets have no
+    /// grid numbers at all, just like seating cards and countdowns, and puzzles use the digits 1-9.
+    fn max_number(&self) -> i32 {
+        match self.mode {
+            Mode::Bingo => {}
+            Mode::Raffle | Mode::SeatingCards | Mode::Countdown => return 0,
+            Mode::Puzzle => return puzzle::PUZZLE_GRID_SIZE as i32,
+        }
+        match self.bingo_variant {
+            BingoVariant::Ball75 => 75,
+            BingoVariant::Ball90 => 90,
+            BingoVariant::Ball80 => 80,
+            BingoVariant::Ball30 => 30,
+            BingoVariant::Loteria => loteria::DECK_SIZE as i32,
+            BingoVariant::ScavengerHunt => self
+                .scavenger_hunt
+                .as_ref()
+                .map(|scavenger_hunt| scavenger_hunt.items().len() as i32)
+                .unwrap_or(0),
+            BingoVariant::CellLayout => {
+                cell_layout_params::pool_entries(&self.cell_pools).len() as i32
+            }
+        }
+    }
+
+    /// Reads the puzzles of `mode = "puzzle"` from our `puzzles_filepath`
+    fn puzzles(&self) -> Vec<card_import::ImportedCard> {
+        let filepath = self.puzzles_filepath.as_ref().unwrap_or_else(|| {
+            panic!(
+                "Puzzle sheets need a `puzzles_filepath` with the givens of our puzzles - please \
+                 add it to '{}'!",
+                DRAW_PARAMETERS_FILENAME
+            )
+        });
+        puzzle::read_puzzle_file(filepath)
+    }
+
+    /// Reads the names or table numbers of `mode = "seating_cards"` from our
+    /// `seating_cards_filepath`
+    fn seating_card_texts(&self) -> Vec<String> {
+        let filepath = self.seating_cards_filepath.as_ref().unwrap_or_else(|| {
+            panic!(
+                "Seating cards need a `seating_cards_filepath` with one name or table number per \
+                 line - please add it to '{}'!",
+                DRAW_PARAMETERS_FILENAME
+            )
+        });
+        seating_cards::read_seating_card_texts(filepath)
+    }
+
+    /// Returns how many sheets we create if there is no recipients file
+    fn sheet_count(&self) -> usize {
+        match (self.players, self.cards_per_player) {
+            (Some(players), Some(cards_per_player)) => players * cards_per_player,
+            _ => self.number_of_sheets_to_generate,
+        }
+    }
+
+    fn directory_layout(&self) -> manifest::DirectoryLayout {
+        if let Some(cards_per_player) = self.cards_per_player {
+            manifest::DirectoryLayout::Players { cards_per_player }
+        } else if let Some(sheets_per_directory) = self.sheets_per_directory {
+            manifest::DirectoryLayout::Shards {
+                sheets_per_directory,
+            }
+        } else {
+            manifest::DirectoryLayout::Flat
+        }
+    }
+
+    /// Returns how the Bingo numbers are colored
+    fn number_fill_style(&self) -> FillStyle {
+        if let Some(gradient) = &self.text_gradient {
+            return FillStyle::Gradient {
+                direction: gradient.direction,
+                start: color_from_rgba(gradient.start_color_rgba),
+                end: color_from_rgba(gradient.end_color_rgba),
+            };
+        }
+        FillStyle::Solid(
+            self.text_color_rgba
+                .map(color_from_rgba)
+                .unwrap_or_else(|| color_from_rgb(self.text_color_rgb)),
+        )
+    }
+
+    fn accent_color_names(&self) -> Vec<String> {
+        self.accent_palette
+            .iter()
+            .flatten()
+            .map(|accent_color| accent_color.name.clone())
+            .collect()
+    }
+
+    /// Returns how our header texts are colored. Texts with their own color are always solid.
+    fn header_fill_style(&self, text_color_rgb: Option<(u8, u8, u8)>) -> FillStyle {
+        match (&self.header_text_gradient, text_color_rgb) {
+            (Some(gradient), None) => FillStyle::Gradient {
+                direction: gradient.direction,
+                start: color_from_rgba(gradient.start_color_rgba),
+                end: color_from_rgba(gradient.end_color_rgba),
+            },
+            _ => FillStyle::Solid(color_from_rgb(
+                text_color_rgb
+                    .or(self.header_text_color_rgb)
+                    .unwrap_or(self.text_color_rgb),
+            )),
+        }
+    }
+
+    /// Returns the (column, row) location of all free space cells
+    fn free_space_cells(&self) -> Vec<(i32, i32)> {
+        if self.mode != Mode::Bingo || !self.bingo_variant.has_free_space() {
+            return Vec::new();
+        }
+        self.free_space_cells_x_y
+            .as_ref()
+            .map(|cells| cells.iter().map(|&(x, y)| (x as i32, y as i32)).collect())
+            .unwrap_or_else(|| self.bingo_variant.default_free_space_cells())
+    }
+
+    /// Overrides our parameters with the ones of the given preset
+    fn apply_preset(&mut self, preset: Preset) {
+        match preset {
+            Preset::LargePrint => {
+                let (column_count, row_count) = self.grid_size();
+                let (left, top, right, bottom) =
+                    self.bingo_grid_pixel_location_left_top_right_bottom;
+                let cell_size = u32::min(
+                    right.saturating_sub(left) / column_count as u32,
+                    bottom.saturating_sub(top) / row_count as u32,
+                );
+                // NOTE: Numbers with two digits still need to fit into their grid cell
+                self.text_font_size = u32::max(self.text_font_size, 6 * cell_size / 10);
+                self.header_text_font_size = Some(u32::max(
+                    self.header_text_font_size.unwrap_or(self.text_font_size),
+                    self.text_font_size / 2,
+                ));
+                self.bold_text = true;
+                self.minimum_number_contrast = Some(f32::max(
+                    self.minimum_number_contrast.unwrap_or(0.0),
+                    LARGE_PRINT_MINIMUM_NUMBER_CONTRAST,
+                ));
+                self.auto_number_outline = true;
+            }
+        }
+    }
+
+    fn referenced_filepaths(&self) -> Vec<String> {
+        [
+            &self.background_image_filepath,
+            &self.font_filepath,
+            &self.header_font_filepath,
+            &self.overlay_image_filepath,
+            &self.bonus_cell_image_filepath,
+            &self.braille_font_filepath,
+            &self.cell_layout_filepath,
+            &self.nicknames_filepath,
+            &self.mask_image_filepath,
+        ]
+        .iter()
+        .filter_map(|filepath| filepath.as_ref().cloned())
+        .chain(
+            self.overlays
+                .iter()
+                .filter_map(|overlay| overlay.image_filepath().cloned()),
+        )
+        .collect()
+    }
+}
+
+/// Returns the parameters that we write into a new draw parameters file for the given background
+/// image size
+fn default_draw_params(background_width: u32, background_height: u32) -> DrawParams {
+    DrawParams {
+        config_version: params_migration::CURRENT_CONFIG_VERSION,
+        number_of_sheets_to_generate: 10,
+        text_font_size: background_height / 20,
+        text_color_rgb: (255, 128, 64),
+        bingo_grid_pixel_location_left_top_right_bottom: (
+            0,
+            0,
+            background_width,
+            background_height,
+        ),
+        ..Default::default()
+    }
+}
+
+/// Returns the given draw parameters in the format of our draw parameters file
+fn draw_params_to_toml_string(params: &DrawParams) -> String {
+    // NOTE: TOML needs all plain values of a table before its nested tables. A `toml::Value` writes
+    //       them in that order no matter which of our fields are tables and otherwise keeps the
+    //       order of our fields thanks to the `preserve_order` feature of `toml`.
+    toml::Value::try_from(params)
+        .and_then(|params_value| toml::to_string(&params_value))
+        .unwrap_or_else(|error| panic!("Could not write the draw parameters: {}", error))
+}
+
+/// Parameters that must never be written next to our sheets because anyone who knows them can
+/// forge security codes or tell which serial numbers are valid
+const SECRET_PARAMETER_KEYS: [&str; 2] = ["security_code_secret_key", "serial_number_salt"];
+const REDACTED_VALUE: &str = "<redacted>";
+
+/// Returns the given draw parameters as a TOML value with the values of our secret parameters
+/// replaced. Use this for everything that we write into the output directory.
+fn redacted_draw_params_value(params: &DrawParams) -> toml::Value {
+    let mut params_value =
+        toml::Value::try_from(params).expect("Could not convert draw parameters");
+    let params_table = params_value.as_table_mut().unwrap();
+    for key in SECRET_PARAMETER_KEYS.iter() {
+        if let Some(value) = params_table.get_mut(*key) {
+            *value = toml::Value::String(REDACTED_VALUE.to_owned());
+        }
+    }
+    params_value
+}
+
+const TOML_DOCUMENTATION_HEADER: &str =
+"####################################################################################################
+#
+# In this file we can change various things about how Chotto should draw our Bingo-sheets by editing
+# the parameters at the bottom.
+#
+# The `number_of_sheets_to_generate` parameter indicates how many Bingo-sheets we want Chotto 
+# to generate. The final sheets will be placed in the `output_sheets` directory once Chotto was run.
+# There we can also open the `index.html` file in our browser which shows a summary of the run and
+# small previews of the first sheets. To check our design before creating all sheets we can also
+# run `chotto.exe proof` which writes a few sample sheets as 4x4 thumbnails per page into the
+# `proof_sheets` directory. If we later need more sheets we can run
+# `chotto.exe extend --count 500` which adds new sheets to the `output_sheets` directory that
+# continue the serial numbers and never duplicate the existing sheets. If the numbers of our sheets
+# were decided somewhere else (i.e. by a generator that was audited by a regulator) we can run
+# `chotto.exe render-from --cards cards.json` which draws exactly the cards of the given file.
+#
+# To pick patterns and prizes we can run `chotto.exe simulate --games 10000` after the sheets were
+# created. It plays the given number of random games with our sheets and tells how many calls it
+# takes on average until the first line and the first full house and how often several players
+# win at the same time. How many games ended after how many calls is written to the
+# `simulation.csv` file next to our sheets. The `coverall_heatmap.csv` file tells for each cell of
+# our grid how often its number was already called when the first full house was won. Sheets that
+# cannot win a line or a full house are listed in the log and left out of the simulation.
+#
+# A long run can be stopped by pressing Ctrl+C in the console window. Chotto then finishes the
+# sheets that it is currently drawing and writes the manifest of all finished sheets so that we can
+# continue later via `chotto.exe extend`. Pressing Ctrl+C a second time stops immediately.
+#
+# To verify claims during the game (i.e. from a tablet app) we can run
+# `chotto.exe serve --port 8080` which answers `GET /cards/<serial>/<security code>` with the
+# numbers of a card and `POST /verify` with whether a card wins against the numbers called so far.
+# The request for the latter looks like
+# `{ \"card\": \"A-0001\", \"security_code\": \"X7K2\", \"called_numbers\": [3, 17, 22] }`. The
+# security code can be left out for sheets without one. Chotto only listens on this computer by
+# default. To let the tablets of the hall connect we run `chotto.exe serve --bind 0.0.0.0` after
+# setting a secret token in the `CHOTTO_SERVER_TOKEN` environment variable which the tablets send as
+# `Authorization: Bearer <token>`.
+# Programs that drive Chotto as a service (i.e. a kiosk) can send JSON-RPC 2.0 requests to
+# `POST /rpc`. The method `generate_batch` with `{ \"count\": 100, \"profile\": \"a4\" }` starts a new
+# batch in a timestamped directory and returns a `job_id`, `get_progress` with `{ \"job_id\": 1 }`
+# tells how far that batch is and `get_card` with
+# `{ \"card\": \"A-0001\", \"security_code\": \"K7QX-M2ZD\" }` returns a single card. Like for
+# `GET /cards` the security code is needed for cards that have one.
+#
+# For games that are streamed on Twitch we can run `chotto.exe bot --channel <channel>` after
+# setting the OAuth token of the bot account in the `CHOTTO_TWITCH_TOKEN` environment variable
+# (the bot account defaults to the channel itself and can be changed via `--nick <account>`).
+# Viewers get the next free card of our sheets by typing `!card` into the chat and can ask how
+# many of their numbers are still missing via `!check`. The channel owner (and a co-host given via
+# `--caller <user>`) calls numbers via `!call 17` and starts over via `!newgame`. The bot announces
+# every new winner right after the call. Which viewer has which card is kept in the
+# `bot_session.json` file next to our sheets so that the bot can be restarted during a game. The bot
+# talks to the Twitch chat via TLS. Only Twitch is supported, Discord is not.
+#
+# For live games in a hall we can run `chotto.exe caller` which opens a window for the big screen.
+# It shows the last called number in huge type next to a board of all numbers where the numbers
+# that were called so far are lit. Space (or Enter) draws the next number, Backspace takes back the
+# last number and Escape closes the window. The screen uses our background image and number font
+# so that it matches our sheets. Its layout follows the size of the background image, so a
+# background with the aspect ratio of the screen (i.e. 1920x1080) looks best.
+# For streamed games we can add `--overlay caller_overlay.png` which writes the same screen without
+# the background to the given file after each call. Adding that file as image source in OBS shows
+# the board and the last number on top of the stream.
+# The caller writes every called number together with the time of the call to the
+# `caller_session.json` file right after the call. If our laptop crashes in the middle of a game we
+# just start `chotto.exe caller` again and it continues with the numbers called so far, so we can
+# still verify the winners. To start a new game we run `chotto.exe caller --new-game`.
+#
+# Each run is recorded in the `audit_log.jsonl` file next to this file. Every entry contains a hash
+# of the draw parameters, a hash of the random seed together with a secret random nonce, hashes of
+# all created cards and sheet files and the hash of its previous entry. The `run_manifest.toml` of
+# each directory records how many entries the log had after its run and the hash of the last one.
+# An auditor can run `chotto.exe verify-audit --directory output_sheets` to check that no entry of
+# the log and no sheet of the given directory was changed or removed afterwards. The nonce of each
+# run is written next to its seed into the `run_manifest.toml` of its directory. To prove later
+# which seed we used we reveal both and anyone can run
+# `chotto.exe verify-audit --seed <seed> --seed-nonce <nonce>`.
+#
+# If we might need to create the exact same sheets again later (i.e. for an audit or a reprint) we
+# can run `chotto.exe --export-bundle`. This writes a `reproduction_bundle.zip` next to our sheets
+# which contains this file, our images, fonts and the random seed. Running
+# `chotto.exe reproduce reproduction_bundle.zip` on any machine then creates the identical sheets.
+# Even without a bundle Chotto writes a `run_manifest.toml` next to our sheets which lists the
+# version of Chotto, the exact parameters, the random seed and hashes of all input files of each
+# run that created sheets in that directory.
+#
+# If we don't have a background image yet we can start with one of the built-in designs. Running
+# `chotto.exe gallery` lists them and `chotto.exe gallery kids --directory kids_party` creates the
+# background image, a copy of a system font and the draw parameters of the `kids` design in the
+# `kids_party` directory. The background is generated from simple shapes and can be replaced by
+# our own image later.
+#
+# To share our card design with others we can run
+# `chotto.exe export-template --name \"Spring Fair\" --author \"Jane\"` which writes a
+# `Spring Fair.chottotemplate` file with this file, our background, fonts and images. Settings that
+# only concern our own runs like the `security_code_secret_key`, `email`, `s3_upload` or
+# `post_process_command` are left out. Someone else can then run
+# `chotto.exe import-template \"Spring Fair.chottotemplate\"` which unpacks the design into a new
+# directory where they can create sheets with it.
+#
+# By default Chotto seeds its random number generator with the current time. For regulated games
+# that require unpredictable sheets we can set `rng = \"secure\"` which draws all cards and serial
+# numbers from a cryptographically secure random number generator that is seeded by the operating
+# system instead. Its seed is never written anywhere so such runs cannot be reproduced. The
+# commitment to our seed (if any) and where the seed came from are listed in the `index.html` file
+# next to our sheets. The seed itself is only listed in the `run_manifest.toml` file.
+#
+# The `existing_output_policy` parameter defines what happens if the `output_sheets` directory
+# already exists from a previous run. It can be one of the following:
+#   \"prompt\"      - Chotto asks us whether the existing sheets should be deleted
+#   \"delete\"      - Chotto deletes the existing sheets without asking
+#   \"error\"       - Chotto stops so that we can move the existing sheets somewhere else first
+#   \"timestamped\" - Chotto always writes into a new directory with the current date and time in
+#                   its name (i.e. `output_sheets_2024-05-11_19-03`)
+#
+# The `text_font_size` and `text_color_rgb` paramters can be used to customize the final text 
+# size and color. The color values are [Red, Green, Blue] in order and each range between 0-255.
+# Instead we can also write colors as hex values like `\"#FF8040\"` or by their name like
+# `\"orange\"`. This works for all color parameters below. Hex colors with 8 digits like
+# `\"#FF804080\"` also contain the opacity for parameters that end in `_rgba`.
+# The font size is given in pixel-height. Note though that the final numbers on the grid may be 
+# slightly smaller than the given font size. We can just try out some values until it looks good.
+#
+# The `bingo_grid_pixel_location_left_top_right_bottom` parameter defines the rectangular region
+# in the image where the Bingo numbers will be drawn to. The values are [Left, Top, Right, Bottom]
+# and are given in pixels.
+#
+# For example if we have a 100x100px image and only want numbers drawn on the bottom half of the 
+# image we can write:
+#
+# bingo_grid_pixel_location_left_top_right_bottom = [0, 50, 100, 100]
+#
+# If our background image already has a printed grid we can let Chotto measure it for us by
+# running `chotto.exe detect-grid`. It looks for the lines of the grid, prints the pixel locations
+# of the grid and its cells and writes the grid location into this file (the old file is kept as
+# `draw_parameters.txt.bak`). It warns us if the number of cells does not match our Bingo variant
+# or if the cells have different sizes. With `--no-write` it only prints the values.
+#
+# By default Chotto uses the only image file (PNG, JPEG, BMP or TGA) and TTF file next to
+# `chotto.exe` as background image and font. If there are other image or TTF files in the
+# directory we can tell Chotto which ones to use via the `background_image_filepath` and
+# `font_filepath` parameters. For example:
+#
+# background_image_filepath = \"card_designs/spring.png\"
+# font_filepath = \"C:/Windows/Fonts/arial.ttf\"
+#
+# If our background image has transparent parts our numbers are blended onto transparent pixels
+# which makes them look washed out when printed. The `paper_color_rgb` parameter puts our
+# background onto paper of the given color first (i.e. `paper_color_rgb = \"#FFFFFF\"` for white
+# paper) so that our sheets are fully opaque.
+#
+# Setting `ink_saver = true` prints our background in light grey while our numbers and texts keep
+# their full strength. This saves a lot of ink or toner when printing hundreds of sheets (i.e. for
+# a school). The optional `ink_saver_strength` parameter defines how much of the darkness of our
+# background is kept and goes from `0.0` (white) to `1.0` (greyscale only). It defaults to `0.25`.
+#
+# If our background image is too small or too big for printing we can let Chotto resize it first
+# via the `output_width_px` and `output_height_px` parameters. If only one of them is given the
+# aspect ratio of our image is kept. Alternatively we can give the physical size of our sheets
+# together with the resolution of our printer (i.e. `output_size_mm = [210, 297]` and
+# `output_dpi = 300` for A4 paper). Note that all other pixel locations then refer to the resized
+# image. Very large scans (i.e. poster-size PNG files with more than 50 megapixels) are shrunk to
+# this size while they are loaded so that they don't need much memory. For big sheets Chotto also
+# draws fewer sheets at the same time so that they fit into the memory of our computer.
+#
+# The `prefix_numbers_with_column_letter` parameter can be set to `true` if we want each number to
+# be drawn together with the letter of its column (i.e. `B7`, `I22`, `N38` instead of `7`, `22`,
+# `38`).
+#
+# The `column_labels` parameter replaces the column letters B-I-N-G-O of 75-ball Bingo with our own
+# labels (i.e. `column_labels = [\"Ç\", \"H\", \"O\", \"T\", \"T\"]` or the initials of our
+# sponsors). The labels are used for the column headers and for prefixing our numbers.
+#
+# The `gamma_correct_blending` parameter can be set to `true` if the edges of our numbers look too
+# dark or too thin. This is mostly noticeable with light text colors on light backgrounds.
+#
+# The `config_version` parameter is managed by Chotto and should not be changed. When a newer
+# Chotto changes its parameters it updates this file automatically on its next start (including
+# this description) and keeps the old file next to it as backup.
+#
+# Instead of this file Chotto also reads its parameters from a `draw_parameters.yaml` or
+# `draw_parameters.json` file which is useful if they are created by another program. The parameter
+# names are the same in all formats, i.e. `\"text_color_rgb\": [255, 128, 64]` in JSON.
+#
+# Single parameters can be overridden without editing this file via the command line like
+# `chotto.exe --set text_font_size=64` or via environment variables like `CHOTTO_TEXT_FONT_SIZE=64`.
+# Parameters of tables are written as `--set number_chip.radius_px=20` or
+# `CHOTTO_NUMBER_CHIP__RADIUS_PX=20`. The command line wins over environment variables.
+#
+# If we need slightly different parameters for different occasions (i.e. for different paper
+# sizes) we can add profiles at the very end of this file. Each profile only contains the parameters
+# that differ from the ones at the top and is selected via `chotto.exe --profile letter`:
+#
+# [profile.letter]
+# output_size_mm = [216, 279]
+# bingo_grid_pixel_location_left_top_right_bottom = [100, 400, 2450, 3100]
+#
+# The following optional parameters can be added to the bottom of this file if needed:
+#
+# The `text_color_rgba` parameter can be used instead of `text_color_rgb` if our numbers should be
+# partially transparent. The values are [Red, Green, Blue, Opacity] and each range between 0-255.
+# With the `text_gradient` parameter our numbers are colored with a gradient from top to bottom
+# (`\"vertical\"`) or from left to right (`\"horizontal\"`) instead. For example:
+#
+# text_gradient = { direction = \"vertical\", start_color_rgba = [255, 220, 0, 255], end_color_rgba = [255, 80, 0, 255] }
+#
+# The `header_text_gradient` parameter does the same for our column headers, extra texts and text
+# overlays. Texts that have their own `color` are not affected by it.
+#
+# With the `number_effects` and `header_text_effects` parameters our numbers and header texts can
+# get an inner shadow that makes them look cut into the paper and a bevel that makes them look
+# raised. Both effects are optional and all of their values have defaults. The `light_angle_degrees`
+# of the bevel counts counterclockwise from the right so `135` lets the light come from the top
+# left. `depth_px` and `blur_px` can be at most 100. For example:
+#
+# [number_effects.inner_shadow]
+# offset_x_y = [3, 3]
+# blur_px = 3
+# color_rgba = [0, 0, 0, 128]
+#
+# [number_effects.bevel]
+# depth_px = 3
+# light_angle_degrees = 135
+# highlight_color_rgba = [255, 255, 255, 160]
+# shadow_color_rgba = [0, 0, 0, 160]
+#
+# With `mode = \"raffle\"` Chotto creates sequentially numbered raffle tickets instead of Bingo
+# sheets. Our background then contains the design of a ticket together with its stub. Each ticket
+# gets its number at `serial_number_pixel_location_x_y` and the same number is drawn onto the stub
+# which is described by our `[ticket_stub]` section. Raffle tickets need no grid location and
+# their manifest lists no numbers. Texts, overlays, serial number schemes and `chotto.exe extend`
+# work the same as for Bingo sheets. For example:
+#
+# mode = \"raffle\"
+# number_of_sheets_to_generate = 500
+# serial_number_pixel_location_x_y = [900, 300]
+# serial_number_start = 1001
+#
+# [ticket_stub]
+# pixel_location_left_top_right_bottom = [1800, 0, 2480, 600]
+# event_name = \"Spring Raffle\"
+# perforation_side = \"left\"
+#
+# With `mode = \"puzzle\"` Chotto draws 9x9 puzzles like Sudoku that were made somewhere else
+# (i.e. for our newsletter) onto our background instead of Bingo sheets. Our bingo grid location
+# then describes the 9x9 grid of the puzzle and the givens are drawn like Bingo numbers. The
+# puzzles are read from the `puzzles_filepath` file which has one puzzle per line with its 81
+# cells row by row where empty cells are `0` or `.`, optionally followed by a comma and a serial
+# number for the puzzle. We get one sheet per puzzle in the order of the file. For example:
+#
+# mode = \"puzzle\"
+# puzzles_filepath = \"puzzles.csv\"
+#
+# with a `puzzles.csv` file like:
+#
+# 530070000600195000098000060800060003400803001700020006060000280000419005000080079,Week 12
+#
+# A `puzzles.json` file can be used instead which lists the givens of each puzzle row by row like
+# `{ \"puzzles\": [{ \"numbers\": [[5, 3, 0, 0, 7, 0, 0, 0, 0], ...], \"serial\": \"Week 12\" }] }`.
+#
+# With `mode = \"seating_cards\"` Chotto creates seating cards or table numbers for our event
+# instead of Bingo sheets. Each line of the `seating_cards_filepath` file (i.e. the name of a guest
+# or a table number) becomes one sheet where it is drawn centered into the region given by
+# `seating_card_text_pixel_location_left_top_right_bottom` with our text font, size and color.
+# Texts that don't fit into the region are shrunk. The bingo grid location is not needed and our
+# extra texts and overlays are drawn as usual. For example:
+#
+# mode = \"seating_cards\"
+# seating_cards_filepath = \"guests.txt\"
+# seating_card_text_pixel_location_left_top_right_bottom = [200, 600, 2280, 1200]
+#
+# With `mode = \"countdown\"` Chotto creates cards for a countdown or an advent calendar. Each
+# sheet shows one big number with our text font, size and color that counts up from 1 to our
+# `number_of_sheets_to_generate` (or down to 1 with `descending = true`, i.e. for the days until
+# our event). The `[countdown]` section gives the region of the number and optionally a caption
+# with its own region which is drawn with our header font. The caption and our extra texts can
+# contain the `{number}` placeholder. Numbers and captions that don't fit into their region are
+# shrunk. The bingo grid location is not needed. For example:
+#
+# mode = \"countdown\"
+# number_of_sheets_to_generate = 24
+#
+# [countdown]
+# number_pixel_location_left_top_right_bottom = [200, 400, 2280, 2400]
+# caption = \"Only {number} more days until Christmas!\"
+# caption_pixel_location_left_top_right_bottom = [200, 2600, 2280, 3000]
+#
+# The `bingo_variant` parameter defines which kind of Bingo we are playing. It can be one of the
+# following:
+#   \"75_ball\" - Each sheet has a 5x5 grid with the numbers 1-75 and a free space (default)
+#   \"90_ball\" - Each sheet is a book of six tickets stacked on top of each other that together
+#               contain every number from 1-90 exactly once. Each ticket has 3 rows and 9 columns
+#               with 5 numbers per row. The Bingo grid region is split into 18 rows where every
+#               3 rows form one ticket. The free space and column header parameters are ignored.
+#   \"80_ball\" - Each sheet has a 4x4 grid with the numbers 1-80 and no free space. The first
+#               column contains 1-20, the second 21-40 and so on. The columns are filled with the
+#               traditional red, yellow, blue and silver colors. The free space parameters are
+#               ignored and there are no column headers unless we give our own `column_headers`.
+#   \"30_ball\" - Each sheet has a 3x3 grid with the numbers 1-30 and no free space for short speed
+#               Bingo games. The first column contains 1-10, the second 11-20 and the third 21-30.
+#               The free space parameters are ignored and there are no column headers unless we
+#               give our own `column_headers`.
+#   \"loteria\" - Each sheet is a Lotería card with a 4x4 grid of images from a deck of 54 images.
+#               No card uses an image twice and each image appears on about the same number of
+#               cards.
+#   \"scavenger_hunt\" - Each sheet has a 5x5 grid of items from our categorized item lists and
+#               no free space.
+#   \"cell_layout\" - Each sheet has a grid of any size where we decide the content of each cell
+#               with our `cell_layout`.
+#
+# For Lotería we need to give the directory of our deck with `deck_images_directory` (i.e.
+# `deck_images_directory = \"deck\"`). It must contain exactly 54 images which are numbered in the
+# order of their filenames. The manifest uses these numbers instead of Bingo numbers. Each image is
+# scaled to fit into its grid cell while keeping its aspect ratio. Next to our sheets we also get a
+# `deck_call_order.csv` with a shuffled order of the deck for the caller that can be recreated
+# from the seed of our run.
+#
+# For scavenger hunts we list our items by category in a `[scavenger_hunt]` table. The `layout`
+# decides where the items go:
+#   \"random\"                   - Items of all categories can go anywhere (default). Each
+#                                category can have a `min_items_per_card` and `max_items_per_card`.
+#   \"one_per_category_per_row\" - Each row has one item of each category in a random order
+#   \"category_per_column\"      - Each column only has items of its own category
+# The last two layouts need exactly 5 categories with at least 5 items each. The items of all
+# categories are numbered from 1 in the given order and the manifest uses these numbers. Long items
+# are broken into several lines to fit into their grid cell. For example:
+#
+# [scavenger_hunt]
+# layout = \"one_per_category_per_row\"
+# [[scavenger_hunt.categories]]
+# name = \"Office\"
+# items = [\"Stapler\", \"Sticky note\", \"Coffee mug\", \"Whiteboard marker\", \"Plant\"]
+# [[scavenger_hunt.categories]]
+# name = \"People\"
+# items = [\"Someone who speaks 3 languages\", \"A new colleague\", \"Someone with a dog\", ...]
+# ...
+#
+# With a `cell_layout` we describe our grid row by row. A cell with the name of a pool in curly
+# braces gets a random entry of that pool from `cell_pools`, an empty cell stays empty and any other
+# text is drawn on every sheet as is (i.e. `FREE` or the name of a sponsor). A sheet never uses the
+# same pool entry twice. Fixed cells count as covered when checking claims. The entries of all pools
+# are numbered from 1 in the alphabetical order of the pool names and the manifest uses these
+# numbers. Instead of writing the layout into this file we can also give a CSV file without header
+# line via `cell_layout_filepath`. For example:
+#
+# bingo_variant = \"cell_layout\"
+# cell_layout = [
+#     [\"{drinks}\", \"{snacks}\", \"{drinks}\"],
+#     [\"{snacks}\", \"FREE\", \"{snacks}\"],
+#     [\"{drinks}\", \"{snacks}\", \"Sponsored by ACME\"],
+# ]
+# cell_pools = { drinks = [\"Coffee\", \"Tea\", \"Lemonade\", \"Water\"], snacks = [\"Cookie\", \"Apple\", \"Pretzel\", \"Muffin\", \"Chips\"] }
+#
+# The `excluded_numbers` parameter can be used to leave out numbers that should never appear on a
+# 75-ball sheet (i.e. `excluded_numbers = [13, 44]`). Each column then only uses its remaining
+# numbers and still needs at least 5 of them.
+#
+# The `forbidden_number_combinations` parameter prevents certain numbers from appearing together
+# on the same sheet. For example `forbidden_number_combinations = [[13, 66], [4, 44, 74]]` means
+# that no sheet contains both 13 and 66 and no sheet contains all of 4, 44 and 74.
+#
+# With `optimize = \"minimize_ties\"` we avoid that the rows, columns and diagonals of our sheets
+# share many numbers with each other. Then fewer games end with several players completing a line at
+# the same call. This makes generating large batches a bit slower. We can compare the tie rate of
+# two batches with `chotto.exe simulate`. The default is `optimize = \"none\"`.
+#
+# The `sheets_per_directory` parameter splits our sheets into subdirectories of the given size
+# (i.e. with `sheets_per_directory = 1000` the first 1000 sheets go into `output_sheets/000`, the
+# next 1000 into `output_sheets/001` and so on). This helps with programs that get slow when a
+# single directory contains many thousand files. Each subdirectory gets its own `manifest.json`.
+#
+# We can also give the number of `players` and the `cards_per_player` (i.e. `players = 120` and
+# `cards_per_player = 6`). Chotto then ignores `number_of_sheets_to_generate`, creates 720 sheets
+# and puts the sheets of each player into their own `player_001`, `player_002`, ... directory.
+# The `players.csv` file next to these directories lists the sheet numbers and serial numbers of
+# each player so that nobody has to collate the sheets by hand.
+#
+# By default the sheets are numbered in the order in which they were created. With
+# `shuffle_output_order = true` the sheet numbers (and therefore the filenames and the order in
+# which the sheets are printed) are shuffled while each card keeps its serial number. This way
+# sheets that lie next to each other after printing were not created one after another. The
+# `manifest.json` file tells which sheet got which serial number.
+#
+# The `png_compression` parameter trades the size of our PNG files against the time it takes to
+# write them. It can be `\"fast\"` for bigger files that are written a lot faster, `\"best\"` for
+# the smallest files or `\"default\"` for something in between.
+#
+# For print runs with many large sheets the cells of our grid can be drawn on the graphics card
+# with `compositing_backend = \"gpu\"`. This needs a version of Chotto that was built with GPU
+# support via `cargo build --release --features gpu`. Our texts are still drawn as usual. If the
+# graphics card cannot draw our cells exactly like the CPU does (i.e. with
+# `gamma_correct_blending = true`) Chotto draws them on the CPU instead.
+#
+# With `output_mode = \"deltas\"` Chotto does not write full sheets. Instead it writes our
+# background once as `background.png` and a small `deltas/sheet_1.json`, ... file for each sheet
+# that lists its numbers and their pixel locations. This is a lot faster to copy to a remote print
+# shop. The full sheets can then be drawn where they are needed by running
+# `chotto.exe render [--from 1] [--to 100]` next to the same `draw_parameters.txt`.
+#
+# The `post_process_command` parameter runs an external program for each written sheet, i.e. to
+# convert it with ImageMagick or to upload it somewhere. The placeholders `{path}`, `{filename}`,
+# `{directory}`, `{card_id}` and `{sheet_number}` are replaced with the values of each sheet. The
+# command runs in the command prompt and Chotto puts quotes around each value itself so that paths
+# with spaces or special characters work as they are. For example:
+#
+# post_process_command = \"magick {path} -resize 1240x1754 {directory}/small_{filename}\"
+#
+# By default 4 commands run at the same time which can be changed via `post_process_parallelism`.
+# If any of the commands fails Chotto shows which sheets were affected once all sheets are done.
+#
+# The `free_space_text` parameter defines a text that is drawn into the free space in the center of
+# the grid (i.e. `free_space_text = \"FREE\"`).
+#
+# The `free_space_cells_x_y` parameter moves the free space away from the center of the grid. The
+# values are a list of [Column, Row] pairs starting at 0 from the top left cell of the grid. This
+# way we can have several free spaces (i.e. `free_space_cells_x_y = [[1, 1], [3, 3]]`) or none at
+# all (`free_space_cells_x_y = []`).
+#
+# The `free_space_images_directory` parameter can be used to draw a different image into the free
+# space of each sheet. Chotto picks one image of the given directory for each sheet, either at
+# random (`free_space_image_selection = \"random\"`) or one after another
+# (`free_space_image_selection = \"round_robin\"`). The images are resized to fit into their
+# grid cell. Which sheet got which image is written down in the `manifest.json` file next to our
+# sheets.
+#
+# The `number_chip` parameter draws a filled circle or rounded rectangle behind each number which
+# makes the numbers easier to read on photo backgrounds. The `color_rgba` is [Red, Green, Blue,
+# Opacity]. The optional `radius_px` is the radius of the circle or the corner radius of the
+# rounded rectangle and `padding_px` is the space between the rounded rectangle and its grid cell.
+# For example:
+#
+# number_chip = { shape = \"circle\", color_rgba = [255, 255, 255, 200], radius_px = 40 }
+# number_chip = { shape = \"rounded_rect\", color_rgba = [0, 0, 0, 128], padding_px = 8 }
+#
+# Before creating our sheets Chotto checks for each grid cell whether our numbers stand out enough
+# from the background and warns us if they do not. The `minimum_number_contrast` parameter sets the
+# required contrast ratio which goes from 1 (no contrast) to 21 (black on white) and defaults to 3.
+# With `auto_number_outline = true` Chotto instead draws a black or white outline around the
+# numbers whenever they would be hard to read.
+#
+# With `braille_captions = true` Chotto writes each number in Braille below the number itself so
+# that the sheets can be embossed for blind players afterwards. By default the Braille dots are
+# drawn as small circles whose radius can be set with `braille_dot_radius_px`. Alternatively we can
+# give a Braille font via `braille_font_filepath`.
+#
+# With `number_nicknames = true` Chotto writes the nickname of each number that has one in small
+# text below the number itself (i.e. \"two little ducks\" below 22). By default these are the
+# traditional calls of British bingo halls. To change them we run `chotto.exe export-nicknames`
+# which writes them to a `nicknames.csv` file with a `number` and a `nickname` column. After editing
+# it we use it via `nicknames_filepath = \"nicknames.csv\"`. Numbers without nickname are left out
+# or have an empty nickname. The size of the nicknames can be set with `nickname_font_size` and
+# defaults to a tenth of the height of a grid cell. Long nicknames are broken into several lines.
+# Nicknames cannot be used together with `braille_captions`. The caller window of
+# `chotto.exe caller` also shows the nickname of the last number if this is set.
+#
+# The `bold_text = true` parameter makes all texts thicker which is useful for fonts that have no
+# bold variant.
+#
+# For games with visually impaired players we can set `preset = \"large_print\"`. This makes the
+# numbers as big as their grid cells allow, draws all texts bold and adds an outline to the numbers
+# wherever their contrast to the background is below 7:1 which is the highest level recommended by
+# the Web Content Accessibility Guidelines (WCAG). The preset overrides our own values for these
+# parameters only where they would be less accessible.
+#
+# The `output_rotation` parameter rotates our final sheets clockwise by 90, 180 or 270 degrees and
+# the `output_mirror` parameter flips them (`\"horizontal\"` or `\"vertical\"`). This can be
+# needed when printing the back sides of duplex sheets. Note that all pixel locations still refer
+# to our unrotated background image.
+#
+# Thermal printers and risographs often mangle colored images. For those we can set the
+# `output_color_profile` parameter to one of the following:
+#   \"color\"      - Our sheets as they are (default)
+#   \"grayscale\"  - Shades of grey on white paper
+#   \"monochrome\" - Only black and white pixels where all shades of grey are dithered
+# Both write small PNG files without transparency, where monochrome sheets use only one bit per
+# pixel. They therefore cannot be used together with the `corner_radius_px` and
+# `mask_image_filepath` parameters for die-cut sheets. Thermal printers usually print 203 dpi
+# on paper rolls of 58mm (384 pixels) or 80mm (576 pixels) width, so we also want to set i.e.
+# `output_width_px = 576` to let Chotto resize our sheets to fit.
+#
+# The `column_headers_pixel_location_top_bottom` parameter enables drawing the column letters
+# B-I-N-G-O above their respective columns. The values are [Top, Bottom] in pixels and define the
+# vertical region in the image where the letters will be drawn to. The `column_headers` parameter
+# replaces the letters with our own texts, one for each column (i.e.
+# `column_headers = [\"א\", \"ב\", \"ג\", \"ד\", \"ה\"]`). This also works for 90-ball Bingo which
+# has no column letters by default. With `vertical_column_headers = true` the characters of each
+# column header are stacked from top to bottom which fits longer headers above narrow columns.
+#
+# Texts that are not plain English like the free space text, column headers or extra texts are
+# laid out with proper shaping so that right-to-left scripts like Hebrew or Arabic are drawn
+# correctly. Chotto guesses the direction of each text from its script. If that goes wrong we can
+# set `text_direction = \"right_to_left\"` or `text_direction = \"left_to_right\"`.
+#
+# Emoji are drawn in color if the header font is a color emoji font that contains its emoji as
+# images (like `Noto Color Emoji`). This way we can i.e. put a picture into the free space with
+# `free_space_text = \"🍀\"` and `header_font_filepath = \"NotoColorEmoji.ttf\"`.
+#
+# The `bonus_cells_x_y` parameter marks grid cells as bonus cells which pay extra when daubed. The
+# values are a list of [Column, Row] pairs starting at 0 from the top left cell of the grid (i.e.
+# `bonus_cells_x_y = [[0, 0], [4, 4]]`). With `random_bonus_cell_count = 1` Chotto instead picks
+# the given number of random cells on each sheet. Bonus cells are tinted with the
+# `bonus_cell_color_rgba` color which is [Red, Green, Blue, Opacity] and defaults to a transparent
+# gold. The `bonus_cell_image_filepath` parameter can additionally draw an image like a star behind
+# the number of each bonus cell. Which cells of a sheet are bonus cells is written down in the
+# `manifest.json` file next to our sheets.
+#
+# The `lucky_numbers` parameter picks numbers for a side prize (i.e. `lucky_numbers = [7, 42]`).
+# Wherever a lucky number appears on a sheet it is drawn in the `lucky_number_color_rgb` color
+# and/or gets a small star in the corner of its cell with `lucky_number_star = true`. The lucky
+# numbers of each sheet are written down in the `manifest.json` file next to our sheets.
+#
+# The `accent_palette` parameter gives our cards some variety. Each sheet randomly picks one of
+# the given colors for its column headers, free space text, serial number and extra texts without
+# their own `color`. With `accent_palette_numbers = true` the numbers get the color as well. The
+# colors are spread evenly over our sheets and the color of each sheet is written down in the
+# `manifest.json` file next to our sheets so that we can call i.e. \"all blue cards\" for a bonus
+# round. For example:
+#
+# accent_palette = [{ name = \"blue\", color_rgb = [0, 90, 200] }, { name = \"red\", color_rgb = [200, 30, 30] }]
+#
+# The `serial_number_pixel_location_x_y` parameter enables drawing a serial number (i.e. `#17`) on
+# each sheet. The values are [X, Y] in pixels and define the center of the serial number text. The
+# serial number matches the number in the filename of the sheet by default.
+#
+# If players should not be able to guess how many sheets we printed or which other serial numbers
+# exist we can change the `serial_number_scheme` parameter. It can be one of the following:
+#   \"sequential\" - Counts up from `serial_number_start` (default 1) in steps of
+#                  `serial_number_stride` (default 1), i.e. 1000, 1007, 1014, ... (default)
+#   \"random\"     - Unique random IDs with `serial_number_length` characters (default 8),
+#                  i.e. `K7QXM2ZD`
+#   \"keyed\"      - The sequential serial number scrambled with our own secret
+#                  `serial_number_salt` into `serial_number_length` characters (default 8, at
+#                  most 12). Serial numbers of neighbouring sheets look unrelated and can't be
+#                  guessed without the salt, but they never repeat.
+# The serial numbers are also used for the `{card_id}` placeholder and barcodes. Which sheet got
+# which serial number is written down in the `manifest.json` file next to our sheets.
+#
+# The `security_code_secret_key` and `security_code_pixel_location_x_y` parameters enable drawing
+# a short security code (i.e. `K7QX-M2ZD`) at the given [X, Y] pixel location of each sheet. The
+# code is derived from the numbers of the sheet and our secret key so that nobody can create valid
+# sheets without knowing the key. We should therefore choose a long random key and keep it to
+# ourselves. The code also depends on where each number is on the sheet so that nobody can move
+# the numbers of a genuine sheet around. At the event we can then check a winning sheet by running
+# `chotto.exe verify-code --code K7QX-M2ZD --numbers 3,17,0,44,61/5,22,...` with all numbers of the
+# sheet row by row from left to right. The rows are separated by `/` and empty cells and the free
+# space are `0`. Alternatively `--sheet 17` checks the code against the numbers of sheet 17 in our
+# `manifest.json`.
+#
+# If the serial number or security code of a card is damaged we can still find the card in our
+# batch by taking a photo of it and running `chotto.exe scan card_photo.jpg`. Chotto then reads the
+# numbers of the card and tells us which of our cards it is. The photo should be taken straight
+# from above with the whole grid in view. We can pass `--directory <dir>` if our sheets are not in
+# the default output directory.
+#
+# The `barcode_pixel_location_x_y` parameter enables drawing a Code128 barcode of the serial number
+# centered at the given [X, Y] pixel location of each sheet. This way handheld barcode scanners can
+# register our sheets. The optional `barcode_module_width_px` parameter defines the width of the
+# thinnest bar (default 2) and `barcode_height_px` the height of the barcode (default half a grid
+# cell). If scanners have trouble reading the barcode we can try increasing the module width.
+#
+# The `header_font_filepath`, `header_text_font_size` and `header_text_color_rgb` parameters can
+# be used to draw the free space text, column headers and serial numbers with a different font, size
+# and color than the Bingo numbers (i.e. `header_font_filepath = \"fonts/fancy.ttf\"`). If not
+# given the font, size and color of the Bingo numbers are used.
+#
+# The `cmyk_tiff_icc_profile_filepath` parameter makes Chotto write CMYK TIFF files instead of PNG
+# files. The colors are converted using the given ICC profile which is also embedded into the
+# TIFF files (i.e. `cmyk_tiff_icc_profile_filepath = \"ISOcoated_v2_eci.icc\"`). Our print shop
+# can usually tell us which profile they want us to use.
+#
+# The `bleed_px` parameter extends our image on all sides by the given amount of pixels for printing
+# with bleed. By default the bleed is filled by mirroring the edges of our image. If we want the
+# bleed to be filled with a solid color instead we can add the `bleed_color_rgb` parameter.
+# Setting `crop_marks = true` additionally adds a white margin with crop marks and registration
+# marks around the bleed. Note that all other pixel locations are still given relative to our
+# original image.
+#
+# For die-cut cards we can cut out the shape of our sheets. The `corner_radius_px` parameter makes
+# the corners of our sheets rounded with the given radius in pixels. The `mask_image_filepath`
+# parameter uses a PNG image as mask where transparent (or black) pixels are cut out of our sheets
+# and opaque (or white) pixels are kept (i.e. `mask_image_filepath = \"die_cut.png\"`). The mask
+# is stretched to the size of our sheets if needed. Both can be used together and are applied to
+# the whole sheet including the bleed. The cut out parts stay transparent in our PNG files, which is
+# why cut out sheets cannot be written as CMYK TIFF files or have crop marks.
+#
+# The `overlay_image_filepath` parameter can be used to draw an additional (transparent) PNG image
+# like a sponsor logo on top of each sheet (i.e. `overlay_image_filepath = \"logos/sponsor.png\"`).
+# The `overlay_pixel_location_x_y` parameter defines the center of the overlay and defaults to the
+# center of our image. The optional `overlay_scale` and `overlay_opacity` parameters can be used to
+# resize the overlay (i.e. `0.5` for half its size) or make it more transparent (i.e. `0.3`).
+#
+# The `extra_texts` parameter can be used to draw additional texts onto each sheet. Each text is
+# centered at the given [X, Y] pixel location and is drawn with the header font. The `size` and
+# `color` values are optional. Texts can contain placeholders in curly braces which are replaced
+# with the current date `{date}` or the serial number of the sheet `{card_id}`. For example:
+#
+# extra_texts = [{ text = \"Game Night {date} - Card {card_id}\", x = 50, y = 90, size = 12 }]
+#
+# We can also define our own placeholders by adding a `[text_variables]` section at the very end
+# of this file. Our own placeholders can also override `{date}` (i.e. with the date of our event):
+#
+# [text_variables]
+# date = \"May 11th\"
+# location = \"Community Hall\"
+#
+# For events that combine Bingo with a raffle we can add a `[ticket_stub]` section at the very end
+# of this file. It draws a stub with the name of our event, the serial number of the sheet and a
+# blank line for the name of the player into the given region of each sheet. A dashed line on the
+# `perforation_side` (`\"top\"` by default, or `\"left\"`, `\"right\"`, `\"bottom\"`) marks where
+# the stub is torn off. The `event_name` can contain the same placeholders as our `extra_texts`.
+# The `name_label`, `line_width_px`, `size` and `color` are optional. For example:
+#
+# [ticket_stub]
+# pixel_location_left_top_right_bottom = [100, 3150, 2380, 3450]
+# event_name = \"Spring Raffle {date}\"
+# name_label = \"Name:\"
+# perforation_side = \"top\"
+#
+# If we want to create personalized sheets for a list of people we can place a `recipients.csv`
+# file next to `chotto.exe`. Chotto then creates exactly one sheet for each row of that file instead
+# of using `number_of_sheets_to_generate`. The first line of the file must contain the column names
+# which can then be used as placeholders in our `extra_texts`. For example with a file like:
+#
+#   name,table
+#   Jane Doe,12
+#   John Doe,7
+#
+# we can write `extra_texts = [{ text = \"{name} - Table {table}\", x = 50, y = 10 }]`.
+#
+# If our `recipients.csv` file has an `email` column we can also send each recipient their sheet
+# via email by running `chotto.exe email` after the sheets were created. For this we need to add
+# an `[email]` section at the very end of this file with the details of our email account. The
+# password of the account is not stored in this file but read from the `CHOTTO_SMTP_PASSWORD`
+# environment variable. The subject and body can contain the same placeholders as `extra_texts`:
+#
+# [email]
+# smtp_server = \"smtp.example.com\"
+# smtp_username = \"bingo@example.com\"
+# sender_address = \"Bingo Night <bingo@example.com>\"
+# subject = \"Your Bingo-sheet for {date}\"
+# body = \"Hi {name}, attached is your Bingo-sheet. See you at table {table}!\"
+#
+# If the point-of-sale software of our hall should sell and validate our sheets we can run
+# `chotto.exe export-pos` after the sheets were created. This writes a `pos_export.csv` next to our
+# sheets with the columns given in a `[pos_export]` section at the very end of this file. Possible
+# columns are `\"sheet_number\"`, `\"serial\"`, `\"numbers\"`, `\"price_tier\"`, `\"price\"` and
+# `\"security_code\"`. With `chotto.exe export-pos --post` each sheet is additionally sent as JSON
+# to the given `post_url`. An access token for it can be given via the `CHOTTO_POS_TOKEN`
+# environment variable:
+#
+# [pos_export]
+# columns = [\"serial\", \"numbers\", \"price_tier\", \"price\"]
+# default_price_tier = \"standard\"
+# price_tiers = [{ name = \"early_bird\", price = \"2.50\", first_sheet = 1, last_sheet = 100 }]
+# post_url = \"https://pos.example.com/api/cards\"
+#
+# If our computer has too little disk space for all sheets we can upload them to an S3-compatible
+# storage bucket (i.e. AWS S3, MinIO or Backblaze B2) by adding an `[s3_upload]` section at the
+# very end of this file. Each sheet is then uploaded right after it was written and removed from
+# our disk afterwards unless we set `keep_local_files = true`. Our manifest, run manifest, report
+# and thumbnails are uploaded once the run is finished and also stay on our disk. The report then
+# links to the uploaded sheets. Failed uploads are retried a few times and at the end Chotto checks
+# that each upload arrived completely. The `post_process_command` needs `keep_local_files = true`
+# as it works on the sheets of our disk. The credentials are read from the
+# `CHOTTO_S3_ACCESS_KEY_ID` and `CHOTTO_S3_SECRET_ACCESS_KEY` environment variables:
+#
+# [s3_upload]
+# endpoint = \"https://s3.eu-central-1.amazonaws.com\"
+# bucket = \"bingo-sheets\"
+# prefix = \"game_night/\"
+# region = \"eu-central-1\"
+#
+# The caller window of `chotto.exe caller` can announce each drawn number via the text-to-speech of
+# our computer by adding a `[caller_speech]` section at the very end of this file. The `phrase`
+# can use `{letter}`, `{number}` and `{nickname}` and defaults to `\"{letter}... {number}\"` for
+# 75-ball Bingo (i.e. \"B... 11\") and `\"{number}\"` otherwise. With
+# `traditional_nicknames = true` numbers with a nickname are announced with the `nickname_phrase`
+# (i.e. \"two little ducks, 22\"). The nicknames are the traditional calls or the ones of our
+# `nicknames_filepath` (see `number_nicknames` above). More nicknames can be added by number and
+# replace the ones of the table. The `voice` must be the name of a voice that is
+# installed on our computer:
+#
+# [caller_speech]
+# voice = \"Microsoft Zira Desktop\"
+# phrase = \"{letter}... {number}\"
+# nickname_phrase = \"{nickname}, {number}\"
+# traditional_nicknames = true
+# nicknames = { 7 = \"lucky number seven\", 42 = \"the answer to everything\" }
+#
+# For anything else that we want on our sheets (i.e. several logos, a rotated \"VOID\" stamp or the
+# date of our event) we can add `[[overlays]]` elements at the very end of this file. Each element
+# is either an `image_filepath` of a (transparent) PNG image or a `text` which can use the same
+# placeholders as `extra_texts`. The element is placed with its `anchor` point at the given [X, Y]
+# pixel location. The `anchor` can be one of `\"top_left\"`, `\"top\"`, `\"top_right\"`, `\"left\"`,
+# `\"center\"` (default), `\"right\"`, `\"bottom_left\"`, `\"bottom\"` or `\"bottom_right\"`. The
+# optional `rotation` turns the element clockwise by the given degrees around its anchor point and
+# `opacity` makes it more transparent (i.e. `0.3`). Images can be resized via `scale` and texts can
+# have their own `size` and `color`. All elements are drawn on top of everything else and elements
+# with a higher `z` (default 0) are drawn on top of the ones with a lower `z`. For example:
+#
+# [[overlays]]
+# image_filepath = \"logos/sponsor.png\"
+# x = 40
+# y = 40
+# anchor = \"top_left\"
+# scale = 0.5
+#
+# [[overlays]]
+# text = \"Game Night {date}\"
+# x = 500
+# y = 680
+# anchor = \"bottom\"
+# rotation = -5.0
+# opacity = 0.8
+# z = 1
+#
+####################################################################################################";
+
+struct Input {
+    background_image_filepath: String,
+    font_filepath: String,
+    background_bitmap: Bitmap,
+    font: LoadedFont,
+    /// Identifies the number font file for caching
+    font_hash: u64,
+    header_font: Option<LoadedFont>,
+    params: DrawParams,
+    /// Contains the values of each row of the recipients file by their column name
+    recipients: Option<Vec<HashMap<String, String>>>,
+}
+
+impl Input {
+    fn new() -> Input {
+        let params_from_file = if find_draw_params_file().is_some() {
+            Some(load_draw_params())
+        } else {
+            None
+        };
+
+        // NOTE: Files that are explicitly referenced in the draw parameters or that were created
+        //       by us are not taken into account when searching for the background image and
+        //       number font
+        let mut referenced_filepaths = params_from_file
+            .as_ref()
+            .map(|params| params.referenced_filepaths())
+            .unwrap_or_default();
+        referenced_filepaths.push(DRY_RUN_PREVIEW_FILENAME.to_owned());
+        let files: Vec<String> = collect_files(".")
+            .into_iter()
+            .filter(|filepath| {
+                !referenced_filepaths.iter().any(|referenced_filepath| {
+                    paths_point_to_same_file(filepath, referenced_filepath)
+                })
+            })
+            .collect();
+        let background_image_filepath = params_from_file
+            .as_ref()
+            .and_then(|params| params.background_image_filepath.clone())
+            .or_else(|| find_single_file_with_extension(&files, BACKGROUND_IMAGE_EXTENSIONS));
+        let font_filepath = params_from_file
+            .as_ref()
+            .and_then(|params| params.font_filepath.clone())
+            .or_else(|| find_single_file_with_extension(&files, &[".ttf"]));
+        if background_image_filepath.is_none() || font_filepath.is_none() {
+            show_messagebox(
+                "Chotto",
+                &format!(
+                    "Please place exactly one image file (PNG, JPEG, BMP or TGA) and one TTF file into the directory where `chotto.exe` is located and then restart Chotto. Alternatively we can set the `background_image_filepath` and `font_filepath` parameters in '{}' or run `chotto.exe gallery` to pick one of our built-in designs.",
+                    DRAW_PARAMETERS_FILENAME
+                ),
+                false,
+            );
+            std::process::abort();
+        }
+
+        let background_image_filepath = background_image_filepath.unwrap();
+        let target_size = |width, height| {
+            params_from_file
+                .as_ref()
+                .and_then(|params| target_background_size(params, width, height))
+        };
+        // NOTE: Very large scans are shrunk to the size of our sheets while they are decoded so
+        //       that they fit into memory
+        let mut background_bitmap =
+            match large_background::large_png_size(&background_image_filepath) {
+                Some((width, height)) => large_background::load_png_memory_mapped(
+                    &background_image_filepath,
+                    target_size(width, height),
+                ),
+                None => load_background_image(&background_image_filepath),
+            };
+        assert!(
+            background_bitmap.width != 0 && background_bitmap.height != 0,
+            "Image file '{}' is 0x0 pixels which is not allowed - is the file ok?",
+            background_image_filepath
+        );
+        log::info!(
+            "Using background image '{}' with {}x{}px",
+            background_image_filepath,
+            background_bitmap.width,
+            background_bitmap.height
+        );
+        let background_size = (
+            background_bitmap.width as u32,
+            background_bitmap.height as u32,
+        );
+        if let Some((width, height)) = target_size(background_size.0, background_size.1) {
+            if (width, height) != background_size {
+                background_bitmap = resize_background_image(&background_bitmap, width, height);
+                log::info!("Resized background image to {}x{}px", width, height);
+            }
+        }
+
+        let font_filepath = font_filepath.unwrap();
+        let font = load_font(&font_filepath);
+        let font_hash = bitmap_cache::hash_of(&font.data);
+        log::info!("Using number font '{}'", font_filepath);
+
+        if params_from_file.is_none() {
+            let params = default_draw_params(
+                background_bitmap.width as u32,
+                background_bitmap.height as u32,
+            );
+            let params_string = format!(
+                "{}\n\n{}",
+                TOML_DOCUMENTATION_HEADER,
+                draw_params_to_toml_string(&params)
+            );
+            std::fs::write(DRAW_PARAMETERS_FILENAME, &params_string).expect(&format!(
+                "Could not create file '{}'",
+                DRAW_PARAMETERS_FILENAME
+            ));
+            show_messagebox(
+                "Chotto",
+                &format!(
+                    "Please first fill out the '{}' in the directory where 'chotto.exe' is located and then restart Chotto",
+                    DRAW_PARAMETERS_FILENAME
+                ),
+                false,
+            );
+            std::process::abort();
+        }
+
+        let mut params = params_from_file.unwrap();
+        if let Some(preset) = params.preset {
+            params.apply_preset(preset);
+            log::info!("Using the {:?} preset", preset);
+        }
+        log::debug!("Using draw parameters {:#?}", params);
+        let header_font = params
+            .header_font_filepath
+            .as_ref()
+            .map(|filepath| load_font(filepath));
+
+        assert!(
+            params.sheet_count() <= MAX_SHEET_COUNT,
+            "The maximum sheet count is {} - please reduce it in '{}'!",
+            MAX_SHEET_COUNT,
+            DRAW_PARAMETERS_FILENAME
+        );
+
+        assert!(
+            params.sheets_per_directory != Some(0),
+            "The `sheets_per_directory` parameter must be at least 1 - please fix it in '{}'!",
+            DRAW_PARAMETERS_FILENAME
+        );
+
+        assert!(
+            params.players.is_some() == params.cards_per_player.is_some(),
+            "The `players` and `cards_per_player` parameters must be given together - please fix them in '{}'!",
+            DRAW_PARAMETERS_FILENAME
+        );
+        assert!(
+            params.players != Some(0) && params.cards_per_player != Some(0),
+            "The `players` and `cards_per_player` parameters must be at least 1 - please fix them in '{}'!",
+            DRAW_PARAMETERS_FILENAME
+        );
+        assert!(
+            params.players.is_none() || params.sheets_per_directory.is_none(),
+            "The `players` parameter already puts the sheets of each player into their own directory - please remove `sheets_per_directory` from '{}'!",
+            DRAW_PARAMETERS_FILENAME
+        );
+
+        assert!(
+            [0, 90, 180, 270].contains(&params.output_rotation),
+            "The `output_rotation` parameter must be 0, 90, 180 or 270 - please fix it in '{}'!",
+            DRAW_PARAMETERS_FILENAME
+        );
+
+        let (column_count, row_count) = params.grid_size();
+        if let Some(column_labels) = &params.column_labels {
+            assert!(
+                column_labels.len() == COLUMN_LETTERS.len()
+                    && column_labels.iter().all(|label| !label.is_empty()),
+                "The `column_labels` parameter must contain exactly {} non-empty labels - please fix it in '{}'!",
+                COLUMN_LETTERS.len(),
+                DRAW_PARAMETERS_FILENAME
+            );
+        }
+        if let Some(column_headers) = &params.column_headers {
+            assert!(
+                column_headers.len() == column_count as usize,
+                "The `column_headers` parameter must contain exactly {} texts - please fix it in '{}'!",
+                column_count,
+                DRAW_PARAMETERS_FILENAME
+            );
+        }
+        for (x, y) in params.free_space_cells() {
+            assert!(
+                x < column_count && y < row_count,
+                "The free space cell [{}, {}] is outside of the {}x{} grid - please fix it in '{}'!",
+                x,
+                y,
+                column_count,
+                row_count,
+                DRAW_PARAMETERS_FILENAME
+            );
+        }
+
+        for &lucky_number in &params.lucky_numbers {
+            assert!(
+                1 <= lucky_number
+                    && lucky_number <= params.max_number()
+                    && !params.excluded_numbers.contains(&lucky_number),
+                "The lucky number {} can never appear on a sheet - please fix `lucky_numbers` in '{}'!",
+                lucky_number,
+                DRAW_PARAMETERS_FILENAME
+            );
+        }
+
+        if params.bingo_variant == BingoVariant::CellLayout {
+            // NOTE: This tells the user to add a layout if there is none
+            params.grid_size();
+            cell_layout_params::validate(params.cell_layout.as_ref().unwrap(), &params.cell_pools);
+        }
+
+        if params.bingo_variant == BingoVariant::ScavengerHunt {
+            params
+                .scavenger_hunt
+                .as_ref()
+                .unwrap_or_else(|| {
+                    panic!(
+                        "Scavenger hunt cards need a list of items - please add a \
+                         `[scavenger_hunt]` table to '{}'!",
+                        DRAW_PARAMETERS_FILENAME
+                    )
+                })
+                .validate();
+        }
+
+        if params.bingo_variant == BingoVariant::Loteria {
+            let directory = params.deck_images_directory.as_ref().unwrap_or_else(|| {
+                panic!(
+                    "Lotería cards need the images of our deck - please add \
+                     `deck_images_directory` to '{}'!",
+                    DRAW_PARAMETERS_FILENAME
+                )
+            });
+            deck::deck_image_filepaths(directory);
+        }
+
+        if params.corner_radius_px.is_some() || params.mask_image_filepath.is_some() {
+            assert!(
+                params.cmyk_tiff_icc_profile_filepath.is_none() && !params.crop_marks,
+                "The `corner_radius_px` and `mask_image_filepath` parameters cannot be used \
+                 together with `cmyk_tiff_icc_profile_filepath` or `crop_marks` - please remove \
+                 one of them from '{}'!",
+                DRAW_PARAMETERS_FILENAME
+            );
+        }
+
+        if params.output_color_profile != output_color::OutputColorProfile::Color {
+            assert!(
+                params.cmyk_tiff_icc_profile_filepath.is_none(),
+                "The `output_color_profile` parameter cannot be used together with \
+                 `cmyk_tiff_icc_profile_filepath` - please remove one of them from '{}'!",
+                DRAW_PARAMETERS_FILENAME
+            );
+            // NOTE: Our greyscale and monochrome PNG files have no alpha channel that could keep
+            //       the cut out parts transparent
+            assert!(
+                params.corner_radius_px.is_none() && params.mask_image_filepath.is_none(),
+                "The `output_color_profile` parameter cannot be used together with \
+                 `corner_radius_px` or `mask_image_filepath` - please remove one of them from \
+                 '{}'!",
+                DRAW_PARAMETERS_FILENAME
+            );
+        }
+
+        if params.number_nicknames {
+            assert!(
+                !params.braille_captions,
+                "The `number_nicknames` parameter cannot be used together with `braille_captions` \
+                 as both are drawn below the numbers - please remove one of them from '{}'!",
+                DRAW_PARAMETERS_FILENAME
+            );
+            assert!(
+                matches!(
+                    params.bingo_variant,
+                    BingoVariant::Ball75
+                        | BingoVariant::Ball90
+                        | BingoVariant::Ball80
+                        | BingoVariant::Ball30
+                ),
+                "The `number_nicknames` parameter can only be used with Bingo variants that have \
+                 numbers - please remove it from '{}'!",
+                DRAW_PARAMETERS_FILENAME
+            );
+            nicknames::load_nicknames(params.nicknames_filepath.as_deref(), params.max_number());
+        }
+
+        if !params.excluded_numbers.is_empty() {
+            assert!(
+                params.bingo_variant == BingoVariant::Ball75,
+                "The `excluded_numbers` parameter can only be used with 75-ball Bingo - please \
+                 remove it from '{}'!",
+                DRAW_PARAMETERS_FILENAME
+            );
+            for (column, numbers) in generator::column_numbers_75_ball(&params.excluded_numbers)
+                .iter()
+                .enumerate()
+            {
+                assert!(
+                    numbers.len() >= 5,
+                    "Column {} only has {} numbers left after excluding {:?} but needs at least 5 \
+                     - please exclude fewer numbers in '{}'!",
+                    params.column_labels()[column],
+                    numbers.len(),
+                    params.excluded_numbers,
+                    DRAW_PARAMETERS_FILENAME
+                );
+            }
+            log::info!("Excluding numbers {:?}", params.excluded_numbers);
+        }
+
+        let recipients = if path_exists(RECIPIENTS_FILENAME) {
+            Some(load_recipients())
+        } else {
+            None
+        };
+        if let Some(recipients) = &recipients {
+            log::info!(
+                "Using {} recipients from '{}'",
+                recipients.len(),
+                RECIPIENTS_FILENAME
+            );
+            assert!(
+                recipients.len() <= MAX_SHEET_COUNT,
+                "The maximum sheet count is {} - please reduce the number of rows in '{}'!",
+                MAX_SHEET_COUNT,
+                RECIPIENTS_FILENAME
+            );
+        }
+
+        Input {
+            background_image_filepath,
+            font_filepath,
+            background_bitmap,
+            font,
+            font_hash,
+            header_font,
+            params,
+            recipients,
+        }
+    }
+}
+
+/// Returns the file with one of the given extensions if there is exactly one such file
+fn find_single_file_with_extension(filepaths: &[String], extensions: &[&str]) -> Option<String> {
+    let matching_filepaths: Vec<&String> = filepaths
+        .iter()
+        .filter(|filepath| {
+            extensions
+                .iter()
+                .any(|extension| filepath.to_lowercase().ends_with(extension))
+        })
+        .collect();
+    if matching_filepaths.len() == 1 {
+        Some(matching_filepaths[0].clone())
+    } else {
+        None
+    }
+}
+
+fn load_background_image(filepath: &str) -> Bitmap {
+    if filepath.to_lowercase().ends_with(".png") {
+        return Bitmap::from_png_file_or_panic(filepath);
+    }
+
+    let image = image::open(filepath)
+        .unwrap_or_else(|error| {
+            panic!(
+                "Cannot decode image file '{}' - is the file ok?: {}",
+                filepath, error
+            )
+        })
+        .to_rgba8();
+    bitmap_from_rgba_image(&image)
+}
+
+fn bitmap_from_rgba_image(image: &image::RgbaImage) -> Bitmap {
+    let mut bitmap = Bitmap::new(image.width(), image.height());
+    for (pixel, image_pixel) in bitmap.data.iter_mut().zip(image.pixels()) {
+        let [r, g, b, a] = image_pixel.0;
+        *pixel = PixelRGBA::new(r, g, b, a);
+    }
+    bitmap
+}
+
+fn bitmap_to_rgba_image(bitmap: &Bitmap) -> image::RgbaImage {
+    let pixels: Vec<u8> = bitmap
+        .data
+        .iter()
+        .flat_map(|pixel| [pixel.r, pixel.g, pixel.b, pixel.a])
+        .collect();
+    image::RgbaImage::from_raw(bitmap.width as u32, bitmap.height as u32, pixels).unwrap()
+}
+
+/// Returns the size that our background image should be resized to before drawing if any
+fn target_background_size(params: &DrawParams, width: u32, height: u32) -> Option<(u32, u32)> {
+    const MILLIMETERS_PER_INCH: f32 = 25.4;
+    let (target_width, target_height) = match (params.output_dpi, params.output_size_mm) {
+        (Some(dpi), Some((width_mm, height_mm))) => (
+            Some((width_mm / MILLIMETERS_PER_INCH * dpi as f32).round() as u32),
+            Some((height_mm / MILLIMETERS_PER_INCH * dpi as f32).round() as u32),
+        ),
+        (None, None) => (params.output_width_px, params.output_height_px),
+        _ => panic!(
+            "The `output_dpi` and `output_size_mm` parameters must always be given together - please fix it in '{}'!",
+            DRAW_PARAMETERS_FILENAME
+        ),
+    };
+
+    // NOTE: If only one side is given we keep the aspect ratio of our background image
+    let aspect_ratio = width as f32 / height as f32;
+    match (target_width, target_height) {
+        (None, None) => None,
+        (Some(target_width), Some(target_height)) => Some((target_width, target_height)),
+        (Some(target_width), None) => Some((
+            target_width,
+            (target_width as f32 / aspect_ratio).round() as u32,
+        )),
+        (None, Some(target_height)) => Some((
+            (target_height as f32 * aspect_ratio).round() as u32,
+            target_height,
+        )),
+    }
+}
+
+fn resize_background_image(bitmap: &Bitmap, width: u32, height: u32) -> Bitmap {
+    assert!(
+        width > 0 && height > 0,
+        "The output size of our sheets must be bigger than 0x0 pixels - please fix it in '{}'!",
+        DRAW_PARAMETERS_FILENAME
+    );
+    let resized = image::imageops::resize(
+        &bitmap_to_rgba_image(bitmap),
+        width,
+        height,
+        image::imageops::FilterType::Lanczos3,
+    );
+    bitmap_from_rgba_image(&resized)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DrawParamsFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+/// Returns the filepath and format of our draw parameters file if there is one
+fn find_draw_params_file() -> Option<(&'static str, DrawParamsFormat)> {
+    let existing_files: Vec<(&str, DrawParamsFormat)> = DRAW_PARAMETERS_FILENAMES
+        .iter()
+        .filter(|(filepath, _format)| path_exists(filepath))
+        .cloned()
+        .collect();
+    assert!(
+        existing_files.len() <= 1,
+        "There are multiple draw parameter files {:?} - please keep only one of them",
+        existing_files
+            .iter()
+            .map(|(filepath, _format)| *filepath)
+            .collect::<Vec<_>>()
+    );
+    existing_files.first().cloned()
+}
+
+fn load_draw_params() -> DrawParams {
+    let (filepath, format) = find_draw_params_file()
+        .unwrap_or_else(|| panic!("Could not find file '{}'", DRAW_PARAMETERS_FILENAME));
+    // NOTE: YAML and JSON files are written by other programs which are responsible for keeping
+    //       them up to date
+    if format == DrawParamsFormat::Toml {
+        params_migration::migrate_draw_params_file();
+    }
+    let mut params = params_validation::parse_draw_params(
+        &std::fs::read_to_string(filepath)
+            .unwrap_or_else(|error| panic!("Could not read file '{}': {}", filepath, error)),
+        filepath,
+        format,
+    );
+    if let Some(cell_layout_filepath) = &params.cell_layout_filepath {
+        assert!(
+            params.cell_layout.is_none(),
+            "The `cell_layout` and `cell_layout_filepath` parameters cannot be used together - \
+             please remove one of them from '{}'!",
+            DRAW_PARAMETERS_FILENAME
+        );
+        params.cell_layout = Some(cell_layout_params::load_cell_layout_file(
+            cell_layout_filepath,
+        ));
+    }
+    params
+}
+
+fn load_recipients() -> Vec<HashMap<String, String>> {
+    let mut reader = csv::Reader::from_path(RECIPIENTS_FILENAME)
+        .unwrap_or_else(|error| panic!("Could not read file '{}': {}", RECIPIENTS_FILENAME, error));
+    let column_names = reader
+        .headers()
+        .unwrap_or_else(|error| {
+            panic!(
+                "Could not read column names in first line of '{}': {}",
+                RECIPIENTS_FILENAME, error
+            )
+        })
+        .clone();
+
+    reader
+        .records()
+        .map(|record| {
+            let record = record.unwrap_or_else(|error| {
+                panic!("Could not read file '{}': {}", RECIPIENTS_FILENAME, error)
+            });
+            column_names
+                .iter()
+                .zip(record.iter())
+                .map(|(column_name, value)| {
+                    (column_name.trim().to_owned(), value.trim().to_owned())
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// A font that we can rasterize with fontdue and shape with rustybuzz
+struct LoadedFont {
+    rasterizer: fontdue::Font,
+    /// The contents of the font file which we need for shaping
+    data: Vec<u8>,
+    /// Shared by all text rasterizers that use this font
+    glyph_atlases: glyph_atlas::GlyphAtlases,
+}
+
+fn load_font(filepath: &str) -> LoadedFont {
+    let data = read_file_whole(filepath).expect(&format!("Cannot read font file '{}'", filepath));
+    let rasterizer =
+        fontdue::Font::from_bytes(data.as_slice(), fontdue::FontSettings::default()).expect(
+            &format!("Cannot decode font file '{}' - is the file ok?", filepath),
+        );
+    LoadedFont {
+        rasterizer,
+        data,
+        glyph_atlases: glyph_atlas::GlyphAtlases::default(),
+    }
+}
+
+fn paths_point_to_same_file(first: &str, second: &str) -> bool {
+    match (std::fs::canonicalize(first), std::fs::canonicalize(second)) {
+        (Ok(first), Ok(second)) => first == second,
+        _ => false,
+    }
+}
+
+/// Returns the text that is drawn for the given number. The number is prefixed with the label of
+/// its column if `column_labels` are given.
+fn number_text(
+    number: i32,
+    bingo_variant: BingoVariant,
+    column_labels: Option<&[String]>,
+) -> String {
+    match column_labels {
+        Some(column_labels) if bingo_variant == BingoVariant::Ball75 => {
+            let column_label = &column_labels[((number - 1) / 15) as usize];
+            format!("{}{}", column_label, number)
+        }
+        _ => number.to_string(),
+    }
+}
+
+fn create_number_bitmaps_premultiplied(
+    text_rasterizer: &mut TextRasterizer,
+    bingo_variant: BingoVariant,
+    max_number: i32,
+    column_labels: Option<&[String]>,
+) -> HashMap<i32, Bitmap> {
+    let number_strings: Vec<String> = (1..=max_number)
+        .map(|number| number_text(number, bingo_variant, column_labels))
+        .collect();
+    text_rasterizer.precompute_layouts(
+        &number_strings
+            .iter()
+            .map(|number_string| number_string.as_str())
+            .collect::<Vec<&str>>(),
+    );
+
+    let mut number_bitmaps_premultiplied = HashMap::new();
+    for (number, number_string) in (1..=max_number).zip(number_strings.iter()) {
+        let number_bitmap_premultiplied =
+            text_rasterizer.create_text_bitmap_premultiplied(number_string);
+
+        // number_bitmap_premultiplied
+        //     .to_unpremultiplied_alpha()
+        //     .write_to_png_file(&format!("target/test_numbers/{}.png", number));
+
+        number_bitmaps_premultiplied.insert(number, number_bitmap_premultiplied);
+    }
+    number_bitmaps_premultiplied
+}
+
+/// Returns the given text broken into lines at its spaces so that each line fits into the given
+/// width if possible. Words that are wider than the given width get a line of their own.
+fn wrap_text(text_rasterizer: &TextRasterizer, text: &str, max_width: i32) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        if let Some(line) = lines.last_mut() {
+            let extended_line = format!("{} {}", line, word);
+            if text_rasterizer
+                .create_text_bitmap_premultiplied(&extended_line)
+                .width as i32
+                <= max_width
+            {
+                *line = extended_line;
+                continue;
+            }
+        }
+        lines.push(word.to_owned());
+    }
+    lines
+}
+
+/// Returns the bitmap of each given text by its 1-based position. Long texts are broken into
+/// several centered lines to fit into a grid cell of the given width.
+fn create_wrapped_text_bitmaps_premultiplied(
+    text_rasterizer: &TextRasterizer,
+    texts: &[&str],
+    cell_width: i32,
+) -> HashMap<i32, Bitmap> {
+    let max_width = 9 * cell_width / 10;
+    let line_height = (1.2 * text_rasterizer.font_size).ceil() as i32;
+    texts
+        .iter()
+        .enumerate()
+        .map(|(index, text)| {
+            let line_bitmaps: Vec<Bitmap> = wrap_text(text_rasterizer, text, max_width)
+                .iter()
+                .map(|line| text_rasterizer.create_text_bitmap_premultiplied(line))
+                .collect();
+            let width = line_bitmaps
+                .iter()
+                .map(|bitmap| bitmap.width)
+                .max()
+                .unwrap_or(0);
+            let mut text_bitmap =
+                Bitmap::new(width, (line_height * line_bitmaps.len() as i32) as u32);
+            for (line, line_bitmap) in line_bitmaps.iter().enumerate() {
+                blit_centered_premultiplied(
+                    line_bitmap,
+                    &mut text_bitmap,
+                    Vec2i::new(
+                        width as i32 / 2,
+                        line as i32 * line_height + line_height / 2,
+                    ),
+                    false,
+                );
+            }
+            text_bitmap.trim_by_value(true, true, true, true, PixelRGBA::transparent());
+            (index as i32 + 1, text_bitmap)
+        })
+        .collect()
+}
+
+/// Draws texts with a fixed font, size and color. The glyphs for the characters given on creation
+/// are rasterized once and then reused, all other glyphs are rasterized on demand.
+struct TextRasterizer<'a> {
+    font: &'a LoadedFont,
+    font_size: f32,
+    fill_style: FillStyle,
+    /// Texts that are not plain ASCII or that are written from right to left are shaped
+    text_direction: shaping::TextDirection,
+    /// Makes the glyphs thicker for fonts that have no bold variant
+    bold: bool,
+    /// The glyphs are white and only get their color once the whole text is put together so that
+    /// gradients can span the whole text
+    glyph_atlas: Arc<glyph_atlas::GlyphAtlas>,
+    /// Applied after the text is filled
+    effects: text_effects::TextEffects,
+}
+
+impl<'a> TextRasterizer<'a> {
+    fn new(
+        font: &'a LoadedFont,
+        font_size: f32,
+        fill_style: FillStyle,
+        text_direction: shaping::TextDirection,
+        bold: bool,
+        cached_characters: &str,
+    ) -> TextRasterizer<'a> {
+        let glyph_atlas =
+            font.glyph_atlases
+                .get(&font.rasterizer, font_size, cached_characters, &[]);
+        TextRasterizer {
+            font,
+            font_size,
+            fill_style,
+            text_direction,
+            bold,
+            glyph_atlas,
+            effects: text_effects::TextEffects::default(),
+        }
+    }
+
+    fn with_effects(mut self, effects: Option<&text_effects::TextEffects>) -> TextRasterizer<'a> {
+        self.effects = effects.cloned().unwrap_or_default();
+        self
+    }
+
+    /// Lays out the given texts once upfront so that drawing them later does not need to lay them
+    /// out again, also for other rasterizers of the same font and size
+    fn precompute_layouts(&mut self, texts: &[&str]) {
+        self.glyph_atlas =
+            self.font
+                .glyph_atlases
+                .get(&self.font.rasterizer, self.font_size, "", texts);
+    }
+
+    /// Returns by how many pixels our glyphs grow in each direction if we draw bold text
+    fn bold_width(&self) -> i32 {
+        if self.bold {
+            i32::max(1, (self.font_size / 24.0).round() as i32)
+        } else {
+            0
+        }
+    }
+
+    /// Returns the given white text thicker if we draw bold text. The result grows by
+    /// `bold_width` pixels on each side.
+    fn embolden_white_premultiplied(&self, bitmap: Bitmap) -> Bitmap {
+        if !self.bold {
+            return bitmap;
+        }
+        add_outline_premultiplied(&bitmap, self.bold_width(), PixelRGBA::white())
+    }
+
+    fn create_text_bitmap_premultiplied(&self, text: &str) -> Bitmap {
+        if !shaping::is_simple_text(text, self.text_direction) {
+            return self.create_shaped_text_bitmap_premultiplied(text);
+        }
+
+        let mut text_bitmap_premultiplied =
+            self.embolden_white_premultiplied(self.create_white_text_bitmap_premultiplied(text));
+        self.fill_style
+            .apply_to_white_premultiplied(&mut text_bitmap_premultiplied);
+        self.effects
+            .apply_premultiplied(&mut text_bitmap_premultiplied);
+        text_bitmap_premultiplied
+    }
+
+    /// Returns the given text with its characters stacked from top to bottom
+    fn create_vertical_text_bitmap_premultiplied(&self, text: &str) -> Bitmap {
+        let character_bitmaps: Vec<Bitmap> = text
+            .chars()
+            .filter(|character| !character.is_whitespace())
+            .map(|character| self.create_text_bitmap_premultiplied(&character.to_string()))
+            .collect();
+        let line_height = self.font_size.ceil() as u32;
+        let width = character_bitmaps
+            .iter()
+            .map(|bitmap| bitmap.width)
+            .max()
+            .unwrap_or(0);
+        let mut text_bitmap_premultiplied =
+            Bitmap::new(width, line_height * character_bitmaps.len() as u32);
+        for (line, character_bitmap) in character_bitmaps.iter().enumerate() {
+            let center = Vec2i::new(
+                width as i32 / 2,
+                (line as u32 * line_height + line_height / 2) as i32,
+            );
+            blit_centered_premultiplied(
+                character_bitmap,
+                &mut text_bitmap_premultiplied,
+                center,
+                false,
+            );
+        }
+
+        text_bitmap_premultiplied.trim_by_value(true, true, true, true, PixelRGBA::transparent());
+        text_bitmap_premultiplied
+    }
+
+    /// Returns the trimmed shape of the given simple text in white
+    fn create_white_text_bitmap_premultiplied(&self, text: &str) -> Bitmap {
+        let layout = self.glyph_atlas.layout(&self.font.rasterizer, text);
+        let mut text_bitmap_premultiplied = Bitmap::new(layout.width, layout.height);
+        for &(glyph, pos) in layout.glyphs.iter() {
+            self.glyph_atlas
+                .glyph(&self.font.rasterizer, glyph)
+                .blit_to_alpha_blended_premultiplied(
+                    &mut text_bitmap_premultiplied,
+                    pos,
+                    true,
+                    cottontail::image::ColorBlendMode::Normal,
+                );
+        }
+
+        text_bitmap_premultiplied.trim_by_value(true, true, true, true, PixelRGBA::transparent());
+        text_bitmap_premultiplied
+    }
+
+    /// Same as `create_text_bitmap_premultiplied` but lays out the text with proper shaping which
+    /// is needed for right-to-left scripts and scripts with connected letters. Glyphs of color
+    /// emoji fonts keep their own colors.
+    fn create_shaped_text_bitmap_premultiplied(&self, text: &str) -> Bitmap {
+        let shaped_glyphs = shaping::shape_line(
+            &self.font.data,
+            self.font.rasterizer.units_per_em(),
+            self.font_size,
+            text,
+            self.text_direction,
+        );
+        // NOTE: The second value tells whether the glyph is a color image that must not be
+        //       colored by our fill style
+        let glyphs: Vec<(Vec2i, Bitmap, bool)> = shaped_glyphs
+            .iter()
+            .map(|shaped_glyph| {
+                if let Some(color_glyph) = shaping::color_glyph_image(
+                    &self.font.data,
+                    shaped_glyph.glyph_index,
+                    self.font_size,
+                ) {
+                    let pos = Vec2i::new(
+                        (shaped_glyph.x + color_glyph.x).round() as i32,
+                        (shaped_glyph.y + color_glyph.y).round() as i32,
+                    );
+                    let bitmap = resize_background_image(
+                        &bitmap_from_rgba_image(&color_glyph.image).to_premultiplied_alpha(),
+                        u32::max(1, color_glyph.width.round() as u32),
+                        u32::max(1, color_glyph.height.round() as u32),
+                    );
+                    return (pos, bitmap, true);
+                }
+
+                let (metrics, image_bytes) = self
+                    .font
+                    .rasterizer
+                    .rasterize_indexed(shaped_glyph.glyph_index as usize, self.font_size);
+                // NOTE: Fontdue gives us the bottom left corner of the glyph relative to its
+                //       origin with y pointing up
+                let pos = Vec2i::new(
+                    (shaped_glyph.x + metrics.xmin as f32).round() as i32,
+                    (shaped_glyph.y - (metrics.ymin + metrics.height as i32) as f32).round() as i32,
+                );
+                let bitmap = Bitmap::from_greyscale_bytes_premultiplied(
+                    &image_bytes,
+                    metrics.width as u32,
+                    metrics.height as u32,
+                );
+                (pos, bitmap, false)
+            })
+            .collect();
+
+        let x_min = glyphs.iter().map(|(pos, _, _)| pos.x).min().unwrap_or(0);
+        let y_min = glyphs.iter().map(|(pos, _, _)| pos.y).min().unwrap_or(0);
+        let x_max = glyphs
+            .iter()
+            .map(|(pos, bitmap, _)| pos.x + bitmap.width as i32)
+            .max()
+            .unwrap_or(0);
+        let y_max = glyphs
+            .iter()
+            .map(|(pos, bitmap, _)| pos.y + bitmap.height as i32)
+            .max()
+            .unwrap_or(0);
+        let width = (x_max - x_min) as u32;
+        let height = (y_max - y_min) as u32;
+
+        let mut text_bitmap_premultiplied = Bitmap::new(width, height);
+        for (pos, glyph_bitmap_premultiplied, _) in glyphs.iter().filter(|glyph| !glyph.2) {
+            blit_to_alpha_blended_premultiplied_rows(
+                glyph_bitmap_premultiplied,
+                &mut text_bitmap_premultiplied,
+                *pos - Vec2i::new(x_min, y_min),
+            );
+        }
+        let mut text_bitmap_premultiplied =
+            self.embolden_white_premultiplied(text_bitmap_premultiplied);
+        self.fill_style
+            .apply_to_white_premultiplied(&mut text_bitmap_premultiplied);
+        // NOTE: Colored glyphs like emojis keep their own look
+        self.effects
+            .apply_premultiplied(&mut text_bitmap_premultiplied);
+        let bold_width = self.bold_width();
+        for (pos, glyph_bitmap_premultiplied, _) in glyphs.iter().filter(|glyph| glyph.2) {
+            blit_to_alpha_blended_premultiplied_rows(
+                glyph_bitmap_premultiplied,
+                &mut text_bitmap_premultiplied,
+                *pos - Vec2i::new(x_min - bold_width, y_min - bold_width),
+            );
+        }
+
+        text_bitmap_premultiplied.trim_by_value(true, true, true, true, PixelRGBA::transparent());
+        text_bitmap_premultiplied
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum GradientDirection {
+    /// From top to bottom
+    Vertical,
+    /// From left to right
+    Horizontal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TextGradient {
+    direction: GradientDirection,
+    #[serde(deserialize_with = "colors::rgba")]
+    start_color_rgba: (u8, u8, u8, u8),
+    #[serde(deserialize_with = "colors::rgba")]
+    end_color_rgba: (u8, u8, u8, u8),
+}
+
+/// Defines how the (white) shape of a text is colored
+#[derive(Debug, Clone, Copy)]
+enum FillStyle {
+    Solid(Color),
+    Gradient {
+        direction: GradientDirection,
+        start: Color,
+        end: Color,
+    },
+}
+
+impl FillStyle {
+    /// Returns the color at the given position where (0, 0) is the top left and (1, 1) the bottom
+    /// right of the filled area
+    fn color_at(&self, u: f32, v: f32) -> Color {
+        match *self {
+            FillStyle::Solid(color) => color,
+            FillStyle::Gradient {
+                direction,
+                start,
+                end,
+            } => {
+                let percent = match direction {
+                    GradientDirection::Vertical => v,
+                    GradientDirection::Horizontal => u,
+                };
+                Color {
+                    r: start.r + percent * (end.r - start.r),
+                    g: start.g + percent * (end.g - start.g),
+                    b: start.b + percent * (end.b - start.b),
+                    a: start.a + percent * (end.a - start.a),
+                }
+            }
+        }
+    }
+
+    /// Colors the given premultiplied bitmap whose pixels are all white with varying opacity
+    fn apply_to_white_premultiplied(&self, bitmap: &mut Bitmap) {
+        let width = bitmap.width as i32;
+        let height = bitmap.height as i32;
+        for y in 0..height {
+            for x in 0..width {
+                let color = self.color_at(
+                    (x as f32 + 0.5) / width as f32,
+                    (y as f32 + 0.5) / height as f32,
+                );
+                let pixel = bitmap.get(x, y);
+                let coverage = pixel.a as f32 * color.a;
+                bitmap.set(
+                    x,
+                    y,
+                    PixelRGBA::new(
+                        (coverage * color.r).round() as u8,
+                        (coverage * color.g).round() as u8,
+                        (coverage * color.b).round() as u8,
+                        coverage.round() as u8,
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn blit_centered_premultiplied(
+    bitmap_premultiplied: &Bitmap,
+    target: &mut Bitmap,
+    center: Vec2i,
+    gamma_correct_blending: bool,
+) {
+    let pos = center - bitmap_premultiplied.rect().dim / 2;
+    if gamma_correct_blending {
+        blit_to_alpha_blended_premultiplied_gamma_correct(bitmap_premultiplied, target, pos);
+    } else {
+        blit_to_alpha_blended_premultiplied_rows(bitmap_premultiplied, target, pos);
+    }
+}
+
+/// Returns a copy of the given region of the bitmap which must lie fully inside of it
+fn copy_bitmap_region(bitmap: &Bitmap, pos: Vec2i, width: i32, height: i32) -> Bitmap {
+    let mut result = Bitmap::new(width as u32, height as u32);
+    for y in 0..height {
+        let source_start = ((pos.y + y) * bitmap.width as i32 + pos.x) as usize;
+        let result_start = (y * width) as usize;
+        result.data[result_start..result_start + width as usize]
+            .copy_from_slice(&bitmap.data[source_start..source_start + width as usize]);
+    }
+    result
+}
+
+/// Copies the given bitmap row by row onto the target without blending. The bitmap must lie fully
+/// inside of the target.
+fn paste_bitmap(bitmap: &Bitmap, target: &mut Bitmap, pos: Vec2i) {
+    let width = bitmap.width as usize;
+    for y in 0..bitmap.height as i32 {
+        let source_start = y as usize * width;
+        let target_start = ((pos.y + y) * target.width as i32 + pos.x) as usize;
+        target.data[target_start..target_start + width]
+            .copy_from_slice(&bitmap.data[source_start..source_start + width]);
+    }
+}
+
+/// Same as `Bitmap::blit_to_alpha_blended_premultiplied` but clips the source rect against the
+/// target once and then blends whole rows with integer math. This is considerably faster for the
+/// many small blits that we do on large backgrounds.
+fn blit_to_alpha_blended_premultiplied_rows(source: &Bitmap, target: &mut Bitmap, pos: Vec2i) {
+    let source_width = source.width as i32;
+    let target_width = target.width as i32;
+    let left = i32::max(0, -pos.x);
+    let top = i32::max(0, -pos.y);
+    let right = i32::min(source_width, target_width - pos.x);
+    let bottom = i32::min(source.height as i32, target.height as i32 - pos.y);
+    if left >= right || top >= bottom {
+        return;
+    }
+
+    let row_length = (right - left) as usize;
+    for source_y in top..bottom {
+        let source_start = (source_y * source_width + left) as usize;
+        let target_start = ((pos.y + source_y) * target_width + pos.x + left) as usize;
+        let source_row = &source.data[source_start..source_start + row_length];
+        let target_row = &mut target.data[target_start..target_start + row_length];
+        for (target_pixel, source_pixel) in target_row.iter_mut().zip(source_row.iter()) {
+            match source_pixel.a {
+                0 => {}
+                255 => *target_pixel = *source_pixel,
+                source_alpha => {
+                    let inverse_alpha = 255 - source_alpha as u32;
+                    let blend_channel = |source_channel: u8, target_channel: u8| -> u8 {
+                        // NOTE: This is a fast and exact version of `x / 255` with rounding
+                        let product = target_channel as u32 * inverse_alpha + 128;
+                        let scaled = (product + (product >> 8)) >> 8;
+                        u32::min(255, source_channel as u32 + scaled) as u8
+                    };
+                    *target_pixel = PixelRGBA::new(
+                        blend_channel(source_pixel.r, target_pixel.r),
+                        blend_channel(source_pixel.g, target_pixel.g),
+                        blend_channel(source_pixel.b, target_pixel.b),
+                        blend_channel(source_pixel.a, target_pixel.a),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Same as `Bitmap::blit_to_alpha_blended_premultiplied` but does the blending in linear color
+/// space. This avoids the dark halos around antialiased edges that we get when blending light
+/// colors directly in sRGB space.
+fn blit_to_alpha_blended_premultiplied_gamma_correct(
+    source: &Bitmap,
+    target: &mut Bitmap,
+    pos: Vec2i,
+) {
+    for source_y in 0..source.height as i32 {
+        for source_x in 0..source.width as i32 {
+            let target_x = pos.x + source_x;
+            let target_y = pos.y + source_y;
+            if target_x < 0
+                || target_y < 0
+                || target_x >= target.width as i32
+                || target_y >= target.height as i32
+            {
+                continue;
+            }
+
+            let source_pixel = source.get(source_x, source_y);
+            if source_pixel.a == 0 {
+                continue;
+            }
+            let target_pixel = target.get(target_x, target_y);
+
+            let source_alpha = source_pixel.a as f32 / 255.0;
+            let target_alpha = target_pixel.a as f32 / 255.0;
+            let final_alpha = source_alpha + target_alpha * (1.0 - source_alpha);
+
+            let blend_channel = |source_channel: u8, target_channel: u8| -> u8 {
+                let source_linear = srgb_to_linear(source_channel as f32 / 255.0 / source_alpha);
+                let target_linear = if target_alpha > 0.0 {
+                    srgb_to_linear(target_channel as f32 / 255.0 / target_alpha)
+                } else {
+                    0.0
+                };
+                let final_linear = (source_linear * source_alpha
+                    + target_linear * target_alpha * (1.0 - source_alpha))
+                    / final_alpha;
+                (255.0 * final_alpha * linear_to_srgb(final_linear))
+                    .round()
+                    .clamp(0.0, 255.0) as u8
+            };
+
+            target.set(
+                target_x,
+                target_y,
+                PixelRGBA::new(
+                    blend_channel(source_pixel.r, target_pixel.r),
+                    blend_channel(source_pixel.g, target_pixel.g),
+                    blend_channel(source_pixel.b, target_pixel.b),
+                    (255.0 * final_alpha).round() as u8,
+                ),
+            );
+        }
+    }
+}
+
+/// Converts the given sRGB bitmap into CMYK using the given ICC profile and writes it as TIFF file
+/// with the profile embedded
+fn write_cmyk_tiff_file(sheet: &ComposedSheet, filepath: &str, icc_profile_data: &[u8]) {
+    let srgb_profile = lcms2::Profile::new_srgb();
+    let cmyk_profile = lcms2::Profile::new_icc(icc_profile_data)
+        .unwrap_or_else(|error| panic!("Cannot decode ICC profile - is the file ok?: {}", error));
+    let transform = lcms2::Transform::new(
+        &srgb_profile,
+        lcms2::PixelFormat::RGBA_8,
+        &cmyk_profile,
+        lcms2::PixelFormat::CMYK_8,
+        lcms2::Intent::Perceptual,
+    )
+    .unwrap_or_else(|error| {
+        panic!(
+            "Cannot convert colors with given ICC profile - is it a CMYK profile?: {}",
+            error
+        )
+    });
+
+    let mut pixels_rgba: Vec<[u8; 4]> =
+        Vec::with_capacity(sheet.width() as usize * sheet.height() as usize);
+    sheet.for_each_row(|row| {
+        pixels_rgba.extend(row.iter().map(|pixel| [pixel.r, pixel.g, pixel.b, pixel.a]))
+    });
+    let mut pixels_cmyk: Vec<[u8; 4]> = vec![[0; 4]; pixels_rgba.len()];
+    transform.transform_pixels(&pixels_rgba, &mut pixels_cmyk);
+    let pixels_cmyk: Vec<u8> = pixels_cmyk.iter().flatten().copied().collect();
+
+    // NOTE: We encode into memory first so that the file can be written atomically
+    let mut file_bytes = std::io::Cursor::new(Vec::new());
+    let mut encoder = tiff::encoder::TiffEncoder::new(&mut file_bytes)
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    let mut image = encoder
+        .new_image::<tiff::encoder::colortype::CMYK8>(sheet.width(), sheet.height())
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    const TIFF_TAG_ICC_PROFILE: u16 = 34675;
+    image
+        .encoder()
+        .write_tag(
+            tiff::tags::Tag::Unknown(TIFF_TAG_ICC_PROFILE),
+            icc_profile_data,
+        )
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    image
+        .write_data(&pixels_cmyk)
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    drop(encoder);
+    atomic_file::write_file_atomically(filepath, file_bytes.get_ref());
+}
+
+/// Returns the relative luminance of the given sRGB color as defined by the WCAG
+fn relative_luminance(color: Color) -> f32 {
+    0.2126 * srgb_to_linear(color.r)
+        + 0.7152 * srgb_to_linear(color.g)
+        + 0.0722 * srgb_to_linear(color.b)
+}
+
+/// Returns the WCAG contrast ratio between two colors which goes from 1:1 to 21:1
+fn contrast_ratio(first: Color, second: Color) -> f32 {
+    let first_luminance = relative_luminance(first);
+    let second_luminance = relative_luminance(second);
+    (f32::max(first_luminance, second_luminance) + 0.05)
+        / (f32::min(first_luminance, second_luminance) + 0.05)
+}
+
+/// Returns the given bitmap drawn onto opaque paper of the given color
+fn composite_onto_paper(bitmap: &Bitmap, paper_color_rgb: (u8, u8, u8)) -> Bitmap {
+    let (paper_r, paper_g, paper_b) = paper_color_rgb;
+    let mut result = bitmap.clone();
+    for pixel in result.data.iter_mut() {
+        if pixel.a < 255 {
+            let alpha = pixel.a as u32;
+            let blend_channel = |channel: u8, paper_channel: u8| {
+                ((channel as u32 * alpha + paper_channel as u32 * (255 - alpha) + 127) / 255) as u8
+            };
+            *pixel = PixelRGBA::new(
+                blend_channel(pixel.r, paper_r),
+                blend_channel(pixel.g, paper_g),
+                blend_channel(pixel.b, paper_b),
+                255,
+            );
+        }
+    }
+    result
+}
+
+/// Returns a light greyscale version of the given bitmap that keeps the given fraction of its
+/// darkness
+fn lighten_for_ink_saver(bitmap: &Bitmap, strength: f32) -> Bitmap {
+    let mut result = bitmap.clone();
+    for pixel in result.data.iter_mut() {
+        *pixel = lighten_pixel_for_ink_saver(*pixel, strength);
+    }
+    result
+}
+
+/// Same as `lighten_for_ink_saver` for a single pixel with premultiplied alpha
+fn lighten_pixel_for_ink_saver(pixel: PixelRGBA, strength: f32) -> PixelRGBA {
+    if pixel.a == 0 {
+        return pixel;
+    }
+    // NOTE: Grey uses the least amount of toner and ink while keeping the structure of our
+    //       background visible
+    let alpha = pixel.a as f32 / 255.0;
+    let luminance = relative_luminance(Color {
+        r: pixel.r as f32 / 255.0 / alpha,
+        g: pixel.g as f32 / 255.0 / alpha,
+        b: pixel.b as f32 / 255.0 / alpha,
+        a: 1.0,
+    });
+    let darkness = 1.0 - linear_to_srgb(luminance);
+    let grey = (255.0 * alpha * (1.0 - strength * darkness))
+        .round()
+        .clamp(0.0, 255.0) as u8;
+    PixelRGBA::new(grey, grey, grey, pixel.a)
+}
+
+/// Returns the given color drawn with its opacity on top of the given opaque background color
+fn blend_color_over(color: Color, background: Color) -> Color {
+    Color {
+        r: color.r * color.a + background.r * (1.0 - color.a),
+        g: color.g * color.a + background.g * (1.0 - color.a),
+        b: color.b * color.a + background.b * (1.0 - color.a),
+        a: 1.0,
+    }
+}
+
+/// Returns the nickname of each number that has one in a small text that fits below the number
+fn create_nickname_bitmaps_premultiplied(
+    font: &LoadedFont,
+    params: &DrawParams,
+    cell_width: i32,
+    cell_height: i32,
+) -> HashMap<i32, Bitmap> {
+    let nicknames =
+        nicknames::load_nicknames(params.nicknames_filepath.as_deref(), params.max_number());
+    let mut numbers: Vec<i32> = nicknames.keys().cloned().collect();
+    numbers.sort();
+    let nickname_text_rasterizer = TextRasterizer::new(
+        font,
+        params
+            .nickname_font_size
+            .unwrap_or(u32::max(1, cell_height as u32 / 10)) as f32,
+        params.number_fill_style(),
+        params.text_direction,
+        params.bold_text,
+        "",
+    );
+    let mut nickname_bitmaps = create_wrapped_text_bitmaps_premultiplied(
+        &nickname_text_rasterizer,
+        &numbers
+            .iter()
+            .map(|number| nicknames[number].as_str())
+            .collect::<Vec<&str>>(),
+        cell_width,
+    );
+    numbers
+        .iter()
+        .enumerate()
+        .map(|(index, &number)| {
+            (
+                number,
+                nickname_bitmaps.remove(&(index as i32 + 1)).unwrap(),
+            )
+        })
+        .collect()
+}
+
+/// Returns the Braille caption of each number drawn either with the Braille font given in our draw
+/// parameters or as dots
+fn create_braille_bitmaps_premultiplied(
+    params: &DrawParams,
+    cell_height: i32,
+) -> HashMap<i32, Bitmap> {
+    let text_color = params.number_fill_style().color_at(0.5, 0.5);
+    let numbers = 1..=params.max_number();
+    if let Some(filepath) = &params.braille_font_filepath {
+        let braille_font = load_font(filepath);
+        let braille_text_rasterizer = TextRasterizer::new(
+            &braille_font,
+            (cell_height / 5) as f32,
+            FillStyle::Solid(text_color),
+            shaping::TextDirection::LeftToRight,
+            false,
+            "",
+        );
+        return numbers
+            .map(|number| {
+                (
+                    number,
+                    braille_text_rasterizer
+                        .create_text_bitmap_premultiplied(&braille::braille_text(number)),
+                )
+            })
+            .collect();
+    }
+
+    let dot_radius = params
+        .braille_dot_radius_px
+        .map(|radius| radius as i32)
+        .unwrap_or(i32::max(1, cell_height / 40));
+    let dot_color = PixelRGBA::new(
+        (255.0 * text_color.r * text_color.a).round() as u8,
+        (255.0 * text_color.g * text_color.a).round() as u8,
+        (255.0 * text_color.b * text_color.a).round() as u8,
+        (255.0 * text_color.a).round() as u8,
+    );
+    numbers
+        .map(|number| {
+            (
+                number,
+                braille::create_braille_bitmap_premultiplied(number, dot_radius, dot_color),
+            )
+        })
+        .collect()
+}
+
+/// Compares the colors of our numbers with the average color of the background (and the number
+/// chip if any) of each grid cell. Returns the [column, row] location and contrast ratio of all
+/// cells where the contrast is below our minimum.
+fn find_low_contrast_cells(
+    grid_region_background: &Bitmap,
+    top_left: Vec2i,
+    cell_width: i32,
+    cell_height: i32,
+    params: &DrawParams,
+) -> Vec<(i32, i32, f32)> {
+    let minimum_contrast = params
+        .minimum_number_contrast
+        .unwrap_or(DEFAULT_MINIMUM_NUMBER_CONTRAST);
+    let fill_style = params.number_fill_style();
+    let text_colors = [fill_style.color_at(0.0, 0.0), fill_style.color_at(1.0, 1.0)];
+
+    let free_space_cells = params.free_space_cells();
+    let mut low_contrast_cells = Vec::new();
+    let (column_count, row_count) = params.grid_size();
+    for row in 0..row_count {
+        for column in 0..column_count {
+            if free_space_cells.contains(&(column, row)) {
+                continue;
+            }
+            let left = top_left.x + column * cell_width;
+            let top = top_left.y + row * cell_height;
+            let mut sum = [0.0f32; 3];
+            let mut pixel_count = 0;
+            for y in
+                i32::max(0, top)..i32::min(grid_region_background.height as i32, top + cell_height)
+            {
+                for x in i32::max(0, left)
+                    ..i32::min(grid_region_background.width as i32, left + cell_width)
+                {
+                    let color = grid_region_background.get(x, y).to_color();
+                    sum[0] += color.r;
+                    sum[1] += color.g;
+                    sum[2] += color.b;
+                    pixel_count += 1;
+                }
+            }
+            if pixel_count == 0 {
+                continue;
+            }
+
+            let mut cell_color = Color {
+                r: sum[0] / pixel_count as f32,
+                g: sum[1] / pixel_count as f32,
+                b: sum[2] / pixel_count as f32,
+                a: 1.0,
+            };
+            if let Some(number_chip) = &params.number_chip {
+                cell_color = blend_color_over(color_from_rgba(number_chip.color_rgba), cell_color);
+            }
+            let contrast = text_colors
+                .iter()
+                .map(|&text_color| {
+                    contrast_ratio(blend_color_over(text_color, cell_color), cell_color)
+                })
+                .fold(std::f32::MAX, f32::min);
+            if contrast < minimum_contrast {
+                low_contrast_cells.push((column, row, contrast));
+            }
+        }
+    }
+    low_contrast_cells
+}
+
+/// Outlines the given number bitmaps so that they stay readable on the busy parts of our
+/// background. Light texts get a black outline and dark texts a white one.
+fn add_number_outlines(
+    number_bitmaps_premultiplied: &mut HashMap<i32, Bitmap>,
+    text_color: Color,
+    params: &DrawParams,
+) {
+    let outline_color = if relative_luminance(text_color) > 0.18 {
+        PixelRGBA::black()
+    } else {
+        PixelRGBA::white()
+    };
+    let outline_width = i32::max(1, params.text_font_size as i32 / 16);
+    for number_bitmap in number_bitmaps_premultiplied.values_mut() {
+        *number_bitmap = add_outline_premultiplied(number_bitmap, outline_width, outline_color);
+    }
+}
+
+/// Returns the given bitmap with an outline of the given width and color around its shape
+fn add_outline_premultiplied(bitmap: &Bitmap, width: i32, color: PixelRGBA) -> Bitmap {
+    let mut result = Bitmap::new(
+        bitmap.width + 2 * width as u32,
+        bitmap.height + 2 * width as u32,
+    );
+    let outline_color = color.to_color();
+    for y in 0..result.height as i32 {
+        for x in 0..result.width as i32 {
+            let mut coverage = 0;
+            for offset_y in -width..=width {
+                for offset_x in -width..=width {
+                    if offset_x * offset_x + offset_y * offset_y > width * width {
+                        continue;
+                    }
+                    let source_x = x - width + offset_x;
+                    let source_y = y - width + offset_y;
+                    if source_x < 0
+                        || source_y < 0
+                        || source_x >= bitmap.width as i32
+                        || source_y >= bitmap.height as i32
+                    {
+                        continue;
+                    }
+                    coverage = u8::max(coverage, bitmap.get(source_x, source_y).a);
+                }
+            }
+            let alpha = coverage as f32 * outline_color.a;
+            result.set(
+                x,
+                y,
+                PixelRGBA::new(
+                    (alpha * outline_color.r).round() as u8,
+                    (alpha * outline_color.g).round() as u8,
+                    (alpha * outline_color.b).round() as u8,
+                    alpha.round() as u8,
+                ),
+            );
+        }
+    }
+    blit_to_alpha_blended_premultiplied_rows(bitmap, &mut result, Vec2i::new(width, width));
+    result
 }
 
-struct Input {
-    background_bitmap: Bitmap,
-    font: fontdue::Font,
-    params: DrawParams,
+fn srgb_to_linear(value: f32) -> f32 {
+    let value = value.clamp(0.0, 1.0);
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
 }
 
-impl Input {
-    fn new() -> Input {
-        let files = collect_files(".");
-        if files
-            .iter()
-            .filter(|filepath| filepath.to_lowercase().ends_with(".png"))
-            .count()
-            != 1
-            || files
-                .iter()
-                .filter(|filepath| filepath.to_lowercase().ends_with(".ttf"))
-                .count()
-                != 1
-        {
-            show_messagebox(
-                "Chotto",
-                "Please place exactly one PNG and one TTF file into the directory where `chotto.exe` is located and then restart Chotto",
-                false,
-            );
-            std::process::abort();
-        }
+fn linear_to_srgb(value: f32) -> f32 {
+    let value = value.clamp(0.0, 1.0);
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
 
-        let mut background_bitmap = Bitmap::new_empty();
-        let mut font = None;
-        for filepath in collect_files(".") {
-            if filepath.to_lowercase().ends_with(".png") {
-                background_bitmap = Bitmap::from_png_file_or_panic(&filepath);
-                assert!(
-                    background_bitmap.width != 0 && background_bitmap.height != 0,
-                    "Image file '{}' is 0x0 pixels which is not allowed - is the file ok?",
-                    filepath
-                );
-            }
-            if filepath.to_lowercase().ends_with(".ttf") {
-                let font_data = read_file_whole(&filepath)
-                    .expect(&format!("Cannot read font file '{}'", filepath));
-                font = Some(
-                    fontdue::Font::from_bytes(font_data, fontdue::FontSettings::default()).expect(
-                        &format!("Cannot decode font file '{}' - is the file ok?", filepath),
-                    ),
-                );
-            }
-        }
+/// Returns the extended bitmap and the offset of the original bitmap (the trim box) within it
+fn extend_with_bleed_and_crop_marks(
+    bitmap: &Bitmap,
+    bleed_px: i32,
+    bleed_color: Option<PixelRGBA>,
+    crop_marks: bool,
+) -> (Bitmap, Vec2i) {
+    let trim_width = bitmap.width as i32;
+    let trim_height = bitmap.height as i32;
+    let mark_thickness = i32::max(1, i32::min(trim_width, trim_height) / 1000);
+    let mark_length = i32::max(bleed_px, i32::min(trim_width, trim_height) / 30);
+    let mark_gap = i32::max(2 * mark_thickness, bleed_px / 2);
+    let marks_margin = if crop_marks {
+        mark_gap + mark_length
+    } else {
+        0
+    };
+
+    let offset = bleed_px + marks_margin;
+    let mut result = Bitmap::new(
+        (trim_width + 2 * offset) as u32,
+        (trim_height + 2 * offset) as u32,
+    );
+    let result_width = result.width as i32;
+    let result_height = result.height as i32;
 
-        if font.is_none() {
-            unreachable!();
+    let mirrored = |value: i32, size: i32| -> i32 {
+        if value < 0 {
+            i32::min(-value - 1, size - 1)
+        } else if value >= size {
+            i32::max(2 * size - value - 1, 0)
+        } else {
+            value
         }
+    };
+    for y in 0..result_height {
+        for x in 0..result_width {
+            let source_x = x - offset;
+            let source_y = y - offset;
+            let is_inside_trim =
+                0 <= source_x && source_x < trim_width && 0 <= source_y && source_y < trim_height;
+            let is_inside_bleed = -bleed_px <= source_x
+                && source_x < trim_width + bleed_px
+                && -bleed_px <= source_y
+                && source_y < trim_height + bleed_px;
 
-        const TOML_DOCUMENTATION_HEADER: &str =
-"####################################################################################################
-#
-# In this file we can change various things about how Chotto should draw our Bingo-sheets by editing
-# the four parameters at the bottom.
-#
-# The `number_of_sheets_to_generate` parameter indicates how many Bingo-sheets we want Chotto 
-# to generate. The final sheets will be placed in the `output_sheets` directory once Chotto was run.
-#
-# The `text_font_size` and `text_color_rgb` paramters can be used to customize the final text 
-# size and color. The color values are [Red, Green, Blue] in order and each range between 0-255.
-# The font size is given in pixel-height. Note though that the final numbers on the grid may be 
-# slightly smaller than the given font size. We can just try out some values until it looks good.
-#
-# The `bingo_grid_pixel_location_left_top_right_bottom` parameter defines the rectangular region
-# in the image where the Bingo numbers will be drawn to. The values are [Left, Top, Right, Bottom]
-# and are given in pixels.
-#
-# For example if we have a 100x100px image and only want numbers drawn on the bottom half of the 
-# image we can write:
-#
-# bingo_grid_pixel_location_left_top_right_bottom = [0, 50, 100, 100]
-#
-####################################################################################################";
-        const DRAW_PARAMETERS_FILENAME: &str = "draw_parameters.txt";
-        if !path_exists(DRAW_PARAMETERS_FILENAME) {
-            let params = DrawParams {
-                number_of_sheets_to_generate: 10,
-                text_font_size: background_bitmap.height as u32 / 20,
-                text_color_rgb: (255, 128, 64),
-                bingo_grid_pixel_location_left_top_right_bottom: (
-                    0,
-                    0,
-                    background_bitmap.width as u32,
-                    background_bitmap.height as u32,
-                ),
+            let pixel = if is_inside_trim {
+                bitmap.get(source_x, source_y)
+            } else if is_inside_bleed {
+                bleed_color.unwrap_or_else(|| {
+                    bitmap.get(
+                        mirrored(source_x, trim_width),
+                        mirrored(source_y, trim_height),
+                    )
+                })
+            } else {
+                PixelRGBA::white()
             };
-            let params_string = format!(
-                "{}\n\n{}",
-                TOML_DOCUMENTATION_HEADER,
-                toml::to_string(&params).unwrap()
+            result.set(x, y, pixel);
+        }
+    }
+
+    if crop_marks {
+        let mark_color = PixelRGBA::black();
+        let trim_left = offset;
+        let trim_top = offset;
+        let trim_right = offset + trim_width;
+        let trim_bottom = offset + trim_height;
+        let half_thickness = mark_thickness / 2;
+
+        // Crop marks
+        for &x in &[trim_left, trim_right] {
+            fill_rect(
+                &mut result,
+                x - half_thickness,
+                0,
+                mark_thickness,
+                mark_length,
+                mark_color,
             );
-            std::fs::write(DRAW_PARAMETERS_FILENAME, &params_string).expect(&format!(
-                "Could not create file '{}'",
-                DRAW_PARAMETERS_FILENAME
-            ));
-            show_messagebox(
-                "Chotto",
-                &format!(
-                    "Please first fill out the '{}' in the directory where 'chotto.exe' is located and then restart Chotto",
-                    DRAW_PARAMETERS_FILENAME
-                ),
-                false,
+            fill_rect(
+                &mut result,
+                x - half_thickness,
+                result_height - mark_length,
+                mark_thickness,
+                mark_length,
+                mark_color,
+            );
+        }
+        for &y in &[trim_top, trim_bottom] {
+            fill_rect(
+                &mut result,
+                0,
+                y - half_thickness,
+                mark_length,
+                mark_thickness,
+                mark_color,
+            );
+            fill_rect(
+                &mut result,
+                result_width - mark_length,
+                y - half_thickness,
+                mark_length,
+                mark_thickness,
+                mark_color,
             );
-            std::process::abort();
         }
 
-        let params: DrawParams = toml::from_str(
-            &std::fs::read_to_string(DRAW_PARAMETERS_FILENAME).expect(&format!(
-                "Could not read file '{}'",
-                DRAW_PARAMETERS_FILENAME
-            )),
-        )
-        .unwrap_or_else(|error| panic!("Could not read draw parameters: {}", error));
-
-        assert!(
-            params.number_of_sheets_to_generate <= MAX_SHEET_COUNT,
-            "The maximum sheet count is {} - please reduce it in '{}'!",
-            MAX_SHEET_COUNT,
-            DRAW_PARAMETERS_FILENAME
-        );
-        Input {
-            background_bitmap,
-            font: font.unwrap(),
-            params,
+        // Registration marks
+        let radius = mark_length / 3;
+        for &center in &[
+            Vec2i::new(result_width / 2, mark_length / 2),
+            Vec2i::new(result_width / 2, result_height - mark_length / 2),
+            Vec2i::new(mark_length / 2, result_height / 2),
+            Vec2i::new(result_width - mark_length / 2, result_height / 2),
+        ] {
+            fill_rect(
+                &mut result,
+                center.x - mark_length / 2,
+                center.y - half_thickness,
+                mark_length,
+                mark_thickness,
+                mark_color,
+            );
+            fill_rect(
+                &mut result,
+                center.x - half_thickness,
+                center.y - mark_length / 2,
+                mark_thickness,
+                mark_length,
+                mark_color,
+            );
+            for y in (center.y - radius - mark_thickness)..=(center.y + radius + mark_thickness) {
+                for x in (center.x - radius - mark_thickness)..=(center.x + radius + mark_thickness)
+                {
+                    let distance = f32::sqrt(
+                        ((x - center.x) * (x - center.x) + (y - center.y) * (y - center.y)) as f32,
+                    );
+                    if (distance - radius as f32).abs() <= mark_thickness as f32 / 2.0 {
+                        fill_rect(&mut result, x, y, 1, 1, mark_color);
+                    }
+                }
+            }
         }
     }
+
+    (result, Vec2i::new(offset, offset))
 }
 
-/// NOTE: In this function we make sure that each column k of each newly generated grid is
-///       maximally different to each respective column k of the previously generated grids.
-///       We do this by first generating all possible arrangements for each column. Then for
-///       each new grid we randomly pick one such arrangement for column k until we get a column
-///       that is different enough from the respective column k of the previous grids.
-/// NOTE: Doing this column based approach is more precise and faster than comparing whole grids
-///       because we can test all possibilities for each column faster. The problem is that our
-///       solution space is smaller than with the grid-based approach. This is ok for our case
-///       though as we won't generate more than `MAX_SHEET_COUNT` sheets
-///
-fn create_random_number_grids(sheet_count: usize) -> Vec<Grid<i32>> {
-    let start = SystemTime::now();
-    let since_the_epoch = start.duration_since(UNIX_EPOCH).unwrap();
-    let seed = (since_the_epoch.as_nanos() & (std::u64::MAX as u128)) as u64;
-    let mut random = Random::new_from_seed(seed);
-
-    // Create shufflebags
-    let column_bags: Vec<Shufflebag<_>> = [
-        (1..=15).collect::<Vec<_>>(),
-        (16..=30).collect::<Vec<_>>(),
-        (31..=45).collect::<Vec<_>>(),
-        (46..=60).collect::<Vec<_>>(),
-        (61..=75).collect::<Vec<_>>(),
-    ]
-    .iter()
-    .map(|column| Shufflebag::new(get_all_possible_arrangements_of_size_k(5, column)))
-    .collect();
-
-    // Create columns
-    let mut columns: Vec<Vec<Vec<i32>>> = vec![Vec::new(); 5];
-    for (col_index, mut column_bag) in column_bags.into_iter().enumerate() {
-        let mut matching_cells_tolerance = 0;
-        let mut failed_pick_count = 0;
-
-        while columns[col_index].len() < sheet_count {
-            let new_column = column_bag.get_next(&mut random);
-
-            if columns[col_index]
-                .iter()
-                .map(|previous_column| count_matching_cells(&new_column, previous_column))
-                .max()
-                .unwrap_or(0)
-                > matching_cells_tolerance
-            {
-                failed_pick_count += 1;
-                if failed_pick_count >= column_bag.elems.len() {
-                    // We tried out all possible arrangements with this tolerance level.
-                    // To generate more columns we need to increase our tolerance and therefore
-                    // allow new columns to be more similar to existing ones.
-                    matching_cells_tolerance += 1;
-                    failed_pick_count = 0;
-                    column_bag.reset();
-                }
-                continue;
-            }
+/// Returns the coverage of each pixel of a sheet with the given size that is cut out with rounded
+/// corners and/or the mask image of our parameters or `None` if our sheets are not cut out
+fn create_sheet_mask(params: &DrawParams, width: u32, height: u32) -> Option<Vec<u8>> {
+    if params.corner_radius_px.is_none() && params.mask_image_filepath.is_none() {
+        return None;
+    }
 
-            columns[col_index].push(new_column);
+    let mut mask = vec![255u8; (width * height) as usize];
+    if let Some(filepath) = &params.mask_image_filepath {
+        let mut mask_image = Bitmap::from_png_file_or_panic(filepath);
+        if mask_image.width != width || mask_image.height != height {
+            mask_image = resample_bilinear(&mask_image, width as i32, height as i32);
+        }
+        // NOTE: Masks without transparency are usually drawn in black and white
+        let use_brightness = mask_image.data.iter().all(|pixel| pixel.a == 255);
+        for (coverage, pixel) in mask.iter_mut().zip(mask_image.data.iter()) {
+            *coverage = if use_brightness {
+                ((pixel.r as u32 + pixel.g as u32 + pixel.b as u32) / 3) as u8
+            } else {
+                pixel.a
+            };
         }
     }
 
-    // Create grids out of our columns
-    (0..sheet_count)
-        .into_iter()
-        .map(|sheet_index| {
-            let mut grid = Grid::new(5, 5);
+    if let Some(radius) = params.corner_radius_px {
+        let radius = f32::min(radius as f32, f32::min(width as f32, height as f32) / 2.0);
+        for y in 0..height {
+            for x in 0..width {
+                // NOTE: Only pixels in the corner squares can be outside of the rounded rect
+                let pixel_x = x as f32 + 0.5;
+                let pixel_y = y as f32 + 0.5;
+                let corner_x = pixel_x.clamp(radius, width as f32 - radius);
+                let corner_y = pixel_y.clamp(radius, height as f32 - radius);
+                let distance = f32::hypot(pixel_x - corner_x, pixel_y - corner_y);
+                let corner_coverage = (radius - distance + 0.5).clamp(0.0, 1.0);
+                let coverage = &mut mask[(y * width + x) as usize];
+                *coverage = (*coverage as f32 * corner_coverage).round() as u8;
+            }
+        }
+    }
+    Some(mask)
+}
 
-            for y in 0..5 {
-                for x in 0..5 {
-                    if y == 2 && x == 2 {
-                        continue;
-                    }
+/// Multiplies each pixel of the given bitmap with its coverage of the given mask of a sheet with
+/// the given width. The bitmap lies at the given position of that sheet.
+fn apply_mask_premultiplied(bitmap: &mut Bitmap, pos: Vec2i, mask: &[u8], mask_width: u32) {
+    let width = bitmap.width as usize;
+    for (y, row) in bitmap.data.chunks_mut(width.max(1)).enumerate() {
+        let mask_start = (pos.y as usize + y) * mask_width as usize + pos.x as usize;
+        apply_mask_to_row_premultiplied(row, &mask[mask_start..mask_start + width]);
+    }
+}
 
-                    grid.set(x, y, columns[x as usize][sheet_index][y as usize]);
-                }
+fn apply_mask_to_row_premultiplied(row: &mut [PixelRGBA], mask: &[u8]) {
+    for (pixel, &coverage) in row.iter_mut().zip(mask.iter()) {
+        match coverage {
+            255 => {}
+            0 => *pixel = PixelRGBA::transparent(),
+            _ => {
+                let scale = |channel: u8| (channel as u32 * coverage as u32 / 255) as u8;
+                *pixel = PixelRGBA::new(
+                    scale(pixel.r),
+                    scale(pixel.g),
+                    scale(pixel.b),
+                    scale(pixel.a),
+                );
             }
-            grid
-        })
-        .collect()
+        }
+    }
 }
 
-fn get_all_possible_arrangements_of_size_k<ElemType: Clone + Copy + Eq + PartialEq>(
-    k: usize,
-    elements: &[ElemType],
-) -> Vec<Vec<ElemType>> {
-    assert!(0 < k && k <= elements.len());
+fn draw_rect_outline(
+    bitmap: &mut Bitmap,
+    left: i32,
+    top: i32,
+    width: i32,
+    height: i32,
+    color: PixelRGBA,
+) {
+    fill_rect(bitmap, left, top, width, 1, color);
+    fill_rect(bitmap, left, top + height - 1, width, 1, color);
+    fill_rect(bitmap, left, top, 1, height, color);
+    fill_rect(bitmap, left + width - 1, top, 1, height, color);
+}
 
-    if k == 1 {
-        let mut result = Vec::new();
-        for elem in elements {
-            result.push(vec![*elem]);
+/// Fills the given rectangle while ignoring all pixels outside of the bitmap
+fn fill_rect(bitmap: &mut Bitmap, left: i32, top: i32, width: i32, height: i32, color: PixelRGBA) {
+    let bitmap_width = bitmap.width as i32;
+    let bitmap_height = bitmap.height as i32;
+    for y in i32::max(top, 0)..i32::min(top + height, bitmap_height) {
+        for x in i32::max(left, 0)..i32::min(left + width, bitmap_width) {
+            bitmap.set(x, y, color);
         }
-        return result;
     }
+}
+
+fn create_number_chip_premultiplied(
+    number_chip: &NumberChip,
+    cell_width: i32,
+    cell_height: i32,
+) -> Bitmap {
+    let cell_size = i32::min(cell_width, cell_height) as f32;
+    let (width, height, radius) = match number_chip.shape {
+        NumberChipShape::Circle => {
+            let radius = number_chip
+                .radius_px
+                .map(|radius| radius as f32)
+                .unwrap_or(0.4 * cell_size);
+            (2.0 * radius, 2.0 * radius, radius)
+        }
+        NumberChipShape::RoundedRect => {
+            let padding = number_chip
+                .padding_px
+                .map(|padding| padding as f32)
+                .unwrap_or(0.1 * cell_size);
+            let width = f32::max(1.0, cell_width as f32 - 2.0 * padding);
+            let height = f32::max(1.0, cell_height as f32 - 2.0 * padding);
+            let radius = number_chip
+                .radius_px
+                .map(|radius| radius as f32)
+                .unwrap_or(0.2 * cell_size)
+                .min(0.5 * f32::min(width, height));
+            (width, height, radius)
+        }
+    };
 
-    let k_minus_one_subsets = get_all_possible_arrangements_of_size_k(k - 1, elements);
+    let (r, g, b, a) = number_chip.color_rgba;
+    let mut chip = Bitmap::new(width.ceil() as u32, height.ceil() as u32);
+    let half_width = width / 2.0;
+    let half_height = height / 2.0;
+    for y in 0..chip.height as i32 {
+        for x in 0..chip.width as i32 {
+            // NOTE: We use the signed distance of the pixel center to the rounded rect for
+            //       antialiasing of the edges
+            let qx = (x as f32 + 0.5 - half_width).abs() - (half_width - radius);
+            let qy = (y as f32 + 0.5 - half_height).abs() - (half_height - radius);
+            let outside_distance = f32::hypot(f32::max(qx, 0.0), f32::max(qy, 0.0));
+            let inside_distance = f32::min(f32::max(qx, qy), 0.0);
+            let distance = outside_distance + inside_distance - radius;
+            let coverage = (0.5 - distance).max(0.0).min(1.0);
 
-    let mut result = Vec::new();
-    for k_minus_one_subset in k_minus_one_subsets {
-        for &fixed in elements {
-            let mut subset = k_minus_one_subset.clone();
-            if subset.contains(&fixed) {
-                continue;
+            let alpha = a as f32 * coverage / 255.0;
+            chip.set(
+                x,
+                y,
+                PixelRGBA::new(
+                    (r as f32 * alpha).round() as u8,
+                    (g as f32 * alpha).round() as u8,
+                    (b as f32 * alpha).round() as u8,
+                    (255.0 * alpha).round() as u8,
+                ),
+            );
+        }
+    }
+    chip
+}
+
+/// Creates a five-pointed star with the given outer radius
+fn create_star_badge_premultiplied(radius: f32, color_rgb: (u8, u8, u8)) -> Bitmap {
+    let inner_radius = 0.4 * radius;
+    let corners: Vec<(f32, f32)> = (0..10)
+        .map(|index| {
+            let corner_radius = if index % 2 == 0 { radius } else { inner_radius };
+            let angle = std::f32::consts::PI * (index as f32 / 5.0 - 0.5);
+            (
+                radius + corner_radius * angle.cos(),
+                radius + corner_radius * angle.sin(),
+            )
+        })
+        .collect();
+    let is_inside = |x: f32, y: f32| {
+        let mut inside = false;
+        for index in 0..corners.len() {
+            let (x0, y0) = corners[index];
+            let (x1, y1) = corners[(index + 1) % corners.len()];
+            if (y0 > y) != (y1 > y) && x < x0 + (y - y0) * (x1 - x0) / (y1 - y0) {
+                inside = !inside;
+            }
+        }
+        inside
+    };
+
+    // NOTE: We sample each pixel 4x4 times for antialiasing of the edges
+    const SAMPLES: i32 = 4;
+    let (r, g, b) = color_rgb;
+    let size = (2.0 * radius).ceil() as u32;
+    let mut star = Bitmap::new(size, size);
+    for y in 0..size as i32 {
+        for x in 0..size as i32 {
+            let mut covered_sample_count = 0;
+            for sample_y in 0..SAMPLES {
+                for sample_x in 0..SAMPLES {
+                    if is_inside(
+                        x as f32 + (sample_x as f32 + 0.5) / SAMPLES as f32,
+                        y as f32 + (sample_y as f32 + 0.5) / SAMPLES as f32,
+                    ) {
+                        covered_sample_count += 1;
+                    }
+                }
             }
-            subset.push(fixed);
-            result.push(subset);
+            let coverage = covered_sample_count as f32 / (SAMPLES * SAMPLES) as f32;
+            star.set(
+                x,
+                y,
+                PixelRGBA::new(
+                    (r as f32 * coverage).round() as u8,
+                    (g as f32 * coverage).round() as u8,
+                    (b as f32 * coverage).round() as u8,
+                    (255.0 * coverage).round() as u8,
+                ),
+            );
         }
     }
+    star
+}
 
-    result
+/// Loads the given image and resizes it to fit into a grid cell while keeping its aspect ratio
+fn load_cell_image_premultiplied(filepath: &str, cell_width: i32, cell_height: i32) -> Bitmap {
+    let image = load_background_image(filepath).to_premultiplied_alpha();
+    let scale = f32::min(
+        cell_width as f32 / image.width as f32,
+        cell_height as f32 / image.height as f32,
+    );
+    resize_background_image(
+        &image,
+        u32::max(1, (image.width as f32 * scale).round() as u32),
+        u32::max(1, (image.height as f32 * scale).round() as u32),
+    )
 }
 
-fn count_matching_cells(column: &[i32], existing_column: &[i32]) -> usize {
-    column
+/// Returns the filename and bitmap of each image in the given directory sorted by filename. The
+/// bitmaps are resized to fit into a grid cell.
+fn load_free_space_images_premultiplied(
+    directory: &str,
+    cell_width: i32,
+    cell_height: i32,
+) -> Vec<(String, Bitmap)> {
+    let mut filepaths: Vec<String> = collect_files(directory)
+        .into_iter()
+        .filter(|filepath| {
+            BACKGROUND_IMAGE_EXTENSIONS
+                .iter()
+                .any(|extension| filepath.to_lowercase().ends_with(extension))
+        })
+        .collect();
+    filepaths.sort();
+    assert!(
+        !filepaths.is_empty(),
+        "There are no images in the free space images directory '{}'",
+        directory
+    );
+
+    filepaths
         .iter()
-        .zip(existing_column.iter())
-        .filter(|(left, right)| left == right)
-        .count()
+        .map(|filepath| {
+            let image = load_cell_image_premultiplied(filepath, cell_width, cell_height);
+            let name = std::path::Path::new(filepath)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string();
+            (name, image)
+        })
+        .collect()
 }
 
-fn create_number_bitmaps_premultiplied(
-    font: fontdue::Font,
-    font_size: f32,
-    color: Color,
-) -> HashMap<i32, Bitmap> {
-    let digits_metrics_bitmaps_premultiplied: HashMap<char, (fontdue::Metrics, Bitmap)> =
-        "0123456789"
-            .chars()
-            .map(|digit| (digit, font.rasterize(digit, font_size)))
-            .map(|(digit, (metrics, image_bytes))| {
-                let mut bitmap_premultiplied = Bitmap::from_greyscale_bytes_premultiplied(
-                    &image_bytes,
-                    metrics.width as u32,
-                    metrics.height as u32,
-                );
-                for pixel in bitmap_premultiplied.data.iter_mut() {
-                    pixel.r = ((pixel.r as f32) * color.r) as u8;
-                    pixel.g = ((pixel.g as f32) * color.g) as u8;
-                    pixel.b = ((pixel.b as f32) * color.b) as u8;
-                }
-                (digit, (metrics, bitmap_premultiplied))
-            })
-            .collect();
-
-    // for (digit, (_metrics, bitmap_premultiplied)) in digits_metrics_bitmaps_premultiplied.iter() {
-    //     bitmap_premultiplied
-    //         .to_unpremultiplied_alpha()
-    //         .write_to_png_file(&format!("target/test_digits/{}.png", digit));
-    // }
+fn create_overlay_bitmap_premultiplied(filepath: &str, scale: f32, opacity: f32) -> Bitmap {
+    assert!(
+        scale > 0.0,
+        "The overlay scale must be bigger than 0 - please fix it in '{}'!",
+        DRAW_PARAMETERS_FILENAME
+    );
+    assert!(
+        0.0 <= opacity && opacity <= 1.0,
+        "The overlay opacity must be between 0.0 and 1.0 - please fix it in '{}'!",
+        DRAW_PARAMETERS_FILENAME
+    );
 
-    let mut number_bitmaps_premultiplied = HashMap::new();
-    for number in 1..=75 {
-        let number_string = number.to_string();
-        let mut layout =
-            fontdue::layout::Layout::new(fontdue::layout::CoordinateSystem::PositiveYDown);
-        layout.append(
-            &[&font],
-            &fontdue::layout::TextStyle::new(&number_string, font_size, 0),
-        );
-        let glyphs = layout.glyphs().clone();
+    let overlay = Bitmap::from_png_file_or_panic(filepath).to_premultiplied_alpha();
+    let mut overlay = resample_bilinear(
+        &overlay,
+        i32::max(1, (overlay.width as f32 * scale).round() as i32),
+        i32::max(1, (overlay.height as f32 * scale).round() as i32),
+    );
+    for pixel in overlay.data.iter_mut() {
+        pixel.r = ((pixel.r as f32) * opacity).round() as u8;
+        pixel.g = ((pixel.g as f32) * opacity).round() as u8;
+        pixel.b = ((pixel.b as f32) * opacity).round() as u8;
+        pixel.a = ((pixel.a as f32) * opacity).round() as u8;
+    }
+    overlay
+}
 
-        let x_min = glyphs
-            .iter()
-            .fold(std::f32::MAX, |acc, glyph_pos| f32::min(acc, glyph_pos.x));
-        let y_min = glyphs
-            .iter()
-            .fold(std::f32::MAX, |acc, glyph_pos| f32::min(acc, glyph_pos.y));
-        let offset_x = if x_min < 0.0 { -x_min } else { 0.0 };
-        let offset_y = if y_min < 0.0 { -y_min } else { 0.0 };
-        let x_max = offset_x
-            + glyphs.iter().fold(std::f32::MIN, |acc, glyph_pos| {
-                f32::max(acc, glyph_pos.x + glyph_pos.width as f32)
-            });
-        let y_max = offset_y
-            + glyphs.iter().fold(std::f32::MIN, |acc, glyph_pos| {
-                f32::max(acc, glyph_pos.y + glyph_pos.height as f32)
-            });
+/// Resizes the given bitmap with bilinear filtering. Should only be used with premultiplied alpha
+/// bitmaps or bitmaps without transparency.
+fn resample_bilinear(bitmap: &Bitmap, new_width: i32, new_height: i32) -> Bitmap {
+    let width = bitmap.width as i32;
+    let height = bitmap.height as i32;
+    let mut result = Bitmap::new(new_width as u32, new_height as u32);
+    for y in 0..new_height {
+        for x in 0..new_width {
+            // NOTE: We sample at the pixel centers
+            let source_x = ((x as f32 + 0.5) * width as f32 / new_width as f32 - 0.5)
+                .clamp(0.0, (width - 1) as f32);
+            let source_y = ((y as f32 + 0.5) * height as f32 / new_height as f32 - 0.5)
+                .clamp(0.0, (height - 1) as f32);
+            let left = source_x.floor() as i32;
+            let top = source_y.floor() as i32;
+            let right = i32::min(left + 1, width - 1);
+            let bottom = i32::min(top + 1, height - 1);
+            let percent_x = source_x - left as f32;
+            let percent_y = source_y - top as f32;
 
-        let mut number_bitmap_premultiplied = Bitmap::new(x_max.ceil() as u32, y_max.ceil() as u32);
-        for glyphpos in glyphs.iter() {
-            let digit = glyphpos.key.c;
-            let (_digit_metrics, digit_bitmap_premultiplied) =
-                digits_metrics_bitmaps_premultiplied.get(&digit).unwrap();
-            digit_bitmap_premultiplied.blit_to_alpha_blended_premultiplied(
-                &mut number_bitmap_premultiplied,
-                Vec2i::new(
-                    (offset_x + glyphpos.x.round()) as i32,
-                    (offset_y + glyphpos.y.round()) as i32,
+            let top_left = bitmap.get(left, top);
+            let top_right = bitmap.get(right, top);
+            let bottom_left = bitmap.get(left, bottom);
+            let bottom_right = bitmap.get(right, bottom);
+            let interpolate = |top_left: u8, top_right: u8, bottom_left: u8, bottom_right: u8| {
+                let top = top_left as f32 + percent_x * (top_right as f32 - top_left as f32);
+                let bottom =
+                    bottom_left as f32 + percent_x * (bottom_right as f32 - bottom_left as f32);
+                (top + percent_y * (bottom - top)).round() as u8
+            };
+            result.set(
+                x,
+                y,
+                PixelRGBA::new(
+                    interpolate(top_left.r, top_right.r, bottom_left.r, bottom_right.r),
+                    interpolate(top_left.g, top_right.g, bottom_left.g, bottom_right.g),
+                    interpolate(top_left.b, top_right.b, bottom_left.b, bottom_right.b),
+                    interpolate(top_left.a, top_right.a, bottom_left.a, bottom_right.a),
                 ),
-                true,
-                cottontail::image::ColorBlendMode::Normal,
             );
-
-            // println!("{:#?}", &glyphpos);
-            // println!("{:#?}", &digit_metrics);
         }
+    }
+    result
+}
 
-        number_bitmap_premultiplied.trim_by_value(true, true, true, true, PixelRGBA::transparent());
-        // number_bitmap_premultiplied
-        //     .to_unpremultiplied_alpha()
-        //     .write_to_png_file(&format!("target/test_numbers/{}.png", number));
-
-        number_bitmaps_premultiplied.insert(number, number_bitmap_premultiplied);
+/// Replaces all `{name}` placeholders in the given text with their respective values. Unknown
+/// placeholders are left as they are.
+fn expand_text_variables(text: &str, variables: &HashMap<String, String>) -> String {
+    let mut result = text.to_owned();
+    for (name, value) in variables {
+        result = result.replace(&format!("{{{}}}", name), value);
     }
-    number_bitmaps_premultiplied
+    result
+}
+
+fn color_from_rgb(rgb: (u8, u8, u8)) -> Color {
+    PixelRGBA::new(rgb.0, rgb.1, rgb.2, 255).to_color()
+}
+
+fn color_from_rgba(rgba: (u8, u8, u8, u8)) -> Color {
+    PixelRGBA::new(rgba.0, rgba.1, rgba.2, rgba.3).to_color()
 }
 
 #[cfg(windows)]
 fn show_messagebox(caption: &str, message: &str, is_error: bool) {
+    if HEADLESS.load(Ordering::SeqCst) {
+        return;
+    }
+
     use std::iter::once;
     use std::os::windows::ffi::OsStrExt;
     use std::ptr::null_mut;
@@ -481,10 +6353,44 @@ fn show_messagebox(caption: &str, message: &str, is_error: bool) {
     };
 }
 
+#[cfg(windows)]
+fn show_messagebox_yes_no(caption: &str, message: &str) -> bool {
+    if HEADLESS.load(Ordering::SeqCst) {
+        log::warn!("Answering 'no' to '{}' because we run headless", message);
+        return false;
+    }
+
+    use std::iter::once;
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr::null_mut;
+    use winapi::um::winuser::{MessageBoxW, IDYES, MB_ICONWARNING, MB_YESNO};
+
+    let caption_wide: Vec<u16> = std::ffi::OsStr::new(caption)
+        .encode_wide()
+        .chain(once(0))
+        .collect();
+    let message_wide: Vec<u16> = std::ffi::OsStr::new(message)
+        .encode_wide()
+        .chain(once(0))
+        .collect();
+
+    let result = unsafe {
+        MessageBoxW(
+            null_mut(),
+            message_wide.as_ptr(),
+            caption_wide.as_ptr(),
+            MB_YESNO | MB_ICONWARNING,
+        )
+    };
+    result == IDYES
+}
+
 fn set_panic_hook() {
     std::panic::set_hook(Box::new(|panic_info| {
         let (message, location) = panic_message_split_to_message_and_location(panic_info);
         let final_message = format!("{}\n\nError occured at: {}", message, location);
+        log::error!("{}", final_message);
+        log::logger().flush();
 
         show_messagebox("Chotto Error", &final_message, true);
 
@@ -492,3 +6398,162 @@ fn set_panic_hook() {
         std::process::abort();
     }));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_round_trip_through_toml(params: &DrawParams) {
+        let params_string = draw_params_to_toml_string(params);
+        let read_params: DrawParams = toml::from_str(&params_string)
+            .unwrap_or_else(|error| panic!("Could not read back '{}': {}", params_string, error));
+        assert_eq!(draw_params_to_toml_string(&read_params), params_string);
+    }
+
+    #[test]
+    fn default_draw_params_round_trip_through_toml() {
+        assert_round_trip_through_toml(&DrawParams::default());
+        assert_round_trip_through_toml(&default_draw_params(2480, 3508));
+    }
+
+    #[test]
+    fn draw_params_with_tables_before_values_round_trip_through_toml() {
+        let mut params = default_draw_params(2480, 3508);
+        params.cell_pools.insert(
+            "drinks".to_owned(),
+            vec!["Coffee".to_owned(), "Tea".to_owned()],
+        );
+        params.excluded_numbers = vec![13];
+        params.extra_texts = Some(vec![ExtraText {
+            text: "Summer Fair".to_owned(),
+            x: 1240,
+            y: 200,
+            size: Some(80),
+            color: None,
+        }]);
+        assert_round_trip_through_toml(&params);
+
+        let params_string = draw_params_to_toml_string(&params);
+        assert!(
+            params_string.find("excluded_numbers").unwrap()
+                < params_string.find("[cell_pools]").unwrap()
+        );
+    }
+
+    fn create_test_cards(params: &DrawParams, seed: u64) -> Vec<Card> {
+        let mut random = Random::new_from_seed(seed);
+        let number_grids =
+            generator::create_random_number_grids(40, &[], &[(2, 2)], &[], &[], &mut random);
+        create_cards_from_number_grids(params, &[], &[], &number_grids, &mut random)
+    }
+
+    #[test]
+    fn sheets_show_the_same_cards_with_any_number_of_threads() {
+        let mut params = default_draw_params(2480, 3508);
+        params.serial_number.scheme = serial_number::SerialNumberScheme::Random;
+        params.shuffle_output_order = true;
+        params.sheets_per_directory = Some(7);
+
+        let sheets_drawn_with_threads = |thread_count: usize| {
+            let thread_pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(thread_count)
+                .build()
+                .unwrap();
+            let cards = thread_pool.install(|| create_test_cards(&params, 1234));
+            map_cards_in_parallel(&thread_pool, &cards, (), |_, card| {
+                // NOTE: With several threads the later sheets finish first
+                let wait_millis = (cards.len() - card.sheet_number) as u64;
+                std::thread::sleep(std::time::Duration::from_millis(wait_millis));
+                (
+                    sheet_filepath(&params, OUTPUT_SHEETS_DIRECTORY, card.sheet_number, None),
+                    card.serial.clone(),
+                    card.numbers.clone(),
+                )
+            })
+        };
+        let sheets = sheets_drawn_with_threads(1);
+        assert_eq!(sheets.len(), 40);
+        assert_eq!(
+            sheets
+                .iter()
+                .map(|(sheet_filepath, _, _)| sheet_filepath)
+                .collect::<HashSet<_>>()
+                .len(),
+            sheets.len()
+        );
+        assert_eq!(sheets_drawn_with_threads(8), sheets);
+    }
+
+    #[test]
+    fn shuffling_the_output_order_keeps_each_serial_with_its_card() {
+        let mut params = default_draw_params(2480, 3508);
+        let cards = create_test_cards(&params, 1234);
+        params.shuffle_output_order = true;
+        let shuffled_cards = create_test_cards(&params, 1234);
+
+        assert_eq!(
+            shuffled_cards
+                .iter()
+                .map(|card| card.sheet_number)
+                .collect::<Vec<_>>(),
+            (1..=cards.len()).collect::<Vec<_>>()
+        );
+        assert!(shuffled_cards
+            .iter()
+            .any(|card| card.serial != card.sheet_number.to_string()));
+        for shuffled_card in &shuffled_cards {
+            let card = cards
+                .iter()
+                .find(|card| card.serial == shuffled_card.serial)
+                .unwrap();
+            assert_eq!(shuffled_card.numbers, card.numbers);
+        }
+    }
+
+    #[test]
+    fn transformed_sheet_regions_stay_on_the_transformed_sheet() {
+        let mut background = Bitmap::new(7, 5);
+        for (index, pixel) in background.data.iter_mut().enumerate() {
+            *pixel = PixelRGBA::new(index as u8, 0, 0, 255);
+        }
+        let region_pos = Vec2i::new(1, 2);
+        let mut region_bitmap = Bitmap::new(4, 2);
+        for (index, pixel) in region_bitmap.data.iter_mut().enumerate() {
+            *pixel = PixelRGBA::new(0, index as u8 + 1, 0, 255);
+        }
+        let sheet = SheetRegion {
+            pos: region_pos,
+            bitmap: region_bitmap.clone(),
+        };
+        let whole_sheet = ComposedSheet::new(&background, &sheet).to_bitmap();
+
+        for &rotation in &[0, 90, 180, 270] {
+            for &mirror in &[
+                OutputMirror::None,
+                OutputMirror::Horizontal,
+                OutputMirror::Vertical,
+            ] {
+                let region = transform_sheet_region(
+                    SheetRegion {
+                        pos: region_pos,
+                        bitmap: region_bitmap.clone(),
+                    },
+                    7,
+                    5,
+                    rotation,
+                    mirror,
+                );
+                let transformed_background = transform_sheet(background.clone(), rotation, mirror);
+                assert_eq!(
+                    ComposedSheet::new(&transformed_background, &region)
+                        .to_bitmap()
+                        .data,
+                    transform_sheet(whole_sheet.clone(), rotation, mirror).data,
+                    "Rotation {} with mirror {:?}",
+                    rotation,
+                    mirror
+                );
+            }
+        }
+    }
+}
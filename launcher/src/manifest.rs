@@ -0,0 +1,191 @@
+//! The manifest lists the contents of each sheet of a run so that we can later look up which
+//! numbers (and other randomized content) a given sheet has
+
+use cottontail::{
+    core::serde_derive::{Deserialize, Serialize},
+    image::Grid,
+};
+
+use crate::atomic_file;
+
+pub const MANIFEST_FILENAME: &str = "manifest.json";
+
+/// Everything that makes a sheet different from the other sheets of the same run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Card {
+    /// Starts at 1 and matches the number in the filename of the sheet
+    pub sheet_number: usize,
+    /// The serial number that is printed on the sheet
+    pub serial: String,
+    /// The numbers of the grid row by row where empty cells and the free space are 0
+    pub numbers: Vec<Vec<i32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub free_space_image: Option<String>,
+    /// The name of the color of our `accent_palette` that this card was drawn with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accent_color: Option<String>,
+    /// The [column, row] locations of the bonus cells
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bonus_cells: Vec<(usize, usize)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub security_code: Option<String>,
+    /// The lucky numbers of our draw parameters that are on this card
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lucky_numbers: Vec<i32>,
+    /// The name or table number of a seating card
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seating_card_text: Option<String>,
+    /// The big number of a countdown card
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub countdown_number: Option<u32>,
+}
+
+impl Card {
+    pub fn new(sheet_number: usize, number_grid: &Grid<i32>) -> Card {
+        let numbers = (0..number_grid.height as i32)
+            .map(|y| {
+                (0..number_grid.width as i32)
+                    .map(|x| number_grid.get(x, y))
+                    .collect()
+            })
+            .collect();
+        Card {
+            sheet_number,
+            serial: sheet_number.to_string(),
+            numbers,
+            free_space_image: None,
+            accent_color: None,
+            bonus_cells: Vec::new(),
+            security_code: None,
+            lucky_numbers: Vec::new(),
+            seating_card_text: None,
+            countdown_number: None,
+        }
+    }
+}
+
+impl Card {
+    pub fn number_grid(&self) -> Grid<i32> {
+        let height = self.numbers.len();
+        let width = self.numbers.first().map(|row| row.len()).unwrap_or(0);
+        let mut grid = Grid::new(width as u32, height as u32);
+        for (y, row) in self.numbers.iter().enumerate() {
+            for (x, number) in row.iter().enumerate() {
+                grid.set(x as i32, y as i32, *number);
+            }
+        }
+        grid
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub cards: Vec<Card>,
+}
+
+/// How our sheets are split into subdirectories of the output directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectoryLayout {
+    Flat,
+    /// Subdirectories `000`, `001`, ... with the given number of sheets each
+    Shards {
+        sheets_per_directory: usize,
+    },
+    /// Subdirectories `player_001`, `player_002`, ... with all sheets of one player each
+    Players {
+        cards_per_player: usize,
+    },
+}
+
+impl DirectoryLayout {
+    /// Returns the name of the subdirectory that the given sheet is written to
+    pub fn subdirectory_name(self, sheet_number: usize) -> Option<String> {
+        match self {
+            DirectoryLayout::Flat => None,
+            DirectoryLayout::Shards {
+                sheets_per_directory,
+            } => Some(format!("{:03}", (sheet_number - 1) / sheets_per_directory)),
+            DirectoryLayout::Players { cards_per_player } => Some(format!(
+                "player_{:03}",
+                (sheet_number - 1) / cards_per_player + 1
+            )),
+        }
+    }
+}
+
+/// Writes the manifest into the given directory. If our layout has subdirectories each of them
+/// gets its own manifest that only lists the sheets in that subdirectory.
+pub fn write_manifest(directory: &str, manifest: &Manifest, layout: DirectoryLayout) {
+    if layout != DirectoryLayout::Flat {
+        let mut subdirectories: Vec<(String, Manifest)> = Vec::new();
+        for card in &manifest.cards {
+            let subdirectory_name = layout.subdirectory_name(card.sheet_number).unwrap();
+            match subdirectories.last_mut() {
+                Some((name, subdirectory)) if *name == subdirectory_name => {
+                    subdirectory.cards.push(card.clone())
+                }
+                _ => subdirectories.push((
+                    subdirectory_name,
+                    Manifest {
+                        cards: vec![card.clone()],
+                    },
+                )),
+            }
+        }
+        for (subdirectory_name, subdirectory) in subdirectories {
+            write_manifest(
+                &format!("{}/{}", directory, subdirectory_name),
+                &subdirectory,
+                DirectoryLayout::Flat,
+            );
+        }
+        return;
+    }
+
+    let filepath = format!("{}/{}", directory, MANIFEST_FILENAME);
+    let manifest_string = serde_json::to_string_pretty(manifest).unwrap();
+    atomic_file::write_file_atomically(&filepath, manifest_string.as_bytes());
+}
+
+/// Returns true if the given directory (or one of its subdirectories) contains a manifest
+pub fn has_manifest(directory: &str) -> bool {
+    let directory = std::path::Path::new(directory);
+    directory.join(MANIFEST_FILENAME).exists()
+        || std::fs::read_dir(directory)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .any(|entry| entry.path().join(MANIFEST_FILENAME).exists())
+            })
+            .unwrap_or(false)
+}
+
+/// Reads the manifest of the given directory or combines the manifests of its subdirectories if
+/// our output was split into subdirectories
+pub fn read_manifest(directory: &str) -> Manifest {
+    let filepath = format!("{}/{}", directory, MANIFEST_FILENAME);
+    if !std::path::Path::new(&filepath).exists() {
+        let mut shard_directories: Vec<std::path::PathBuf> = std::fs::read_dir(directory)
+            .unwrap_or_else(|error| panic!("Could not read directory '{}': {}", directory, error))
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.join(MANIFEST_FILENAME).exists())
+            .collect();
+        shard_directories.sort();
+        assert!(
+            !shard_directories.is_empty(),
+            "There is no '{}' in '{}' - please create the sheets again first",
+            MANIFEST_FILENAME,
+            directory
+        );
+        let cards = shard_directories
+            .iter()
+            .flat_map(|shard_directory| read_manifest(&shard_directory.to_string_lossy()).cards)
+            .collect();
+        return Manifest { cards };
+    }
+
+    let manifest_string = std::fs::read_to_string(&filepath)
+        .unwrap_or_else(|error| panic!("Could not read file '{}': {}", filepath, error));
+    serde_json::from_str(&manifest_string)
+        .unwrap_or_else(|error| panic!("Could not read manifest '{}': {}", filepath, error))
+}
@@ -0,0 +1,162 @@
+//! The nicknames of our numbers. By default these are the traditional calls of British bingo halls
+//! (i.e. "two little ducks" for 22). We can write them to a CSV file via `chotto export-nicknames`,
+//! edit them there and use that file via the `nicknames_filepath` parameter.
+
+use std::collections::HashMap;
+
+use crate::{command_line_option_value, DRAW_PARAMETERS_FILENAME};
+
+pub const NICKNAMES_FILENAME: &str = "nicknames.csv";
+
+/// The traditional calls of British bingo halls
+const TRADITIONAL_NICKNAMES: [(i32, &str); 56] = [
+    (1, "Kelly's eye"),
+    (2, "one little duck"),
+    (3, "cup of tea"),
+    (4, "knock at the door"),
+    (5, "man alive"),
+    (6, "tom mix"),
+    (7, "lucky seven"),
+    (8, "garden gate"),
+    (9, "doctor's orders"),
+    (10, "cock and hen"),
+    (11, "legs eleven"),
+    (12, "one dozen"),
+    (13, "unlucky for some"),
+    (14, "valentine's day"),
+    (15, "young and keen"),
+    (16, "sweet sixteen"),
+    (17, "dancing queen"),
+    (18, "coming of age"),
+    (19, "goodbye teens"),
+    (20, "one score"),
+    (21, "key of the door"),
+    (22, "two little ducks"),
+    (23, "thee and me"),
+    (24, "two dozen"),
+    (25, "duck and dive"),
+    (26, "pick and mix"),
+    (27, "gateway to heaven"),
+    (28, "overweight"),
+    (29, "rise and shine"),
+    (30, "dirty Gertie"),
+    (31, "get up and run"),
+    (32, "buckle my shoe"),
+    (33, "all the threes"),
+    (34, "ask for more"),
+    (35, "jump and jive"),
+    (36, "three dozen"),
+    (39, "steps"),
+    (44, "droopy drawers"),
+    (45, "halfway there"),
+    (48, "four dozen"),
+    (52, "deck of cards"),
+    (55, "snakes alive"),
+    (57, "Heinz varieties"),
+    (59, "Brighton line"),
+    (66, "clickety click"),
+    (67, "stairway to heaven"),
+    (73, "queen bee"),
+    (76, "trombones"),
+    (77, "sunset strip"),
+    (81, "stop and run"),
+    (83, "time for tea"),
+    (85, "staying alive"),
+    (86, "between the sticks"),
+    (87, "Torquay in Devon"),
+    (88, "two fat ladies"),
+    (90, "top of the shop"),
+];
+
+/// Returns the nicknames of our numbers from the given CSV file with a `number` and a `nickname`
+/// column or the traditional nicknames if no file is given
+pub fn load_nicknames(filepath: Option<&str>, max_number: i32) -> HashMap<i32, String> {
+    let filepath = match filepath {
+        Some(filepath) => filepath,
+        None => {
+            return TRADITIONAL_NICKNAMES
+                .iter()
+                .filter(|(number, _nickname)| *number <= max_number)
+                .map(|(number, nickname)| (*number, nickname.to_string()))
+                .collect()
+        }
+    };
+
+    let mut reader = csv::Reader::from_path(filepath)
+        .unwrap_or_else(|error| panic!("Could not read file '{}': {}", filepath, error));
+    let headers = reader
+        .headers()
+        .unwrap_or_else(|error| panic!("Could not read file '{}': {}", filepath, error))
+        .clone();
+    let column_index = |name: &str| {
+        headers
+            .iter()
+            .position(|header| header.trim() == name)
+            .unwrap_or_else(|| {
+                panic!(
+                    "The nicknames file '{}' has no `{}` column - please fix it!",
+                    filepath, name
+                )
+            })
+    };
+    let number_column = column_index("number");
+    let nickname_column = column_index("nickname");
+
+    let mut nicknames = HashMap::new();
+    for record in reader.records() {
+        let record =
+            record.unwrap_or_else(|error| panic!("Could not read file '{}': {}", filepath, error));
+        let number_text = record.get(number_column).unwrap_or_default().trim();
+        let nickname = record.get(nickname_column).unwrap_or_default().trim();
+        if nickname.is_empty() {
+            continue;
+        }
+        let number = number_text
+            .parse::<i32>()
+            .ok()
+            .filter(|number| (1..=max_number).contains(number))
+            .unwrap_or_else(|| {
+                panic!(
+                    "The nickname '{}' in '{}' is given for '{}' which is not a number between 1 \
+                     and {} - please fix it!",
+                    nickname, filepath, number_text, max_number
+                )
+            });
+        nicknames.insert(number, nickname.to_owned());
+    }
+    nicknames
+}
+
+/// Writes the traditional nicknames to a CSV file so that we can edit them
+///
+/// Usage: `chotto export-nicknames [--output nicknames.csv]`
+pub fn export_nicknames(args: &[String]) {
+    let filepath = command_line_option_value(args, "--output")
+        .unwrap_or_else(|| NICKNAMES_FILENAME.to_owned());
+    assert!(
+        !std::path::Path::new(&filepath).exists(),
+        "The file '{}' already exists - please remove or rename it first",
+        filepath
+    );
+
+    let mut writer = csv::Writer::from_path(&filepath)
+        .unwrap_or_else(|error| panic!("Could not create file '{}': {}", filepath, error));
+    writer
+        .write_record(&["number", "nickname"])
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    for (number, nickname) in TRADITIONAL_NICKNAMES.iter() {
+        writer
+            .write_record(&[number.to_string().as_str(), nickname])
+            .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    }
+    writer
+        .flush()
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    log::info!(
+        "Wrote {} nicknames to '{}' - use it via `nicknames_filepath = \"{}\"` in '{}'",
+        TRADITIONAL_NICKNAMES.len(),
+        filepath,
+        filepath,
+        DRAW_PARAMETERS_FILENAME
+    );
+}
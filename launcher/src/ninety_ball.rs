@@ -0,0 +1,195 @@
+//! Generation of 90-ball Bingo books. A book consists of six tickets with three rows and nine
+//! columns each. Every row of a ticket contains five numbers and every column of a ticket contains
+//! at least one number. Together the six tickets of a book contain every number from 1 to 90
+//! exactly once.
+
+use crate::generator::{
+    accepted_by_all, check_rejected_grid_count, is_duplicate, shuffled, CardConstraint, RandomBag,
+    RandomSource,
+};
+use cottontail::image::Grid;
+
+pub const TICKETS_PER_BOOK: usize = 6;
+pub const TICKET_ROW_COUNT: usize = 3;
+pub const COLUMN_COUNT: usize = 9;
+const NUMBERS_PER_TICKET_ROW: usize = 5;
+const NUMBERS_PER_TICKET: usize = TICKET_ROW_COUNT * NUMBERS_PER_TICKET_ROW;
+
+/// Returns one grid per book where the tickets are stacked vertically. Empty cells are 0. The new
+/// books never duplicate the given books of an earlier batch.
+pub fn create_random_books(
+    book_count: usize,
+    constraints: &[&dyn CardConstraint],
+    previous_books: &[Grid<i32>],
+    random: &mut dyn RandomSource,
+) -> Vec<Grid<i32>> {
+    log::debug!(
+        "Generating {} 90-ball books after {} previous books",
+        book_count,
+        previous_books.len()
+    );
+
+    let mut books: Vec<Grid<i32>> = previous_books.to_vec();
+    let mut rejected_book_count = 0;
+    while books.len() < previous_books.len() + book_count {
+        let book = create_random_book(random);
+        if is_duplicate(&book, &books) || !accepted_by_all(constraints, &book, &books) {
+            rejected_book_count += 1;
+            check_rejected_grid_count(rejected_book_count);
+            continue;
+        }
+        rejected_book_count = 0;
+        books.push(book);
+    }
+    books.split_off(previous_books.len())
+}
+
+fn create_random_book(random: &mut dyn RandomSource) -> Grid<i32> {
+    let ticket_column_counts = loop {
+        if let Some(counts) = try_distribute_column_counts(random) {
+            break counts;
+        }
+    };
+
+    let mut column_numbers: Vec<Vec<i32>> = (0..COLUMN_COUNT)
+        .map(|column| shuffled(numbers_of_column(column), random))
+        .collect();
+
+    let mut grid = Grid::new(
+        COLUMN_COUNT as u32,
+        (TICKETS_PER_BOOK * TICKET_ROW_COUNT) as u32,
+    );
+    for (ticket_index, column_counts) in ticket_column_counts.iter().enumerate() {
+        let column_rows = assign_rows(column_counts, random);
+        for column in 0..COLUMN_COUNT {
+            let mut numbers: Vec<i32> = column_numbers[column]
+                .drain(..column_counts[column])
+                .collect();
+            numbers.sort();
+            for (number, row) in numbers.iter().zip(column_rows[column].iter()) {
+                let y = ticket_index * TICKET_ROW_COUNT + row;
+                grid.set(column as i32, y as i32, *number);
+            }
+        }
+    }
+    grid
+}
+
+/// Column 0 contains 1-9, column 1 contains 10-19 and so on. The last column contains 80-90.
+fn numbers_of_column(column: usize) -> Vec<i32> {
+    let first = if column == 0 { 1 } else { 10 * column as i32 };
+    let last = if column == COLUMN_COUNT - 1 {
+        90
+    } else {
+        10 * column as i32 + 9
+    };
+    (first..=last).collect()
+}
+
+/// Decides how many numbers of each column go onto each ticket of a book. Returns `None` if we
+/// painted ourselves into a corner and need to start over.
+fn try_distribute_column_counts(random: &mut dyn RandomSource) -> Option<Vec<Vec<usize>>> {
+    // Every ticket needs at least one number in each column
+    let mut counts = vec![vec![1; COLUMN_COUNT]; TICKETS_PER_BOOK];
+
+    let mut remaining_columns = Vec::new();
+    for column in 0..COLUMN_COUNT {
+        let remaining_count = numbers_of_column(column).len() - TICKETS_PER_BOOK;
+        remaining_columns.extend(std::iter::repeat(column).take(remaining_count));
+    }
+
+    for column in shuffled(remaining_columns, random) {
+        let candidate_tickets: Vec<usize> = (0..TICKETS_PER_BOOK)
+            .filter(|&ticket| {
+                counts[ticket][column] < TICKET_ROW_COUNT
+                    && counts[ticket].iter().sum::<usize>() < NUMBERS_PER_TICKET
+            })
+            .collect();
+        if candidate_tickets.is_empty() {
+            return None;
+        }
+        let ticket = RandomBag::new(candidate_tickets).get_next(random);
+        counts[ticket][column] += 1;
+    }
+
+    Some(counts)
+}
+
+/// Returns the rows of a ticket that each column occupies so that every row has exactly five
+/// numbers.
+///
+/// NOTE: We place the columns with the most numbers first and always pick the least filled rows.
+///       This keeps the row fill counts within one of each other so that we always end up with
+///       five numbers per row.
+fn assign_rows(column_counts: &[usize], random: &mut dyn RandomSource) -> Vec<Vec<usize>> {
+    let mut column_order = shuffled((0..COLUMN_COUNT).collect(), random);
+    column_order.sort_by_key(|&column| std::cmp::Reverse(column_counts[column]));
+
+    let mut row_fill_counts = [0; TICKET_ROW_COUNT];
+    let mut column_rows = vec![Vec::new(); COLUMN_COUNT];
+    for column in column_order {
+        let mut rows = shuffled((0..TICKET_ROW_COUNT).collect(), random);
+        rows.sort_by_key(|&row| row_fill_counts[row]);
+        let mut rows: Vec<usize> = rows.into_iter().take(column_counts[column]).collect();
+        rows.sort();
+        for &row in &rows {
+            row_fill_counts[row] += 1;
+        }
+        column_rows[column] = rows;
+    }
+    debug_assert!(row_fill_counts
+        .iter()
+        .all(|&count| count == NUMBERS_PER_TICKET_ROW));
+
+    column_rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cottontail::math::Random;
+
+    #[test]
+    fn books_contain_every_number_once_in_valid_tickets() {
+        let books = create_random_books(100, &[], &[], &mut Random::new_from_seed(1234));
+        assert_eq!(books.len(), 100);
+        for (book_index, book) in books.iter().enumerate() {
+            assert!(!is_duplicate(book, &books[..book_index]));
+            assert_eq!(book.width as usize, COLUMN_COUNT);
+            assert_eq!(book.height as usize, TICKETS_PER_BOOK * TICKET_ROW_COUNT);
+
+            let mut book_numbers: Vec<i32> = book
+                .data
+                .iter()
+                .cloned()
+                .filter(|&number| number != 0)
+                .collect();
+            book_numbers.sort();
+            assert_eq!(book_numbers, (1..=90).collect::<Vec<_>>());
+
+            for ticket in 0..TICKETS_PER_BOOK {
+                let rows: Vec<i32> = (0..TICKET_ROW_COUNT)
+                    .map(|row| (ticket * TICKET_ROW_COUNT + row) as i32)
+                    .collect();
+                for &y in &rows {
+                    let row_count = (0..COLUMN_COUNT as i32)
+                        .filter(|&x| book.get(x, y) != 0)
+                        .count();
+                    assert_eq!(row_count, NUMBERS_PER_TICKET_ROW);
+                }
+                for x in 0..COLUMN_COUNT {
+                    let numbers: Vec<i32> = rows
+                        .iter()
+                        .map(|&y| book.get(x as i32, y))
+                        .filter(|&number| number != 0)
+                        .collect();
+                    assert!(!numbers.is_empty());
+                    assert!(numbers.windows(2).all(|pair| pair[0] < pair[1]));
+                    assert!(numbers
+                        .iter()
+                        .all(|number| numbers_of_column(x).contains(number)));
+                }
+            }
+        }
+    }
+}
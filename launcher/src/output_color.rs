@@ -0,0 +1,73 @@
+//! Converts our final sheets for printers that cannot handle colors well. Thermal printers and
+//! risographs only print black (or one ink) and mangle colored PNG files in unpredictable ways, so
+//! we give them greyscale or already dithered black and white sheets.
+
+use cottontail::{
+    core::serde_derive::{Deserialize, Serialize},
+    image::{Bitmap, PixelRGBA},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputColorProfile {
+    Color,
+    /// Shades of grey on white paper
+    Grayscale,
+    /// Only black and white pixels where shades of grey are dithered
+    Monochrome,
+}
+
+impl Default for OutputColorProfile {
+    fn default() -> Self {
+        OutputColorProfile::Color
+    }
+}
+
+/// Converts the given sheet with premultiplied alpha to the given profile. Greyscale and
+/// monochrome sheets are drawn onto white paper and are therefore opaque afterwards.
+pub fn convert_sheet_premultiplied(sheet: &mut Bitmap, profile: OutputColorProfile) {
+    if profile == OutputColorProfile::Color {
+        return;
+    }
+
+    for pixel in sheet.data.iter_mut() {
+        // NOTE: Drawing premultiplied colors onto white just adds the white that shines through
+        let paper = 255 - pixel.a as u32;
+        let brightness = (2126 * (pixel.r as u32 + paper)
+            + 7152 * (pixel.g as u32 + paper)
+            + 722 * (pixel.b as u32 + paper))
+            / 10000;
+        let grey = u32::min(255, brightness) as u8;
+        *pixel = PixelRGBA::new(grey, grey, grey, 255);
+    }
+
+    if profile == OutputColorProfile::Monochrome {
+        dither_floyd_steinberg(sheet);
+    }
+}
+
+/// Turns the given greyscale bitmap into black and white pixels by spreading the error of each
+/// pixel onto its neighbours that were not visited yet
+fn dither_floyd_steinberg(bitmap: &mut Bitmap) {
+    let width = bitmap.width as usize;
+    let height = bitmap.height as usize;
+    // NOTE: We only need the errors of the current and the next row. Both have an extra pixel on
+    //       each side so that we don't need to check the borders.
+    let mut current_row_errors = vec![0.0f32; width + 2];
+    let mut next_row_errors = vec![0.0f32; width + 2];
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = &mut bitmap.data[y * width + x];
+            let value = pixel.r as f32 + current_row_errors[x + 1];
+            let result = if value < 128.0 { 0 } else { 255 };
+            let error = value - result as f32;
+            current_row_errors[x + 2] += error * 7.0 / 16.0;
+            next_row_errors[x] += error * 3.0 / 16.0;
+            next_row_errors[x + 1] += error * 5.0 / 16.0;
+            next_row_errors[x + 2] += error * 1.0 / 16.0;
+            *pixel = PixelRGBA::new(result, result, result, 255);
+        }
+        std::mem::swap(&mut current_row_errors, &mut next_row_errors);
+        next_row_errors.iter_mut().for_each(|error| *error = 0.0);
+    }
+}
@@ -0,0 +1,288 @@
+//! Draws the extra elements of our `[[overlays]]` array onto each sheet. Each element is an image
+//! or a text that is placed with one of its anchor points at a given pixel location and can be
+//! rotated and faded out. Elements with a higher `z` are drawn on top of the ones with a lower `z`.
+
+use std::collections::HashMap;
+
+use cottontail::{
+    core::serde_derive::{Deserialize, Serialize},
+    image::{Bitmap, PixelRGBA},
+    math::Vec2i,
+};
+
+use crate::{
+    color_from_rgb, colors, expand_text_variables, resample_bilinear, sheet_canvas::SheetCanvas,
+    DrawParams, FillStyle, LoadedFont, TextRasterizer,
+};
+
+/// The point of an element that is placed at its pixel location
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Default for Anchor {
+    fn default() -> Self {
+        Anchor::Center
+    }
+}
+
+impl Anchor {
+    /// Returns the anchor point within a rect of the given size
+    fn point(self, width: f32, height: f32) -> (f32, f32) {
+        let x = match self {
+            Anchor::TopLeft | Anchor::Left | Anchor::BottomLeft => 0.0,
+            Anchor::Top | Anchor::Center | Anchor::Bottom => width / 2.0,
+            Anchor::TopRight | Anchor::Right | Anchor::BottomRight => width,
+        };
+        let y = match self {
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => 0.0,
+            Anchor::Left | Anchor::Center | Anchor::Right => height / 2.0,
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => height,
+        };
+        (x, y)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OverlayParams {
+    image_filepath: Option<String>,
+    /// Can contain the same placeholders as `extra_texts`
+    text: Option<String>,
+    x: u32,
+    y: u32,
+    #[serde(default)]
+    anchor: Anchor,
+    /// Clockwise rotation in degrees around the anchor point
+    #[serde(default)]
+    rotation: f32,
+    opacity: Option<f32>,
+    /// Only used for images
+    scale: Option<f32>,
+    /// Only used for texts which use the header font size by default
+    size: Option<u32>,
+    #[serde(default, deserialize_with = "colors::optional_rgb")]
+    color: Option<(u8, u8, u8)>,
+    #[serde(default)]
+    z: i32,
+}
+
+impl OverlayParams {
+    /// Returns the location of the anchor point relative to the trim box
+    pub fn location(&self) -> Vec2i {
+        Vec2i::new(self.x as i32, self.y as i32)
+    }
+
+    pub fn image_filepath(&self) -> Option<&String> {
+        self.image_filepath.as_ref()
+    }
+
+    /// Returns a description of what is wrong with this element if anything
+    pub fn problem(&self) -> Option<&'static str> {
+        if self.image_filepath.is_some() == self.text.is_some() {
+            Some("must contain either an `image_filepath` or a `text` in each element")
+        } else if !self
+            .opacity
+            .map(|opacity| (0.0..=1.0).contains(&opacity))
+            .unwrap_or(true)
+        {
+            Some("must have an `opacity` between 0.0 and 1.0 in each element")
+        } else if !self.scale.map(|scale| scale > 0.0).unwrap_or(true) {
+            Some("must have a `scale` bigger than 0.0 in each element")
+        } else {
+            None
+        }
+    }
+}
+
+enum OverlayContent<'a> {
+    /// Images are the same on every sheet so we scale, rotate and fade them only once
+    Image { bitmap: Bitmap, anchor_point: Vec2i },
+    /// Texts can contain placeholders and are drawn for each sheet
+    Text {
+        text: String,
+        rasterizer: TextRasterizer<'a>,
+    },
+}
+
+struct Overlay<'a> {
+    params: OverlayParams,
+    content: OverlayContent<'a>,
+}
+
+/// Contains the prepared elements of our `[[overlays]]` array in the order that they are drawn in
+pub struct OverlayLayer<'a> {
+    overlays: Vec<Overlay<'a>>,
+}
+
+impl<'a> OverlayLayer<'a> {
+    pub fn new(params: &DrawParams, header_font: &'a LoadedFont) -> OverlayLayer<'a> {
+        let mut overlays: Vec<Overlay> = params
+            .overlays
+            .iter()
+            .map(|overlay_params| {
+                let content = match (&overlay_params.image_filepath, &overlay_params.text) {
+                    (Some(filepath), _) => {
+                        let image =
+                            Bitmap::from_png_file_or_panic(filepath).to_premultiplied_alpha();
+                        let scale = overlay_params.scale.unwrap_or(1.0);
+                        let image = resample_bilinear(
+                            &image,
+                            i32::max(1, (image.width as f32 * scale).round() as i32),
+                            i32::max(1, (image.height as f32 * scale).round() as i32),
+                        );
+                        let (bitmap, anchor_point) = transform_premultiplied(
+                            &image,
+                            overlay_params.anchor,
+                            overlay_params.rotation,
+                            overlay_params.opacity.unwrap_or(1.0),
+                        );
+                        OverlayContent::Image {
+                            bitmap,
+                            anchor_point,
+                        }
+                    }
+                    (None, text) => {
+                        let text = text.clone().unwrap_or_default();
+                        let rasterizer = TextRasterizer::new(
+                            header_font,
+                            overlay_params
+                                .size
+                                .or(params.header_text_font_size)
+                                .unwrap_or(params.text_font_size)
+                                as f32,
+                            params.header_fill_style(overlay_params.color),
+                            params.text_direction,
+                            params.bold_text,
+                            &format!("0123456789{}", text),
+                        )
+                        .with_effects(params.header_text_effects.as_ref());
+                        OverlayContent::Text { text, rasterizer }
+                    }
+                };
+                Overlay {
+                    params: overlay_params.clone(),
+                    content,
+                }
+            })
+            .collect();
+        // NOTE: The sort is stable so elements with the same `z` are drawn in the order of our file
+        overlays.sort_by_key(|overlay| overlay.params.z);
+        OverlayLayer { overlays }
+    }
+
+    /// Draws all elements onto the given sheet whose trim box starts at the given offset
+    pub fn draw(
+        &self,
+        sheet: &mut SheetCanvas,
+        trim_offset: Vec2i,
+        text_variables: &HashMap<String, String>,
+        gamma_correct_blending: bool,
+    ) {
+        for overlay in &self.overlays {
+            let text_bitmap;
+            let (bitmap, anchor_point) = match &overlay.content {
+                OverlayContent::Image {
+                    bitmap,
+                    anchor_point,
+                } => (bitmap, *anchor_point),
+                OverlayContent::Text { text, rasterizer } => {
+                    let text = expand_text_variables(text, text_variables);
+                    let (bitmap, anchor_point) = transform_premultiplied(
+                        &rasterizer.create_text_bitmap_premultiplied(&text),
+                        overlay.params.anchor,
+                        overlay.params.rotation,
+                        overlay.params.opacity.unwrap_or(1.0),
+                    );
+                    text_bitmap = bitmap;
+                    (&text_bitmap, anchor_point)
+                }
+            };
+            let pos = trim_offset + overlay.params.location() - anchor_point;
+            sheet.blit_premultiplied(bitmap, pos, gamma_correct_blending);
+        }
+    }
+}
+
+/// Returns the given bitmap rotated clockwise by the given degrees and faded out by the given
+/// opacity together with the location of its anchor point within the returned bitmap
+fn transform_premultiplied(
+    bitmap: &Bitmap,
+    anchor: Anchor,
+    rotation: f32,
+    opacity: f32,
+) -> (Bitmap, Vec2i) {
+    let width = bitmap.width as f32;
+    let height = bitmap.height as f32;
+    let (anchor_x, anchor_y) = anchor.point(width, height);
+
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let result_width = i32::max(1, (width * cos.abs() + height * sin.abs()).ceil() as i32);
+    let result_height = i32::max(1, (width * sin.abs() + height * cos.abs()).ceil() as i32);
+    let result_center_x = result_width as f32 / 2.0;
+    let result_center_y = result_height as f32 / 2.0;
+
+    let mut result = Bitmap::new(result_width as u32, result_height as u32);
+    for y in 0..result_height {
+        for x in 0..result_width {
+            // NOTE: We rotate the pixel center of the result back into the source bitmap and
+            //       sample it there. Pixels outside of the source bitmap are transparent.
+            let offset_x = x as f32 + 0.5 - result_center_x;
+            let offset_y = y as f32 + 0.5 - result_center_y;
+            let source_x = cos * offset_x + sin * offset_y + width / 2.0 - 0.5;
+            let source_y = -sin * offset_x + cos * offset_y + height / 2.0 - 0.5;
+            if source_x <= -1.0 || source_y <= -1.0 || source_x >= width || source_y >= height {
+                continue;
+            }
+
+            let left = source_x.floor() as i32;
+            let top = source_y.floor() as i32;
+            let percent_x = source_x - left as f32;
+            let percent_y = source_y - top as f32;
+            let sample = |x: i32, y: i32| {
+                if x < 0 || y < 0 || x >= bitmap.width as i32 || y >= bitmap.height as i32 {
+                    PixelRGBA::transparent()
+                } else {
+                    bitmap.get(x, y)
+                }
+            };
+            let top_left = sample(left, top);
+            let top_right = sample(left + 1, top);
+            let bottom_left = sample(left, top + 1);
+            let bottom_right = sample(left + 1, top + 1);
+            let interpolate = |top_left: u8, top_right: u8, bottom_left: u8, bottom_right: u8| {
+                let top = top_left as f32 + percent_x * (top_right as f32 - top_left as f32);
+                let bottom =
+                    bottom_left as f32 + percent_x * (bottom_right as f32 - bottom_left as f32);
+                ((top + percent_y * (bottom - top)) * opacity).round() as u8
+            };
+            result.set(
+                x,
+                y,
+                PixelRGBA::new(
+                    interpolate(top_left.r, top_right.r, bottom_left.r, bottom_right.r),
+                    interpolate(top_left.g, top_right.g, bottom_left.g, bottom_right.g),
+                    interpolate(top_left.b, top_right.b, bottom_left.b, bottom_right.b),
+                    interpolate(top_left.a, top_right.a, bottom_left.a, bottom_right.a),
+                ),
+            );
+        }
+    }
+
+    let anchor_offset_x = anchor_x - width / 2.0;
+    let anchor_offset_y = anchor_y - height / 2.0;
+    let anchor_point = Vec2i::new(
+        (cos * anchor_offset_x - sin * anchor_offset_y + result_center_x).round() as i32,
+        (sin * anchor_offset_x + cos * anchor_offset_y + result_center_y).round() as i32,
+    );
+    (result, anchor_point)
+}
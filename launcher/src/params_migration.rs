@@ -0,0 +1,143 @@
+//! Migration of draw parameter files that were written by older versions of Chotto. Each file
+//! contains a `config_version`. Whenever our parameters change in a way that old files cannot be
+//! read anymore we increase `CURRENT_CONFIG_VERSION` and add a migration below. Migrations work on
+//! the text of the file so that the comments of the user are kept.
+
+use crate::{DRAW_PARAMETERS_FILENAME, TOML_DOCUMENTATION_HEADER};
+
+/// Files without a `config_version` were written before we introduced it and have version 0
+pub const CURRENT_CONFIG_VERSION: u32 = 1;
+
+struct Migration {
+    /// The version of the file after the migration
+    to_version: u32,
+    /// Parameters that were renamed as (old name, new name)
+    renamed_keys: &'static [(&'static str, &'static str)],
+}
+
+const MIGRATIONS: [Migration; 1] = [
+    // NOTE: Version 1 only adds the `config_version` itself and the newest documentation
+    Migration {
+        to_version: 1,
+        renamed_keys: &[],
+    },
+];
+
+/// Returns the version of the given draw parameters
+fn config_version(params_string: &str) -> u32 {
+    params_string
+        .parse::<toml::Value>()
+        .ok()
+        .and_then(|params| {
+            params
+                .get("config_version")
+                .and_then(|version| version.as_integer())
+        })
+        .map(|version| version as u32)
+        .unwrap_or(0)
+}
+
+/// Returns the key that the given line assigns a value to if any
+pub fn assigned_key(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    if line.starts_with('#') || line.starts_with('[') {
+        return None;
+    }
+    line.find('=')
+        .map(|equals_index| line[..equals_index].trim().trim_matches('"'))
+}
+
+/// Splits the given file into its leading comment block and the rest
+fn split_header(params_string: &str) -> (Vec<&str>, Vec<&str>) {
+    let lines: Vec<&str> = params_string.lines().collect();
+    let header_length = lines
+        .iter()
+        .position(|line| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        })
+        .unwrap_or(lines.len());
+    (
+        lines[..header_length].to_vec(),
+        lines[header_length..].to_vec(),
+    )
+}
+
+fn migrate(params_string: &str, from_version: u32) -> String {
+    let (header_lines, body_lines) = split_header(params_string);
+
+    // NOTE: We only replace the documentation if it was written by us. Otherwise we keep it as is
+    //       because it could contain notes of the user.
+    let header = if header_lines.first().map(|line| line.trim_end())
+        == TOML_DOCUMENTATION_HEADER.lines().next()
+    {
+        TOML_DOCUMENTATION_HEADER.to_owned() + "\n"
+    } else {
+        header_lines
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect()
+    };
+
+    let mut body_lines: Vec<String> = body_lines
+        .iter()
+        .filter(|line| assigned_key(line) != Some("config_version"))
+        .map(|line| line.to_string())
+        .collect();
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|migration| migration.to_version > from_version)
+    {
+        for line in body_lines.iter_mut() {
+            for (old_key, new_key) in migration.renamed_keys {
+                if assigned_key(line) == Some(old_key) {
+                    *line = line.replacen(old_key, new_key, 1);
+                }
+            }
+        }
+    }
+
+    // NOTE: The version must come before the first table of the file to be a top level parameter
+    format!(
+        "{}\nconfig_version = {}\n{}\n",
+        header,
+        CURRENT_CONFIG_VERSION,
+        body_lines.join("\n")
+    )
+}
+
+/// Migrates our draw parameters file to the current version if necessary. The original file is
+/// kept as backup next to it.
+pub fn migrate_draw_params_file() {
+    let params_string = std::fs::read_to_string(DRAW_PARAMETERS_FILENAME)
+        .unwrap_or_else(|_| panic!("Could not read file '{}'", DRAW_PARAMETERS_FILENAME));
+    let version = config_version(&params_string);
+    assert!(
+        version <= CURRENT_CONFIG_VERSION,
+        "'{}' has `config_version = {}` and was written by a newer Chotto - please update Chotto first",
+        DRAW_PARAMETERS_FILENAME,
+        version
+    );
+    if version == CURRENT_CONFIG_VERSION {
+        return;
+    }
+
+    let backup_filepath = format!("{}.v{}.bak", DRAW_PARAMETERS_FILENAME, version);
+    std::fs::write(&backup_filepath, &params_string)
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", backup_filepath, error));
+    std::fs::write(DRAW_PARAMETERS_FILENAME, migrate(&params_string, version)).unwrap_or_else(
+        |error| {
+            panic!(
+                "Could not write file '{}': {}",
+                DRAW_PARAMETERS_FILENAME, error
+            )
+        },
+    );
+    log::info!(
+        "Updated '{}' from version {} to version {} - the old file was kept as '{}'",
+        DRAW_PARAMETERS_FILENAME,
+        version,
+        CURRENT_CONFIG_VERSION,
+        backup_filepath
+    );
+}
@@ -0,0 +1,241 @@
+//! Overrides of single draw parameters without editing our draw parameters file. This is useful
+//! for scripts that create many batches with slightly different parameters.
+//!
+//! Overrides can be given as environment variables like `CHOTTO_TEXT_FONT_SIZE=64` or on the
+//! command line like `chotto --set text_font_size=64`. Parameters of nested tables are written as
+//! `--set number_chip.radius_px=20` or `CHOTTO_NUMBER_CHIP__RADIUS_PX=20`. The command line wins
+//! over environment variables which win over the file.
+//!
+//! Our draw parameters file can also contain profiles like `[profile.a4]` and `[profile.letter]`
+//! which are selected via `chotto --profile a4`. The parameters of the selected profile replace the
+//! ones at the top of the file. Overrides are applied after the profile.
+
+const SET_OPTION: &str = "--set";
+const PROFILE_OPTION: &str = "--profile";
+const PROFILES_TABLE_NAME: &str = "profile";
+const ENVIRONMENT_VARIABLE_PREFIX: &str = "CHOTTO_";
+/// Environment variables with our prefix that are no draw parameters
+const RESERVED_ENVIRONMENT_VARIABLES: [&str; 6] = [
+    crate::email::SMTP_PASSWORD_ENVIRONMENT_VARIABLE,
+    crate::pos_export::POS_TOKEN_ENVIRONMENT_VARIABLE,
+    crate::s3_upload::ACCESS_KEY_ID_ENVIRONMENT_VARIABLE,
+    crate::s3_upload::SECRET_ACCESS_KEY_ENVIRONMENT_VARIABLE,
+    crate::server::SERVER_TOKEN_ENVIRONMENT_VARIABLE,
+    crate::bot::TWITCH_TOKEN_ENVIRONMENT_VARIABLE,
+];
+
+pub struct ParamOverride {
+    /// The dotted path of the parameter, i.e. `number_chip.radius_px`
+    key: String,
+    value: String,
+    /// Where the override came from for our diagnostics
+    source: String,
+}
+
+/// Returns the `--set key=value` options of the given command line arguments
+fn command_line_overrides(args: &[String]) -> Vec<ParamOverride> {
+    args.iter()
+        .enumerate()
+        .filter(|(_, arg)| *arg == SET_OPTION)
+        .map(|(index, _)| {
+            let assignment = args.get(index + 1).unwrap_or_else(|| {
+                panic!(
+                    "Missing value for '{}' - i.e. `{} text_font_size=64`",
+                    SET_OPTION, SET_OPTION
+                )
+            });
+            let (key, value) = split_assignment(assignment).unwrap_or_else(|| {
+                panic!(
+                    "'{} {}' is not valid - please write it like `{} text_font_size=64`",
+                    SET_OPTION, assignment, SET_OPTION
+                )
+            });
+            ParamOverride {
+                key,
+                value,
+                source: format!("{} {}", SET_OPTION, assignment),
+            }
+        })
+        .collect()
+}
+
+/// Returns the given command line arguments without our `--set key=value` and `--profile <name>`
+/// options
+pub fn remove_command_line_overrides(args: Vec<String>) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == SET_OPTION || arg == PROFILE_OPTION {
+            args.next();
+        } else {
+            result.push(arg);
+        }
+    }
+    result
+}
+
+fn split_assignment(assignment: &str) -> Option<(String, String)> {
+    let equals_index = assignment.find('=')?;
+    let key = assignment[..equals_index].trim();
+    if key.is_empty() {
+        return None;
+    }
+    Some((
+        key.to_owned(),
+        assignment[equals_index + 1..].trim().to_owned(),
+    ))
+}
+
+fn environment_overrides() -> Vec<ParamOverride> {
+    let mut overrides: Vec<ParamOverride> = std::env::vars()
+        .filter(|(name, _)| {
+            name.starts_with(ENVIRONMENT_VARIABLE_PREFIX)
+                && !RESERVED_ENVIRONMENT_VARIABLES.contains(&name.as_str())
+        })
+        .map(|(name, value)| ParamOverride {
+            key: name[ENVIRONMENT_VARIABLE_PREFIX.len()..]
+                .to_lowercase()
+                .replace("__", "."),
+            value,
+            source: format!("environment variable {}", name),
+        })
+        .collect();
+    // NOTE: The order of environment variables is arbitrary so we sort them for reproducibility
+    overrides.sort_by(|a, b| a.key.cmp(&b.key));
+    overrides
+}
+
+/// Panics if there are any overrides because the given action only works with the parameters of
+/// our draw parameters file
+pub fn assert_no_overrides(action: &str) {
+    if let Some(profile) = selected_profile() {
+        panic!(
+            "Cannot {} while the profile '{}' is selected - please move its parameters to the top of our draw parameters file instead",
+            action, profile
+        );
+    }
+    let overrides = all_overrides();
+    assert!(
+        overrides.is_empty(),
+        "Cannot {} while draw parameters are overridden by {} - please put them into our draw parameters file instead",
+        action,
+        overrides
+            .iter()
+            .map(|param_override| format!("'{}'", param_override.source))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+}
+
+/// Returns where each of our overrides came from, i.e. `--set text_font_size=64`
+pub fn override_descriptions() -> Vec<String> {
+    all_overrides()
+        .into_iter()
+        .map(|param_override| param_override.source)
+        .collect()
+}
+
+/// Returns all overrides from environment variables and command line in the order they must be
+/// applied in
+pub fn all_overrides() -> Vec<ParamOverride> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut overrides = environment_overrides();
+    overrides.extend(command_line_overrides(&args));
+    overrides
+}
+
+/// Returns the name of the profile given via `--profile <name>` if any
+pub fn selected_profile() -> Option<String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    crate::command_line_option_value(&args, PROFILE_OPTION)
+}
+
+/// Replaces the parameters of the given table with the ones of the given overriding table.
+/// Nested tables are merged the same way.
+fn merge_tables(table: &mut toml::value::Table, overriding_table: toml::value::Table) {
+    for (key, overriding_value) in overriding_table {
+        match (table.get_mut(&key), overriding_value) {
+            (
+                Some(toml::Value::Table(nested_table)),
+                toml::Value::Table(overriding_nested_table),
+            ) => merge_tables(nested_table, overriding_nested_table),
+            (_, overriding_value) => {
+                table.insert(key, overriding_value);
+            }
+        }
+    }
+}
+
+/// Removes all profiles from the given draw parameters and applies the one with the given name
+pub fn apply_profile(params_value: &mut toml::Value, profile_name: Option<&str>) {
+    let params_table = params_value.as_table_mut().unwrap();
+    let profiles = match params_table.remove(PROFILES_TABLE_NAME) {
+        Some(toml::Value::Table(profiles)) => profiles,
+        Some(_) => panic!(
+            "`{}` must be a table of profiles like `[{}.a4]`",
+            PROFILES_TABLE_NAME, PROFILES_TABLE_NAME
+        ),
+        None => toml::value::Table::new(),
+    };
+    let profile_name = match profile_name {
+        Some(profile_name) => profile_name,
+        None => return,
+    };
+
+    let profile = profiles.get(profile_name).cloned().unwrap_or_else(|| {
+        panic!(
+            "There is no profile '{}' in our draw parameters - the available profiles are {:?}",
+            profile_name,
+            profiles.keys().collect::<Vec<_>>()
+        )
+    });
+    match profile {
+        toml::Value::Table(profile) => merge_tables(params_table, profile),
+        _ => panic!(
+            "The profile '{}' must be a table like `[{}.{}]`",
+            profile_name, PROFILES_TABLE_NAME, profile_name
+        ),
+    }
+    log::info!("Using the draw parameters profile '{}'", profile_name);
+}
+
+/// Parses the given value like it was written in our TOML file. Values that are no valid TOML
+/// (like `hello` without quotes) are taken as text.
+fn parse_value(value: &str) -> toml::Value {
+    format!("value = {}", value)
+        .parse::<toml::Value>()
+        .ok()
+        .and_then(|table| table.get("value").cloned())
+        .unwrap_or_else(|| toml::Value::String(value.to_owned()))
+}
+
+/// Applies the given overrides to the given draw parameters
+pub fn apply_overrides(params_value: &mut toml::Value, overrides: &[ParamOverride]) {
+    for param_override in overrides {
+        let mut table = &mut *params_value;
+        let path: Vec<&str> = param_override.key.split('.').collect();
+        for table_name in &path[..path.len() - 1] {
+            table = table
+                .as_table_mut()
+                .unwrap()
+                .entry(table_name.to_string())
+                .or_insert_with(|| toml::Value::Table(toml::value::Table::new()));
+            assert!(
+                table.is_table(),
+                "Cannot apply '{}' because `{}` is not a table",
+                param_override.source,
+                table_name
+            );
+        }
+        table.as_table_mut().unwrap().insert(
+            path[path.len() - 1].to_owned(),
+            parse_value(&param_override.value),
+        );
+        log::info!(
+            "Overriding `{}` with `{}` via '{}'",
+            param_override.key,
+            param_override.value,
+            param_override.source
+        );
+    }
+}
@@ -0,0 +1,512 @@
+//! Reading of our draw parameters with helpful diagnostics. Instead of failing on the first serde
+//! error we collect all problems of the file (unknown keys, missing keys and values out of range)
+//! and report each of them together with its line number.
+
+use std::collections::HashSet;
+
+use crate::{
+    default_draw_params, params_overrides, CompositingBackend, DrawParams, DrawParamsFormat, Mode,
+    TOML_DOCUMENTATION_HEADER,
+};
+
+/// An unknown key is assumed to be a typo of a known key if they differ in at most this many
+/// characters
+const MAX_TYPO_DISTANCE: usize = 3;
+
+struct Problem {
+    line: Option<usize>,
+    message: String,
+}
+
+/// Parses the given content of a draw parameters file of the given format into a TOML value
+pub fn parse_params_value(
+    params_string: &str,
+    format: DrawParamsFormat,
+) -> Result<toml::Value, String> {
+    // NOTE: We convert YAML and JSON files to TOML values so that we can check them the same way
+    match format {
+        DrawParamsFormat::Toml => params_string.parse().map_err(|error| format!("{}", error)),
+        DrawParamsFormat::Yaml => serde_yaml::from_str::<serde_yaml::Value>(params_string)
+            .map_err(|error| error.to_string())
+            .and_then(|value| toml::Value::try_from(value).map_err(|error| error.to_string())),
+        DrawParamsFormat::Json => serde_json::from_str::<serde_json::Value>(params_string)
+            .map_err(|error| error.to_string())
+            .and_then(|value| toml::Value::try_from(value).map_err(|error| error.to_string())),
+    }
+}
+
+/// Parses the given content of our draw parameters file and panics with a list of all problems
+/// found in it
+pub fn parse_draw_params(
+    params_string: &str,
+    filepath: &str,
+    format: DrawParamsFormat,
+) -> DrawParams {
+    let mut params_value = parse_params_value(params_string, format).unwrap_or_else(|error| {
+        panic!(
+            "'{}' is not a valid {:?} file - please fix it: {}",
+            filepath, format, error
+        )
+    });
+
+    let profile = params_overrides::selected_profile();
+    params_overrides::apply_profile(&mut params_value, profile.as_deref());
+    let overrides = params_overrides::all_overrides();
+    if !overrides.is_empty() {
+        params_overrides::apply_overrides(&mut params_value, &overrides);
+    }
+
+    // NOTE: We prefer to read the parameters from the original text because the errors then
+    //       contain line numbers
+    let params: Result<DrawParams, (String, Option<usize>)> = match format {
+        _ if profile.is_some() || !overrides.is_empty() => {
+            params_value.clone().try_into().map_err(|error| {
+                (
+                    format!(
+                        "{} (after applying the selected profile and the overrides of the command line and environment variables)",
+                        error
+                    ),
+                    None,
+                )
+            })
+        }
+        DrawParamsFormat::Toml => toml::from_str(params_string).map_err(|error| {
+            let line = error.line_col().map(|(line, _column)| line + 1);
+            (error.to_string(), line)
+        }),
+        DrawParamsFormat::Yaml => serde_yaml::from_str(params_string).map_err(|error| {
+            let line = error.location().map(|location| location.line());
+            (error.to_string(), line)
+        }),
+        DrawParamsFormat::Json => serde_json::from_str(params_string).map_err(|error| {
+            let line = Some(error.line()).filter(|&line| line > 0);
+            (error.to_string(), line)
+        }),
+    };
+    let params = params.unwrap_or_else(|(error_message, line)| {
+        panic!(
+            "{}",
+            describe_problems(
+                params_string,
+                filepath,
+                &[deserialize_error_to_problem(
+                    params_string,
+                    format,
+                    error_message,
+                    line
+                )]
+            )
+        )
+    });
+
+    let mut problems = find_unknown_keys(params_string, &params_value, &params);
+    problems.extend(find_values_out_of_range(params_string, &params));
+    assert!(
+        problems.is_empty(),
+        "{}",
+        describe_problems(params_string, filepath, &problems)
+    );
+    params
+}
+
+fn describe_problems(params_string: &str, filepath: &str, problems: &[Problem]) -> String {
+    let problem_list: Vec<String> = problems
+        .iter()
+        .map(|problem| match problem.line {
+            Some(line) => format!(
+                "- line {}: {}\n    {}",
+                line,
+                problem.message,
+                params_string.lines().nth(line - 1).unwrap_or("").trim()
+            ),
+            None => format!("- {}", problem.message),
+        })
+        .collect();
+    format!(
+        "Found {} problem(s) in '{}' - please fix them:\n{}",
+        problems.len(),
+        filepath,
+        problem_list.join("\n")
+    )
+}
+
+/// Turns the given serde error into a problem with a suggestion how to fix it if possible
+fn deserialize_error_to_problem(
+    params_string: &str,
+    format: DrawParamsFormat,
+    error_message: String,
+    line: Option<usize>,
+) -> Problem {
+    if let Some(key) = quoted_name_after(&error_message, "missing field `") {
+        let suggestion = toml::Value::try_from(default_draw_params(1000, 1000))
+            .ok()
+            .and_then(|defaults| defaults.get(&key).cloned());
+        let message = match suggestion {
+            Some(value) => {
+                let example = match format {
+                    DrawParamsFormat::Toml => format!("{} = {}", key, value),
+                    DrawParamsFormat::Yaml => format!("{}: {}", key, value),
+                    DrawParamsFormat::Json => format!("\"{}\": {}", key, value),
+                };
+                format!(
+                    "The parameter `{}` is missing - please add it, i.e. `{}`",
+                    key, example
+                )
+            }
+            None => format!("The parameter `{}` is missing - please add it", key),
+        };
+        return Problem {
+            line: None,
+            message,
+        };
+    }
+
+    let line = line.or_else(|| {
+        quoted_name_after(&error_message, "for key `")
+            .and_then(|key| find_key_line(params_string, &key))
+    });
+    Problem {
+        line,
+        message: error_message,
+    }
+}
+
+/// Returns the name in backticks that directly follows the given prefix in the given text
+fn quoted_name_after(text: &str, prefix: &str) -> Option<String> {
+    let start = text.find(prefix)? + prefix.len();
+    let length = text[start..].find('`')?;
+    // NOTE: Nested keys are given like `number_chip.color_rgba`
+    text[start..start + length]
+        .rsplit('.')
+        .next()
+        .map(|key| key.to_owned())
+}
+
+/// Returns the (1-based) line number where the given key is assigned a value in any of our formats
+fn find_key_line(params_string: &str, key: &str) -> Option<usize> {
+    params_string
+        .lines()
+        .position(|line| {
+            let line = line.trim();
+            if line.starts_with('#') {
+                return false;
+            }
+            line.match_indices(key).any(|(index, _)| {
+                let is_word_start = line[..index]
+                    .chars()
+                    .last()
+                    .map(|previous| !(previous.is_alphanumeric() || previous == '_'))
+                    .unwrap_or(true);
+                let rest = line[index + key.len()..].trim_start_matches(|c| c == '"' || c == ' ');
+                is_word_start
+                    && (rest.starts_with('=') || rest.starts_with(':') || rest.starts_with(']'))
+            })
+        })
+        .map(|line_index| line_index + 1)
+}
+
+/// Returns the keys of all tables in the given value as dotted paths
+fn collect_key_paths(value: &toml::Value, prefix: &str, paths: &mut Vec<String>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                collect_key_paths(value, &path, paths);
+                paths.push(path);
+            }
+        }
+        toml::Value::Array(array) => {
+            for element in array {
+                collect_key_paths(element, prefix, paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns all names in backticks of our documentation. These also contain the optional parameters
+/// that are not part of our default parameters.
+fn documented_names() -> HashSet<String> {
+    TOML_DOCUMENTATION_HEADER
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .filter(|name| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        })
+        .map(|name| name.to_owned())
+        .collect()
+}
+
+/// Finds all keys in our file that are not used by our parameters. Serde silently ignores those
+/// which is why we compare the keys of the file with the ones of the parsed parameters.
+fn find_unknown_keys(
+    params_string: &str,
+    params_value: &toml::Value,
+    params: &DrawParams,
+) -> Vec<Problem> {
+    let mut used_paths = Vec::new();
+    collect_key_paths(
+        &toml::Value::try_from(params).expect("Could not convert draw parameters"),
+        "",
+        &mut used_paths,
+    );
+    let used_paths: HashSet<String> = used_paths.into_iter().collect();
+
+    let mut known_names = documented_names();
+    let mut default_paths = Vec::new();
+    collect_key_paths(
+        &toml::Value::try_from(default_draw_params(1000, 1000)).unwrap(),
+        "",
+        &mut default_paths,
+    );
+    known_names.extend(
+        used_paths
+            .iter()
+            .chain(default_paths.iter())
+            .map(|path| path.rsplit('.').next().unwrap().to_owned()),
+    );
+
+    let mut given_paths = Vec::new();
+    collect_key_paths(params_value, "", &mut given_paths);
+    given_paths.sort();
+    given_paths.dedup();
+
+    // NOTE: The names of text variables are chosen by the user
+    given_paths
+        .into_iter()
+        .filter(|path| !used_paths.contains(path) && !path.starts_with("text_variables."))
+        .map(|path| {
+            let key = path.rsplit('.').next().unwrap();
+            let closest_name = known_names
+                .iter()
+                .map(|name| (edit_distance(key, name), name))
+                .filter(|(distance, _)| *distance <= MAX_TYPO_DISTANCE)
+                .min();
+            let message = match closest_name {
+                Some((_, name)) => format!(
+                    "The parameter `{}` is unknown - did you mean `{}`?",
+                    path, name
+                ),
+                None => format!(
+                    "The parameter `{}` is unknown - please check its spelling",
+                    path
+                ),
+            };
+            Problem {
+                line: find_key_line(params_string, key),
+                message,
+            }
+        })
+        .collect()
+}
+
+/// Checks the values that have the right type but still make no sense
+fn find_values_out_of_range(params_string: &str, params: &DrawParams) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut check = |is_ok: bool, key: &str, requirement: &str| {
+        if !is_ok {
+            problems.push(Problem {
+                line: find_key_line(params_string, key),
+                message: format!("The parameter `{}` {}", key, requirement),
+            });
+        }
+    };
+
+    check(
+        params.number_of_sheets_to_generate > 0,
+        "number_of_sheets_to_generate",
+        "must be at least 1",
+    );
+    check(
+        params.text_font_size > 0,
+        "text_font_size",
+        "must be at least 1",
+    );
+    check(
+        params.header_text_font_size != Some(0),
+        "header_text_font_size",
+        "must be at least 1",
+    );
+    check(
+        params.post_process_parallelism != Some(0),
+        "post_process_parallelism",
+        "must be at least 1",
+    );
+    check(
+        params.post_process_command.is_none()
+            || params
+                .s3_upload
+                .as_ref()
+                .map(|s3_upload| s3_upload.keep_local_files)
+                .unwrap_or(true),
+        "post_process_command",
+        "needs `keep_local_files = true` in the `[s3_upload]` section as our sheets are removed after uploading them",
+    );
+    let (left, top, right, bottom) = params.bingo_grid_pixel_location_left_top_right_bottom;
+    check(
+        !params.mode.has_grid() || (left < right && top < bottom),
+        "bingo_grid_pixel_location_left_top_right_bottom",
+        "must have its left value smaller than its right value and its top value smaller than its bottom value",
+    );
+    check(
+        params.mode != Mode::Raffle || params.serial_number_pixel_location_x_y.is_some(),
+        "mode",
+        "needs a `serial_number_pixel_location_x_y` for the numbers of our raffle tickets",
+    );
+    check(
+        params.mode != Mode::Raffle || params.ticket_stub.is_some(),
+        "mode",
+        "needs a `[ticket_stub]` section for the stubs of our raffle tickets",
+    );
+    check(
+        params.mode != Mode::Puzzle || params.puzzles_filepath.is_some(),
+        "mode",
+        "needs a `puzzles_filepath` with the givens of our puzzles",
+    );
+    check(
+        params.mode != Mode::SeatingCards || params.seating_cards_filepath.is_some(),
+        "mode",
+        "needs a `seating_cards_filepath` with one name or table number per line",
+    );
+    check(
+        params.mode != Mode::SeatingCards
+            || params
+                .seating_card_text_pixel_location_left_top_right_bottom
+                .map(|(left, top, right, bottom)| left < right && top < bottom)
+                .unwrap_or(false),
+        "seating_card_text_pixel_location_left_top_right_bottom",
+        "is needed for seating cards and must have its left value smaller than its right value and its top value smaller than its bottom value",
+    );
+    check(
+        params.mode != Mode::Countdown || params.countdown.is_some(),
+        "mode",
+        "needs a `[countdown]` section with the region of our numbers",
+    );
+    check(
+        params.compositing_backend != CompositingBackend::Gpu || cfg!(feature = "gpu"),
+        "compositing_backend",
+        "can only be `\"gpu\"` if Chotto was built with `cargo build --release --features gpu`",
+    );
+    check(
+        params
+            .minimum_number_contrast
+            .map(|contrast| (1.0..=21.0).contains(&contrast))
+            .unwrap_or(true),
+        "minimum_number_contrast",
+        "must be between 1.0 and 21.0",
+    );
+    check(
+        params
+            .overlay_opacity
+            .map(|opacity| (0.0..=1.0).contains(&opacity))
+            .unwrap_or(true),
+        "overlay_opacity",
+        "must be between 0.0 and 1.0",
+    );
+    check(
+        params
+            .overlay_scale
+            .map(|scale| scale > 0.0)
+            .unwrap_or(true),
+        "overlay_scale",
+        "must be bigger than 0.0",
+    );
+    check(
+        params.braille_dot_radius_px != Some(0),
+        "braille_dot_radius_px",
+        "must be at least 1",
+    );
+    check(
+        params
+            .ink_saver_strength
+            .map(|strength| (0.0..=1.0).contains(&strength))
+            .unwrap_or(true),
+        "ink_saver_strength",
+        "must be between 0.0 and 1.0",
+    );
+    check(
+        params.corner_radius_px != Some(0),
+        "corner_radius_px",
+        "must be at least 1",
+    );
+    for problem in params
+        .overlays
+        .iter()
+        .filter_map(|overlay| overlay.problem())
+    {
+        check(false, "overlays", problem);
+    }
+    if let Some(accent_palette) = &params.accent_palette {
+        let names: HashSet<&str> = accent_palette
+            .iter()
+            .map(|accent_color| accent_color.name.as_str())
+            .collect();
+        check(
+            !accent_palette.is_empty(),
+            "accent_palette",
+            "must contain at least one color",
+        );
+        check(
+            names.len() == accent_palette.len(),
+            "accent_palette",
+            "must not contain the same `name` twice",
+        );
+    }
+    check(
+        !params.accent_palette_numbers || params.accent_palette.is_some(),
+        "accent_palette_numbers",
+        "needs an `accent_palette` to pick the colors from",
+    );
+    if let Some(problem) = params
+        .countdown
+        .as_ref()
+        .and_then(|countdown| countdown.problem())
+    {
+        check(false, "countdown", problem);
+    }
+    if let Some(problem) = params
+        .ticket_stub
+        .as_ref()
+        .and_then(|ticket_stub| ticket_stub.problem())
+    {
+        check(false, "ticket_stub", problem);
+    }
+    for (key, effects) in [
+        ("number_effects", &params.number_effects),
+        ("header_text_effects", &params.header_text_effects),
+    ]
+    .iter()
+    {
+        if let Some(problem) = effects.as_ref().and_then(|effects| effects.problem()) {
+            check(false, key, problem);
+        }
+    }
+    problems
+}
+
+/// Returns the number of characters that need to be inserted, removed or replaced to turn one
+/// text into the other
+fn edit_distance(first: &str, second: &str) -> usize {
+    let second: Vec<char> = second.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=second.len()).collect();
+    for (first_index, first_char) in first.chars().enumerate() {
+        let mut row = vec![first_index + 1];
+        for (second_index, second_char) in second.iter().enumerate() {
+            let substitution_cost = if first_char == *second_char { 0 } else { 1 };
+            row.push(usize::min(
+                previous_row[second_index] + substitution_cost,
+                usize::min(previous_row[second_index + 1] + 1, row[second_index] + 1),
+            ));
+        }
+        previous_row = row;
+    }
+    previous_row[second.len()]
+}
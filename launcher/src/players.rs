@@ -0,0 +1,60 @@
+//! When we create sheets for a given number of players (`players` and `cards_per_player`) each
+//! player gets their own `player_001`, `player_002`, ... directory. This writes an overview of which
+//! sheets and serial numbers belong to which player so that volunteers can hand out the sheets
+//! without collating them by hand.
+
+use crate::{manifest::Card, DrawParams};
+
+pub const PLAYERS_FILENAME: &str = "players.csv";
+
+/// Writes the player overview into the given output directory. Does nothing if our sheets are not
+/// grouped by player.
+pub fn write_player_list(output_directory: &str, params: &DrawParams, cards: &[Card]) {
+    let cards_per_player = match params.cards_per_player {
+        Some(cards_per_player) => cards_per_player,
+        None => return,
+    };
+    let layout = params.directory_layout();
+
+    let mut players: Vec<(usize, Vec<&Card>)> = Vec::new();
+    for card in cards {
+        let player_number = (card.sheet_number - 1) / cards_per_player + 1;
+        match players.last_mut() {
+            Some((number, player_cards)) if *number == player_number => player_cards.push(card),
+            _ => players.push((player_number, vec![card])),
+        }
+    }
+
+    let filepath = format!("{}/{}", output_directory, PLAYERS_FILENAME);
+    let mut writer = csv::Writer::from_path(&filepath)
+        .unwrap_or_else(|error| panic!("Could not create file '{}': {}", filepath, error));
+    writer
+        .write_record(&["player", "directory", "sheets", "serials"])
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    for (player_number, player_cards) in &players {
+        let first_sheet_number = player_cards[0].sheet_number;
+        let last_sheet_number = player_cards[player_cards.len() - 1].sheet_number;
+        let serials: Vec<&str> = player_cards
+            .iter()
+            .map(|card| card.serial.as_str())
+            .collect();
+        writer
+            .write_record(&[
+                player_number.to_string(),
+                layout
+                    .subdirectory_name(first_sheet_number)
+                    .unwrap_or_default(),
+                format!("{}-{}", first_sheet_number, last_sheet_number),
+                serials.join(" "),
+            ])
+            .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    }
+    writer
+        .flush()
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    log::info!(
+        "Wrote the sheets of {} players to '{}'",
+        players.len(),
+        filepath
+    );
+}
@@ -0,0 +1,128 @@
+//! PNG encoding of our final sheets. Encoding a large sheet takes longer than drawing it so we
+//! reuse our buffers between sheets and let the user trade file size against speed.
+
+use std::cell::RefCell;
+
+use cottontail::{
+    core::serde_derive::{Deserialize, Serialize},
+    image::Bitmap,
+};
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+
+use crate::{atomic_file, sheet_canvas::ComposedSheet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PngCompression {
+    /// Bigger files that are written a lot faster
+    Fast,
+    Default,
+    /// The smallest files but slow to write
+    Best,
+}
+
+impl Default for PngCompression {
+    fn default() -> Self {
+        PngCompression::Default
+    }
+}
+
+thread_local! {
+    /// The raw RGBA pixels and the encoded PNG file of the last sheet written by this thread
+    static BUFFERS: RefCell<(Vec<u8>, Vec<u8>)> = RefCell::new((Vec::new(), Vec::new()));
+}
+
+/// Writes the given unpremultiplied bitmap atomically as PNG file and creates its parent
+/// directories if necessary
+pub fn write_png_file(bitmap: &Bitmap, filepath: &str, compression: PngCompression) {
+    write_composed_png_file(&ComposedSheet::from_bitmap(bitmap), filepath, compression);
+}
+
+/// Same as `write_png_file` for a sheet that shares its background with other sheets
+pub fn write_composed_png_file(sheet: &ComposedSheet, filepath: &str, compression: PngCompression) {
+    write_png_file_with_color_type(sheet, filepath, compression, image::ColorType::Rgba8);
+}
+
+/// Same as `write_composed_png_file` but only writes the red channel of the given opaque greyscale
+/// sheet which makes the file a lot smaller
+pub fn write_composed_greyscale_png_file(
+    sheet: &ComposedSheet,
+    filepath: &str,
+    compression: PngCompression,
+) {
+    write_png_file_with_color_type(sheet, filepath, compression, image::ColorType::L8);
+}
+
+/// Same as `write_composed_greyscale_png_file` but writes one bit per pixel for the given opaque
+/// sheet that was already dithered to black and white. Pixels brighter than half are white.
+pub fn write_composed_monochrome_png_file(
+    sheet: &ComposedSheet,
+    filepath: &str,
+    compression: PngCompression,
+) {
+    let compression = match compression {
+        PngCompression::Fast => png::Compression::Fast,
+        PngCompression::Default => png::Compression::Default,
+        PngCompression::Best => png::Compression::Best,
+    };
+
+    BUFFERS.with(|buffers| {
+        let (pixel_bytes, file_bytes) = &mut *buffers.borrow_mut();
+        pixel_bytes.clear();
+        // NOTE: Each row starts at a new byte and its first pixel is the highest bit of that byte
+        sheet.for_each_row(|row| {
+            pixel_bytes.extend(row.chunks(8).map(|pixels| {
+                pixels
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, pixel)| pixel.r >= 128)
+                    .fold(0u8, |byte, (index, _)| byte | (0x80 >> index))
+            }));
+        });
+
+        file_bytes.clear();
+        let mut encoder = png::Encoder::new(&mut *file_bytes, sheet.width(), sheet.height());
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::One);
+        encoder.set_compression(compression);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(pixel_bytes))
+            .unwrap_or_else(|error| panic!("Could not encode PNG file '{}': {}", filepath, error));
+        atomic_file::write_file_atomically(filepath, file_bytes);
+    });
+}
+
+fn write_png_file_with_color_type(
+    sheet: &ComposedSheet,
+    filepath: &str,
+    compression: PngCompression,
+    color_type: image::ColorType,
+) {
+    let (compression_type, filter_type) = match compression {
+        PngCompression::Fast => (CompressionType::Fast, FilterType::Sub),
+        PngCompression::Default => (CompressionType::Default, FilterType::Adaptive),
+        PngCompression::Best => (CompressionType::Best, FilterType::Adaptive),
+    };
+
+    BUFFERS.with(|buffers| {
+        let (pixel_bytes, file_bytes) = &mut *buffers.borrow_mut();
+        pixel_bytes.clear();
+        sheet.for_each_row(|row| {
+            if color_type == image::ColorType::L8 {
+                pixel_bytes.extend(row.iter().map(|pixel| pixel.r));
+            } else {
+                pixel_bytes.reserve(4 * row.len());
+                for pixel in row {
+                    pixel_bytes.extend_from_slice(&[pixel.r, pixel.g, pixel.b, pixel.a]);
+                }
+            }
+        });
+
+        file_bytes.clear();
+        PngEncoder::new_with_quality(&mut *file_bytes, compression_type, filter_type)
+            .encode(pixel_bytes, sheet.width(), sheet.height(), color_type)
+            .unwrap_or_else(|error| panic!("Could not encode PNG file '{}': {}", filepath, error));
+        atomic_file::write_file_atomically(filepath, file_bytes);
+    });
+}
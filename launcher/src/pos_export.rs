@@ -0,0 +1,187 @@
+//! Exports our cards for the point-of-sale software of a bingo hall so that it can sell and
+//! validate them without typing in their numbers by hand. The export is a CSV file with
+//! configurable columns and can optionally also be sent card by card to a REST endpoint.
+//!
+//! Usage: `chotto export-pos [--directory <dir>] [--post]`
+
+use cottontail::core::serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    command_line_flag, command_line_option_value, load_draw_params,
+    manifest::{self, Card},
+    DRAW_PARAMETERS_FILENAME, OUTPUT_SHEETS_DIRECTORY,
+};
+
+pub const POS_EXPORT_FILENAME: &str = "pos_export.csv";
+/// The access token for the REST endpoint is not stored in our draw parameters file
+pub const POS_TOKEN_ENVIRONMENT_VARIABLE: &str = "CHOTTO_POS_TOKEN";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PosColumn {
+    SheetNumber,
+    Serial,
+    /// All numbers of the card row by row where rows are separated by `/`
+    Numbers,
+    PriceTier,
+    Price,
+    SecurityCode,
+}
+
+/// A range of sheets that is sold for the same price
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceTier {
+    pub name: String,
+    pub price: Option<String>,
+    pub first_sheet: usize,
+    pub last_sheet: usize,
+}
+
+fn default_columns() -> Vec<PosColumn> {
+    vec![PosColumn::Serial, PosColumn::Numbers, PosColumn::PriceTier]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PosExportParams {
+    #[serde(default = "default_columns")]
+    columns: Vec<PosColumn>,
+    #[serde(default)]
+    price_tiers: Vec<PriceTier>,
+    /// The tier of all sheets that are not part of any of our price tiers
+    default_price_tier: Option<String>,
+    /// Each card is sent as JSON to this URL when running `chotto export-pos --post`
+    post_url: Option<String>,
+}
+
+impl PosExportParams {
+    fn price_tier(&self, sheet_number: usize) -> Option<&PriceTier> {
+        self.price_tiers
+            .iter()
+            .find(|tier| tier.first_sheet <= sheet_number && sheet_number <= tier.last_sheet)
+    }
+
+    fn price_tier_name(&self, sheet_number: usize) -> String {
+        self.price_tier(sheet_number)
+            .map(|tier| tier.name.clone())
+            .or_else(|| self.default_price_tier.clone())
+            .unwrap_or_default()
+    }
+
+    fn price(&self, sheet_number: usize) -> String {
+        self.price_tier(sheet_number)
+            .and_then(|tier| tier.price.clone())
+            .unwrap_or_default()
+    }
+}
+
+fn column_name(column: PosColumn) -> &'static str {
+    match column {
+        PosColumn::SheetNumber => "sheet_number",
+        PosColumn::Serial => "serial",
+        PosColumn::Numbers => "numbers",
+        PosColumn::PriceTier => "price_tier",
+        PosColumn::Price => "price",
+        PosColumn::SecurityCode => "security_code",
+    }
+}
+
+fn numbers_to_string(card: &Card) -> String {
+    card.numbers
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|number| number.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn column_value(params: &PosExportParams, card: &Card, column: PosColumn) -> String {
+    match column {
+        PosColumn::SheetNumber => card.sheet_number.to_string(),
+        PosColumn::Serial => card.serial.clone(),
+        PosColumn::Numbers => numbers_to_string(card),
+        PosColumn::PriceTier => params.price_tier_name(card.sheet_number),
+        PosColumn::Price => params.price(card.sheet_number),
+        PosColumn::SecurityCode => card.security_code.clone().unwrap_or_default(),
+    }
+}
+
+fn write_pos_csv(filepath: &str, params: &PosExportParams, cards: &[Card]) {
+    let mut writer = csv::Writer::from_path(filepath)
+        .unwrap_or_else(|error| panic!("Could not create file '{}': {}", filepath, error));
+    writer
+        .write_record(params.columns.iter().map(|column| column_name(*column)))
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    for card in cards {
+        writer
+            .write_record(
+                params
+                    .columns
+                    .iter()
+                    .map(|column| column_value(params, card, *column)),
+            )
+            .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    }
+    writer
+        .flush()
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+}
+
+fn post_cards(url: &str, params: &PosExportParams, cards: &[Card]) {
+    let token = std::env::var(POS_TOKEN_ENVIRONMENT_VARIABLE).ok();
+    for card in cards {
+        let body = serde_json::json!({
+            "sheet_number": card.sheet_number,
+            "serial": card.serial,
+            "numbers": card.numbers,
+            "price_tier": params.price_tier_name(card.sheet_number),
+            "price": params.price(card.sheet_number),
+            "security_code": card.security_code,
+        });
+        let mut request = ureq::post(url);
+        if let Some(token) = &token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        request.send_json(body).unwrap_or_else(|error| {
+            panic!(
+                "Could not send sheet {} (serial '{}') to '{}': {} - all sheets before it were sent successfully",
+                card.sheet_number, card.serial, url, error
+            )
+        });
+        log::debug!("Sent sheet {} to '{}'", card.sheet_number, url);
+    }
+    log::info!("Sent {} sheets to '{}'", cards.len(), url);
+}
+
+pub fn export_pos(args: &[String]) {
+    let directory = command_line_option_value(args, "--directory")
+        .unwrap_or_else(|| OUTPUT_SHEETS_DIRECTORY.to_owned());
+    let params = load_draw_params();
+    let pos_params = params.pos_export.clone().unwrap_or_else(|| {
+        panic!(
+            "Please first add a `[pos_export]` section to '{}'",
+            DRAW_PARAMETERS_FILENAME
+        )
+    });
+
+    let cards = manifest::read_manifest(&directory).cards;
+    let filepath = format!("{}/{}", directory, POS_EXPORT_FILENAME);
+    write_pos_csv(&filepath, &pos_params, &cards);
+    log::info!("Wrote {} sheets to '{}'", cards.len(), filepath);
+
+    if command_line_flag(args, "--post") {
+        let url = pos_params.post_url.as_ref().unwrap_or_else(|| {
+            panic!(
+                "Please first add a `post_url` to the `[pos_export]` section of '{}'",
+                DRAW_PARAMETERS_FILENAME
+            )
+        });
+        post_cards(url, &pos_params, &cards);
+    }
+
+    #[cfg(not(debug_assertions))]
+    crate::show_messagebox("Chotto", "Finished exporting sheets.", false);
+}
@@ -0,0 +1,182 @@
+//! Runs the external `post_process_command` of our draw parameters for each written sheet file,
+//! i.e. to convert it with ImageMagick, upload it somewhere or stamp it with a third-party tool.
+//!
+//! The command is run by the shell of the operating system (`cmd /C` on Windows and `sh -c`
+//! otherwise). The placeholders `{path}`, `{filename}`, `{directory}`, `{card_id}` and
+//! `{sheet_number}` are replaced with quoted references to environment variables that hold the
+//! values of the sheet. This way the shell never interprets a value as part of the command, even if
+//! a directory or serial number contains quotes, `;`, `&` or `$`.
+
+use std::process::Command;
+
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::manifest::Card;
+
+/// Most post-processing commands are limited by disk or network speed and not by our CPU
+const DEFAULT_POST_PROCESS_PARALLELISM: usize = 4;
+
+/// The placeholders of our command and the environment variables that hold their values
+const PLACEHOLDER_ENVIRONMENT_VARIABLES: [(&str, &str); 5] = [
+    ("{path}", "SHEET_PATH"),
+    ("{filename}", "SHEET_FILENAME"),
+    ("{directory}", "SHEET_DIRECTORY"),
+    ("{card_id}", "SHEET_CARD_ID"),
+    ("{sheet_number}", "SHEET_NUMBER"),
+];
+
+/// Returns the values of our placeholders for the given sheet in the same order as
+/// `PLACEHOLDER_ENVIRONMENT_VARIABLES`
+fn placeholder_values(sheet_filepath: &str, card: &Card) -> [String; 5] {
+    let (directory, filename) = match sheet_filepath.rfind('/') {
+        Some(separator_index) => (
+            &sheet_filepath[..separator_index],
+            &sheet_filepath[separator_index + 1..],
+        ),
+        None => (".", sheet_filepath),
+    };
+    [
+        sheet_filepath.to_owned(),
+        filename.to_owned(),
+        directory.to_owned(),
+        card.serial.clone(),
+        card.sheet_number.to_string(),
+    ]
+}
+
+fn replace_placeholders(command_template: &str) -> String {
+    let mut command_line = command_template.to_owned();
+    for (placeholder, variable_name) in &PLACEHOLDER_ENVIRONMENT_VARIABLES {
+        let variable_reference = if cfg!(windows) {
+            format!("\"%{}%\"", variable_name)
+        } else {
+            format!("\"${}\"", variable_name)
+        };
+        // NOTE: Our documentation used to ask for quotes around placeholders. We replace those
+        //       together with the placeholder so that the value does not end up unquoted between
+        //       two empty strings.
+        command_line = command_line
+            .replace(&format!("\"{}\"", placeholder), &variable_reference)
+            .replace(placeholder, &variable_reference);
+    }
+    command_line
+}
+
+fn shell_command(command_line: &str, sheet_filepath: &str, card: &Card) -> Command {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(&["/C", command_line]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.args(&["-c", command_line]);
+        command
+    };
+    let values = placeholder_values(sheet_filepath, card);
+    for ((_, variable_name), value) in PLACEHOLDER_ENVIRONMENT_VARIABLES.iter().zip(&values) {
+        command.env(variable_name, value);
+    }
+    command
+}
+
+/// Runs the given command once for each written sheet with at most `parallelism` commands at the
+/// same time. Returns the sheet number and an error message for each command that could not be
+/// run or failed.
+fn run_commands(
+    command_template: &str,
+    parallelism: usize,
+    sheets: &[(String, &Card)],
+) -> Vec<(usize, String)> {
+    let thread_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(parallelism)
+        .build()
+        .expect("Could not create threads for post-processing");
+    let command_line = replace_placeholders(command_template);
+    thread_pool.install(|| {
+        sheets
+            .par_iter()
+            .filter_map(|(sheet_filepath, card)| {
+                log::debug!(
+                    "Post-processing sheet {}: {}",
+                    card.sheet_number,
+                    command_line
+                );
+                let error = match shell_command(&command_line, sheet_filepath, card).output() {
+                    Ok(output) if output.status.success() => return None,
+                    Ok(output) => format!(
+                        "Sheet {}: `{}` failed with {}: {}",
+                        card.sheet_number,
+                        command_line,
+                        output.status,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ),
+                    Err(error) => format!(
+                        "Sheet {}: Could not run `{}`: {}",
+                        card.sheet_number, command_line, error
+                    ),
+                };
+                Some((card.sheet_number, error))
+            })
+            .collect()
+    })
+}
+
+/// Runs our post-processing command for the given sheet files. All sheets are processed even if
+/// some of them fail so that we can see every problem at once.
+pub fn post_process_sheets(
+    command_template: &str,
+    parallelism: Option<usize>,
+    sheets: &[(String, &Card)],
+) {
+    let parallelism = parallelism.unwrap_or(DEFAULT_POST_PROCESS_PARALLELISM);
+    let start_time = std::time::Instant::now();
+    let mut errors = run_commands(command_template, parallelism, sheets);
+    if !errors.is_empty() {
+        errors.sort_by_key(|(sheet_number, _)| *sheet_number);
+        let messages: Vec<String> = errors.into_iter().map(|(_, message)| message).collect();
+        panic!(
+            "Post-processing failed for {} of {} sheets (all sheets were written nevertheless):\n\n{}",
+            messages.len(),
+            sheets.len(),
+            messages.join("\n")
+        );
+    }
+    log::info!(
+        "Post-processed {} sheets in {:.2}s",
+        sheets.len(),
+        start_time.elapsed().as_secs_f32()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn passes_values_without_letting_the_shell_interpret_them() {
+        let directory = std::env::temp_dir().join("chotto_test_post_process_quoting");
+        std::fs::create_dir_all(&directory).unwrap();
+        let directory = directory.to_string_lossy().to_string();
+        let serial = "A-1'; touch injected; echo \"$(touch injected)\"";
+        let card: Card = serde_json::from_value(serde_json::json!({
+            "sheet_number": 1,
+            "serial": serial,
+            "numbers": [],
+        }))
+        .unwrap();
+
+        let errors = run_commands(
+            "cd {directory} && printf %s {card_id} > \"{filename}.txt\"",
+            1,
+            &[(format!("{}/sheet_1.png", directory), &card)],
+        );
+        let written_serial = std::fs::read_to_string(format!("{}/sheet_1.png.txt", directory));
+        let injected = std::path::Path::new(&directory).join("injected").exists();
+        std::fs::remove_dir_all(&directory).ok();
+
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(written_serial.unwrap(), serial);
+        assert!(!injected);
+    }
+}
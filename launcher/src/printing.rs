@@ -0,0 +1,112 @@
+//! Sends sheets directly to a printer queue by drawing them onto a GDI printer device context
+
+use std::{ffi::OsStr, iter::once, os::windows::ffi::OsStrExt, ptr::null_mut};
+
+use cottontail::image::Bitmap;
+use winapi::um::wingdi::{
+    CreateDCW, DeleteDC, EndDoc, EndPage, GetDeviceCaps, StartDocW, StartPage, StretchDIBits,
+    BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS, DOCINFOW, HORZRES, SRCCOPY, VERTRES,
+};
+
+fn to_wide_string(text: &str) -> Vec<u16> {
+    OsStr::new(text).encode_wide().chain(once(0)).collect()
+}
+
+/// Prints each given PNG file on its own page in the given order. The images are scaled to fit the
+/// printable area of the page while keeping their aspect ratio.
+pub fn print_png_files(printer_name: &str, filepaths: &[&String]) {
+    let printer_name_wide = to_wide_string(printer_name);
+    let document_name_wide = to_wide_string("Chotto Bingo-sheets");
+
+    unsafe {
+        let device_context = CreateDCW(
+            null_mut(),
+            printer_name_wide.as_ptr(),
+            null_mut(),
+            null_mut(),
+        );
+        assert!(
+            !device_context.is_null(),
+            "Could not find printer '{}' - is the name correct?",
+            printer_name
+        );
+
+        let page_width = GetDeviceCaps(device_context, HORZRES);
+        let page_height = GetDeviceCaps(device_context, VERTRES);
+
+        let document_info = DOCINFOW {
+            cbSize: std::mem::size_of::<DOCINFOW>() as i32,
+            lpszDocName: document_name_wide.as_ptr(),
+            lpszOutput: null_mut(),
+            lpszDatatype: null_mut(),
+            fwType: 0,
+        };
+        assert!(
+            StartDocW(device_context, &document_info) > 0,
+            "Could not start print job on printer '{}'",
+            printer_name
+        );
+
+        for filepath in filepaths {
+            let bitmap = Bitmap::from_png_file_or_panic(filepath);
+            let width = bitmap.width as i32;
+            let height = bitmap.height as i32;
+
+            // NOTE: GDI expects the pixels in BGRA order
+            let pixels_bgra: Vec<u8> = bitmap
+                .data
+                .iter()
+                .flat_map(|pixel| [pixel.b, pixel.g, pixel.r, pixel.a])
+                .collect();
+
+            let scale = f32::min(
+                page_width as f32 / width as f32,
+                page_height as f32 / height as f32,
+            );
+            let print_width = (width as f32 * scale) as i32;
+            let print_height = (height as f32 * scale) as i32;
+
+            let mut bitmap_info: BITMAPINFO = std::mem::zeroed();
+            bitmap_info.bmiHeader = BITMAPINFOHEADER {
+                biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: width,
+                // NOTE: A negative height means our rows are ordered top to bottom
+                biHeight: -height,
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB,
+                biSizeImage: 0,
+                biXPelsPerMeter: 0,
+                biYPelsPerMeter: 0,
+                biClrUsed: 0,
+                biClrImportant: 0,
+            };
+
+            assert!(
+                StartPage(device_context) > 0,
+                "Could not start new page for '{}' on printer '{}'",
+                filepath,
+                printer_name
+            );
+            StretchDIBits(
+                device_context,
+                (page_width - print_width) / 2,
+                (page_height - print_height) / 2,
+                print_width,
+                print_height,
+                0,
+                0,
+                width,
+                height,
+                pixels_bgra.as_ptr() as *const _,
+                &bitmap_info,
+                DIB_RGB_COLORS,
+                SRCCOPY,
+            );
+            EndPage(device_context);
+        }
+
+        EndDoc(device_context);
+        DeleteDC(device_context);
+    }
+}
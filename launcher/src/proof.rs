@@ -0,0 +1,100 @@
+//! Proof pages that show a sample of sheets as thumbnails so that the design can be signed off
+//! before the full print run
+
+use cottontail::{
+    image::{Bitmap, PixelRGBA},
+    math::{Random, Vec2i},
+};
+
+use crate::{
+    blit_centered_premultiplied, command_line_option_value, create_cards, create_time_based_seed,
+    fill_rect, resize_background_image, Input, SheetRenderer,
+};
+
+const PROOF_SHEETS_DIRECTORY: &str = "proof_sheets";
+const TILE_MARGIN_PX: i32 = 16;
+
+/// Usage: `chotto proof [--count 16] [--columns 4] [--rows 4]`
+pub fn create_proof_sheets(args: &[String]) {
+    let parse_option = |name: &str, default: usize| {
+        command_line_option_value(args, name)
+            .map(|value| {
+                value
+                    .parse::<usize>()
+                    .unwrap_or_else(|_| panic!("Invalid value '{}' for '{}'", value, name))
+            })
+            .unwrap_or(default)
+    };
+    let columns = parse_option("--columns", 4);
+    let rows = parse_option("--rows", 4);
+    let count = parse_option("--count", columns * rows);
+    assert!(
+        columns > 0 && rows > 0 && count > 0,
+        "The count, columns and rows of our proof sheets must be at least 1"
+    );
+
+    let Input {
+        background_bitmap,
+        font,
+        font_hash,
+        header_font,
+        params,
+        recipients,
+        ..
+    } = Input::new();
+    let sheet_renderer = SheetRenderer::new(
+        &background_bitmap,
+        &font,
+        font_hash,
+        header_font.as_ref(),
+        &params,
+        recipients.as_ref(),
+    );
+
+    let mut random = Random::new_from_seed(create_time_based_seed());
+    let count = recipients
+        .as_ref()
+        .map(|recipients| usize::min(count, recipients.len()))
+        .unwrap_or(count);
+    let cards = create_cards(&sheet_renderer, &[], count, &mut random);
+
+    // NOTE: Our proof pages have the same size as a single sheet
+    let page_width = sheet_renderer.background.width as i32;
+    let page_height = sheet_renderer.background.height as i32;
+    let tile_width = (page_width - TILE_MARGIN_PX * (columns as i32 + 1)) / columns as i32;
+    let tile_height = (page_height - TILE_MARGIN_PX * (rows as i32 + 1)) / rows as i32;
+    let scale = f32::min(
+        tile_width as f32 / page_width as f32,
+        tile_height as f32 / page_height as f32,
+    );
+    let thumbnail_width = u32::max(1, (page_width as f32 * scale) as u32);
+    let thumbnail_height = u32::max(1, (page_height as f32 * scale) as u32);
+
+    let tiles_per_page = columns * rows;
+    for (page_index, page_cards) in cards.chunks(tiles_per_page).enumerate() {
+        let mut page = Bitmap::new(page_width as u32, page_height as u32);
+        fill_rect(&mut page, 0, 0, page_width, page_height, PixelRGBA::white());
+
+        for (tile_index, card) in page_cards.iter().enumerate() {
+            let sheet = sheet_renderer.draw_sheet(card);
+            let thumbnail = resize_background_image(&sheet, thumbnail_width, thumbnail_height);
+            let column = (tile_index % columns) as i32;
+            let row = (tile_index / columns) as i32;
+            let center = Vec2i::new(
+                TILE_MARGIN_PX + column * (tile_width + TILE_MARGIN_PX) + tile_width / 2,
+                TILE_MARGIN_PX + row * (tile_height + TILE_MARGIN_PX) + tile_height / 2,
+            );
+            blit_centered_premultiplied(&thumbnail, &mut page, center, false);
+        }
+
+        let filepath = format!("{}/proof_{}.png", PROOF_SHEETS_DIRECTORY, page_index + 1);
+        page.to_unpremultiplied_alpha().write_to_png_file(&filepath);
+        log::info!("Created proof sheet '{}'", filepath);
+    }
+
+    log::info!(
+        "Finished creating proof sheets with {} sample sheets in '{}'",
+        cards.len(),
+        PROOF_SHEETS_DIRECTORY
+    );
+}
@@ -0,0 +1,145 @@
+//! Reading of the 9x9 puzzles (i.e. Sudoku) that are drawn with `mode = "puzzle"`. The puzzles are
+//! made somewhere else and we only place their givens into the grid of our background like the
+//! numbers of a Bingo sheet.
+//!
+//! A JSON puzzle file lists the givens of each puzzle row by row where empty cells are 0. Each
+//! puzzle can also have a `serial` that is printed instead of its sheet number:
+//!
+//! {
+//!   "puzzles": [
+//!     { "numbers": [[5, 3, 0, 0, 7, 0, 0, 0, 0], [6, 0, 0, 1, 9, 5, 0, 0, 0], ...] },
+//!     ...
+//!   ]
+//! }
+//!
+//! Any other puzzle file has one puzzle per line with its 81 cells row by row where empty cells
+//! are `0` or `.`, optionally followed by a comma and the serial of the puzzle:
+//!
+//! 530070000600195000098000060800060003400803001700020006060000280000419005000080079,Week 12
+
+use cottontail::core::serde_derive::{Deserialize, Serialize};
+
+use crate::card_import::ImportedCard;
+
+pub const PUZZLE_GRID_SIZE: usize = 9;
+const PUZZLE_BOX_SIZE: usize = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PuzzleFile {
+    puzzles: Vec<ImportedCard>,
+}
+
+/// Reads the given puzzle file and checks that all of its puzzles are valid
+pub fn read_puzzle_file(filepath: &str) -> Vec<ImportedCard> {
+    let puzzle_file_string = std::fs::read_to_string(filepath)
+        .unwrap_or_else(|error| panic!("Could not read file '{}': {}", filepath, error));
+    let puzzles = if filepath.to_lowercase().ends_with(".json") {
+        serde_json::from_str::<PuzzleFile>(&puzzle_file_string)
+            .unwrap_or_else(|error| panic!("Could not read puzzles from '{}': {}", filepath, error))
+            .puzzles
+    } else {
+        puzzle_file_string
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .enumerate()
+            .map(|(puzzle_index, line)| parse_puzzle_line(line, puzzle_index, filepath))
+            .collect()
+    };
+    assert!(
+        !puzzles.is_empty(),
+        "There are no puzzles in '{}'",
+        filepath
+    );
+
+    for (puzzle_index, puzzle) in puzzles.iter().enumerate() {
+        assert!(
+            puzzle.numbers.len() == PUZZLE_GRID_SIZE
+                && puzzle
+                    .numbers
+                    .iter()
+                    .all(|row| row.len() == PUZZLE_GRID_SIZE),
+            "Puzzle {} in '{}' must have {} rows of {} numbers each",
+            puzzle_index + 1,
+            filepath,
+            PUZZLE_GRID_SIZE,
+            PUZZLE_GRID_SIZE
+        );
+        for (y, row) in puzzle.numbers.iter().enumerate() {
+            for (x, &number) in row.iter().enumerate() {
+                assert!(
+                    0 <= number && number <= PUZZLE_GRID_SIZE as i32,
+                    "Puzzle {} in '{}' contains the number {} which is not between 1 and {}",
+                    puzzle_index + 1,
+                    filepath,
+                    number,
+                    PUZZLE_GRID_SIZE
+                );
+                // NOTE: A given that repeats in its row, column or box makes the puzzle unsolvable
+                //       which usually means that the file was mixed up
+                let repeats = number != 0
+                    && (0..PUZZLE_GRID_SIZE).any(|other| {
+                        let box_x = x / PUZZLE_BOX_SIZE * PUZZLE_BOX_SIZE + other % PUZZLE_BOX_SIZE;
+                        let box_y = y / PUZZLE_BOX_SIZE * PUZZLE_BOX_SIZE + other / PUZZLE_BOX_SIZE;
+                        (other != x && row[other] == number)
+                            || (other != y && puzzle.numbers[other][x] == number)
+                            || ((box_x, box_y) != (x, y) && puzzle.numbers[box_y][box_x] == number)
+                    });
+                assert!(
+                    !repeats,
+                    "Puzzle {} in '{}' contains the number {} more than once in the row, column or \
+                     box of [{}, {}]",
+                    puzzle_index + 1,
+                    filepath,
+                    number,
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    puzzles
+}
+
+fn parse_puzzle_line(line: &str, puzzle_index: usize, filepath: &str) -> ImportedCard {
+    let (cells, serial) = match line.find(',') {
+        Some(comma_index) => (
+            line[..comma_index].trim(),
+            Some(line[comma_index + 1..].trim().to_owned()).filter(|serial| !serial.is_empty()),
+        ),
+        None => (line, None),
+    };
+    let numbers: Vec<i32> = cells
+        .chars()
+        .map(|cell| match cell {
+            '.' => 0,
+            _ => cell
+                .to_digit(10)
+                .map(|digit| digit as i32)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "Puzzle {} in '{}' contains '{}' which is neither a digit nor '.'",
+                        puzzle_index + 1,
+                        filepath,
+                        cell
+                    )
+                }),
+        })
+        .collect();
+    assert!(
+        numbers.len() == PUZZLE_GRID_SIZE * PUZZLE_GRID_SIZE,
+        "Puzzle {} in '{}' must have {} cells but has {}",
+        puzzle_index + 1,
+        filepath,
+        PUZZLE_GRID_SIZE * PUZZLE_GRID_SIZE,
+        numbers.len()
+    );
+    ImportedCard {
+        numbers: numbers
+            .chunks(PUZZLE_GRID_SIZE)
+            .map(|row| row.to_vec())
+            .collect(),
+        serial,
+    }
+}
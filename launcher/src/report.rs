@@ -0,0 +1,137 @@
+//! Writes an `index.html` summary into the output directory after each run so that we can look
+//! through the batch in a browser without opening the sheets one by one
+
+use cottontail::image::Bitmap;
+
+use crate::{redacted_draw_params_value, resize_background_image, DrawParams};
+
+pub const REPORT_FILENAME: &str = "index.html";
+/// The number of sheets from the start of the batch that are shown on the contact sheet
+pub const THUMBNAIL_COUNT: usize = 24;
+const THUMBNAIL_WIDTH: u32 = 256;
+const THUMBNAILS_DIRECTORY: &str = "thumbnails";
+
+pub struct Thumbnail {
+    pub sheet_number: usize,
+    pub serial: String,
+    /// Relative to the output directory
+    pub sheet_filepath: String,
+    /// Relative to the output directory
+    pub thumbnail_filepath: String,
+}
+
+pub struct RunReport<'a> {
+    pub params: &'a DrawParams,
+    /// The commitment to our random seed from the audit log. We never show the seed itself because
+    /// the report is shared before the game and the seed would reveal all cards and draws.
+    /// Empty for runs with `rng = "secure"` which have no seed that could be published.
+    pub seed_commitment: &'a str,
+    /// Where our random seed came from
+    pub entropy_source: &'a str,
+    pub sheet_count: usize,
+    pub elapsed_seconds: f64,
+    pub thumbnails: Vec<Thumbnail>,
+}
+
+/// Writes a downscaled version of the given premultiplied sheet into the thumbnails directory and
+/// returns its filepath relative to the output directory
+pub fn write_thumbnail(
+    sheet_premultiplied: &Bitmap,
+    output_directory: &str,
+    sheet_number: usize,
+) -> String {
+    let scale = THUMBNAIL_WIDTH as f32 / sheet_premultiplied.width as f32;
+    let height = u32::max(
+        1,
+        (sheet_premultiplied.height as f32 * scale).round() as u32,
+    );
+    let thumbnail = resize_background_image(sheet_premultiplied, THUMBNAIL_WIDTH, height)
+        .to_unpremultiplied_alpha();
+
+    // NOTE: We don't name our thumbnails `sheet_N` so that they are not mistaken for sheets
+    let relative_filepath = format!("{}/thumbnail_{}.png", THUMBNAILS_DIRECTORY, sheet_number);
+    thumbnail.write_to_png_file(&format!("{}/{}", output_directory, relative_filepath));
+    relative_filepath
+}
+
+/// Returns the given draw parameters in the same format as our draw parameters file
+pub fn draw_params_to_string(params: &DrawParams) -> String {
+    toml::Value::try_from(params)
+        .ok()
+        .and_then(|params| toml::to_string_pretty(&params).ok())
+        .unwrap_or_else(|| format!("{:#?}", params))
+}
+
+pub fn write_report(output_directory: &str, report: &RunReport) {
+    // NOTE: The report is meant to be looked at by others so it must not contain our secrets
+    let params_string = toml::to_string_pretty(&redacted_draw_params_value(report.params))
+        .unwrap_or_else(|error| format!("Could not write the draw parameters: {}", error));
+
+    let mut thumbnails_html = String::new();
+    for thumbnail in &report.thumbnails {
+        thumbnails_html += &format!(
+            "      <a href=\"{}\"><figure><img src=\"{}\" width=\"{}\"><figcaption>Sheet {} - #{}</figcaption></figure></a>\n",
+            escape_html(&thumbnail.sheet_filepath),
+            escape_html(&thumbnail.thumbnail_filepath),
+            THUMBNAIL_WIDTH,
+            thumbnail.sheet_number,
+            escape_html(&thumbnail.serial)
+        );
+    }
+
+    let html = format!(
+        "<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset=\"utf-8\">
+    <title>Chotto - {sheet_count} sheets</title>
+    <style>
+      body {{ font-family: sans-serif; margin: 2em; }}
+      td {{ padding-right: 2em; }}
+      .thumbnails {{ display: flex; flex-wrap: wrap; }}
+      figure {{ margin: 0.5em; }}
+      figcaption {{ text-align: center; color: #333; }}
+      pre {{ background: #eee; padding: 1em; }}
+    </style>
+  </head>
+  <body>
+    <h1>Chotto - {sheet_count} sheets</h1>
+    <table>
+      <tr><td>Created</td><td>{created}</td></tr>
+      <tr><td>Sheets</td><td>{sheet_count}</td></tr>
+      <tr><td>Random seed commitment</td><td>{seed_commitment} (from {entropy_source})</td></tr>
+      <tr><td>Duration</td><td>{elapsed_seconds:.1}s</td></tr>
+    </table>
+    <h2>First {thumbnail_count} sheets</h2>
+    <div class=\"thumbnails\">
+{thumbnails_html}    </div>
+    <h2>Draw parameters</h2>
+    <pre>{params}</pre>
+  </body>
+</html>
+",
+        sheet_count = report.sheet_count,
+        created = chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+        seed_commitment = if report.seed_commitment.is_empty() {
+            "none"
+        } else {
+            report.seed_commitment
+        },
+        entropy_source = escape_html(report.entropy_source),
+        elapsed_seconds = report.elapsed_seconds,
+        thumbnail_count = report.thumbnails.len(),
+        thumbnails_html = thumbnails_html,
+        params = escape_html(&params_string),
+    );
+
+    let filepath = format!("{}/{}", output_directory, REPORT_FILENAME);
+    std::fs::write(&filepath, html)
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
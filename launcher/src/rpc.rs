@@ -0,0 +1,360 @@
+//! A JSON-RPC 2.0 interface for programs that drive Chotto as a background service (i.e. a kiosk
+//! application). Requests are sent to `POST /rpc` of `chotto serve`.
+//!
+//! Methods:
+//! - `generate_batch` with `{ "count": 100, "profile": "a4", "overrides": { "text_font_size": 64 } }`
+//!   (all optional) starts creating a new batch of sheets and returns `{ "job_id": 1 }`. Only the
+//!   layout and count parameters of `ALLOWED_OVERRIDE_KEYS` can be overridden.
+//! - `get_progress` with `{ "job_id": 1 }` returns the state of the job, how many sheets were drawn
+//!   so far and the output directory of the batch
+//! - `get_card` with `{ "card": "A-0001", "security_code": "K7QX-M2ZD", "directory":
+//!   "output_sheets_2021-06-01_18-30" }` returns a card of the given directory (or of the directory
+//!   that `chotto serve` was started with). The directory must be one of our output directories
+//!   next to `chotto.exe`. Like `GET /cards` the security code is needed for cards that have one.
+//!
+//! Each batch is created by a separate headless Chotto process so that a failing batch does not
+//! take down the service. We follow its progress via its log output.
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader},
+    process::{Command, Stdio},
+    sync::{Arc, Mutex},
+};
+
+use cottontail::core::serde_derive::{Deserialize, Serialize};
+
+use crate::{manifest, server::CardRegistry, BingoVariant, HEADLESS_FLAG, OUTPUT_SHEETS_DIRECTORY};
+
+/// The parameters that clients may override. Anything else (i.e. commands, accounts, file paths)
+/// could be abused by anyone who can reach our service to run commands or read files on our
+/// machine.
+const ALLOWED_OVERRIDE_KEYS: &[&str] = &[
+    "number_of_sheets_to_generate",
+    "text_font_size",
+    "text_color_rgb",
+    "text_color_rgba",
+    "bingo_grid_pixel_location_left_top_right_bottom",
+    "excluded_numbers",
+    "output_width_px",
+    "output_height_px",
+    "output_dpi",
+    "output_size_mm",
+    "sheets_per_directory",
+    "players",
+    "cards_per_player",
+    "shuffle_output_order",
+    "output_rotation",
+    "output_mirror",
+    "paper_color_rgb",
+    "ink_saver",
+    "ink_saver_strength",
+    "header_text_font_size",
+    "header_text_color_rgb",
+    "column_headers_pixel_location_top_bottom",
+    "free_space_cells_x_y",
+    "serial_number_pixel_location_x_y",
+    "barcode_pixel_location_x_y",
+    "bleed_px",
+    "crop_marks",
+    "corner_radius_px",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Running,
+    Finished,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobProgress {
+    pub state: JobState,
+    pub sheets_drawn: usize,
+    pub sheet_count: Option<usize>,
+    pub output_directory: Option<String>,
+    /// The last error message of the job if it failed
+    pub error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Jobs {
+    next_job_id: u64,
+    progress_by_job_id: HashMap<u64, Arc<Mutex<JobProgress>>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RpcRequest {
+    #[serde(default)]
+    id: serde_json::Value,
+    method: String,
+    #[serde(default)]
+    params: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GenerateBatchParams {
+    count: Option<usize>,
+    profile: Option<String>,
+    /// Values can be anything that `--set key=value` accepts
+    #[serde(default)]
+    overrides: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GetProgressParams {
+    job_id: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GetCardParams {
+    card: String,
+    security_code: Option<String>,
+    directory: Option<String>,
+}
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+fn invalid_params(error: serde_json::Error) -> RpcError {
+    RpcError {
+        code: INVALID_PARAMS,
+        message: error.to_string(),
+    }
+}
+
+/// Updates the progress of a job from a line of its log output
+fn update_progress(progress: &mut JobProgress, log_line: &str) {
+    if let Some(rest) = log_line.split("Writing sheets to '").nth(1) {
+        progress.output_directory = rest
+            .split('\'')
+            .next()
+            .map(|directory| directory.to_owned());
+    }
+    // NOTE: The line looks like `Drew sheets 101-200 of 500 in 1.23s`
+    if let Some(rest) = log_line.split("Drew sheets ").nth(1) {
+        let mut words = rest.split_whitespace();
+        let last_drawn = words
+            .next()
+            .and_then(|range| range.split('-').nth(1))
+            .and_then(|last| last.parse().ok());
+        let sheet_count = words.nth(1).and_then(|count| count.parse().ok());
+        if let Some(last_drawn) = last_drawn {
+            progress.sheets_drawn = last_drawn;
+        }
+        if sheet_count.is_some() {
+            progress.sheet_count = sheet_count;
+        }
+    }
+}
+
+fn generate_batch(
+    jobs: &mut Jobs,
+    params: GenerateBatchParams,
+) -> Result<serde_json::Value, RpcError> {
+    let mut overrides: Vec<(&String, &serde_json::Value)> = params.overrides.iter().collect();
+    overrides.sort_by_key(|(key, _)| key.as_str());
+    if let Some((key, _)) = overrides
+        .iter()
+        .find(|(key, _)| !ALLOWED_OVERRIDE_KEYS.contains(&key.as_str()))
+    {
+        return Err(RpcError {
+            code: INVALID_PARAMS,
+            message: format!(
+                "The parameter '{}' cannot be overridden - allowed are {:?}",
+                key, ALLOWED_OVERRIDE_KEYS
+            ),
+        });
+    }
+
+    let executable = std::env::current_exe().map_err(|error| RpcError {
+        code: SERVER_ERROR,
+        message: format!("Could not find our executable: {}", error),
+    })?;
+    let mut command = Command::new(executable);
+    command.arg(HEADLESS_FLAG);
+    // NOTE: Batches of a service must never overwrite each other or ask the user what to do
+    command.args(&["--set", "existing_output_policy=\"timestamped\""]);
+    if let Some(count) = params.count {
+        command.args(&["--set", &format!("number_of_sheets_to_generate={}", count)]);
+    }
+    if let Some(profile) = &params.profile {
+        command.args(&["--profile", profile]);
+    }
+    for (key, value) in overrides {
+        command.args(&["--set", &format!("{}={}", key, value)]);
+    }
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| RpcError {
+            code: SERVER_ERROR,
+            message: format!("Could not start Chotto: {}", error),
+        })?;
+
+    jobs.next_job_id += 1;
+    let job_id = jobs.next_job_id;
+    let progress = Arc::new(Mutex::new(JobProgress {
+        state: JobState::Running,
+        sheets_drawn: 0,
+        sheet_count: params.count,
+        output_directory: None,
+        error: None,
+    }));
+    jobs.progress_by_job_id.insert(job_id, progress.clone());
+    log::info!("Started job {} to generate a batch", job_id);
+
+    let stderr = child.stderr.take().unwrap();
+    let stderr_progress = progress.clone();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().filter_map(|line| line.ok()) {
+            if line.contains("ERROR") {
+                stderr_progress.lock().unwrap().error = Some(line);
+            }
+        }
+    });
+    let stdout = child.stdout.take().unwrap();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().filter_map(|line| line.ok()) {
+            update_progress(&mut progress.lock().unwrap(), &line);
+        }
+        let succeeded = child.wait().map(|status| status.success()).unwrap_or(false);
+        let mut progress = progress.lock().unwrap();
+        progress.state = if succeeded {
+            JobState::Finished
+        } else {
+            JobState::Failed
+        };
+        log::info!("Job {} finished with state {:?}", job_id, progress.state);
+    });
+
+    Ok(serde_json::json!({ "job_id": job_id }))
+}
+
+fn get_progress(jobs: &Jobs, params: GetProgressParams) -> Result<serde_json::Value, RpcError> {
+    let progress = jobs
+        .progress_by_job_id
+        .get(&params.job_id)
+        .ok_or_else(|| RpcError {
+            code: INVALID_PARAMS,
+            message: format!("There is no job {}", params.job_id),
+        })?;
+    let progress = progress.lock().unwrap().clone();
+    Ok(serde_json::to_value(progress).unwrap())
+}
+
+/// Returns true if the given directory is one of the output directories that our batches write
+/// into, i.e. `output_sheets` or `output_sheets_2021-06-01_18-30`
+fn is_output_directory(directory: &str) -> bool {
+    let mut components = std::path::Path::new(directory).components();
+    match (components.next(), components.next()) {
+        (Some(std::path::Component::Normal(name)), None) => {
+            let name = name.to_string_lossy();
+            name == OUTPUT_SHEETS_DIRECTORY
+                || name.starts_with(&format!("{}_", OUTPUT_SHEETS_DIRECTORY))
+        }
+        _ => false,
+    }
+}
+
+fn get_card(
+    default_directory: &str,
+    variant: BingoVariant,
+    params: GetCardParams,
+) -> Result<serde_json::Value, RpcError> {
+    let directory = match params.directory {
+        Some(directory) if directory != default_directory && !is_output_directory(&directory) => {
+            return Err(RpcError {
+                code: INVALID_PARAMS,
+                message: format!(
+                    "'{}' is not one of our output directories like '{}'",
+                    directory, OUTPUT_SHEETS_DIRECTORY
+                ),
+            })
+        }
+        Some(directory) => directory,
+        None => default_directory.to_owned(),
+    };
+    if !manifest::has_manifest(&directory) {
+        return Err(RpcError {
+            code: INVALID_PARAMS,
+            message: format!("There are no sheets in '{}'", directory),
+        });
+    }
+    let registry = CardRegistry::new(manifest::read_manifest(&directory).cards, variant);
+    registry
+        .find_with_security_code(&params.card, params.security_code.as_deref())
+        .map(|card| serde_json::to_value(card).unwrap())
+        .ok_or_else(|| RpcError {
+            code: INVALID_PARAMS,
+            message: format!(
+                "There is no card '{}' with this security code in '{}'",
+                params.card, directory
+            ),
+        })
+}
+
+/// Answers the given JSON-RPC request body
+pub fn handle_rpc(
+    jobs: &mut Jobs,
+    default_directory: &str,
+    variant: BingoVariant,
+    request_body: &[u8],
+) -> String {
+    let request: RpcRequest = match serde_json::from_slice(request_body) {
+        Ok(request) => request,
+        Err(error) => {
+            return serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": PARSE_ERROR, "message": error.to_string() },
+            })
+            .to_string()
+        }
+    };
+
+    let params = if request.params.is_null() {
+        serde_json::json!({})
+    } else {
+        request.params
+    };
+    let result = match request.method.as_str() {
+        "generate_batch" => serde_json::from_value(params)
+            .map_err(invalid_params)
+            .and_then(|params| generate_batch(jobs, params)),
+        "get_progress" => serde_json::from_value(params)
+            .map_err(invalid_params)
+            .and_then(|params| get_progress(jobs, params)),
+        "get_card" => serde_json::from_value(params)
+            .map_err(invalid_params)
+            .and_then(|params| get_card(default_directory, variant, params)),
+        method => Err(RpcError {
+            code: METHOD_NOT_FOUND,
+            message: format!("Unknown method '{}'", method),
+        }),
+    };
+
+    match result {
+        Ok(result) => serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": request.id,
+            "result": result,
+        }),
+        Err(error) => serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": request.id,
+            "error": { "code": error.code, "message": error.message },
+        }),
+    }
+    .to_string()
+}
@@ -0,0 +1,141 @@
+//! The run manifest records exactly what produced the sheets of a batch: the version of Chotto, the
+//! effective draw parameters, the random seed and the hashes of all input files. This lets us
+//! answer reprint requests months later. Each run that writes into the batch (the first one and
+//! every `extend`) adds its own entry to the `run_manifest.toml` of the batch directory.
+
+use cottontail::core::serde_derive::{Deserialize, Serialize};
+
+use crate::{atomic_file, audit_log, params_overrides, redacted_draw_params_value, DrawParams};
+
+pub const RUN_MANIFEST_FILENAME: &str = "run_manifest.toml";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputFile {
+    pub filepath: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Run {
+    pub command: String,
+    pub timestamp: String,
+    pub chotto_version: String,
+    /// Runs with `rng = "secure"` have no seed so that nobody can recreate their cards
+    pub seed: Option<u64>,
+    /// The nonce of the seed commitment in our audit log. Only publish it together with the seed.
+    #[serde(default)]
+    pub seed_nonce: String,
+    /// How many entries our audit log had right after this run and the hash of the last of them.
+    /// This lets `chotto verify-audit` notice when the newest entries were removed.
+    #[serde(default)]
+    pub audit_entry_count: usize,
+    #[serde(default)]
+    pub audit_entry_hash: String,
+    /// Where our random seed came from
+    pub entropy_source: String,
+    pub first_sheet_number: usize,
+    pub last_sheet_number: usize,
+    pub profile: Option<String>,
+    /// The command line options and environment variables that overrode parameters of our draw
+    /// parameters file
+    pub overrides: Vec<String>,
+    pub input_files: Vec<InputFile>,
+    /// The draw parameters after applying the profile, overrides and preset. Our secret key and
+    /// serial number salt are redacted.
+    pub params: toml::Value,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub runs: Vec<Run>,
+}
+
+/// Adds an entry for the given run to the run manifest of the given output directory. The first
+/// sheet of the run is expected to start a new batch unless the command is `extend`.
+pub fn write_run_manifest(
+    output_directory: &str,
+    command: &str,
+    params: &DrawParams,
+    seed: Option<u64>,
+    audit_entry: &audit_log::AppendedEntry,
+    sheet_numbers: (usize, usize),
+    input_filepaths: &[String],
+) {
+    let filepath = format!("{}/{}", output_directory, RUN_MANIFEST_FILENAME);
+    let mut run_manifest = if command == "extend" {
+        std::fs::read_to_string(&filepath)
+            .ok()
+            .and_then(|manifest_string| toml::from_str(&manifest_string).ok())
+            .unwrap_or_default()
+    } else {
+        RunManifest::default()
+    };
+
+    let input_files = input_filepaths
+        .iter()
+        .map(|input_filepath| {
+            let content = std::fs::read(input_filepath).unwrap_or_else(|error| {
+                panic!("Could not read file '{}': {}", input_filepath, error)
+            });
+            InputFile {
+                filepath: input_filepath.clone(),
+                sha256: audit_log::sha256_hex(&content),
+            }
+        })
+        .collect();
+    run_manifest.runs.push(Run {
+        command: command.to_owned(),
+        timestamp: chrono::Local::now().to_rfc3339(),
+        chotto_version: env!("CARGO_PKG_VERSION").to_owned(),
+        seed,
+        seed_nonce: audit_entry.seed_nonce.clone(),
+        audit_entry_count: audit_entry.entry_count,
+        audit_entry_hash: audit_entry.entry_hash.clone(),
+        entropy_source: params.rng.entropy_source().to_owned(),
+        first_sheet_number: sheet_numbers.0,
+        last_sheet_number: sheet_numbers.1,
+        profile: params_overrides::selected_profile(),
+        overrides: params_overrides::override_descriptions(),
+        input_files,
+        params: redacted_draw_params_value(params),
+    });
+
+    let manifest_string = toml::to_string_pretty(&run_manifest)
+        .unwrap_or_else(|error| panic!("Could not write run manifest: {}", error));
+    atomic_file::write_file_atomically(&filepath, manifest_string.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn does_not_write_secrets() {
+        let output_directory = std::env::temp_dir()
+            .join("chotto_test_run_manifest_secrets")
+            .to_string_lossy()
+            .to_string();
+        std::fs::create_dir_all(&output_directory).unwrap();
+
+        let mut params = DrawParams::default();
+        params.security_code_secret_key = Some("our secret key".to_owned());
+        params.serial_number.salt = Some("our secret salt".to_owned());
+        write_run_manifest(
+            &output_directory,
+            "create",
+            &params,
+            Some(1234),
+            &audit_log::AppendedEntry::default(),
+            (1, 10),
+            &[],
+        );
+
+        let manifest_string =
+            std::fs::read_to_string(format!("{}/{}", output_directory, RUN_MANIFEST_FILENAME))
+                .unwrap();
+        std::fs::remove_dir_all(&output_directory).ok();
+        assert!(!manifest_string.contains("our secret key"));
+        assert!(!manifest_string.contains("our secret salt"));
+        assert!(manifest_string.contains("security_code_secret_key"));
+    }
+}
@@ -0,0 +1,288 @@
+//! Uploads our sheets to an S3-compatible bucket (AWS, MinIO, Backblaze, ...) right after they were
+//! written so that machines with little local storage can create large batches. Each sheet is
+//! removed from the local disk once it was uploaded unless we ask to keep it. The files that
+//! describe our run (i.e. manifest, run manifest, report and thumbnails) are uploaded once the run
+//! is finished and always stay on our disk.
+//!
+//! The credentials are not stored in our draw parameters file but read from the
+//! `CHOTTO_S3_ACCESS_KEY_ID` and `CHOTTO_S3_SECRET_ACCESS_KEY` environment variables.
+
+use std::{sync::Mutex, time::Duration};
+
+use cottontail::core::serde_derive::{Deserialize, Serialize};
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+use crate::audit_log::sha256_hex;
+
+pub const ACCESS_KEY_ID_ENVIRONMENT_VARIABLE: &str = "CHOTTO_S3_ACCESS_KEY_ID";
+pub const SECRET_ACCESS_KEY_ENVIRONMENT_VARIABLE: &str = "CHOTTO_S3_SECRET_ACCESS_KEY";
+
+const MAX_UPLOAD_ATTEMPTS: u32 = 4;
+
+fn default_region() -> String {
+    "us-east-1".to_owned()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3UploadParams {
+    /// The base URL of the storage service, i.e. `https://s3.eu-central-1.amazonaws.com`
+    endpoint: String,
+    bucket: String,
+    /// Is put in front of the path of each sheet, i.e. `bingo/2021-06-01/`
+    #[serde(default)]
+    prefix: String,
+    #[serde(default = "default_region")]
+    region: String,
+    #[serde(default)]
+    pub keep_local_files: bool,
+}
+
+pub struct S3Uploader {
+    params: S3UploadParams,
+    access_key_id: String,
+    secret_access_key: String,
+    /// The key and size of each uploaded object for our final consistency check
+    uploaded_objects: Mutex<Vec<(String, u64)>>,
+}
+
+fn hmac_sha256(key: &[u8], message: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_varkey(key).expect("HMAC can take a key of any size");
+    mac.update(message.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Adds the paths of all files in the given directory and its subdirectories
+fn collect_files_recursive(directory: &str, filepaths: &mut Vec<String>) {
+    if let Ok(entries) = std::fs::read_dir(directory) {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = format!("{}/{}", directory, entry.file_name().to_string_lossy());
+            if entry.path().is_dir() {
+                collect_files_recursive(&path, filepaths);
+            } else {
+                filepaths.push(path);
+            }
+        }
+    }
+}
+
+/// Percent-encodes everything but the unreserved characters and `/` as required by AWS
+fn uri_encode_path(path: &str) -> String {
+    path.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+impl S3Uploader {
+    pub fn new(params: &S3UploadParams) -> S3Uploader {
+        let read_environment_variable = |name: &str| {
+            std::env::var(name).unwrap_or_else(|_| {
+                panic!(
+                    "Please set the `{}` environment variable to upload our sheets to '{}'",
+                    name, params.bucket
+                )
+            })
+        };
+        S3Uploader {
+            params: params.clone(),
+            access_key_id: read_environment_variable(ACCESS_KEY_ID_ENVIRONMENT_VARIABLE),
+            secret_access_key: read_environment_variable(SECRET_ACCESS_KEY_ENVIRONMENT_VARIABLE),
+            uploaded_objects: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn object_path(&self, key: &str) -> String {
+        uri_encode_path(&format!("/{}/{}", self.params.bucket, key))
+    }
+
+    /// Creates a request that is signed with AWS Signature Version 4
+    fn signed_request(&self, method: &str, key: &str, payload: &[u8]) -> ureq::Request {
+        let endpoint = self.params.endpoint.trim_end_matches('/');
+        let host = endpoint.split("://").last().unwrap_or(endpoint).to_owned();
+        let path = self.object_path(key);
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date_stamp = now.format("%Y%m%d").to_string();
+        let payload_hash = sha256_hex(payload);
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, path, host, payload_hash, amz_date, signed_headers, payload_hash
+        );
+        let credential_scope = format!("{}/{}/s3/aws4_request", date_stamp, self.params.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            credential_scope,
+            sha256_hex(canonical_request.as_bytes())
+        );
+        let signing_key = [
+            date_stamp.as_str(),
+            self.params.region.as_str(),
+            "s3",
+            "aws4_request",
+        ]
+        .iter()
+        .fold(
+            format!("AWS4{}", self.secret_access_key).into_bytes(),
+            |key, part| hmac_sha256(&key, part),
+        );
+        let signature: String = hmac_sha256(&signing_key, &string_to_sign)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        ureq::request(method, &format!("{}{}", endpoint, path))
+            .set("x-amz-content-sha256", &payload_hash)
+            .set("x-amz-date", &amz_date)
+            .set(
+                "Authorization",
+                &format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.access_key_id, credential_scope, signed_headers, signature
+                ),
+            )
+    }
+
+    fn object_key(&self, filepath: &str, output_directory: &str) -> String {
+        let relative_filepath = filepath
+            .strip_prefix(&format!("{}/", output_directory))
+            .unwrap_or(filepath);
+        format!("{}{}", self.params.prefix, relative_filepath)
+    }
+
+    /// Returns the URL of the object that the given file of our output directory is uploaded to
+    pub fn object_url(&self, filepath: &str, output_directory: &str) -> String {
+        format!(
+            "{}{}",
+            self.params.endpoint.trim_end_matches('/'),
+            self.object_path(&self.object_key(filepath, output_directory))
+        )
+    }
+
+    /// Returns true if our sheets are removed from the local disk after uploading them
+    pub fn removes_local_files(&self) -> bool {
+        !self.params.keep_local_files
+    }
+
+    /// Uploads the given sheet that was written into our output directory and removes it from our
+    /// disk afterwards unless we asked to keep it.
+    pub fn upload_sheet_file(&self, filepath: &str, output_directory: &str) {
+        self.upload_file(filepath, output_directory);
+        if self.removes_local_files() {
+            std::fs::remove_file(filepath).unwrap_or_else(|error| {
+                log::warn!("Could not remove uploaded file '{}': {}", filepath, error)
+            });
+        }
+    }
+
+    /// Uploads all files of our output directory that were not uploaded yet (i.e. our manifest,
+    /// run manifest, report and thumbnails) and checks that all our uploads arrived. This must be
+    /// called after the last file of our run was written. These files stay on our disk.
+    pub fn finish_upload(&self, output_directory: &str) {
+        let uploaded_keys: Vec<String> = self
+            .uploaded_objects
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, _size)| key.clone())
+            .collect();
+        let mut filepaths = Vec::new();
+        collect_files_recursive(output_directory, &mut filepaths);
+        filepaths.sort();
+        for filepath in filepaths {
+            if !uploaded_keys.contains(&self.object_key(&filepath, output_directory)) {
+                self.upload_file(&filepath, output_directory);
+            }
+        }
+        self.verify_uploads();
+    }
+
+    /// Uploads the given file that was written into our output directory. Failed uploads are
+    /// retried a few times with increasing pauses as storage services sometimes refuse requests
+    /// under load.
+    fn upload_file(&self, filepath: &str, output_directory: &str) {
+        let key = self.object_key(filepath, output_directory);
+        let content = std::fs::read(filepath)
+            .unwrap_or_else(|error| panic!("Could not read file '{}': {}", filepath, error));
+
+        let mut attempt = 1;
+        loop {
+            match self
+                .signed_request("PUT", &key, &content)
+                .send_bytes(&content)
+            {
+                Ok(_) => break,
+                Err(error) if attempt < MAX_UPLOAD_ATTEMPTS => {
+                    log::warn!(
+                        "Could not upload '{}' (attempt {} of {}): {}",
+                        key,
+                        attempt,
+                        MAX_UPLOAD_ATTEMPTS,
+                        error
+                    );
+                    std::thread::sleep(Duration::from_secs(1 << attempt));
+                    attempt += 1;
+                }
+                Err(error) => panic!(
+                    "Could not upload '{}' to bucket '{}' after {} attempts: {}",
+                    key, self.params.bucket, MAX_UPLOAD_ATTEMPTS, error
+                ),
+            }
+        }
+        log::debug!("Uploaded '{}'", key);
+
+        self.uploaded_objects
+            .lock()
+            .unwrap()
+            .push((key, content.len() as u64));
+    }
+
+    /// Checks that every uploaded object exists in our bucket with the size that we uploaded
+    fn verify_uploads(&self) {
+        let uploaded_objects = self.uploaded_objects.lock().unwrap();
+        let problems: Vec<String> = uploaded_objects
+            .iter()
+            .filter_map(
+                |(key, size)| match self.signed_request("HEAD", key, &[]).call() {
+                    Ok(response) => {
+                        let remote_size = response
+                            .header("Content-Length")
+                            .and_then(|length| length.parse::<u64>().ok());
+                        if remote_size == Some(*size) {
+                            None
+                        } else {
+                            Some(format!(
+                                "'{}' has {} bytes instead of {} bytes",
+                                key,
+                                remote_size
+                                    .map(|remote_size| remote_size.to_string())
+                                    .unwrap_or_else(|| "an unknown number of".to_owned()),
+                                size
+                            ))
+                        }
+                    }
+                    Err(error) => Some(format!("'{}' could not be checked: {}", key, error)),
+                },
+            )
+            .collect();
+        assert!(
+            problems.is_empty(),
+            "The following uploaded files are missing or broken in bucket '{}':\n\n{}",
+            self.params.bucket,
+            problems.join("\n")
+        );
+        log::info!(
+            "Uploaded {} files to bucket '{}' at '{}'",
+            uploaded_objects.len(),
+            self.params.bucket,
+            self.params.prefix
+        );
+    }
+}
@@ -0,0 +1,434 @@
+//! Reads the numbers of a photographed card and finds that card in our batch. This lets us verify
+//! a physical card at the prize desk even if its serial number is damaged or unreadable.
+//!
+//! Usage: `chotto scan card_photo.jpg [--directory <dir>]`
+//!
+//! The photo should show the card straight from above so that the lines of its grid are horizontal
+//! and vertical. We find the grid like `chotto detect-grid` does and compare the content of each
+//! cell with our numbers drawn in our own font. Only the numbers that appear in the same column of
+//! some card of our batch are considered which makes the matching a lot more reliable.
+
+use std::collections::{BTreeSet, HashMap};
+
+use cottontail::image::{Bitmap, PixelRGBA};
+
+use crate::{
+    color_from_rgb, command_line_option_value, create_number_bitmaps_premultiplied, grid_detection,
+    load_background_image,
+    manifest::{self, Card},
+    show_messagebox, BingoVariant, FillStyle, Input, TextRasterizer, OUTPUT_SHEETS_DIRECTORY,
+};
+
+/// The content of a cell and our numbers are compared at this size in pixels
+const GLYPH_SIZE: usize = 32;
+/// We only read the inside of a cell so that its grid lines are left out
+const CELL_MARGIN_RATIO: f32 = 0.12;
+/// Cells whose dark and light pixels differ less than this in brightness are empty
+const MIN_INK_CONTRAST: f32 = 0.2;
+/// A cell is only read as a number if it matches that number at least this well where `1.0` is a
+/// perfect match
+const MIN_MATCH_SCORE: f32 = 0.6;
+
+pub fn scan_card(args: &[String]) {
+    let photo_filepath = args
+        .first()
+        .filter(|arg| !arg.starts_with("--"))
+        .expect("Please give the photo of the card via `chotto scan <photo>`");
+    let directory = command_line_option_value(args, "--directory")
+        .unwrap_or_else(|| OUTPUT_SHEETS_DIRECTORY.to_owned());
+
+    let input = Input::new();
+    let params = &input.params;
+    assert!(
+        matches!(
+            params.bingo_variant,
+            BingoVariant::Ball75
+                | BingoVariant::Ball90
+                | BingoVariant::Ball80
+                | BingoVariant::Ball30
+        ),
+        "Only cards with numbers can be scanned but our cards are {:?}",
+        params.bingo_variant
+    );
+    let cards = manifest::read_manifest(&directory).cards;
+    let (column_count, row_count) = params.grid_size();
+
+    let photo = load_background_image(photo_filepath);
+    let grid = grid_detection::locate_grid(&photo).unwrap_or_else(|| {
+        panic!(
+            "Could not find the grid of the card in '{}' - please take the photo straight from \
+             above with the whole card in view",
+            photo_filepath
+        )
+    });
+    let column_boundaries =
+        cell_boundaries(&grid.column_boundaries, grid.left, grid.right, column_count);
+    let row_boundaries = cell_boundaries(&grid.row_boundaries, grid.top, grid.bottom, row_count);
+
+    let number_glyphs = create_number_glyphs(&input);
+    let candidates_by_column =
+        candidate_numbers_by_column(&cards, column_count as usize, params.max_number());
+    let (r, g, b) = params.text_color_rgb;
+    let ink_is_dark = brightness(PixelRGBA::new(r, g, b, 255)) < 0.5;
+    let read_numbers: Vec<Vec<Option<i32>>> = row_boundaries
+        .windows(2)
+        .map(|rows| {
+            column_boundaries
+                .windows(2)
+                .zip(&candidates_by_column)
+                .map(|(columns, candidates)| {
+                    read_cell(
+                        &photo,
+                        (columns[0], rows[0], columns[1], rows[1]),
+                        candidates,
+                        &number_glyphs,
+                        ink_is_dark,
+                    )
+                })
+                .collect()
+        })
+        .collect();
+
+    let unreadable_count = read_numbers
+        .iter()
+        .flatten()
+        .filter(|number| number.is_none())
+        .count();
+    log::info!(
+        "Read the following numbers from '{}' where `?` could not be read:\n{}",
+        photo_filepath,
+        read_numbers
+            .iter()
+            .map(|row| row
+                .iter()
+                .map(|number| match number {
+                    Some(0) => format!("{:>3}", "-"),
+                    Some(number) => format!("{:>3}", number),
+                    None => format!("{:>3}", "?"),
+                })
+                .collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+
+    let matching_cards: Vec<&Card> = cards
+        .iter()
+        .filter(|card| count_mismatches(card, &read_numbers) == 0)
+        .collect();
+    match matching_cards.as_slice() {
+        [card] => {
+            log::info!(
+                "The card in '{}' is card '{}' (sheet {}) with {} unreadable cells",
+                photo_filepath,
+                card.serial,
+                card.sheet_number,
+                unreadable_count
+            );
+            show_messagebox(
+                "Chotto",
+                &format!(
+                    "The card in '{}' is card '{}' (sheet {}).",
+                    photo_filepath, card.serial, card.sheet_number
+                ),
+                false,
+            );
+        }
+        [] => {
+            let closest = cards
+                .iter()
+                .min_by_key(|card| count_mismatches(card, &read_numbers));
+            let closest_description = closest
+                .map(|card| {
+                    format!(
+                        " - the closest card '{}' (sheet {}) differs in {} cells",
+                        card.serial,
+                        card.sheet_number,
+                        count_mismatches(card, &read_numbers)
+                    )
+                })
+                .unwrap_or_default();
+            log::warn!(
+                "The card in '{}' does NOT match any card of '{}'{}",
+                photo_filepath,
+                directory,
+                closest_description
+            );
+            show_messagebox(
+                "Chotto",
+                &format!(
+                    "The card in '{}' does NOT match any card of our batch{}!",
+                    photo_filepath, closest_description
+                ),
+                true,
+            );
+        }
+        _ => {
+            log::warn!(
+                "The card in '{}' matches {} cards of '{}' because {} cells could not be read",
+                photo_filepath,
+                matching_cards.len(),
+                directory,
+                unreadable_count
+            );
+            show_messagebox(
+                "Chotto",
+                &format!(
+                    "The card in '{}' matches {} cards of our batch because {} cells could not be \
+                     read - please take a sharper photo.",
+                    photo_filepath,
+                    matching_cards.len(),
+                    unreadable_count
+                ),
+                true,
+            );
+        }
+    }
+}
+
+/// Returns the given number of cells between `start` and `end`. We use the lines that we found in
+/// the photo if there are enough of them and otherwise split the grid evenly.
+fn cell_boundaries(detected_boundaries: &[i32], start: i32, end: i32, count: i32) -> Vec<i32> {
+    if detected_boundaries.len() == count as usize + 1 {
+        detected_boundaries.to_vec()
+    } else {
+        (0..=count)
+            .map(|index| start + (end - start) * index / count)
+            .collect()
+    }
+}
+
+/// Returns the normalized glyphs of all our numbers drawn just like on our sheets
+fn create_number_glyphs(input: &Input) -> HashMap<i32, Vec<f32>> {
+    let params = &input.params;
+    let column_labels = params.column_labels();
+    let prefix_column_labels = if params.prefix_numbers_with_column_letter {
+        Some(column_labels.as_slice())
+    } else {
+        None
+    };
+    let mut text_rasterizer = TextRasterizer::new(
+        &input.font,
+        params.text_font_size as f32,
+        FillStyle::Solid(color_from_rgb((255, 255, 255))),
+        params.text_direction,
+        params.bold_text,
+        "0123456789",
+    );
+    create_number_bitmaps_premultiplied(
+        &mut text_rasterizer,
+        params.bingo_variant,
+        params.max_number(),
+        prefix_column_labels,
+    )
+    .into_iter()
+    .filter_map(|(number, bitmap)| {
+        let ink: Vec<f32> = bitmap
+            .data
+            .iter()
+            .map(|pixel| pixel.a as f32 / 255.0)
+            .collect();
+        normalized_glyph(&ink, bitmap.width as usize, bitmap.height as usize)
+            .map(|glyph| (number, glyph))
+    })
+    .collect()
+}
+
+/// Returns the numbers that can appear in each column which are the ones that some card of our
+/// batch has in that column
+fn candidate_numbers_by_column(
+    cards: &[Card],
+    column_count: usize,
+    max_number: i32,
+) -> Vec<Vec<i32>> {
+    let mut candidates = vec![BTreeSet::new(); column_count];
+    for card in cards {
+        for row in &card.numbers {
+            for (column, &number) in row.iter().enumerate().take(column_count) {
+                if number != 0 {
+                    candidates[column].insert(number);
+                }
+            }
+        }
+    }
+    candidates
+        .into_iter()
+        .map(|numbers| {
+            if numbers.is_empty() {
+                (1..=max_number).collect()
+            } else {
+                numbers.into_iter().collect()
+            }
+        })
+        .collect()
+}
+
+/// Returns the number in the given cell rect, `Some(0)` if the cell is empty or `None` if we
+/// cannot read it
+fn read_cell(
+    photo: &Bitmap,
+    (left, top, right, bottom): (i32, i32, i32, i32),
+    candidates: &[i32],
+    number_glyphs: &HashMap<i32, Vec<f32>>,
+    ink_is_dark: bool,
+) -> Option<i32> {
+    let margin_x = ((right - left) as f32 * CELL_MARGIN_RATIO) as i32;
+    let margin_y = ((bottom - top) as f32 * CELL_MARGIN_RATIO) as i32;
+    let width = right - left - 2 * margin_x;
+    let height = bottom - top - 2 * margin_y;
+    if width <= 0 || height <= 0 {
+        return None;
+    }
+
+    let cell_brightness: Vec<f32> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (left + margin_x + x, top + margin_y + y)))
+        .map(|(x, y)| brightness(photo.get(x, y)))
+        .collect();
+    let (threshold, dark_mean, light_mean) = split_brightness(&cell_brightness);
+    if light_mean - dark_mean < MIN_INK_CONTRAST {
+        return Some(0);
+    }
+    let ink: Vec<f32> = cell_brightness
+        .iter()
+        .map(|&value| {
+            if (value < threshold) == ink_is_dark {
+                1.0
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    let glyph = normalized_glyph(&ink, width as usize, height as usize)?;
+
+    let (number, score) = candidates
+        .iter()
+        .filter_map(|number| {
+            number_glyphs.get(number).map(|number_glyph| {
+                let score: f32 = glyph.iter().zip(number_glyph).map(|(a, b)| a * b).sum();
+                (*number, score)
+            })
+        })
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())?;
+    if score >= MIN_MATCH_SCORE {
+        Some(number)
+    } else {
+        None
+    }
+}
+
+/// Returns how many cells that we could read differ from the given card. The free space and empty
+/// cells of the card are ignored as they might contain images or texts.
+fn count_mismatches(card: &Card, read_numbers: &[Vec<Option<i32>>]) -> usize {
+    card.numbers
+        .iter()
+        .flatten()
+        .zip(read_numbers.iter().flatten())
+        .filter(|(&number, &read_number)| {
+            number != 0 && read_number.map(|read| read != number).unwrap_or(false)
+        })
+        .count()
+}
+
+/// Scales the bounding box of the given ink coverage into a square of `GLYPH_SIZE` while keeping
+/// its aspect ratio. The result has a mean of zero and a length of one so that the dot product of
+/// two glyphs tells how well they match. Returns `None` if there is no ink at all.
+fn normalized_glyph(ink: &[f32], width: usize, height: usize) -> Option<Vec<f32>> {
+    let ink_at = |x: usize, y: usize| ink[y * width + x];
+    let columns: Vec<usize> = (0..width)
+        .filter(|&x| (0..height).any(|y| ink_at(x, y) > 0.5))
+        .collect();
+    let rows: Vec<usize> = (0..height)
+        .filter(|&y| (0..width).any(|x| ink_at(x, y) > 0.5))
+        .collect();
+    let (&left, &right) = (columns.first()?, columns.last()?);
+    let (&top, &bottom) = (rows.first()?, rows.last()?);
+
+    // NOTE: We center the bounding box in a square so that i.e. `7` and `17` keep their shape
+    let box_width = (right - left + 1) as f32;
+    let box_height = (bottom - top + 1) as f32;
+    let side = f32::max(box_width, box_height);
+    let origin_x = left as f32 - (side - box_width) / 2.0;
+    let origin_y = top as f32 - (side - box_height) / 2.0;
+    let step = side / GLYPH_SIZE as f32;
+
+    let mut glyph = Vec::with_capacity(GLYPH_SIZE * GLYPH_SIZE);
+    for glyph_y in 0..GLYPH_SIZE {
+        for glyph_x in 0..GLYPH_SIZE {
+            let start_x = (origin_x + glyph_x as f32 * step).floor() as i64;
+            let start_y = (origin_y + glyph_y as f32 * step).floor() as i64;
+            let end_x = i64::max(
+                start_x + 1,
+                (origin_x + (glyph_x + 1) as f32 * step).ceil() as i64,
+            );
+            let end_y = i64::max(
+                start_y + 1,
+                (origin_y + (glyph_y + 1) as f32 * step).ceil() as i64,
+            );
+            let mut sum = 0.0;
+            for y in start_y..end_y {
+                for x in start_x..end_x {
+                    if 0 <= x && x < width as i64 && 0 <= y && y < height as i64 {
+                        sum += ink_at(x as usize, y as usize);
+                    }
+                }
+            }
+            glyph.push(sum / ((end_x - start_x) * (end_y - start_y)) as f32);
+        }
+    }
+
+    let mean = glyph.iter().sum::<f32>() / glyph.len() as f32;
+    glyph.iter_mut().for_each(|value| *value -= mean);
+    let length = glyph.iter().map(|value| value * value).sum::<f32>().sqrt();
+    if length == 0.0 {
+        return None;
+    }
+    glyph.iter_mut().for_each(|value| *value /= length);
+    Some(glyph)
+}
+
+/// Returns the threshold that separates the given brightness values best into dark and light ones
+/// (Otsu's method) together with the mean brightness of the dark and the light values
+fn split_brightness(values: &[f32]) -> (f32, f32, f32) {
+    let mut histogram = [0usize; 256];
+    for value in values {
+        histogram[(value * 255.0).round() as usize] += 1;
+    }
+    let total_count = values.len() as f32;
+    let total_sum: f32 = histogram
+        .iter()
+        .enumerate()
+        .map(|(bucket, &count)| bucket as f32 * count as f32)
+        .sum();
+
+    let mut best = (0.0, 0.0, 0.0);
+    let mut best_variance = -1.0;
+    let mut dark_count = 0.0;
+    let mut dark_sum = 0.0;
+    for (bucket, &count) in histogram.iter().enumerate().take(255) {
+        dark_count += count as f32;
+        dark_sum += bucket as f32 * count as f32;
+        let light_count = total_count - dark_count;
+        if dark_count == 0.0 || light_count == 0.0 {
+            continue;
+        }
+        let dark_mean = dark_sum / dark_count;
+        let light_mean = (total_sum - dark_sum) / light_count;
+        let variance = dark_count * light_count * (light_mean - dark_mean).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best = (
+                (bucket as f32 + 0.5) / 255.0,
+                dark_mean / 255.0,
+                light_mean / 255.0,
+            );
+        }
+    }
+    best
+}
+
+/// Returns the brightness of the given pixel on white paper from 0.0 (black) to 1.0 (white)
+fn brightness(pixel: PixelRGBA) -> f32 {
+    let alpha = pixel.a as f32 / 255.0;
+    let luma =
+        (0.2126 * pixel.r as f32 + 0.7152 * pixel.g as f32 + 0.0722 * pixel.b as f32) / 255.0;
+    luma * alpha + (1.0 - alpha)
+}
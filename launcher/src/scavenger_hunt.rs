@@ -0,0 +1,145 @@
+//! Generation of scavenger hunt cards. Each card is a 5x5 grid of items that come from a list of
+//! categories. An item is represented by its 1-based position in the list of all items of all
+//! categories.
+
+use crate::generator::{
+    accepted_by_all, check_rejected_grid_count, is_duplicate, shuffled, CardConstraint,
+    RandomSource,
+};
+use cottontail::image::Grid;
+
+pub const GRID_SIZE: usize = 5;
+const ITEMS_PER_CARD: usize = GRID_SIZE * GRID_SIZE;
+
+pub struct Category {
+    pub items: Vec<i32>,
+    /// Only used by the random layout
+    pub min_items_per_card: usize,
+    /// Only used by the random layout
+    pub max_items_per_card: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// Items of all categories can go anywhere on the card
+    Random,
+    /// Each row contains one item of each category in a random order
+    OnePerCategoryPerRow,
+    /// Each column contains only items of its own category
+    CategoryPerColumn,
+}
+
+/// Returns one grid per card. The new cards never duplicate each other or the given cards of an
+/// earlier batch.
+/// NOTE: The structured layouts need exactly as many categories as the card has columns and at
+///       least as many items per category as the card has rows. The random layout needs the
+///       minimum and maximum number of items per category to leave room for a full card. This is
+///       checked when loading our draw parameters.
+pub fn create_random_cards(
+    card_count: usize,
+    categories: &[Category],
+    layout: Layout,
+    constraints: &[&dyn CardConstraint],
+    previous_cards: &[Grid<i32>],
+    random: &mut dyn RandomSource,
+) -> Vec<Grid<i32>> {
+    log::debug!(
+        "Generating {} scavenger hunt cards after {} previous cards",
+        card_count,
+        previous_cards.len()
+    );
+
+    let mut cards: Vec<Grid<i32>> = previous_cards.to_vec();
+    let mut rejected_card_count = 0;
+    while cards.len() < previous_cards.len() + card_count {
+        let card = match layout {
+            Layout::Random => create_random_layout_card(categories, random),
+            Layout::OnePerCategoryPerRow => {
+                create_one_per_category_per_row_card(categories, random)
+            }
+            Layout::CategoryPerColumn => create_category_per_column_card(categories, random),
+        };
+        if is_duplicate(&card, &cards) || !accepted_by_all(constraints, &card, &cards) {
+            rejected_card_count += 1;
+            check_rejected_grid_count(rejected_card_count);
+            continue;
+        }
+        rejected_card_count = 0;
+        cards.push(card);
+    }
+    cards.split_off(previous_cards.len())
+}
+
+/// Returns `GRID_SIZE` distinct random items of the given category
+fn pick_items(category: &Category, random: &mut dyn RandomSource) -> Vec<i32> {
+    let mut items = shuffled(category.items.clone(), random);
+    items.truncate(GRID_SIZE);
+    items
+}
+
+fn create_category_per_column_card(
+    categories: &[Category],
+    random: &mut dyn RandomSource,
+) -> Grid<i32> {
+    debug_assert!(categories.len() == GRID_SIZE);
+    let mut card = Grid::new(GRID_SIZE as u32, GRID_SIZE as u32);
+    for (x, category) in categories.iter().enumerate() {
+        for (y, item) in pick_items(category, random).into_iter().enumerate() {
+            card.set(x as i32, y as i32, item);
+        }
+    }
+    card
+}
+
+fn create_one_per_category_per_row_card(
+    categories: &[Category],
+    random: &mut dyn RandomSource,
+) -> Grid<i32> {
+    debug_assert!(categories.len() == GRID_SIZE);
+    let category_items: Vec<Vec<i32>> = categories
+        .iter()
+        .map(|category| pick_items(category, random))
+        .collect();
+    let mut card = Grid::new(GRID_SIZE as u32, GRID_SIZE as u32);
+    for y in 0..GRID_SIZE {
+        let row_items: Vec<i32> = category_items.iter().map(|items| items[y]).collect();
+        for (x, item) in shuffled(row_items, random).into_iter().enumerate() {
+            card.set(x as i32, y as i32, item);
+        }
+    }
+    card
+}
+
+/// First picks the minimum number of items of each category and then fills the rest of the card
+/// with items of categories that did not reach their maximum yet
+fn create_random_layout_card(categories: &[Category], random: &mut dyn RandomSource) -> Grid<i32> {
+    let mut picked_items: Vec<i32> = Vec::with_capacity(ITEMS_PER_CARD);
+    let mut remaining_items: Vec<(usize, i32)> = Vec::new();
+    for (category_index, category) in categories.iter().enumerate() {
+        let items = shuffled(category.items.clone(), random);
+        let (required_items, optional_items) = items.split_at(category.min_items_per_card);
+        picked_items.extend_from_slice(required_items);
+        remaining_items.extend(optional_items.iter().map(|&item| (category_index, item)));
+    }
+
+    let mut picked_counts: Vec<usize> = categories
+        .iter()
+        .map(|category| category.min_items_per_card)
+        .collect();
+    for (category_index, item) in shuffled(remaining_items, random) {
+        if picked_items.len() == ITEMS_PER_CARD {
+            break;
+        }
+        if picked_counts[category_index] < categories[category_index].max_items_per_card {
+            picked_counts[category_index] += 1;
+            picked_items.push(item);
+        }
+    }
+    debug_assert!(picked_items.len() == ITEMS_PER_CARD);
+
+    let mut card = Grid::new(GRID_SIZE as u32, GRID_SIZE as u32);
+    for (index, item) in shuffled(picked_items, random).into_iter().enumerate() {
+        card.set((index % GRID_SIZE) as i32, (index / GRID_SIZE) as i32, item);
+    }
+    card
+}
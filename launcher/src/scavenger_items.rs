@@ -0,0 +1,143 @@
+//! The categorized item lists of scavenger hunt cards. All items of all categories are numbered
+//! from 1 in the order in which they are given in our draw parameters. The numbers in our cards and
+//! manifest refer to these items.
+
+use chotto::scavenger_hunt::{self, Category, Layout};
+use cottontail::core::serde_derive::{Deserialize, Serialize};
+
+use crate::DRAW_PARAMETERS_FILENAME;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScavengerHuntLayout {
+    Random,
+    OnePerCategoryPerRow,
+    CategoryPerColumn,
+}
+
+impl Default for ScavengerHuntLayout {
+    fn default() -> Self {
+        ScavengerHuntLayout::Random
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScavengerHuntCategory {
+    name: String,
+    items: Vec<String>,
+    #[serde(default)]
+    min_items_per_card: usize,
+    max_items_per_card: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScavengerHuntParams {
+    #[serde(default)]
+    layout: ScavengerHuntLayout,
+    categories: Vec<ScavengerHuntCategory>,
+}
+
+impl ScavengerHuntParams {
+    /// Returns the items of all categories in the order of their numbers
+    pub fn items(&self) -> Vec<&str> {
+        self.categories
+            .iter()
+            .flat_map(|category| category.items.iter().map(|item| item.as_str()))
+            .collect()
+    }
+
+    pub fn layout(&self) -> Layout {
+        match self.layout {
+            ScavengerHuntLayout::Random => Layout::Random,
+            ScavengerHuntLayout::OnePerCategoryPerRow => Layout::OnePerCategoryPerRow,
+            ScavengerHuntLayout::CategoryPerColumn => Layout::CategoryPerColumn,
+        }
+    }
+
+    /// Returns our categories with the numbers of their items for the card generation
+    pub fn categories(&self) -> Vec<Category> {
+        let mut next_number = 1;
+        self.categories
+            .iter()
+            .map(|category| {
+                let items: Vec<i32> =
+                    (next_number..next_number + category.items.len() as i32).collect();
+                next_number += category.items.len() as i32;
+                Category {
+                    items,
+                    min_items_per_card: category.min_items_per_card,
+                    max_items_per_card: category.max_items_per_card.unwrap_or(category.items.len()),
+                }
+            })
+            .collect()
+    }
+
+    /// Panics if our categories cannot fill a card with the chosen layout
+    pub fn validate(&self) {
+        let items = self.items();
+        for (index, item) in items.iter().enumerate() {
+            assert!(
+                !items[..index].contains(item),
+                "The scavenger hunt item '{}' is given more than once - please fix it in '{}'!",
+                item,
+                DRAW_PARAMETERS_FILENAME
+            );
+        }
+
+        match self.layout {
+            ScavengerHuntLayout::OnePerCategoryPerRow | ScavengerHuntLayout::CategoryPerColumn => {
+                assert!(
+                    self.categories.len() == scavenger_hunt::GRID_SIZE,
+                    "The scavenger hunt layout {:?} needs exactly {} categories but there are {} - \
+                     please fix it in '{}'!",
+                    self.layout,
+                    scavenger_hunt::GRID_SIZE,
+                    self.categories.len(),
+                    DRAW_PARAMETERS_FILENAME
+                );
+                for category in &self.categories {
+                    assert!(
+                        category.items.len() >= scavenger_hunt::GRID_SIZE,
+                        "The scavenger hunt category '{}' needs at least {} items but has {} - \
+                         please fix it in '{}'!",
+                        category.name,
+                        scavenger_hunt::GRID_SIZE,
+                        category.items.len(),
+                        DRAW_PARAMETERS_FILENAME
+                    );
+                }
+            }
+            ScavengerHuntLayout::Random => {
+                let items_per_card = scavenger_hunt::GRID_SIZE * scavenger_hunt::GRID_SIZE;
+                let mut min_item_count = 0;
+                let mut max_item_count = 0;
+                for category in &self.categories {
+                    let max_items_per_card =
+                        category.max_items_per_card.unwrap_or(category.items.len());
+                    assert!(
+                        category.min_items_per_card <= max_items_per_card
+                            && max_items_per_card <= category.items.len(),
+                        "The scavenger hunt category '{}' has {} items but wants between {} and \
+                         {} items per card - please fix it in '{}'!",
+                        category.name,
+                        category.items.len(),
+                        category.min_items_per_card,
+                        max_items_per_card,
+                        DRAW_PARAMETERS_FILENAME
+                    );
+                    min_item_count += category.min_items_per_card;
+                    max_item_count += max_items_per_card;
+                }
+                assert!(
+                    min_item_count <= items_per_card && items_per_card <= max_item_count,
+                    "A scavenger hunt card has {} items but our categories want at least {} and \
+                     at most {} items per card - please fix it in '{}'!",
+                    items_per_card,
+                    min_item_count,
+                    max_item_count,
+                    DRAW_PARAMETERS_FILENAME
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Seating cards and table numbers for the events where we also play Bingo. With
+//! `mode = "seating_cards"` each sheet shows one line of our `seating_cards_filepath` (i.e. the
+//! name of a guest or a table number) centered in a given region of our background.
+
+use cottontail::math::Vec2i;
+
+use crate::{resample_bilinear, sheet_canvas::SheetCanvas, TextRasterizer};
+
+/// Returns the texts of our seating cards which are the non-empty lines of the given file
+pub fn read_seating_card_texts(filepath: &str) -> Vec<String> {
+    let texts: Vec<String> = std::fs::read_to_string(filepath)
+        .unwrap_or_else(|error| panic!("Could not read file '{}': {}", filepath, error))
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_owned())
+        .collect();
+    assert!(
+        !texts.is_empty(),
+        "There are no names or table numbers in '{}'",
+        filepath
+    );
+    texts
+}
+
+/// Draws the given text centered into the given region of the sheet. Texts that are too big for
+/// the region are shrunk to fit so that long names don't need their own font size.
+pub fn draw_text_fitted_into_region(
+    rasterizer: &TextRasterizer,
+    text: &str,
+    region_left_top_right_bottom: (i32, i32, i32, i32),
+    sheet: &mut SheetCanvas,
+    gamma_correct_blending: bool,
+) {
+    let (left, top, right, bottom) = region_left_top_right_bottom;
+    let region_width = right - left;
+    let region_height = bottom - top;
+    let mut text_bitmap = rasterizer.create_text_bitmap_premultiplied(text);
+    if text_bitmap.width == 0 || text_bitmap.height == 0 {
+        return;
+    }
+
+    let scale = f32::min(
+        region_width as f32 / text_bitmap.width as f32,
+        region_height as f32 / text_bitmap.height as f32,
+    );
+    if scale < 1.0 {
+        log::debug!(
+            "Shrinking the text '{}' by {:.2} to fit its region",
+            text,
+            scale
+        );
+        text_bitmap = resample_bilinear(
+            &text_bitmap,
+            i32::max(1, (text_bitmap.width as f32 * scale) as i32),
+            i32::max(1, (text_bitmap.height as f32 * scale) as i32),
+        );
+    }
+    sheet.blit_centered_premultiplied(
+        &text_bitmap,
+        Vec2i::new(left + region_width / 2, top + region_height / 2),
+        gamma_correct_blending,
+    );
+}
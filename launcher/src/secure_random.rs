@@ -0,0 +1,59 @@
+//! Cryptographically secure random numbers for regulated games that require unpredictable sheets
+//! (`rng = "secure"`). Unlike our usual generator nobody can predict the next numbers from the
+//! ones that were drawn before, and its seed never leaves the memory of our process.
+
+use chotto::generator::RandomSource;
+use rand_chacha::{
+    rand_core::{RngCore, SeedableRng},
+    ChaCha20Rng,
+};
+
+pub struct SecureRandom {
+    rng: ChaCha20Rng,
+}
+
+impl SecureRandom {
+    /// Seeds a new generator with the secure random numbers of our operating system
+    pub fn new() -> SecureRandom {
+        let mut seed = [0u8; 32];
+        getrandom::getrandom(&mut seed).unwrap_or_else(|error| {
+            panic!(
+                "Could not get secure random numbers from the operating system: {}",
+                error
+            )
+        });
+        SecureRandom {
+            rng: ChaCha20Rng::from_seed(seed),
+        }
+    }
+}
+
+impl RandomSource for SecureRandom {
+    fn u32_bounded_exclusive(&mut self, bound: u32) -> u32 {
+        assert!(bound > 0, "Cannot draw a random number below 0");
+        // NOTE: We throw away the lowest `2^32 % bound` numbers so that the remaining ones are a
+        //       multiple of `bound` and every result is equally likely
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let number = self.rng.next_u32();
+            if number >= threshold {
+                return number % bound;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_all_numbers_below_the_bound() {
+        let mut random = SecureRandom::new();
+        let mut counts = [0; 6];
+        for _ in 0..6000 {
+            counts[random.u32_bounded_exclusive(6) as usize] += 1;
+        }
+        assert!(counts.iter().all(|&count| count > 800));
+    }
+}
@@ -0,0 +1,146 @@
+//! Short HMAC-based codes that are printed on each sheet so that we can tell genuine sheets from
+//! sheets that players printed themselves
+
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+use crate::{
+    command_line_option_value, load_draw_params, manifest, show_messagebox,
+    DRAW_PARAMETERS_FILENAME, OUTPUT_SHEETS_DIRECTORY,
+};
+
+/// Leaves out characters that are easily mistaken for each other like `0` and `O` or `1` and `I`
+const CODE_ALPHABET: &[u8; 32] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const CODE_LENGTH: usize = 8;
+
+/// Returns a code like `K7QX-M2ZD` that depends on the numbers of a card row by row and our
+/// secret key. Empty cells and the free space are 0.
+pub fn create_security_code(numbers: &[Vec<i32>], secret_key: &str) -> String {
+    // NOTE: Each number is bound to its cell. Otherwise a forger could rearrange the numbers of a
+    //       genuine card into a winning line and the card would still have a valid code.
+    let message = numbers
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .map(move |(x, number)| format!("{},{},{}", y, x, number))
+        })
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let mut mac =
+        Hmac::<Sha256>::new_varkey(secret_key.as_bytes()).expect("HMAC can take a key of any size");
+    mac.update(message.as_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    // NOTE: We use 5 bits of the hash per character
+    let mut bits: u64 = 0;
+    for byte in hash.iter().take(5) {
+        bits = (bits << 8) | *byte as u64;
+    }
+    let code: String = (0..CODE_LENGTH)
+        .map(|index| {
+            let shift = 5 * (CODE_LENGTH - 1 - index);
+            CODE_ALPHABET[((bits >> shift) & 0b11111) as usize] as char
+        })
+        .collect();
+    format!("{}-{}", &code[..CODE_LENGTH / 2], &code[CODE_LENGTH / 2..])
+}
+
+/// Usage: `chotto verify-code --code K7QX-M2ZD --numbers 3,17,0,.../5,22,...`
+///    or: `chotto verify-code --code K7QX-M2ZD --sheet 17 [--directory <dir>]`
+pub fn verify_code(args: &[String]) {
+    let params = load_draw_params();
+    let secret_key = params.security_code_secret_key.unwrap_or_else(|| {
+        panic!(
+            "Please first add the `security_code_secret_key` parameter to '{}'",
+            DRAW_PARAMETERS_FILENAME
+        )
+    });
+    let code = command_line_option_value(args, "--code")
+        .expect("Please specify the code of the sheet via `--code <code>`");
+
+    let numbers: Vec<Vec<i32>> = if let Some(numbers) = command_line_option_value(args, "--numbers")
+    {
+        parse_grid(&numbers)
+    } else if let Some(sheet_number) = command_line_option_value(args, "--sheet") {
+        let sheet_number = sheet_number
+            .parse::<usize>()
+            .unwrap_or_else(|_| panic!("Invalid sheet number '{}'", sheet_number));
+        let directory = command_line_option_value(args, "--directory")
+            .unwrap_or_else(|| OUTPUT_SHEETS_DIRECTORY.to_owned());
+        let manifest = manifest::read_manifest(&directory);
+        let card = manifest
+            .cards
+            .iter()
+            .find(|card| card.sheet_number == sheet_number)
+            .unwrap_or_else(|| panic!("There is no sheet {} in '{}'", sheet_number, directory));
+        card.numbers.clone()
+    } else {
+        panic!("Please specify the numbers of the sheet row by row via `--numbers <n1,n2,.../n6,...>` or its sheet number via `--sheet <number>`");
+    };
+
+    let expected_code = create_security_code(&numbers, &secret_key);
+    let normalized_code = code.trim().to_uppercase().replace(' ', "");
+    if normalized_code == expected_code || normalized_code == expected_code.replace('-', "") {
+        log::info!("Code '{}' is valid", code);
+        show_messagebox("Chotto", &format!("Code '{}' is valid.", code), false);
+    } else {
+        log::warn!("Code '{}' is NOT valid", code);
+        show_messagebox(
+            "Chotto",
+            &format!(
+                "Code '{}' is NOT valid - this sheet was not created by us!",
+                code
+            ),
+            true,
+        );
+    }
+}
+
+/// Parses the numbers of a grid like `3,17,0,44,61/5,22,...` whose rows are separated by `/` and
+/// whose empty cells and free space are 0
+fn parse_grid(grid: &str) -> Vec<Vec<i32>> {
+    grid.split('/')
+        .map(|row| {
+            row.split(',')
+                .map(|number| {
+                    number
+                        .trim()
+                        .parse::<i32>()
+                        .unwrap_or_else(|_| panic!("Invalid number '{}'", number))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moving_numbers_changes_the_code() {
+        let numbers = vec![vec![3, 17, 35], vec![5, 0, 44], vec![12, 22, 41]];
+        let code = create_security_code(&numbers, "secret");
+        assert_eq!(create_security_code(&numbers, "secret"), code);
+
+        let mut swapped_in_column = numbers.clone();
+        swapped_in_column[0][0] = 12;
+        swapped_in_column[2][0] = 3;
+        assert_ne!(create_security_code(&swapped_in_column, "secret"), code);
+
+        let mut swapped_in_row = numbers.clone();
+        swapped_in_row[0].swap(0, 1);
+        assert_ne!(create_security_code(&swapped_in_row, "secret"), code);
+    }
+
+    #[test]
+    fn parses_grids_row_by_row() {
+        assert_eq!(
+            parse_grid("3, 17,35/5,0,44"),
+            vec![vec![3, 17, 35], vec![5, 0, 44]]
+        );
+    }
+}
@@ -0,0 +1,278 @@
+//! Serial numbers that are printed on our sheets. Besides counting up from 1 we can also use serial
+//! numbers that don't tell players how many sheets were printed or which other serial numbers
+//! exist.
+
+use std::collections::HashSet;
+
+use chotto::generator::RandomSource;
+use cottontail::core::serde_derive::{Deserialize, Serialize};
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+/// Leaves out characters that are easily mistaken for each other like `0` and `O` or `1` and `I`
+const SERIAL_ALPHABET: &[u8; 32] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const DEFAULT_SERIAL_LENGTH: usize = 8;
+const MIN_SERIAL_LENGTH: usize = 4;
+/// Keyed serial numbers of this length have 60 bits which is the most that fits into a `u64`
+const MAX_KEYED_SERIAL_LENGTH: usize = 12;
+const FEISTEL_ROUND_COUNT: u8 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SerialNumberScheme {
+    /// `start`, `start + stride`, `start + 2 * stride`, ...
+    Sequential,
+    /// Unique random alphanumeric IDs
+    Random,
+    /// The sequential serial number scrambled by a permutation that depends on a secret salt
+    Keyed,
+}
+
+impl Default for SerialNumberScheme {
+    fn default() -> Self {
+        SerialNumberScheme::Sequential
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SerialNumberParams {
+    #[serde(default, rename = "serial_number_scheme")]
+    pub scheme: SerialNumberScheme,
+    #[serde(rename = "serial_number_start")]
+    pub start: Option<u64>,
+    #[serde(rename = "serial_number_stride")]
+    pub stride: Option<u64>,
+    #[serde(rename = "serial_number_length")]
+    pub length: Option<usize>,
+    #[serde(rename = "serial_number_salt")]
+    pub salt: Option<String>,
+}
+
+/// Returns the serial numbers for the sheets with the given (1-based) sheet numbers. Serial
+/// numbers in `existing_serials` are never returned again.
+pub fn create_serial_numbers(
+    params: &SerialNumberParams,
+    sheet_numbers: &[usize],
+    existing_serials: &HashSet<String>,
+    random: &mut dyn RandomSource,
+) -> Vec<String> {
+    let start = params.start.unwrap_or(1);
+    let stride = params.stride.unwrap_or(1);
+    assert!(stride > 0, "The `serial_number_stride` must be at least 1");
+    let sequential_number = |sheet_number: usize| {
+        (sheet_number as u64 - 1)
+            .checked_mul(stride)
+            .and_then(|offset| offset.checked_add(start))
+            .unwrap_or_else(|| {
+                panic!(
+                    "The serial number of sheet {} is too big - please decrease the \
+                     `serial_number_start` or `serial_number_stride`",
+                    sheet_number
+                )
+            })
+    };
+
+    match params.scheme {
+        SerialNumberScheme::Sequential => sheet_numbers
+            .iter()
+            .map(|&sheet_number| sequential_number(sheet_number).to_string())
+            .collect(),
+        SerialNumberScheme::Keyed => {
+            let salt = params
+                .salt
+                .as_ref()
+                .expect("Please add a secret `serial_number_salt` for the `keyed` scheme");
+            let length = params.length.unwrap_or(DEFAULT_SERIAL_LENGTH);
+            assert!(
+                MIN_SERIAL_LENGTH <= length && length <= MAX_KEYED_SERIAL_LENGTH,
+                "The `serial_number_length` must be between {} and {} for keyed serial numbers",
+                MIN_SERIAL_LENGTH,
+                MAX_KEYED_SERIAL_LENGTH
+            );
+            sheet_numbers
+                .iter()
+                .map(|&sheet_number| {
+                    encode_with_alphabet(
+                        keyed_permutation(sequential_number(sheet_number), salt, length),
+                        length,
+                    )
+                })
+                .collect()
+        }
+        SerialNumberScheme::Random => {
+            let length = params.length.unwrap_or(DEFAULT_SERIAL_LENGTH);
+            assert!(
+                length >= MIN_SERIAL_LENGTH,
+                "The `serial_number_length` must be at least {} for random serial numbers",
+                MIN_SERIAL_LENGTH
+            );
+            let mut used_serials = existing_serials.clone();
+            sheet_numbers
+                .iter()
+                .map(|_| loop {
+                    // NOTE: Each character is drawn on its own so that all serial numbers of the
+                    //       given length are equally likely
+                    let serial: String = (0..length)
+                        .map(|_| {
+                            let index = random.u32_bounded_exclusive(SERIAL_ALPHABET.len() as u32);
+                            SERIAL_ALPHABET[index as usize] as char
+                        })
+                        .collect();
+                    if used_serials.insert(serial.clone()) {
+                        break serial;
+                    }
+                })
+                .collect()
+        }
+    }
+}
+
+/// Encodes the given number in the base of our alphabet length, padded to the given length
+fn encode_with_alphabet(mut number: u64, length: usize) -> String {
+    let base = SERIAL_ALPHABET.len() as u64;
+    let mut result = Vec::with_capacity(length);
+    while result.len() < length {
+        result.push(SERIAL_ALPHABET[(number % base) as usize] as char);
+        number /= base;
+    }
+    result.iter().rev().collect()
+}
+
+/// Returns the number that the given number below `32^length` is mapped to by a permutation of all
+/// numbers below `32^length` that is chosen by the given salt. Without the salt neighbouring
+/// numbers are mapped to numbers that look unrelated.
+fn keyed_permutation(number: u64, salt: &str, length: usize) -> u64 {
+    let number_count = (SERIAL_ALPHABET.len() as u64).pow(length as u32);
+    assert!(
+        number < number_count,
+        "The serial number {} does not fit into {} characters - please increase the \
+         `serial_number_length`",
+        number,
+        length
+    );
+    let half_bit_count = (5 * length as u32 + 1) / 2;
+    let half_mask = (1 << half_bit_count) - 1;
+
+    // NOTE: The Feistel network permutes all numbers with `2 * half_bit_count` bits. If the
+    //       result is too big we apply it again until we land below `number_count` which keeps
+    //       the mapping a permutation of the numbers below `number_count` (cycle walking).
+    let mut result = number;
+    loop {
+        let mut left = result >> half_bit_count;
+        let mut right = result & half_mask;
+        for round in 0..FEISTEL_ROUND_COUNT {
+            let mut mac = Hmac::<Sha256>::new_varkey(salt.as_bytes())
+                .expect("HMAC can take a key of any size");
+            mac.update(&[round]);
+            mac.update(&right.to_le_bytes());
+            let hash = mac.finalize().into_bytes();
+            let mut round_key = [0; 8];
+            round_key.copy_from_slice(&hash[..8]);
+            let next_right = left ^ (u64::from_le_bytes(round_key) & half_mask);
+            left = right;
+            right = next_right;
+        }
+        result = (left << half_bit_count) | right;
+        if result < number_count {
+            return result;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cottontail::math::Random;
+
+    fn params(scheme: SerialNumberScheme) -> SerialNumberParams {
+        SerialNumberParams {
+            scheme,
+            salt: Some("our secret salt".to_owned()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn keyed_serial_numbers_are_unique_and_unrelated() {
+        let sheet_numbers: Vec<usize> = (1..=5000).collect();
+        let serials = create_serial_numbers(
+            &params(SerialNumberScheme::Keyed),
+            &sheet_numbers,
+            &HashSet::new(),
+            &mut Random::new_from_seed(1234),
+        );
+        assert!(serials
+            .iter()
+            .all(|serial| serial.len() == DEFAULT_SERIAL_LENGTH));
+        assert_eq!(serials.iter().collect::<HashSet<_>>().len(), serials.len());
+        for pair in serials.windows(2) {
+            assert_ne!(
+                pair[0][..DEFAULT_SERIAL_LENGTH - 1],
+                pair[1][..DEFAULT_SERIAL_LENGTH - 1]
+            );
+        }
+
+        let other_salt_serials = create_serial_numbers(
+            &SerialNumberParams {
+                salt: Some("another salt".to_owned()),
+                ..params(SerialNumberScheme::Keyed)
+            },
+            &sheet_numbers,
+            &HashSet::new(),
+            &mut Random::new_from_seed(1234),
+        );
+        assert_ne!(serials, other_salt_serials);
+    }
+
+    #[test]
+    fn keyed_permutation_maps_small_numbers_into_the_whole_range() {
+        let length = MIN_SERIAL_LENGTH;
+        let number_count = (SERIAL_ALPHABET.len() as u64).pow(length as u32);
+        let results: HashSet<u64> = (0..1000)
+            .map(|number| keyed_permutation(number, "our secret salt", length))
+            .collect();
+        assert_eq!(results.len(), 1000);
+        assert!(results.iter().all(|&result| result < number_count));
+        assert!(results.iter().any(|&result| result >= number_count / 2));
+    }
+
+    #[test]
+    fn random_serial_numbers_are_unique() {
+        let existing_serials: HashSet<String> = create_serial_numbers(
+            &params(SerialNumberScheme::Random),
+            &(1..=100).collect::<Vec<_>>(),
+            &HashSet::new(),
+            &mut Random::new_from_seed(1234),
+        )
+        .into_iter()
+        .collect();
+        let serials = create_serial_numbers(
+            &params(SerialNumberScheme::Random),
+            &(101..=2000).collect::<Vec<_>>(),
+            &existing_serials,
+            &mut Random::new_from_seed(1234),
+        );
+        assert_eq!(serials.iter().collect::<HashSet<_>>().len(), serials.len());
+        assert!(serials
+            .iter()
+            .all(|serial| !existing_serials.contains(serial)
+                && serial.len() == DEFAULT_SERIAL_LENGTH
+                && serial
+                    .bytes()
+                    .all(|character| SERIAL_ALPHABET.contains(&character))));
+    }
+
+    #[test]
+    #[should_panic(expected = "The serial number of sheet 3 is too big")]
+    fn sequential_serial_numbers_do_not_overflow() {
+        let mut params = params(SerialNumberScheme::Sequential);
+        params.start = Some(u64::MAX - 10);
+        params.stride = Some(10);
+        create_serial_numbers(
+            &params,
+            &[1, 2, 3],
+            &HashSet::new(),
+            &mut Random::new_from_seed(1234),
+        );
+    }
+}
@@ -0,0 +1,432 @@
+//! A small HTTP server that lets the tablet app of a hall look up cards and verify claims in real
+//! time. The cards are read once from the manifest of the given directory on startup.
+//!
+//! Usage: `chotto serve [--port 8080] [--directory <dir>] [--bind <address>]`
+//!
+//! Endpoints:
+//! - `GET /cards/{serial}/{security_code}` returns the card with the given serial number. The
+//!   security code can be left out for cards without one (`GET /cards/{serial}`).
+//! - `POST /verify` with `{ "card": "A-0001", "security_code": "X7K2", "called_numbers": [3, 17] }`
+//!   returns whether the card wins against the called numbers and which lines it completed
+//! - `POST /rpc` accepts JSON-RPC 2.0 requests for generating new batches (see `rpc.rs`)
+//!
+//! We only listen on `127.0.0.1` by default. To serve the tablets of the hall we can bind to
+//! another address via `--bind 0.0.0.0` which requires a secret token in the
+//! `CHOTTO_SERVER_TOKEN` environment variable. Clients then send it in each request as
+//! `Authorization: Bearer <token>`.
+//!
+//! NOTE: This only understands the small subset of HTTP/1.1 that the endpoints above need.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use cottontail::core::serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    claims, command_line_option_value, load_draw_params, manifest, manifest::Card, rpc,
+    BingoVariant, OUTPUT_SHEETS_DIRECTORY,
+};
+
+pub const SERVER_TOKEN_ENVIRONMENT_VARIABLE: &str = "CHOTTO_SERVER_TOKEN";
+const DEFAULT_PORT: u16 = 8080;
+const DEFAULT_BIND_ADDRESS: &str = "127.0.0.1";
+/// Protects us from clients that send endless requests
+const MAX_REQUEST_BODY_SIZE: usize = 1024 * 1024;
+/// Protects us from clients that send endless lines before the body
+const MAX_HEADER_LINE_LENGTH: usize = 8 * 1024;
+const MAX_HEADER_COUNT: usize = 100;
+/// Protects us from clients that open a connection and never finish their request
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct VerifyRequest {
+    /// The serial number of the card
+    card: String,
+    /// The security code that is printed on the card if it has one
+    #[serde(default)]
+    security_code: Option<String>,
+    called_numbers: Vec<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct VerifyResponse {
+    card: String,
+    sheet_number: usize,
+    #[serde(flatten)]
+    result: claims::ClaimResult,
+}
+
+/// The cards of our batch by their serial number
+pub struct CardRegistry {
+    pub cards: Vec<Card>,
+    card_indices_by_serial: HashMap<String, usize>,
+    pub variant: BingoVariant,
+}
+
+impl CardRegistry {
+    pub fn new(cards: Vec<Card>, variant: BingoVariant) -> CardRegistry {
+        let card_indices_by_serial = cards
+            .iter()
+            .enumerate()
+            .map(|(index, card)| (card.serial.clone(), index))
+            .collect();
+        CardRegistry {
+            cards,
+            card_indices_by_serial,
+            variant,
+        }
+    }
+
+    /// Finds a card by its serial number
+    pub fn find(&self, serial: &str) -> Option<&Card> {
+        self.card_indices_by_serial
+            .get(serial)
+            .map(|&index| &self.cards[index])
+    }
+
+    /// Finds a card by its serial number for a client. Cards with a security code are only found
+    /// together with their code so that clients cannot list our cards by guessing serial numbers.
+    pub fn find_with_security_code(
+        &self,
+        serial: &str,
+        security_code: Option<&str>,
+    ) -> Option<&Card> {
+        self.find(serial).filter(|card| match &card.security_code {
+            Some(card_security_code) => security_code
+                .map(|security_code| tokens_match(card_security_code, security_code))
+                .unwrap_or(false),
+            None => true,
+        })
+    }
+}
+
+struct ServerState {
+    registry: CardRegistry,
+    jobs: rpc::Jobs,
+    directory: String,
+    /// Clients must send this token if it is given
+    token: Option<String>,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    /// The token of the `Authorization: Bearer <token>` header if any
+    token: Option<String>,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn json<T: Serialize>(value: &T) -> Response {
+        Response {
+            status: "200 OK",
+            body: serde_json::to_string(value).unwrap(),
+        }
+    }
+
+    fn error(status: &'static str, message: &str) -> Response {
+        Response {
+            status,
+            body: serde_json::json!({ "error": message }).to_string(),
+        }
+    }
+}
+
+fn read_request(stream: &TcpStream) -> Result<Request, String> {
+    let mut reader = BufReader::new(stream);
+    let request_line = read_header_line(&mut reader)?;
+    let mut request_line_parts = request_line.split_whitespace();
+    let method = request_line_parts.next().unwrap_or_default().to_owned();
+    let path = request_line_parts.next().unwrap_or_default().to_owned();
+
+    let mut content_length = 0;
+    let mut token = None;
+    for header_index in 0.. {
+        if header_index == MAX_HEADER_COUNT {
+            return Err("Request has too many headers".to_owned());
+        }
+        let header_line = read_header_line(&mut reader)?;
+        let header_line = header_line.trim();
+        if header_line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header_line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| "Invalid Content-Length".to_owned())?;
+            }
+            if name.trim().eq_ignore_ascii_case("authorization") {
+                token = value
+                    .trim()
+                    .strip_prefix("Bearer ")
+                    .map(|token| token.trim().to_owned());
+            }
+        }
+    }
+    if content_length > MAX_REQUEST_BODY_SIZE {
+        return Err("Request body is too large".to_owned());
+    }
+
+    let mut body = vec![0; content_length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|error| error.to_string())?;
+    Ok(Request {
+        method,
+        path,
+        token,
+        body,
+    })
+}
+
+/// Reads the next line of the request line or headers but not more than `MAX_HEADER_LINE_LENGTH`
+fn read_header_line(reader: &mut impl BufRead) -> Result<String, String> {
+    let mut line = String::new();
+    reader
+        .take(MAX_HEADER_LINE_LENGTH as u64 + 1)
+        .read_line(&mut line)
+        .map_err(|error| error.to_string())?;
+    if line.len() > MAX_HEADER_LINE_LENGTH {
+        return Err("Request header line is too long".to_owned());
+    }
+    Ok(line)
+}
+
+/// Compares the given tokens in constant time so that their content cannot be found by timing
+/// our answers
+fn tokens_match(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+fn handle_request(state: &mut ServerState, request: &Request) -> Response {
+    if let Some(token) = &state.token {
+        let is_authorized = request
+            .token
+            .as_ref()
+            .map(|given_token| tokens_match(token, given_token))
+            .unwrap_or(false);
+        if !is_authorized {
+            return Response::error("401 Unauthorized", "Missing or wrong token");
+        }
+    }
+
+    let registry = &state.registry;
+    let path_segments: Vec<&str> = request
+        .path
+        .trim_matches('/')
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    match (request.method.as_str(), path_segments.as_slice()) {
+        ("GET", ["cards", serial]) | ("GET", ["cards", serial, _]) => {
+            let security_code = path_segments.get(2).cloned();
+            match registry.find_with_security_code(serial, security_code) {
+                Some(card) => Response::json(card),
+                None => Response::error(
+                    "404 Not Found",
+                    &format!("There is no card '{}' with this security code", serial),
+                ),
+            }
+        }
+        ("POST", ["verify"]) => {
+            let verify_request: VerifyRequest = match serde_json::from_slice(&request.body) {
+                Ok(verify_request) => verify_request,
+                Err(error) => {
+                    return Response::error(
+                        "400 Bad Request",
+                        &format!("Invalid verify request: {}", error),
+                    )
+                }
+            };
+            let card = match registry.find_with_security_code(
+                &verify_request.card,
+                verify_request.security_code.as_deref(),
+            ) {
+                Some(card) => card,
+                None => {
+                    return Response::error(
+                        "404 Not Found",
+                        &format!(
+                            "There is no card '{}' with this security code",
+                            verify_request.card
+                        ),
+                    )
+                }
+            };
+            let called_numbers: HashSet<i32> =
+                verify_request.called_numbers.iter().cloned().collect();
+            Response::json(&VerifyResponse {
+                card: card.serial.clone(),
+                sheet_number: card.sheet_number,
+                result: claims::check_claim(card, &called_numbers, registry.variant),
+            })
+        }
+        ("POST", ["rpc"]) => Response {
+            status: "200 OK",
+            body: rpc::handle_rpc(
+                &mut state.jobs,
+                &state.directory,
+                registry.variant,
+                &request.body,
+            ),
+        },
+        _ => Response::error("404 Not Found", "Unknown endpoint"),
+    }
+}
+
+fn handle_connection(state: &Mutex<ServerState>, mut stream: TcpStream) {
+    if let Err(error) = stream
+        .set_read_timeout(Some(CONNECTION_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(CONNECTION_TIMEOUT)))
+    {
+        log::warn!("Could not set the timeouts of a connection: {}", error);
+        return;
+    }
+    let response = match read_request(&stream) {
+        Ok(request) => {
+            let response = handle_request(
+                &mut state
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()),
+                &request,
+            );
+            log::info!("{} {} -> {}", request.method, request.path, response.status);
+            response
+        }
+        Err(error) => Response::error("400 Bad Request", &error),
+    };
+    let response_string = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.body.len(),
+        response.body
+    );
+    if let Err(error) = stream.write_all(response_string.as_bytes()) {
+        log::warn!("Could not send response: {}", error);
+    }
+}
+
+pub fn serve(args: &[String]) {
+    let directory = command_line_option_value(args, "--directory")
+        .unwrap_or_else(|| OUTPUT_SHEETS_DIRECTORY.to_owned());
+    let port = command_line_option_value(args, "--port")
+        .map(|port| {
+            port.parse::<u16>()
+                .unwrap_or_else(|_| panic!("Invalid port '{}'", port))
+        })
+        .unwrap_or(DEFAULT_PORT);
+    let bind_address = command_line_option_value(args, "--bind")
+        .unwrap_or_else(|| DEFAULT_BIND_ADDRESS.to_owned());
+    let token = std::env::var(SERVER_TOKEN_ENVIRONMENT_VARIABLE)
+        .ok()
+        .filter(|token| !token.is_empty());
+    let is_local = bind_address
+        .parse::<std::net::IpAddr>()
+        .map(|address| address.is_loopback())
+        .unwrap_or(false);
+    assert!(
+        is_local || token.is_some(),
+        "Serving on '{}' lets anyone in the network create batches and look up cards - please \
+         set a secret token in the {} environment variable which clients must send as \
+         `Authorization: Bearer <token>`",
+        bind_address,
+        SERVER_TOKEN_ENVIRONMENT_VARIABLE
+    );
+
+    let params = load_draw_params();
+    // NOTE: A service that only generates batches via `POST /rpc` may start without any sheets
+    let cards = if manifest::has_manifest(&directory) {
+        manifest::read_manifest(&directory).cards
+    } else {
+        log::warn!("There are no sheets in '{}' to look up yet", directory);
+        Vec::new()
+    };
+    let state = ServerState {
+        registry: CardRegistry::new(cards, params.bingo_variant),
+        jobs: rpc::Jobs::default(),
+        directory,
+        token,
+    };
+
+    let listener = TcpListener::bind((bind_address.as_str(), port)).unwrap_or_else(|error| {
+        panic!(
+            "Could not listen on '{}' port {}: {}",
+            bind_address, port, error
+        )
+    });
+    log::info!(
+        "Serving {} cards of '{}' on '{}' port {}",
+        state.registry.cards.len(),
+        state.directory,
+        bind_address,
+        port
+    );
+    // NOTE: Each connection gets its own thread so that a slow client cannot block the others
+    let state = Arc::new(Mutex::new(state));
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let state = state.clone();
+                std::thread::spawn(move || handle_connection(&state, stream));
+            }
+            Err(error) => log::warn!("Could not accept connection: {}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_header_lines_that_are_too_long() {
+        let mut request = std::io::Cursor::new(format!(
+            "GET / HTTP/1.1\r\nX-Padding: {}\r\n\r\n",
+            "a".repeat(MAX_HEADER_LINE_LENGTH)
+        ));
+        assert_eq!(
+            read_header_line(&mut request).unwrap(),
+            "GET / HTTP/1.1\r\n"
+        );
+        assert!(read_header_line(&mut request).is_err());
+    }
+
+    #[test]
+    fn finds_cards_only_with_their_security_code() {
+        let card: Card = serde_json::from_value(serde_json::json!({
+            "sheet_number": 1,
+            "serial": "A-1",
+            "numbers": [],
+            "security_code": "X7K2",
+        }))
+        .unwrap();
+        let registry = CardRegistry::new(vec![card], BingoVariant::Ball75);
+        assert!(registry
+            .find_with_security_code("A-1", Some("X7K2"))
+            .is_some());
+        assert!(registry
+            .find_with_security_code("A-1", Some("X7K3"))
+            .is_none());
+        assert!(registry
+            .find_with_security_code("A-1", Some("X7K"))
+            .is_none());
+        assert!(registry.find_with_security_code("A-1", None).is_none());
+    }
+}
@@ -0,0 +1,114 @@
+//! Text shaping via rustybuzz. Fontdue lays out text one character after another from left to
+//! right which breaks scripts like Hebrew or Arabic that are written from right to left and whose
+//! letters change their shape depending on their neighbours.
+
+use cottontail::core::serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextDirection {
+    /// Guesses the direction from the script of the text
+    Auto,
+    LeftToRight,
+    RightToLeft,
+}
+
+impl Default for TextDirection {
+    fn default() -> Self {
+        TextDirection::Auto
+    }
+}
+
+/// A glyph of a shaped line of text
+pub struct ShapedGlyph {
+    /// The index of the glyph in the font which can differ from the glyph that its character would
+    /// normally map to
+    pub glyph_index: u16,
+    /// The position of the glyph origin on the baseline in pixels where y points down
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Returns true if the given text can be laid out without shaping
+pub fn is_simple_text(text: &str, direction: TextDirection) -> bool {
+    direction != TextDirection::RightToLeft && text.is_ascii()
+}
+
+/// Shapes the given text into a single line of glyphs. The glyphs are returned in visual order
+/// from left to right, also for right-to-left text.
+pub fn shape_line(
+    font_data: &[u8],
+    units_per_em: f32,
+    font_size: f32,
+    text: &str,
+    direction: TextDirection,
+) -> Vec<ShapedGlyph> {
+    let face = rustybuzz::Face::from_slice(font_data, 0)
+        .expect("Cannot shape text because the font could not be decoded");
+    let mut buffer = rustybuzz::UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    match direction {
+        TextDirection::Auto => {}
+        TextDirection::LeftToRight => buffer.set_direction(rustybuzz::Direction::LeftToRight),
+        TextDirection::RightToLeft => buffer.set_direction(rustybuzz::Direction::RightToLeft),
+    }
+    let glyph_buffer = rustybuzz::shape(&face, &[], buffer);
+
+    let scale = font_size / units_per_em;
+    let mut pen_x = 0.0;
+    let mut pen_y = 0.0;
+    let mut glyphs = Vec::new();
+    for (info, position) in glyph_buffer
+        .glyph_infos()
+        .iter()
+        .zip(glyph_buffer.glyph_positions().iter())
+    {
+        glyphs.push(ShapedGlyph {
+            glyph_index: info.glyph_id as u16,
+            x: pen_x + position.x_offset as f32 * scale,
+            y: pen_y - position.y_offset as f32 * scale,
+        });
+        pen_x += position.x_advance as f32 * scale;
+        pen_y -= position.y_advance as f32 * scale;
+    }
+    glyphs
+}
+
+/// A glyph of a color font which we draw as image instead of rasterizing its outline
+pub struct ColorGlyph {
+    /// The top left corner of the image relative to the glyph origin in pixels where y points
+    /// down
+    pub x: f32,
+    pub y: f32,
+    /// The size of the image in pixels after scaling it to our font size
+    pub width: f32,
+    pub height: f32,
+    pub image: image::RgbaImage,
+}
+
+/// Returns the embedded color image of the given glyph if the font has one. This is the case for
+/// color emoji fonts that store their glyphs as PNG images in CBDT or sbix tables.
+///
+/// NOTE: Color fonts that use COLR layers are not supported yet. Their glyphs are drawn with
+///       their outline in our text color instead.
+pub fn color_glyph_image(font_data: &[u8], glyph_index: u16, font_size: f32) -> Option<ColorGlyph> {
+    let face = ttf_parser::Face::from_slice(font_data, 0).ok()?;
+    let raster_image =
+        face.glyph_raster_image(ttf_parser::GlyphId(glyph_index), font_size.round() as u16)?;
+    if raster_image.format != ttf_parser::RasterImageFormat::PNG {
+        return None;
+    }
+    let image = image::load_from_memory_with_format(raster_image.data, image::ImageFormat::Png)
+        .ok()?
+        .to_rgba8();
+
+    let scale = font_size / raster_image.pixels_per_em as f32;
+    Some(ColorGlyph {
+        x: raster_image.x as f32 * scale,
+        y: -(raster_image.y as f32 + raster_image.height as f32) * scale,
+        width: raster_image.width as f32 * scale,
+        height: raster_image.height as f32 * scale,
+        image,
+    })
+}
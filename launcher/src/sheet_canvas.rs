@@ -0,0 +1,264 @@
+//! Our sheets only differ from their shared background where a card draws its cells and texts.
+//! Instead of copying the whole background for each sheet we draw onto a copy of the part of it
+//! that we actually touch. That region grows whenever something is drawn outside of it. The
+//! encoders then put the region back onto the rows of the shared background while writing them.
+
+use cottontail::{
+    image::{Bitmap, PixelRGBA},
+    math::Vec2i,
+};
+
+use crate::{
+    blit_to_alpha_blended_premultiplied_gamma_correct, blit_to_alpha_blended_premultiplied_rows,
+    copy_bitmap_region, fill_rect, paste_bitmap,
+};
+
+/// The part of a sheet that differs from our background. It always lies fully inside the sheet.
+pub struct SheetRegion {
+    pub pos: Vec2i,
+    pub bitmap: Bitmap,
+}
+
+impl SheetRegion {
+    fn is_empty(&self) -> bool {
+        self.bitmap.width == 0 || self.bitmap.height == 0
+    }
+
+    fn contains_row(&self, y: i32) -> bool {
+        self.pos.y <= y && y < self.pos.y + self.bitmap.height as i32
+    }
+}
+
+/// Draws onto a copy of the given part of our background only
+pub struct SheetCanvas<'b> {
+    background: &'b Bitmap,
+    region: SheetRegion,
+}
+
+impl<'b> SheetCanvas<'b> {
+    /// The given region must be a copy of the same part of the background
+    pub fn new(background: &'b Bitmap, region: SheetRegion) -> SheetCanvas<'b> {
+        SheetCanvas { background, region }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.background.width as i32
+    }
+
+    pub fn height(&self) -> i32 {
+        self.background.height as i32
+    }
+
+    /// Grows our region so that it also covers the given rectangle as far as it lies on the sheet.
+    /// Returns the bitmap of our region together with its position on the sheet.
+    pub fn region_covering(
+        &mut self,
+        left: i32,
+        top: i32,
+        width: i32,
+        height: i32,
+    ) -> (&mut Bitmap, Vec2i) {
+        let mut new_left = i32::max(0, left);
+        let mut new_top = i32::max(0, top);
+        let mut new_right = i32::min(self.width(), left + width);
+        let mut new_bottom = i32::min(self.height(), top + height);
+        if new_left < new_right && new_top < new_bottom {
+            let region = &self.region;
+            if !region.is_empty() {
+                new_left = i32::min(new_left, region.pos.x);
+                new_top = i32::min(new_top, region.pos.y);
+                new_right = i32::max(new_right, region.pos.x + region.bitmap.width as i32);
+                new_bottom = i32::max(new_bottom, region.pos.y + region.bitmap.height as i32);
+            }
+            let new_pos = Vec2i::new(new_left, new_top);
+            let new_width = new_right - new_left;
+            let new_height = new_bottom - new_top;
+            if region.is_empty()
+                || new_pos != region.pos
+                || new_width != region.bitmap.width as i32
+                || new_height != region.bitmap.height as i32
+            {
+                let mut bitmap =
+                    copy_bitmap_region(self.background, new_pos, new_width, new_height);
+                if !region.is_empty() {
+                    paste_bitmap(&region.bitmap, &mut bitmap, region.pos - new_pos);
+                }
+                self.region = SheetRegion {
+                    pos: new_pos,
+                    bitmap,
+                };
+            }
+        }
+        (&mut self.region.bitmap, self.region.pos)
+    }
+
+    pub fn blit_premultiplied(
+        &mut self,
+        bitmap_premultiplied: &Bitmap,
+        pos: Vec2i,
+        gamma_correct_blending: bool,
+    ) {
+        let (region_bitmap, region_pos) = self.region_covering(
+            pos.x,
+            pos.y,
+            bitmap_premultiplied.width as i32,
+            bitmap_premultiplied.height as i32,
+        );
+        if gamma_correct_blending {
+            blit_to_alpha_blended_premultiplied_gamma_correct(
+                bitmap_premultiplied,
+                region_bitmap,
+                pos - region_pos,
+            );
+        } else {
+            blit_to_alpha_blended_premultiplied_rows(
+                bitmap_premultiplied,
+                region_bitmap,
+                pos - region_pos,
+            );
+        }
+    }
+
+    pub fn blit_centered_premultiplied(
+        &mut self,
+        bitmap_premultiplied: &Bitmap,
+        center: Vec2i,
+        gamma_correct_blending: bool,
+    ) {
+        let pos = center - bitmap_premultiplied.rect().dim / 2;
+        self.blit_premultiplied(bitmap_premultiplied, pos, gamma_correct_blending);
+    }
+
+    /// Fills the given rectangle while ignoring all pixels outside of the sheet
+    pub fn fill_rect(&mut self, left: i32, top: i32, width: i32, height: i32, color: PixelRGBA) {
+        let (region_bitmap, region_pos) = self.region_covering(left, top, width, height);
+        fill_rect(
+            region_bitmap,
+            left - region_pos.x,
+            top - region_pos.y,
+            width,
+            height,
+            color,
+        );
+    }
+
+    pub fn into_region(self) -> SheetRegion {
+        self.region
+    }
+}
+
+/// A sheet made of a shared background and the region of it that one card has drawn onto
+pub struct ComposedSheet<'b> {
+    background: &'b Bitmap,
+    region: Option<&'b SheetRegion>,
+}
+
+impl<'b> ComposedSheet<'b> {
+    /// The region must lie on a background of the same size as the one it was drawn onto
+    pub fn new(background: &'b Bitmap, region: &'b SheetRegion) -> ComposedSheet<'b> {
+        ComposedSheet {
+            background,
+            region: Some(region),
+        }
+    }
+
+    /// A sheet that was already drawn in full
+    pub fn from_bitmap(bitmap: &'b Bitmap) -> ComposedSheet<'b> {
+        ComposedSheet {
+            background: bitmap,
+            region: None,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.background.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.background.height
+    }
+
+    /// Calls the given function with each row of the sheet from top to bottom. Rows outside of our
+    /// region are handed out straight from the background without copying them.
+    pub fn for_each_row(&self, mut use_row: impl FnMut(&[PixelRGBA])) {
+        let width = self.background.width as usize;
+        let mut composed_row = Vec::new();
+        for y in 0..self.background.height as i32 {
+            let row_start = y as usize * width;
+            let background_row = &self.background.data[row_start..row_start + width];
+            match self.region.filter(|region| region.contains_row(y)) {
+                None => use_row(background_row),
+                Some(region) => {
+                    let region_width = region.bitmap.width as usize;
+                    let region_row_start = (y - region.pos.y) as usize * region_width;
+                    let left = region.pos.x as usize;
+                    composed_row.clear();
+                    composed_row.extend_from_slice(background_row);
+                    composed_row[left..left + region_width].copy_from_slice(
+                        &region.bitmap.data[region_row_start..region_row_start + region_width],
+                    );
+                    use_row(&composed_row);
+                }
+            }
+        }
+    }
+
+    pub fn to_bitmap(&self) -> Bitmap {
+        let mut result = self.background.clone();
+        if let Some(region) = self.region {
+            paste_bitmap(&region.bitmap, &mut result, region.pos);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::blit_centered_premultiplied;
+
+    fn create_test_background() -> Bitmap {
+        let mut background = Bitmap::new(40, 30);
+        for y in 0..30 {
+            for x in 0..40 {
+                background.set(x, y, PixelRGBA::new(6 * x as u8, 8 * y as u8, 100, 255));
+            }
+        }
+        background
+    }
+
+    #[test]
+    fn composed_sheets_match_drawing_onto_the_whole_background() {
+        let background = create_test_background();
+        let mut text = Bitmap::new(12, 5);
+        for pixel in text.data.iter_mut() {
+            *pixel = PixelRGBA::new(100, 0, 0, 128);
+        }
+
+        let mut whole_sheet = background.clone();
+        let grid_region_pos = Vec2i::new(10, 10);
+        let mut canvas = SheetCanvas::new(
+            &background,
+            SheetRegion {
+                pos: grid_region_pos,
+                bitmap: copy_bitmap_region(&background, grid_region_pos, 5, 5),
+            },
+        );
+        // NOTE: The second text hangs over the left border of the sheet
+        for &center in &[Vec2i::new(30, 4), Vec2i::new(2, 25)] {
+            blit_centered_premultiplied(&text, &mut whole_sheet, center, false);
+            canvas.blit_centered_premultiplied(&text, center, false);
+        }
+        fill_rect(&mut whole_sheet, 35, 20, 10, 3, PixelRGBA::black());
+        canvas.fill_rect(35, 20, 10, 3, PixelRGBA::black());
+
+        let region = canvas.into_region();
+        assert!(region.bitmap.height < background.height);
+        let sheet = ComposedSheet::new(&background, &region);
+        assert_eq!(sheet.to_bitmap().data, whole_sheet.data);
+        let mut rows = Vec::new();
+        sheet.for_each_row(|row| rows.extend_from_slice(row));
+        assert_eq!(rows, whole_sheet.data);
+    }
+}
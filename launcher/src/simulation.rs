@@ -0,0 +1,412 @@
+//! Plays many random games against the sheets of a batch to tell organizers how long a game takes
+//! until the first win and how often several players win at the same time. This helps with picking
+//! patterns and prizes before the event. A coverall heatmap shows which cells of our cards are
+//! usually still open when the first full house is won.
+//!
+//! Usage: `chotto simulate [--games 10000] [--seed 1234] [--directory <dir>]`
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use chotto::generator;
+use cottontail::math::Random;
+
+use crate::{
+    claims, command_line_option_value, create_time_based_seed, load_draw_params,
+    manifest::{self, Card},
+    OUTPUT_SHEETS_DIRECTORY,
+};
+
+pub const SIMULATION_FILENAME: &str = "simulation.csv";
+pub const COVERALL_HEATMAP_FILENAME: &str = "coverall_heatmap.csv";
+const DEFAULT_GAME_COUNT: usize = 10_000;
+
+/// A group of numbers that wins as soon as all of them were called
+type Pattern = Vec<i32>;
+
+/// All patterns that make a card win for one kind of prize
+struct CardPatterns {
+    patterns: Vec<Pattern>,
+}
+
+impl CardPatterns {
+    /// Returns after how many calls the first of our patterns is complete or nothing if we have no
+    /// pattern that can be completed. `call_indices` contains the 1-based position of each number
+    /// in the draw order.
+    fn calls_until_win(&self, call_indices: &[usize]) -> Option<usize> {
+        self.patterns
+            .iter()
+            .filter_map(|pattern| {
+                pattern
+                    .iter()
+                    .map(|number| call_indices[*number as usize])
+                    .max()
+            })
+            .min()
+    }
+}
+
+/// The outcome of a single game for one kind of prize
+#[derive(Clone, Copy)]
+struct GameResult {
+    calls_until_first_win: usize,
+    winner_count: usize,
+}
+
+fn play_game(cards: &[CardPatterns], call_indices: &[usize]) -> GameResult {
+    let mut calls_until_first_win = usize::MAX;
+    let mut winner_count = 0;
+    for calls in cards
+        .iter()
+        .filter_map(|card| card.calls_until_win(call_indices))
+    {
+        if calls < calls_until_first_win {
+            calls_until_first_win = calls;
+            winner_count = 1;
+        } else if calls == calls_until_first_win {
+            winner_count += 1;
+        }
+    }
+    GameResult {
+        calls_until_first_win,
+        winner_count,
+    }
+}
+
+/// Returns the average, median, minimum and maximum calls until the first win and the share of
+/// games with more than one winner as human readable summary
+fn summarize(prize_name: &str, results: &[GameResult]) -> String {
+    let mut calls: Vec<usize> = results
+        .iter()
+        .map(|result| result.calls_until_first_win)
+        .collect();
+    calls.sort();
+    let average_calls = calls.iter().sum::<usize>() as f64 / calls.len() as f64;
+    let tie_count = results
+        .iter()
+        .filter(|result| result.winner_count > 1)
+        .count();
+    let average_winner_count = results
+        .iter()
+        .map(|result| result.winner_count)
+        .sum::<usize>() as f64
+        / results.len() as f64;
+    format!(
+        "{}: first win after {:.1} calls on average (median {}, fastest {}, slowest {}), \
+         {:.1}% of games end in a tie with {:.2} winners on average",
+        prize_name,
+        average_calls,
+        calls[calls.len() / 2],
+        calls[0],
+        calls[calls.len() - 1],
+        100.0 * tie_count as f64 / results.len() as f64,
+        average_winner_count
+    )
+}
+
+/// Writes how many games had their first win after each number of calls, which shows the spread
+/// of our game lengths at a glance when opened in a spreadsheet
+fn write_histogram(filepath: &str, max_number: usize, results: &[(GameResult, GameResult)]) {
+    let mut line_counts = vec![0usize; max_number + 1];
+    let mut full_house_counts = vec![0usize; max_number + 1];
+    for (line_result, full_house_result) in results {
+        line_counts[line_result.calls_until_first_win] += 1;
+        full_house_counts[full_house_result.calls_until_first_win] += 1;
+    }
+
+    let mut writer = csv::Writer::from_path(filepath)
+        .unwrap_or_else(|error| panic!("Could not create file '{}': {}", filepath, error));
+    writer
+        .write_record(&["calls", "line_first_wins", "full_house_first_wins"])
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    for calls in 1..=max_number {
+        writer
+            .write_record(&[
+                calls.to_string(),
+                line_counts[calls].to_string(),
+                full_house_counts[calls].to_string(),
+            ])
+            .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    }
+    writer
+        .flush()
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+}
+
+/// Returns the cell of the given row and column in our coverall heatmap which has a cell for each
+/// cell of the largest grid of our cards
+fn heatmap_cell_index(row: usize, column: usize, column_count: usize) -> usize {
+    row * column_count + column
+}
+
+/// Returns for each cell of our heatmap on how many of the given cards its number was called
+/// within the given number of calls
+fn count_covered_cells(
+    cards: &[Card],
+    calls: usize,
+    call_indices: &[usize],
+    column_count: usize,
+    cell_count: usize,
+) -> Vec<usize> {
+    let mut covered_counts = vec![0; cell_count];
+    for card in cards {
+        for (row, numbers) in card.numbers.iter().enumerate() {
+            for (column, number) in numbers.iter().enumerate() {
+                if *number != 0 && call_indices[*number as usize] <= calls {
+                    covered_counts[heatmap_cell_index(row, column, column_count)] += 1;
+                }
+            }
+        }
+    }
+    covered_counts
+}
+
+/// Writes which share of each cell of our cards was called by the time of the first full house.
+/// Cells with a low share are the ones that players are usually still waiting for.
+fn write_coverall_heatmap(
+    filepath: &str,
+    cards: &[Card],
+    column_count: usize,
+    covered_counts: &[usize],
+    game_count: usize,
+) {
+    let mut number_counts = vec![0usize; covered_counts.len()];
+    for card in cards {
+        for (row, numbers) in card.numbers.iter().enumerate() {
+            for (column, number) in numbers.iter().enumerate() {
+                if *number != 0 {
+                    number_counts[heatmap_cell_index(row, column, column_count)] += 1;
+                }
+            }
+        }
+    }
+
+    let mut writer = csv::Writer::from_path(filepath)
+        .unwrap_or_else(|error| panic!("Could not create file '{}': {}", filepath, error));
+    let header: Vec<String> = std::iter::once("row".to_owned())
+        .chain((1..=column_count).map(|column| format!("column_{}", column)))
+        .collect();
+    writer
+        .write_record(&header)
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    let row_count = covered_counts.len() / usize::max(1, column_count);
+    for row in 0..row_count {
+        // NOTE: Cells that never have a number (i.e. the free space) stay empty
+        let record: Vec<String> = std::iter::once((row + 1).to_string())
+            .chain((0..column_count).map(|column| {
+                let cell_index = heatmap_cell_index(row, column, column_count);
+                if number_counts[cell_index] == 0 {
+                    String::new()
+                } else {
+                    format!(
+                        "{:.1}",
+                        100.0 * covered_counts[cell_index] as f64
+                            / (number_counts[cell_index] * game_count) as f64
+                    )
+                }
+            }))
+            .collect();
+        writer
+            .write_record(&record)
+            .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+    }
+    writer
+        .flush()
+        .unwrap_or_else(|error| panic!("Could not write file '{}': {}", filepath, error));
+}
+
+/// Returns the patterns of the cards that have at least one of them. Warns about the cards that
+/// cannot win the given kind of prize so that they don't go unnoticed.
+fn winnable_patterns(
+    cards: &[Card],
+    prize_name: &str,
+    patterns_of_card: impl Fn(&Card) -> Vec<Pattern>,
+) -> Vec<CardPatterns> {
+    let all_patterns: Vec<CardPatterns> = cards
+        .iter()
+        .map(|card| CardPatterns {
+            patterns: patterns_of_card(card)
+                .into_iter()
+                .filter(|pattern| !pattern.is_empty())
+                .collect(),
+        })
+        .collect();
+    let unwinnable_serials: Vec<&str> = cards
+        .iter()
+        .zip(all_patterns.iter())
+        .filter(|(_, card_patterns)| card_patterns.patterns.is_empty())
+        .map(|(card, _)| card.serial.as_str())
+        .collect();
+    if !unwinnable_serials.is_empty() {
+        log::warn!(
+            "{} sheets cannot win a {} and are left out of our simulation: {}",
+            unwinnable_serials.len(),
+            prize_name.to_lowercase(),
+            unwinnable_serials.join(", ")
+        );
+    }
+    let patterns: Vec<CardPatterns> = all_patterns
+        .into_iter()
+        .filter(|card_patterns| !card_patterns.patterns.is_empty())
+        .collect();
+    assert!(
+        !patterns.is_empty(),
+        "None of our sheets can win a {} so there is nothing to simulate",
+        prize_name.to_lowercase()
+    );
+    patterns
+}
+
+pub fn simulate_games(args: &[String]) {
+    let directory = command_line_option_value(args, "--directory")
+        .unwrap_or_else(|| OUTPUT_SHEETS_DIRECTORY.to_owned());
+    let game_count = command_line_option_value(args, "--games")
+        .map(|value| {
+            value
+                .parse::<usize>()
+                .ok()
+                .filter(|game_count| *game_count > 0)
+                .unwrap_or_else(|| panic!("Invalid number of games '{}'", value))
+        })
+        .unwrap_or(DEFAULT_GAME_COUNT);
+    let seed = command_line_option_value(args, "--seed")
+        .map(|value| {
+            value
+                .parse::<u64>()
+                .unwrap_or_else(|_| panic!("Invalid seed '{}'", value))
+        })
+        .unwrap_or_else(create_time_based_seed);
+
+    let params = load_draw_params();
+    let variant = params.bingo_variant;
+    let max_number = params.max_number() as usize;
+    let cards = manifest::read_manifest(&directory).cards;
+    assert!(
+        !cards.is_empty(),
+        "There are no sheets in '{}' to simulate games with",
+        directory
+    );
+    let line_patterns =
+        winnable_patterns(&cards, "Line", |card| claims::line_numbers(card, variant));
+    let full_house_patterns = winnable_patterns(&cards, "Full house", |card| {
+        claims::full_house_numbers(card, variant)
+    });
+    let column_count = cards
+        .iter()
+        .flat_map(|card| card.numbers.iter().map(|numbers| numbers.len()))
+        .max()
+        .unwrap_or(0);
+    let row_count = cards
+        .iter()
+        .map(|card| card.numbers.len())
+        .max()
+        .unwrap_or(0);
+    let cell_count = row_count * column_count;
+
+    log::info!(
+        "Simulating {} games with the {} sheets in '{}' using seed {}",
+        game_count,
+        cards.len(),
+        directory,
+        seed
+    );
+    let start_time = std::time::Instant::now();
+    // NOTE: Each game gets its own seed so that the results do not depend on thread scheduling
+    let results: Vec<(GameResult, GameResult, Vec<usize>)> = (0..game_count)
+        .into_par_iter()
+        .map(|game_index| {
+            let mut random = Random::new_from_seed(seed.wrapping_add(game_index as u64));
+            let draw_order = generator::shuffled((1..=max_number).collect(), &mut random);
+            let mut call_indices = vec![0; max_number + 1];
+            for (index, number) in draw_order.iter().enumerate() {
+                call_indices[*number] = index + 1;
+            }
+            let full_house_result = play_game(&full_house_patterns, &call_indices);
+            let covered_counts = count_covered_cells(
+                &cards,
+                full_house_result.calls_until_first_win,
+                &call_indices,
+                column_count,
+                cell_count,
+            );
+            (
+                play_game(&line_patterns, &call_indices),
+                full_house_result,
+                covered_counts,
+            )
+        })
+        .collect();
+    log::info!(
+        "Simulated {} games in {:.2}s",
+        game_count,
+        start_time.elapsed().as_secs_f32()
+    );
+
+    let line_results: Vec<GameResult> = results.iter().map(|(line, _, _)| *line).collect();
+    let full_house_results: Vec<GameResult> = results
+        .iter()
+        .map(|(_, full_house, _)| *full_house)
+        .collect();
+    let mut covered_counts = vec![0; cell_count];
+    for (_, _, game_covered_counts) in &results {
+        for (covered_count, game_covered_count) in
+            covered_counts.iter_mut().zip(game_covered_counts.iter())
+        {
+            *covered_count += game_covered_count;
+        }
+    }
+    let summary = format!(
+        "{}\n{}",
+        summarize("Line", &line_results),
+        summarize("Full house", &full_house_results)
+    );
+    for line in summary.lines() {
+        log::info!("{}", line);
+    }
+
+    let filepath = format!("{}/{}", directory, SIMULATION_FILENAME);
+    let results: Vec<(GameResult, GameResult)> = line_results
+        .into_iter()
+        .zip(full_house_results.into_iter())
+        .collect();
+    write_histogram(&filepath, max_number, &results);
+    log::info!("Wrote the number of calls of each game to '{}'", filepath);
+
+    let heatmap_filepath = format!("{}/{}", directory, COVERALL_HEATMAP_FILENAME);
+    write_coverall_heatmap(
+        &heatmap_filepath,
+        &cards,
+        column_count,
+        &covered_counts,
+        game_count,
+    );
+    log::info!(
+        "Wrote how often each cell was called before the first full house to '{}'",
+        heatmap_filepath
+    );
+
+    #[cfg(not(debug_assertions))]
+    crate::show_messagebox("Chotto", &summary, false);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cards_without_patterns_never_win() {
+        let call_indices = vec![0, 1, 2, 3];
+        let cards = vec![
+            CardPatterns {
+                patterns: Vec::new(),
+            },
+            CardPatterns {
+                patterns: vec![vec![1, 3]],
+            },
+        ];
+        assert_eq!(cards[0].calls_until_win(&call_indices), None);
+
+        let result = play_game(&cards, &call_indices);
+        assert_eq!(result.calls_until_first_win, 3);
+        assert_eq!(result.winner_count, 1);
+    }
+}
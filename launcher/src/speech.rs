@@ -0,0 +1,198 @@
+//! Announces the numbers of `chotto caller` via the text-to-speech of the operating system. We use
+//! SAPI on Windows (via PowerShell), `say` on macOS and speech-dispatcher (`spd-say`) on Linux.
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    process::{Child, Command},
+};
+
+use cottontail::core::serde_derive::{Deserialize, Serialize};
+
+use crate::{expand_text_variables, DRAW_PARAMETERS_FILENAME};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallerSpeechParams {
+    /// The name of an installed voice. The default voice of the system is used if not given.
+    voice: Option<String>,
+    /// Can use `{letter}`, `{number}` and `{nickname}`
+    phrase: Option<String>,
+    /// Used instead of `phrase` for numbers that have a nickname
+    nickname_phrase: Option<String>,
+    /// Uses the nicknames of our nickname table (see `nicknames.rs`)
+    #[serde(default)]
+    traditional_nicknames: bool,
+    /// Our own nicknames by number which replace the traditional ones
+    #[serde(default)]
+    nicknames: BTreeMap<String, String>,
+}
+
+pub struct Speaker {
+    params: CallerSpeechParams,
+    /// The column labels of 75-ball Bingo which are announced before the number
+    column_labels: Vec<String>,
+    numbers_per_column: i32,
+    nicknames: HashMap<i32, String>,
+    /// We stop an announcement that is still running when the next number is called
+    running_announcement: RefCell<Option<Child>>,
+}
+
+impl Speaker {
+    /// Uses the given nicknames of our nickname table if we want traditional nicknames
+    pub fn new(
+        params: &CallerSpeechParams,
+        nickname_table: &HashMap<i32, String>,
+        column_labels: Vec<String>,
+        max_number: i32,
+    ) -> Speaker {
+        let mut nicknames: HashMap<i32, String> = if params.traditional_nicknames {
+            nickname_table.clone()
+        } else {
+            HashMap::new()
+        };
+        for (number_text, nickname) in &params.nicknames {
+            let number = number_text
+                .parse::<i32>()
+                .ok()
+                .filter(|number| (1..=max_number).contains(number))
+                .unwrap_or_else(|| {
+                    panic!(
+                        "The nickname '{}' is given for '{}' which is not a number between 1 and \
+                         {} - please fix it in '{}'!",
+                        nickname, number_text, max_number, DRAW_PARAMETERS_FILENAME
+                    )
+                });
+            nicknames.insert(number, nickname.clone());
+        }
+
+        // NOTE: Modes without numbers have a `max_number` of 0 and we must never divide by 0 in
+        //       `phrase`
+        let numbers_per_column = if column_labels.is_empty() {
+            i32::max(1, max_number)
+        } else {
+            i32::max(1, max_number / column_labels.len() as i32)
+        };
+        Speaker {
+            params: params.clone(),
+            column_labels,
+            numbers_per_column,
+            nicknames,
+            running_announcement: RefCell::new(None),
+        }
+    }
+
+    /// Returns what we say for the given number, i.e. `B... 11` or `two little ducks, 22`
+    fn phrase(&self, number: i32) -> String {
+        let letter = self
+            .column_labels
+            .get(((number - 1) / self.numbers_per_column) as usize)
+            .cloned();
+        let nickname = self.nicknames.get(&number);
+        let template = match (nickname, &self.params.nickname_phrase, &self.params.phrase) {
+            (Some(_), Some(nickname_phrase), _) => nickname_phrase.clone(),
+            (Some(_), None, _) if letter.is_some() => "{letter}... {nickname}, {number}".to_owned(),
+            (Some(_), None, _) => "{nickname}, {number}".to_owned(),
+            (None, _, Some(phrase)) => phrase.clone(),
+            (None, _, None) if letter.is_some() => "{letter}... {number}".to_owned(),
+            (None, _, None) => "{number}".to_owned(),
+        };
+
+        let mut variables = HashMap::new();
+        variables.insert("letter".to_owned(), letter.unwrap_or_default());
+        variables.insert("number".to_owned(), number.to_string());
+        variables.insert("nickname".to_owned(), nickname.cloned().unwrap_or_default());
+        expand_text_variables(&template, &variables)
+    }
+
+    /// Starts announcing the given number and returns right away
+    pub fn announce(&self, number: i32) {
+        let phrase = self.phrase(number);
+        let mut running_announcement = self.running_announcement.borrow_mut();
+        if let Some(mut child) = running_announcement.take() {
+            child.kill().ok();
+            child.wait().ok();
+        }
+        match speech_command(&phrase, self.params.voice.as_deref()).spawn() {
+            Ok(child) => *running_announcement = Some(child),
+            Err(error) => log::warn!("Could not announce '{}': {}", phrase, error),
+        }
+    }
+}
+
+#[cfg(windows)]
+fn speech_command(text: &str, voice: Option<&str>) -> Command {
+    use std::os::windows::process::CommandExt;
+    /// Keeps PowerShell from flashing a console window
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    // NOTE: The text and voice are handed over via environment variables instead of being
+    //       quoted into our script. PowerShell treats several unicode quotes like single quotes
+    //       which makes escaping them reliably hard.
+    let mut script = "Add-Type -AssemblyName System.Speech; \
+                      $speech = New-Object System.Speech.Synthesis.SpeechSynthesizer; "
+        .to_owned();
+    if voice.is_some() {
+        script += "$speech.SelectVoice($env:CHOTTO_SPEECH_VOICE); ";
+    }
+    script += "$speech.Speak($env:CHOTTO_SPEECH_TEXT)";
+
+    let mut command = Command::new("powershell");
+    command
+        .args(&["-NoProfile", "-NonInteractive", "-Command", &script])
+        .env("CHOTTO_SPEECH_TEXT", text)
+        .creation_flags(CREATE_NO_WINDOW);
+    if let Some(voice) = voice {
+        command.env("CHOTTO_SPEECH_VOICE", voice);
+    }
+    command
+}
+
+#[cfg(target_os = "macos")]
+fn speech_command(text: &str, voice: Option<&str>) -> Command {
+    let mut command = Command::new("say");
+    if let Some(voice) = voice {
+        command.args(&["-v", voice]);
+    }
+    command.arg(text);
+    command
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn speech_command(text: &str, voice: Option<&str>) -> Command {
+    let mut command = Command::new("spd-say");
+    if let Some(voice) = voice {
+        command.args(&["-y", voice]);
+    }
+    command.arg(text);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column_labels() -> Vec<String> {
+        ["B", "I", "N", "G", "O"]
+            .iter()
+            .map(|label| label.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn announces_the_column_letter_before_the_number() {
+        let params: CallerSpeechParams = toml::from_str("").unwrap();
+        let speaker = Speaker::new(&params, &HashMap::new(), column_labels(), 75);
+        assert_eq!(speaker.phrase(15), "B... 15");
+        assert_eq!(speaker.phrase(16), "I... 16");
+        assert_eq!(speaker.phrase(75), "O... 75");
+    }
+
+    #[test]
+    fn does_not_divide_by_zero_with_fewer_numbers_than_columns() {
+        let params: CallerSpeechParams = toml::from_str("").unwrap();
+        let speaker = Speaker::new(&params, &HashMap::new(), column_labels(), 3);
+        assert_eq!(speaker.phrase(3), "N... 3");
+        let speaker = Speaker::new(&params, &HashMap::new(), Vec::new(), 0);
+        assert_eq!(speaker.phrase(7), "7");
+    }
+}
@@ -0,0 +1,271 @@
+//! Template files (`.chottotemplate`) contain a ready-made card design so that we can share it with
+//! others without passing around loose files and hand-written draw parameters. A template is a zip
+//! file with our background, fonts, images and draw parameters plus a `template.json` that
+//! describes it.
+//!
+//! Usage: `chotto export-template [--name <name>] [--author <author>] [--description <text>]
+//!                                [--output <file.chottotemplate>]`
+//!    or: `chotto import-template <file.chottotemplate> [--directory <dir>]`
+//!
+//! Settings that belong to our own runs and not to the design (i.e. our secret key, email and
+//! upload accounts or post-processing commands) are left out when exporting. When importing we
+//! remove the ones that could run commands or use accounts on our computer (see
+//! `unsafe_params.rs`).
+
+use std::io::Read;
+
+use cottontail::core::serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    bundle, command_line_option_value, draw_params_to_toml_string, show_messagebox,
+    show_messagebox_yes_no, unsafe_params, Input, DRAW_PARAMETERS_FILENAME,
+    DRAW_PARAMETERS_FILENAMES, TOML_DOCUMENTATION_HEADER,
+};
+
+pub const TEMPLATE_FILE_EXTENSION: &str = ".chottotemplate";
+const TEMPLATE_INFO_FILENAME: &str = "template.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateInfo {
+    pub name: String,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    /// Templates of newer versions might use parameters that older versions don't know
+    pub chotto_version: String,
+}
+
+pub fn export_template(args: &[String]) {
+    let name = command_line_option_value(args, "--name").unwrap_or_else(|| {
+        std::env::current_dir()
+            .ok()
+            .and_then(|directory| directory.file_name().map(|name| name.to_owned()))
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "template".to_owned())
+    });
+    let template_filepath = command_line_option_value(args, "--output")
+        .unwrap_or_else(|| format!("{}{}", name, TEMPLATE_FILE_EXTENSION));
+
+    let Input {
+        mut params,
+        background_image_filepath,
+        font_filepath,
+        ..
+    } = Input::new();
+    let filepaths = bundle::design_filepaths(&params, &background_image_filepath, &font_filepath);
+
+    // NOTE: We name our background and font explicitly as the template might contain other images
+    //       and fonts that would otherwise be mistaken for them
+    params.background_image_filepath =
+        Some(bundle::normalized_filepath(&background_image_filepath));
+    params.font_filepath = Some(bundle::normalized_filepath(&font_filepath));
+    params.security_code_secret_key = None;
+    params.serial_number.salt = None;
+    params.security_code_pixel_location_x_y = None;
+    params.email = None;
+    params.pos_export = None;
+    params.s3_upload = None;
+    params.post_process_command = None;
+    params.post_process_parallelism = None;
+
+    let info = TemplateInfo {
+        name: name.clone(),
+        author: command_line_option_value(args, "--author"),
+        description: command_line_option_value(args, "--description"),
+        chotto_version: env!("CARGO_PKG_VERSION").to_owned(),
+    };
+    let mut entries = vec![
+        (
+            TEMPLATE_INFO_FILENAME.to_owned(),
+            serde_json::to_string_pretty(&info).unwrap().into_bytes(),
+        ),
+        (
+            DRAW_PARAMETERS_FILENAME.to_owned(),
+            format!(
+                "{}\n\n{}",
+                TOML_DOCUMENTATION_HEADER,
+                draw_params_to_toml_string(&params)
+            )
+            .into_bytes(),
+        ),
+    ];
+    for filepath in filepaths {
+        assert!(
+            std::path::Path::new(&filepath).is_relative() && !filepath.contains(".."),
+            "Cannot put '{}' into a template because it is outside of the directory of \
+             `chotto.exe` - please move it next to '{}'",
+            filepath,
+            DRAW_PARAMETERS_FILENAME
+        );
+        let content = std::fs::read(&filepath)
+            .unwrap_or_else(|error| panic!("Could not read file '{}': {}", filepath, error));
+        entries.push((filepath, content));
+    }
+
+    bundle::write_zip_file(&template_filepath, entries);
+    log::info!("Wrote template '{}' to '{}'", name, template_filepath);
+    show_messagebox(
+        "Chotto",
+        &format!("Wrote template '{}' to '{}'.", name, template_filepath),
+        false,
+    );
+}
+
+pub fn import_template(args: &[String]) {
+    let template_filepath = args
+        .first()
+        .filter(|arg| !arg.starts_with("--"))
+        .expect("Please specify the template via `chotto import-template <file.chottotemplate>`");
+    let directory = command_line_option_value(args, "--directory").unwrap_or_else(|| {
+        std::path::Path::new(template_filepath)
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    });
+    let directory_is_empty = std::fs::read_dir(&directory)
+        .map(|mut entries| entries.next().is_none())
+        .unwrap_or(true);
+    assert!(
+        directory_is_empty,
+        "The directory '{}' already exists - please choose another one via `--directory <dir>`",
+        directory
+    );
+
+    let file = std::fs::File::open(template_filepath)
+        .unwrap_or_else(|error| panic!("Could not open file '{}': {}", template_filepath, error));
+    let mut archive = zip::ZipArchive::new(file).unwrap_or_else(|error| {
+        panic!(
+            "Could not read '{}' - is the file ok?: {}",
+            template_filepath, error
+        )
+    });
+    let mut entries: Vec<(String, Vec<u8>)> = Vec::new();
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).unwrap_or_else(|error| {
+            panic!(
+                "Could not read '{}' - is the file ok?: {}",
+                template_filepath, error
+            )
+        });
+        if entry.is_dir() {
+            continue;
+        }
+        // NOTE: Templates come from other people so we never write outside of our directory
+        let name = entry
+            .enclosed_name()
+            .map(|path| bundle::normalized_filepath(&path.to_string_lossy()))
+            .unwrap_or_else(|| {
+                panic!(
+                    "'{}' contains the file '{}' outside of its directory which is not allowed",
+                    template_filepath,
+                    entry.name()
+                )
+            });
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content).unwrap_or_else(|error| {
+            panic!(
+                "Could not read '{}' from '{}': {}",
+                name, template_filepath, error
+            )
+        });
+        entries.push((name, content));
+    }
+
+    let info: TemplateInfo = entries
+        .iter()
+        .find(|(name, _content)| name == TEMPLATE_INFO_FILENAME)
+        .and_then(|(_name, content)| serde_json::from_slice(content).ok())
+        .unwrap_or_else(|| {
+            panic!(
+                "'{}' is not a template because it has no valid '{}'",
+                template_filepath, TEMPLATE_INFO_FILENAME
+            )
+        });
+    assert!(
+        entries
+            .iter()
+            .any(|(name, _content)| name == DRAW_PARAMETERS_FILENAME),
+        "'{}' is not a template because it has no '{}'",
+        template_filepath,
+        DRAW_PARAMETERS_FILENAME
+    );
+    if info.chotto_version != env!("CARGO_PKG_VERSION") {
+        let message = format!(
+            "'{}' was created with Chotto {} but this is Chotto {}. Some of its settings might \
+             not work. Do you want to continue anyway?",
+            template_filepath,
+            info.chotto_version,
+            env!("CARGO_PKG_VERSION")
+        );
+        log::warn!("{}", message);
+        if !show_messagebox_yes_no("Chotto", &message) {
+            return;
+        }
+    }
+
+    let mut removed_keys = Vec::new();
+    for (name, content) in entries {
+        let draw_params_format = DRAW_PARAMETERS_FILENAMES
+            .iter()
+            .find(|(filename, _format)| *filename == name)
+            .map(|(_filename, format)| *format);
+        let content = match draw_params_format {
+            Some(format) => {
+                let (params_string, removed_params_keys) = unsafe_params::without_unsafe_parameters(
+                    &String::from_utf8_lossy(&content),
+                    &format!("{}/{}", template_filepath, name),
+                    format,
+                );
+                removed_keys.extend(removed_params_keys);
+                params_string.into_bytes()
+            }
+            None => content,
+        };
+        let filepath = std::path::Path::new(&directory).join(&name);
+        if let Some(parent) = filepath.parent() {
+            std::fs::create_dir_all(parent).unwrap_or_else(|error| {
+                panic!(
+                    "Could not create directory '{}': {}",
+                    parent.display(),
+                    error
+                )
+            });
+        }
+        std::fs::write(&filepath, content).unwrap_or_else(|error| {
+            panic!("Could not write file '{}': {}", filepath.display(), error)
+        });
+    }
+
+    log::info!(
+        "Imported template '{}'{} into '{}'",
+        info.name,
+        info.author
+            .as_ref()
+            .map(|author| format!(" by {}", author))
+            .unwrap_or_default(),
+        directory
+    );
+    let removed_keys_note = if removed_keys.is_empty() {
+        String::new()
+    } else {
+        log::warn!(
+            "Removed {:?} from the template as they could run commands or use accounts on our \
+             computer",
+            removed_keys
+        );
+        format!(
+            "\n\nThe parameters {} were removed from the template as they could run commands or \
+             use accounts on our computer.",
+            removed_keys.join(", ")
+        )
+    };
+    show_messagebox(
+        "Chotto",
+        &format!(
+            "Imported template '{}' into '{}'. Please run `chotto.exe` from there to create \
+             sheets with it.{}",
+            info.name, directory, removed_keys_note
+        ),
+        false,
+    );
+}
@@ -0,0 +1,216 @@
+//! Effects that are applied to our texts after they are filled with their color or gradient so
+//! that designers don't need to pre-render every number as an image. Each text layer (our numbers
+//! and our header texts) has its own effects.
+//!
+//! The effects only change the colors inside the shape of a text and never its outline, which
+//! keeps the text bitmaps the same size and lets us place them exactly like plain texts.
+
+use cottontail::{
+    core::serde_derive::{Deserialize, Serialize},
+    image::{Bitmap, PixelRGBA},
+};
+
+use crate::colors;
+
+const DEFAULT_INNER_SHADOW_OFFSET_X_Y: (i32, i32) = (3, 3);
+const DEFAULT_INNER_SHADOW_BLUR_PX: u32 = 3;
+const DEFAULT_INNER_SHADOW_COLOR_RGBA: (u8, u8, u8, u8) = (0, 0, 0, 128);
+const DEFAULT_BEVEL_DEPTH_PX: u32 = 3;
+const DEFAULT_BEVEL_LIGHT_ANGLE_DEGREES: f32 = 135.0;
+const DEFAULT_BEVEL_HIGHLIGHT_COLOR_RGBA: (u8, u8, u8, u8) = (255, 255, 255, 160);
+const DEFAULT_BEVEL_SHADOW_COLOR_RGBA: (u8, u8, u8, u8) = (0, 0, 0, 160);
+/// Bigger blurs and bevels take a long time to draw and don't look any different on our texts
+const MAX_EFFECT_SIZE_PX: u32 = 100;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TextEffects {
+    pub inner_shadow: Option<InnerShadow>,
+    pub bevel: Option<Bevel>,
+}
+
+/// A shadow that falls into the text as if it was cut into the paper
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InnerShadow {
+    pub offset_x_y: Option<(i32, i32)>,
+    pub blur_px: Option<u32>,
+    #[serde(default, deserialize_with = "colors::optional_rgba")]
+    pub color_rgba: Option<(u8, u8, u8, u8)>,
+}
+
+/// Lit and shaded edges that make the text look raised
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bevel {
+    pub depth_px: Option<u32>,
+    /// The direction that the light comes from in degrees counterclockwise from the right, i.e.
+    /// `135` is the top left
+    pub light_angle_degrees: Option<f32>,
+    #[serde(default, deserialize_with = "colors::optional_rgba")]
+    pub highlight_color_rgba: Option<(u8, u8, u8, u8)>,
+    #[serde(default, deserialize_with = "colors::optional_rgba")]
+    pub shadow_color_rgba: Option<(u8, u8, u8, u8)>,
+}
+
+impl TextEffects {
+    pub fn is_empty(&self) -> bool {
+        self.inner_shadow.is_none() && self.bevel.is_none()
+    }
+
+    pub fn problem(&self) -> Option<&'static str> {
+        let bevel_depth = self.bevel.as_ref().and_then(|bevel| bevel.depth_px);
+        let shadow_blur = self
+            .inner_shadow
+            .as_ref()
+            .and_then(|inner_shadow| inner_shadow.blur_px);
+        if bevel_depth == Some(0) {
+            Some("must have a bevel `depth_px` of at least 1")
+        } else if bevel_depth.unwrap_or(0) > MAX_EFFECT_SIZE_PX {
+            Some("must have a bevel `depth_px` of at most 100")
+        } else if shadow_blur.unwrap_or(0) > MAX_EFFECT_SIZE_PX {
+            Some("must have an inner shadow `blur_px` of at most 100")
+        } else {
+            None
+        }
+    }
+
+    /// Applies our effects to the given filled text bitmap. The bevel comes first so that the inner
+    /// shadow darkens its highlights as well.
+    pub fn apply_premultiplied(&self, bitmap: &mut Bitmap) {
+        if self.is_empty() || bitmap.width == 0 || bitmap.height == 0 {
+            return;
+        }
+        let width = bitmap.width as i32;
+        let height = bitmap.height as i32;
+        let coverage: Vec<f32> = bitmap
+            .data
+            .iter()
+            .map(|pixel| pixel.a as f32 / 255.0)
+            .collect();
+        let coverage_at = |x: i32, y: i32| {
+            if x < 0 || y < 0 || x >= width || y >= height {
+                0.0
+            } else {
+                coverage[(y * width + x) as usize]
+            }
+        };
+
+        if let Some(bevel) = &self.bevel {
+            let depth = bevel.depth_px.unwrap_or(DEFAULT_BEVEL_DEPTH_PX) as i32;
+            let (light_y, light_x) = bevel
+                .light_angle_degrees
+                .unwrap_or(DEFAULT_BEVEL_LIGHT_ANGLE_DEGREES)
+                .to_radians()
+                .sin_cos();
+            // NOTE: The blurred coverage rises from the edges towards the inside of the text. Its
+            //       slope tells us which way each edge faces.
+            let heights = box_blurred(&coverage, width, height, depth);
+            let height_at = |x: i32, y: i32| {
+                heights[(y.max(0).min(height - 1) * width + x.max(0).min(width - 1)) as usize]
+            };
+            let highlight = bevel
+                .highlight_color_rgba
+                .unwrap_or(DEFAULT_BEVEL_HIGHLIGHT_COLOR_RGBA);
+            let shadow = bevel
+                .shadow_color_rgba
+                .unwrap_or(DEFAULT_BEVEL_SHADOW_COLOR_RGBA);
+            for y in 0..height {
+                for x in 0..width {
+                    let slope_x = (height_at(x + 1, y) - height_at(x - 1, y)) / 2.0;
+                    // NOTE: Our y axis points down while the light angle counts upwards
+                    let slope_y = (height_at(x, y - 1) - height_at(x, y + 1)) / 2.0;
+                    let lighting =
+                        -(slope_x * light_x + slope_y * light_y) * (2 * depth + 1) as f32;
+                    let lighting = lighting.max(-1.0).min(1.0);
+                    let color = if lighting > 0.0 { highlight } else { shadow };
+                    tint_pixel(bitmap, x, y, color, lighting.abs());
+                }
+            }
+        }
+
+        if let Some(inner_shadow) = &self.inner_shadow {
+            let (offset_x, offset_y) = inner_shadow
+                .offset_x_y
+                .unwrap_or(DEFAULT_INNER_SHADOW_OFFSET_X_Y);
+            let blur = inner_shadow.blur_px.unwrap_or(DEFAULT_INNER_SHADOW_BLUR_PX) as i32;
+            // NOTE: The shadow is cast by everything outside of our text onto the inside
+            let outside: Vec<f32> = (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| 1.0 - coverage_at(x - offset_x, y - offset_y))
+                .collect();
+            let shadow_amounts = box_blurred(&outside, width, height, blur);
+            let color = inner_shadow
+                .color_rgba
+                .unwrap_or(DEFAULT_INNER_SHADOW_COLOR_RGBA);
+            for y in 0..height {
+                for x in 0..width {
+                    let amount = shadow_amounts[(y * width + x) as usize];
+                    tint_pixel(bitmap, x, y, color, amount);
+                }
+            }
+        }
+    }
+}
+
+/// Blends the given color over the given premultiplied pixel without changing its coverage
+fn tint_pixel(bitmap: &mut Bitmap, x: i32, y: i32, color_rgba: (u8, u8, u8, u8), amount: f32) {
+    let pixel = bitmap.get(x, y);
+    if pixel.a == 0 || amount <= 0.0 {
+        return;
+    }
+    let percent = amount * color_rgba.3 as f32 / 255.0;
+    let coverage = pixel.a as f32 / 255.0;
+    let blend = |channel: u8, color: u8| {
+        (channel as f32 + percent * (color as f32 * coverage - channel as f32)).round() as u8
+    };
+    bitmap.set(
+        x,
+        y,
+        PixelRGBA::new(
+            blend(pixel.r, color_rgba.0),
+            blend(pixel.g, color_rgba.1),
+            blend(pixel.b, color_rgba.2),
+            pixel.a,
+        ),
+    );
+}
+
+/// Returns the given values blurred with a box of the given radius. Values outside of the given
+/// area repeat the values of the nearest edge.
+fn box_blurred(values: &[f32], width: i32, height: i32, radius: i32) -> Vec<f32> {
+    if radius <= 0 {
+        return values.to_vec();
+    }
+    let blur_line = |line: &mut dyn FnMut(i32) -> f32, count: i32, result: &mut Vec<f32>| {
+        let mut sum: f32 = (-radius..=radius)
+            .map(|index| line(index.max(0).min(count - 1)))
+            .sum();
+        for index in 0..count {
+            result.push(sum / (2 * radius + 1) as f32);
+            sum += line((index + radius + 1).min(count - 1)) - line((index - radius).max(0));
+        }
+    };
+
+    let mut horizontal = Vec::with_capacity(values.len());
+    for y in 0..height {
+        blur_line(
+            &mut |x| values[(y * width + x) as usize],
+            width,
+            &mut horizontal,
+        );
+    }
+    let mut vertical_columns = Vec::with_capacity(values.len());
+    for x in 0..width {
+        blur_line(
+            &mut |y| horizontal[(y * width + x) as usize],
+            height,
+            &mut vertical_columns,
+        );
+    }
+    // NOTE: The vertical pass gave us the values column by column
+    let mut result = vec![0.0; values.len()];
+    for x in 0..width {
+        for y in 0..height {
+            result[(y * width + x) as usize] = vertical_columns[(x * height + y) as usize];
+        }
+    }
+    result
+}
@@ -0,0 +1,189 @@
+//! Draws a ticket stub onto each sheet for events that combine Bingo with a raffle. The stub is a
+//! region of the sheet with the serial number, the name of the event and a blank line for the name
+//! of the player. A dashed line marks where the stub is torn off or where the printer perforates
+//! the paper. The player keeps the sheet and the stub goes into the raffle drum.
+
+use std::collections::HashMap;
+
+use cottontail::{
+    core::serde_derive::{Deserialize, Serialize},
+    image::PixelRGBA,
+    math::Vec2i,
+};
+
+use crate::{
+    color_from_rgb, colors, expand_text_variables, sheet_canvas::SheetCanvas, DrawParams,
+    FillStyle, LoadedFont, TextRasterizer,
+};
+
+const DEFAULT_NAME_LABEL: &str = "Name:";
+const DEFAULT_LINE_WIDTH_PX: u32 = 3;
+
+/// The side of the stub where it is separated from the rest of the sheet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PerforationSide {
+    Left,
+    Top,
+    Right,
+    Bottom,
+}
+
+impl Default for PerforationSide {
+    fn default() -> Self {
+        PerforationSide::Top
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TicketStubParams {
+    /// The region of the stub relative to the trim box
+    pixel_location_left_top_right_bottom: (u32, u32, u32, u32),
+    /// Can contain the same placeholders as `extra_texts`
+    event_name: Option<String>,
+    name_label: Option<String>,
+    #[serde(default)]
+    perforation_side: PerforationSide,
+    line_width_px: Option<u32>,
+    /// Uses the header font size by default
+    size: Option<u32>,
+    #[serde(default, deserialize_with = "colors::optional_rgb")]
+    color: Option<(u8, u8, u8)>,
+}
+
+impl TicketStubParams {
+    /// Returns a description of what is wrong with the stub if anything
+    pub fn problem(&self) -> Option<&'static str> {
+        let (left, top, right, bottom) = self.pixel_location_left_top_right_bottom;
+        if left >= right || top >= bottom {
+            Some(
+                "must have its left value smaller than its right value and its top value smaller \
+                 than its bottom value in `pixel_location_left_top_right_bottom`",
+            )
+        } else if self.line_width_px == Some(0) {
+            Some("must have a `line_width_px` of at least 1")
+        } else {
+            None
+        }
+    }
+}
+
+pub struct TicketStub<'a> {
+    params: TicketStubParams,
+    rasterizer: TextRasterizer<'a>,
+    line_color: PixelRGBA,
+}
+
+impl<'a> TicketStub<'a> {
+    pub fn new(params: &DrawParams, header_font: &'a LoadedFont) -> Option<TicketStub<'a>> {
+        let stub_params = params.ticket_stub.clone()?;
+        let (r, g, b) = stub_params
+            .color
+            .or(params.header_text_color_rgb)
+            .unwrap_or(params.text_color_rgb);
+        let rasterizer = TextRasterizer::new(
+            header_font,
+            stub_params
+                .size
+                .or(params.header_text_font_size)
+                .unwrap_or(params.text_font_size) as f32,
+            FillStyle::Solid(color_from_rgb((r, g, b))),
+            params.text_direction,
+            params.bold_text,
+            &format!(
+                "#0123456789{}{}",
+                stub_params.event_name.clone().unwrap_or_default(),
+                stub_params
+                    .name_label
+                    .as_deref()
+                    .unwrap_or(DEFAULT_NAME_LABEL)
+            ),
+        );
+        Some(TicketStub {
+            params: stub_params,
+            rasterizer,
+            line_color: PixelRGBA::new(r, g, b, 255),
+        })
+    }
+
+    /// Draws the stub of the card with the given serial number onto the given sheet whose trim box
+    /// starts at the given offset
+    pub fn draw(
+        &self,
+        sheet: &mut SheetCanvas,
+        trim_offset: Vec2i,
+        serial: &str,
+        text_variables: &HashMap<String, String>,
+        gamma_correct_blending: bool,
+    ) {
+        let (stub_left, stub_top, stub_right, stub_bottom) =
+            self.params.pixel_location_left_top_right_bottom;
+        let left = trim_offset.x + stub_left as i32;
+        let top = trim_offset.y + stub_top as i32;
+        let width = (stub_right - stub_left) as i32;
+        let height = (stub_bottom - stub_top) as i32;
+        let line_width = self.params.line_width_px.unwrap_or(DEFAULT_LINE_WIDTH_PX) as i32;
+
+        // NOTE: The dashes are a few line widths long so that they are still visible when the
+        //       sheet is printed smaller
+        let dash_length = 4 * line_width;
+        let gap_length = 3 * line_width;
+        let (dash_x, dash_y, dash_count, is_horizontal) = match self.params.perforation_side {
+            PerforationSide::Top => (left, top, width, true),
+            PerforationSide::Bottom => (left, top + height - line_width, width, true),
+            PerforationSide::Left => (left, top, height, false),
+            PerforationSide::Right => (left + width - line_width, top, height, false),
+        };
+        for offset in (0..dash_count).step_by((dash_length + gap_length) as usize) {
+            let length = i32::min(dash_length, dash_count - offset);
+            if is_horizontal {
+                sheet.fill_rect(dash_x + offset, dash_y, length, line_width, self.line_color);
+            } else {
+                sheet.fill_rect(dash_x, dash_y + offset, line_width, length, self.line_color);
+            }
+        }
+
+        // NOTE: The event name, the serial number and the name line each get a third of the stub
+        let center_x = left + width / 2;
+        let row_center_y = |row: i32| top + (2 * row + 1) * height / 6;
+        if let Some(event_name) = &self.params.event_name {
+            let event_name = expand_text_variables(event_name, text_variables);
+            sheet.blit_centered_premultiplied(
+                &self
+                    .rasterizer
+                    .create_text_bitmap_premultiplied(&event_name),
+                Vec2i::new(center_x, row_center_y(0)),
+                gamma_correct_blending,
+            );
+        }
+        sheet.blit_centered_premultiplied(
+            &self
+                .rasterizer
+                .create_text_bitmap_premultiplied(&format!("#{}", serial)),
+            Vec2i::new(center_x, row_center_y(1)),
+            gamma_correct_blending,
+        );
+
+        let label_bitmap = self.rasterizer.create_text_bitmap_premultiplied(
+            self.params
+                .name_label
+                .as_deref()
+                .unwrap_or(DEFAULT_NAME_LABEL),
+        );
+        let padding = width / 10;
+        let label_center = Vec2i::new(
+            left + padding + label_bitmap.width as i32 / 2,
+            row_center_y(2),
+        );
+        sheet.blit_centered_premultiplied(&label_bitmap, label_center, gamma_correct_blending);
+        let line_left = label_center.x + label_bitmap.width as i32 / 2 + line_width * 2;
+        let line_bottom = label_center.y + label_bitmap.height as i32 / 2;
+        sheet.fill_rect(
+            line_left,
+            line_bottom - line_width,
+            left + width - padding - line_left,
+            line_width,
+            self.line_color,
+        );
+    }
+}
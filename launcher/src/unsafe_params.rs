@@ -0,0 +1,165 @@
+//! Draw parameters that we receive from other people (i.e. in templates or reproduction bundles)
+//! must not run commands or use accounts on our machine. We remove such parameters from received
+//! draw parameter files before we use them.
+
+use crate::{params_validation, DrawParamsFormat, TOML_DOCUMENTATION_HEADER};
+
+/// Parameters that run commands on our machine or send our sheets to the accounts of whoever
+/// wrote the file
+pub const UNSAFE_PARAMETER_KEYS: &[&str] = &[
+    "post_process_command",
+    "post_process_parallelism",
+    "email",
+    "pos_export",
+    "s3_upload",
+];
+
+/// Removes the unsafe parameters from all levels of the given draw parameters, including the
+/// tables of our profiles. Returns the dotted paths of the removed parameters.
+pub fn remove_unsafe_parameters(params_value: &mut toml::Value) -> Vec<String> {
+    let mut removed_keys = Vec::new();
+    remove_unsafe_parameters_recursive(params_value, "", &mut removed_keys);
+    removed_keys
+}
+
+fn remove_unsafe_parameters_recursive(
+    value: &mut toml::Value,
+    prefix: &str,
+    removed_keys: &mut Vec<String>,
+) {
+    match value {
+        toml::Value::Table(table) => {
+            for key in UNSAFE_PARAMETER_KEYS {
+                if table.remove(*key).is_some() {
+                    removed_keys.push(format!("{}{}", prefix, key));
+                }
+            }
+            for (key, nested_value) in table.iter_mut() {
+                remove_unsafe_parameters_recursive(
+                    nested_value,
+                    &format!("{}{}.", prefix, key),
+                    removed_keys,
+                );
+            }
+        }
+        toml::Value::Array(values) => {
+            for (index, nested_value) in values.iter_mut().enumerate() {
+                remove_unsafe_parameters_recursive(
+                    nested_value,
+                    &format!("{}{}.", prefix.trim_end_matches('.'), index),
+                    removed_keys,
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the given content of a received draw parameters file without its unsafe parameters
+/// and the dotted paths of the removed parameters. The content is returned unchanged if there was
+/// nothing to remove. Panics if the content cannot be read because we could not check it then.
+pub fn without_unsafe_parameters(
+    params_string: &str,
+    filepath: &str,
+    format: DrawParamsFormat,
+) -> (String, Vec<String>) {
+    let mut params_value = params_validation::parse_params_value(params_string, format)
+        .unwrap_or_else(|error| {
+            panic!(
+                "'{}' is not a valid {:?} file so we cannot check it for parameters that run \
+                 commands: {}",
+                filepath, format, error
+            )
+        });
+    let removed_keys = remove_unsafe_parameters(&mut params_value);
+    if removed_keys.is_empty() {
+        return (params_string.to_owned(), removed_keys);
+    }
+
+    let params_string = match format {
+        DrawParamsFormat::Toml => toml::to_string(&params_value)
+            .map(|params_string| format!("{}\n\n{}", TOML_DOCUMENTATION_HEADER, params_string))
+            .map_err(|error| error.to_string()),
+        DrawParamsFormat::Yaml => {
+            serde_yaml::to_string(&params_value).map_err(|error| error.to_string())
+        }
+        DrawParamsFormat::Json => {
+            serde_json::to_string_pretty(&params_value).map_err(|error| error.to_string())
+        }
+    }
+    .unwrap_or_else(|error| panic!("Could not write '{}': {}", filepath, error));
+    (params_string, removed_keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn removed_toml_keys(params_string: &str) -> (String, Vec<String>) {
+        without_unsafe_parameters(params_string, "test.toml", DrawParamsFormat::Toml)
+    }
+
+    #[test]
+    fn removes_bare_keys() {
+        let (params_string, removed_keys) =
+            removed_toml_keys("text_font_size = 64\npost_process_command = \"calc.exe\"\n");
+        assert_eq!(removed_keys, vec!["post_process_command"]);
+        assert!(!params_string.contains("calc.exe"));
+        assert!(params_string.contains("text_font_size = 64"));
+    }
+
+    #[test]
+    fn removes_single_quoted_keys() {
+        let (params_string, removed_keys) =
+            removed_toml_keys("'post_process_command' = 'calc.exe'\n");
+        assert_eq!(removed_keys, vec!["post_process_command"]);
+        assert!(!params_string.contains("calc.exe"));
+    }
+
+    #[test]
+    fn removes_unicode_escaped_keys() {
+        let (params_string, removed_keys) =
+            removed_toml_keys("\"post_process_\\u0063ommand\" = \"calc.exe\"\n");
+        assert_eq!(removed_keys, vec!["post_process_command"]);
+        assert!(!params_string.contains("calc.exe"));
+    }
+
+    #[test]
+    fn removes_keys_of_inline_profile_tables() {
+        let (params_string, removed_keys) = removed_toml_keys(
+            "profile.x = { text_font_size = 64, post_process_command = \"calc.exe\" }\n",
+        );
+        assert_eq!(removed_keys, vec!["profile.x.post_process_command"]);
+        assert!(!params_string.contains("calc.exe"));
+        assert!(params_string.contains("text_font_size = 64"));
+    }
+
+    #[test]
+    fn removes_account_tables() {
+        let (params_string, removed_keys) = removed_toml_keys(
+            "[email]\nsmtp_server = \"smtp.example.com\"\n\n[s3_upload]\nbucket = \"b\"\n",
+        );
+        assert_eq!(removed_keys, vec!["email", "s3_upload"]);
+        assert!(!params_string.contains("smtp.example.com"));
+    }
+
+    #[test]
+    fn removes_keys_of_json_files() {
+        let (params_string, removed_keys) = without_unsafe_parameters(
+            r#"{ "text_font_size": 64, "post_process_command": "calc.exe" }"#,
+            "test.json",
+            DrawParamsFormat::Json,
+        );
+        assert_eq!(removed_keys, vec!["post_process_command"]);
+        assert!(!params_string.contains("calc.exe"));
+    }
+
+    #[test]
+    fn keeps_safe_files_unchanged() {
+        let params_string = "# Our comment\ntext_font_size = 64\n";
+        assert_eq!(
+            removed_toml_keys(params_string),
+            (params_string.to_owned(), Vec::new())
+        );
+    }
+}